@@ -1,10 +1,21 @@
-use disco_quick::DiscogsReader;
+use disco_quick::filter::expr::{ExprError, Query, Queryable};
+use disco_quick::parser::Parser;
+use disco_quick::reader::EntityReader;
+use disco_quick::{DiscogsReader, ReaderError};
 use std::env;
 use std::time::{Duration, Instant};
 
-/// Count the total items in a dump and report the parsing time.
+/// Count the total items in a dump and report the parsing time. With
+/// `--filter <expression>`, only count the items matching the expression, e.g.
+/// `--filter 'country == "UK" && year >= 1990'`.
 fn main() {
-    for arg in env::args().skip(1) {
+    let mut args = env::args().skip(1);
+    let mut filter = None;
+    while let Some(arg) = args.next() {
+        if arg == "--filter" {
+            filter = args.next();
+            continue;
+        }
         let reader = match DiscogsReader::from_path(arg.as_ref()) {
             Ok(reader) => reader,
             Err(e) => {
@@ -15,11 +26,20 @@ fn main() {
         let reader_name = reader.to_string();
         println!("Processing {}...", arg);
         let now = Instant::now();
+        let filter = filter.as_deref();
         let count = match reader {
-            DiscogsReader::Artists(artists) => artists.count(),
-            DiscogsReader::Labels(labels) => labels.count(),
-            DiscogsReader::Masters(masters) => masters.count(),
-            DiscogsReader::Releases(releases) => releases.count(),
+            DiscogsReader::Artists(artists) => count_matching(*artists, filter),
+            DiscogsReader::Labels(labels) => count_matching(*labels, filter),
+            DiscogsReader::Masters(masters) => count_matching(*masters, filter),
+            DiscogsReader::Releases(releases) => count_matching(*releases, filter),
+            DiscogsReader::Custom(custom) => Ok(custom.count()),
+        };
+        let count = match count {
+            Ok(count) => count,
+            Err(e) => {
+                eprintln!("Invalid filter. {e}");
+                return;
+            }
         };
         let duration = now.elapsed();
         let per_second = count as f32 / duration.as_secs_f32();
@@ -33,6 +53,30 @@ fn main() {
     }
 }
 
+fn count_matching<P: Parser>(
+    reader: EntityReader<P>,
+    filter: Option<&str>,
+) -> Result<usize, ExprError>
+where
+    P::Item: Queryable + 'static,
+{
+    Ok(match filter {
+        Some(filter) => count_ok(reader.filter_query(Query::parse(filter)?)),
+        None => count_ok(reader),
+    })
+}
+
+fn count_ok<T>(items: impl Iterator<Item = Result<T, ReaderError>>) -> usize {
+    let mut count = 0;
+    for item in items {
+        match item {
+            Ok(_) => count += 1,
+            Err(e) => eprintln!("Error after {count} items. {e}"),
+        }
+    }
+    count
+}
+
 fn format_duration(d: Duration) -> String {
     let seconds = d.as_secs();
     let millis = d.subsec_millis();
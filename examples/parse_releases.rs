@@ -0,0 +1,114 @@
+use disco_quick::artist_credit::ArtistCredit;
+use disco_quick::release::{Release, ReleaseFormat, ReleaseIdentifier};
+use disco_quick::shared::ReleaseLabel;
+use disco_quick::track::Track;
+use disco_quick::writer::write_releases;
+use disco_quick::{DiscogsReader, ReaderError};
+use std::env;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+/// Parse a releases dump generated in memory several times and report the best and
+/// median times. Reading from memory leaves out decompression and the disk, so the
+/// times are those of the parser alone. Run it with `--release` on two commits to
+/// compare them, e.g. `cargo run --release --example parse_releases -- 50000 15`
+/// for 50,000 releases parsed 15 times, which are the defaults.
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut number = |default| {
+        args.next()
+            .map_or(default, |a| a.parse().expect("a number"))
+    };
+    let releases = number(50_000);
+    let runs = number(15) as usize;
+
+    let mut xml = Vec::new();
+    write_releases((1..=releases).map(release), &mut xml).expect("writing to memory");
+    println!(
+        "Parsing {releases} releases ({:.1} MB) {runs} times...",
+        xml.len() as f64 / 1e6
+    );
+
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let input = xml.clone();
+        let start = Instant::now();
+        let count = parse(input).expect("the generated dump is valid");
+        times.push(start.elapsed());
+        assert_eq!(count, releases as usize);
+    }
+    times.sort();
+    let per_release = |d: Duration| d.as_nanos() as f64 / releases as f64;
+    let (best, median) = (times[0], times[times.len() / 2]);
+    println!(
+        "Best {best:.1?} ({:.0} ns/release), median {median:.1?} ({:.0} ns/release)",
+        per_release(best),
+        per_release(median),
+    );
+    if cfg!(debug_assertions) {
+        println!("This is a debug build, so the times aren't representative");
+    }
+}
+
+fn parse(xml: Vec<u8>) -> Result<usize, ReaderError> {
+    let DiscogsReader::Releases(reader) = DiscogsReader::from_reader(Cursor::new(xml), None)?
+    else {
+        unreachable!("the dump is of releases");
+    };
+    let mut count = 0;
+    for release in *reader {
+        release?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// A release with roughly the fields and number of credits of a typical one.
+fn release(id: u32) -> Release {
+    let credit = |n: u32, role: Option<&str>| ArtistCredit {
+        id: (id % 1000 + n).into(),
+        name: format!("Artist {n}"),
+        join: Some("&".to_string()),
+        role: role.map(Into::into),
+        ..Default::default()
+    };
+    let tracklist = (1..=10)
+        .map(|n| Track {
+            position: format!("A{n}"),
+            title: format!("Track {n}"),
+            duration: Some(format!("{}:{:02}", 3 + n % 3, n * 5 % 60)),
+            extraartists: vec![credit(n, Some("Written-By"))],
+            ..Default::default()
+        })
+        .collect();
+    Release {
+        id: id.into(),
+        title: format!("Release {id}"),
+        artists: vec![credit(1, None), credit(2, None)],
+        extraartists: vec![credit(3, Some("Producer")), credit(4, Some("Mixed By"))],
+        labels: vec![ReleaseLabel {
+            id: (id % 500).into(),
+            name: format!("Label {}", id % 500),
+            catno: Some(format!("CAT {id}")),
+            ..Default::default()
+        }],
+        formats: vec![ReleaseFormat {
+            qty: "1".to_string(),
+            name: "Vinyl".into(),
+            descriptions: vec!["LP".to_string(), "Album".to_string()],
+            ..Default::default()
+        }],
+        genres: vec!["Electronic".into()],
+        styles: vec!["House".into(), "Techno".into()],
+        country: "UK".into(),
+        released: format!("{}", 1970 + id % 50),
+        notes: Some("Recorded live.".to_string()),
+        tracklist,
+        identifiers: vec![ReleaseIdentifier {
+            r#type: "Barcode".to_string(),
+            value: Some("5012345678900".to_string()),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
@@ -17,6 +17,7 @@ fn main() {
             DiscogsReader::Labels(labels) => handle_labels(*labels),
             DiscogsReader::Masters(masters) => handle_masters(*masters),
             DiscogsReader::Releases(releases) => handle_releases(*releases),
+            DiscogsReader::Custom(custom) => eprintln!("Can't print {} as JSON", custom.tag()),
         };
     }
 }
@@ -25,26 +26,39 @@ const AMOUNT: usize = 10;
 
 fn handle_artists(artists: ArtistsReader) {
     for artist in artists.take(AMOUNT) {
-        println!("{}", to_string_pretty(&artist).unwrap());
+        match artist {
+            Ok(artist) => println!("{}", to_string_pretty(&artist).unwrap()),
+            Err(e) => eprintln!("{e}"),
+        }
     }
 }
 
 fn handle_labels(labels: LabelsReader) {
     for label in labels.take(AMOUNT) {
-        println!("{}", to_string_pretty(&label).unwrap());
+        match label {
+            Ok(label) => println!("{}", to_string_pretty(&label).unwrap()),
+            Err(e) => eprintln!("{e}"),
+        }
     }
 }
 
 fn handle_masters(masters: MastersReader) {
     for master in masters.take(AMOUNT) {
-        if master.artists.len() > 1 {
-            println!("{}", to_string_pretty(&master).unwrap());
+        match master {
+            Ok(master) if master.artists.len() > 1 => {
+                println!("{}", to_string_pretty(&master).unwrap())
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("{e}"),
         }
     }
 }
 
 fn handle_releases(releases: ReleasesReader) {
     for release in releases.take(AMOUNT) {
-        println!("{}", to_string_pretty(&release).unwrap());
+        match release {
+            Ok(release) => println!("{}", to_string_pretty(&release).unwrap()),
+            Err(e) => eprintln!("{e}"),
+        }
     }
 }
@@ -1,32 +1,44 @@
+use crate::id::ArtistId;
+use crate::options::ReaderOptions;
 use crate::parser::{Parser, ParserError};
-use crate::reader::XmlReader;
-use crate::shared::Image;
+use crate::reader::EntityReader;
+#[cfg(feature = "extra-fields")]
+use crate::shared::UnknownElement;
+use crate::shared::{normalize_in_place, DataQuality, Image};
 use crate::util::get_attr_id;
 use log::debug;
 use quick_xml::events::Event;
+#[cfg(feature = "extra-fields")]
+use std::collections::BTreeMap;
 use std::fmt;
 use std::mem::take;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Artist {
-    pub id: i32,
+    pub id: ArtistId,
     pub name: String,
     pub real_name: Option<String>,
     pub profile: Option<String>,
-    pub data_quality: String,
+    pub data_quality: DataQuality,
     pub name_variations: Vec<String>,
     pub urls: Vec<String>,
     pub aliases: Vec<ArtistInfo>,
     pub members: Vec<ArtistInfo>,
     pub groups: Vec<ArtistInfo>,
     pub images: Vec<Image>,
+    /// The text of elements the parser doesn't know, by name, so data isn't lost when
+    /// Discogs adds elements before the crate supports them. Elements with children
+    /// aren't kept.
+    #[cfg(feature = "extra-fields")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_fields: BTreeMap<String, String>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ArtistInfo {
-    pub id: u32,
+    pub id: ArtistId,
     pub name: String,
 }
 
@@ -36,39 +48,8 @@ impl fmt::Display for Artist {
     }
 }
 
-pub struct ArtistsReader {
-    buf: Vec<u8>,
-    reader: XmlReader,
-    parser: ArtistParser,
-}
-
-impl ArtistsReader {
-    pub fn new(reader: XmlReader, buf: Vec<u8>) -> Self {
-        Self {
-            buf,
-            reader,
-            parser: ArtistParser::new(),
-        }
-    }
-}
-
-impl Iterator for ArtistsReader {
-    type Item = Artist;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.reader.read_event_into(&mut self.buf).unwrap() {
-                Event::Eof => {
-                    return None;
-                }
-                ev => self.parser.process(ev).unwrap(),
-            };
-            if self.parser.item_ready {
-                return Some(self.parser.take());
-            }
-            self.buf.clear();
-        }
-    }
-}
+/// Iterator over the artists in an artists dump.
+pub type ArtistsReader = EntityReader<ArtistParser>;
 
 #[derive(Debug, Default)]
 enum ParserState {
@@ -87,13 +68,19 @@ enum ParserState {
     MemberName,
     Groups,
     Images,
+    #[cfg(feature = "extra-fields")]
+    Unknown,
 }
 
+/// Parses an `<artist>` element of the artists dump into an [`Artist`].
 #[derive(Debug, Default)]
 pub struct ArtistParser {
     state: ParserState,
     current_item: Artist,
     item_ready: bool,
+    normalize_text: bool,
+    #[cfg(feature = "extra-fields")]
+    unknown: UnknownElement,
 }
 
 impl Parser for ArtistParser {
@@ -102,13 +89,40 @@ impl Parser for ArtistParser {
         Self::default()
     }
 
+    fn with_options(options: &ReaderOptions) -> Self {
+        Self {
+            normalize_text: options.normalize_text,
+            ..Self::default()
+        }
+    }
+
     fn take(&mut self) -> Self::Item {
         self.item_ready = false;
         take(&mut self.current_item)
     }
 
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
+
+    fn current_id(&self) -> Option<u32> {
+        Some(self.current_item.id.get()).filter(|id| *id != 0)
+    }
+
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
+            #[cfg(feature = "extra-fields")]
+            ParserState::Unknown => {
+                if self
+                    .unknown
+                    .process(ev, &mut self.current_item.extra_fields)?
+                {
+                    ParserState::Artist
+                } else {
+                    ParserState::Unknown
+                }
+            }
+
             ParserState::Artist => match ev {
                 Event::Start(e) if e.local_name().as_ref() == b"artist" => ParserState::Artist,
 
@@ -124,9 +138,18 @@ impl Parser for ArtistParser {
                     b"members" => ParserState::Members,
                     b"groups" => ParserState::Groups,
                     b"images" => ParserState::Images,
+                    #[cfg(feature = "extra-fields")]
+                    _ => {
+                        self.unknown.start(&e);
+                        ParserState::Unknown
+                    }
+                    #[cfg(not(feature = "extra-fields"))]
                     _ => ParserState::Artist,
                 },
                 Event::End(e) if e.local_name().as_ref() == b"artist" => {
+                    if self.normalize_text {
+                        self.current_item.profile.as_mut().map(normalize_in_place);
+                    }
                     self.item_ready = true;
                     ParserState::Artist
                 }
@@ -170,7 +193,7 @@ impl Parser for ArtistParser {
 
             ParserState::DataQuality => match ev {
                 Event::Text(e) => {
-                    self.current_item.data_quality = e.unescape()?.to_string();
+                    self.current_item.data_quality = DataQuality::from(&*e.unescape()?);
                     ParserState::DataQuality
                 }
                 _ => ParserState::Artist,
@@ -189,7 +212,7 @@ impl Parser for ArtistParser {
             ParserState::Aliases => match ev {
                 Event::Start(e) if e.local_name().as_ref() == b"name" => {
                     let alias = ArtistInfo {
-                        id: get_attr_id(e),
+                        id: get_attr_id(e)?,
                         ..Default::default()
                     };
                     self.current_item.aliases.push(alias);
@@ -206,7 +229,19 @@ impl Parser for ArtistParser {
             },
 
             ParserState::Members => match ev {
-                Event::Start(e) if e.local_name().as_ref() == b"name" => ParserState::MemberName,
+                Event::Start(e) if e.local_name().as_ref() == b"name" => {
+                    // The ID may only be present as an attribute of <name>
+                    if let Ok(id) = get_attr_id(e) {
+                        if self.current_item.members.last().map(|m| m.id) != Some(id) {
+                            let member = ArtistInfo {
+                                id,
+                                ..Default::default()
+                            };
+                            self.current_item.members.push(member);
+                        }
+                    }
+                    ParserState::MemberName
+                }
                 Event::Start(e) if e.local_name().as_ref() == b"id" => ParserState::MemberId,
                 Event::End(e) if e.local_name().as_ref() == b"members" => ParserState::Artist,
                 _ => ParserState::Members,
@@ -226,8 +261,9 @@ impl Parser for ArtistParser {
 
             ParserState::MemberName => match ev {
                 Event::Text(e) => {
-                    let i = self.current_item.members.len() - 1;
-                    self.current_item.members[i].name = e.unescape()?.to_string();
+                    if let Some(member) = self.current_item.members.last_mut() {
+                        member.name = e.unescape()?.to_string();
+                    }
                     ParserState::Members
                 }
                 _ => ParserState::Members,
@@ -236,7 +272,7 @@ impl Parser for ArtistParser {
             ParserState::Groups => match ev {
                 Event::Start(e) if e.local_name().as_ref() == b"name" => {
                     let group = ArtistInfo {
-                        id: get_attr_id(e),
+                        id: get_attr_id(e)?,
                         ..Default::default()
                     };
                     self.current_item.groups.push(group);
@@ -266,7 +302,7 @@ impl Parser for ArtistParser {
 
             ParserState::Images => match ev {
                 Event::Empty(e) if e.local_name().as_ref() == b"image" => {
-                    let image = Image::from_event(e);
+                    let image = Image::from_event(e)?;
                     self.current_item.images.push(image);
                     ParserState::Images
                 }
@@ -1,23 +1,29 @@
+use crate::id::ArtistId;
+use crate::intern::{intern, Symbol};
 use crate::parser::{Parser, ParserError};
+use crate::position::TrackSelection;
+use crate::role::RoleKind;
+use crate::tags::Tag;
 use quick_xml::events::Event;
 use std::mem::take;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ArtistCredit {
-    pub id: u32,
+    pub id: ArtistId,
     pub name: String,
     pub anv: Option<String>,
     pub join: Option<String>,
-    pub role: Option<String>,
+    pub role: Option<Symbol>,
     pub tracks: Option<String>,
 }
 
+/// Parses an `<artist>` element of an `<artists>` or `<extraartists>` list into an [`ArtistCredit`].
 #[derive(Debug, Default)]
 pub struct ArtistCreditParser {
     state: ParserState,
-    pub current_item: ArtistCredit,
-    pub item_ready: bool,
+    current_item: ArtistCredit,
+    item_ready: bool,
 }
 
 #[derive(Debug, Default)]
@@ -32,50 +38,221 @@ enum ParserState {
     Tracks,
 }
 
-pub fn get_credit_string(credits: &Vec<ArtistCredit>) -> String {
-    if credits.len() == 1 {
-        credits[0].name.to_owned()
-    } else {
-        let mut credit_string = String::new();
-        for credit in credits {
-            credit_string.push_str(&credit.name);
-            if let Some(join) = &credit.join {
-                if join != "," {
-                    credit_string.push(' ')
+/// The Discogs names of `credits` with their join phrases, e.g. "A & B", rendered
+/// by [`credit_string_with`] in [`CreditStyle::NAMES`].
+///
+/// ```
+/// use disco_quick::artist_credit::{get_credit_string, ArtistCredit};
+///
+/// let credit = |name: &str, join: &str| ArtistCredit {
+///     name: name.to_string(),
+///     join: Some(join.to_string()),
+///     ..Default::default()
+/// };
+/// let credits = [credit("A", ","), credit("B", "&"), credit("C", "&")];
+/// assert_eq!(get_credit_string(&credits), "A, B & C");
+/// ```
+pub fn get_credit_string(credits: &[ArtistCredit]) -> String {
+    credit_string_with(credits, &[], CreditStyle::NAMES)
+}
+
+impl ArtistCredit {
+    /// The name the artist is credited as on the release, or their Discogs name if
+    /// there's no ANV.
+    pub fn credited_name(&self) -> &str {
+        self.anv
+            .as_deref()
+            .filter(|a| !a.is_empty())
+            .unwrap_or(&self.name)
+    }
+
+    /// The tracks the credit is limited to, or None if it applies to all of them.
+    pub fn track_selection(&self) -> Option<TrackSelection> {
+        let selection = TrackSelection::parse(self.tracks.as_deref()?);
+        (!selection.is_empty()).then_some(selection)
+    }
+}
+
+/// How [`credit_string_with`] renders credits. The default uses the credited names
+/// and adds featured artists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CreditStyle {
+    /// Use the name each artist is credited as instead of their Discogs name.
+    pub prefer_anv: bool,
+    /// Add the extra artists with a Featuring role, e.g. "A & B feat. C".
+    pub featuring: bool,
+}
+
+impl CreditStyle {
+    /// Only the Discogs names of the credits, as in the dumps' display strings.
+    pub const NAMES: Self = Self {
+        prefer_anv: false,
+        featuring: false,
+    };
+}
+
+impl Default for CreditStyle {
+    fn default() -> Self {
+        Self {
+            prefer_anv: true,
+            featuring: true,
+        }
+    }
+}
+
+/// Render `credits` with their join phrases, followed by any featured artists in
+/// `extra` if the style includes them. The join phrase of the last credit is ignored.
+pub fn credit_string_with(
+    credits: &[ArtistCredit],
+    extra: &[ArtistCredit],
+    style: CreditStyle,
+) -> String {
+    let name = |credit: &'_ ArtistCredit| -> String {
+        if style.prefer_anv {
+            credit.credited_name().to_owned()
+        } else {
+            credit.name.to_owned()
+        }
+    };
+    let mut credit_string = String::new();
+    for (i, credit) in credits.iter().enumerate() {
+        credit_string.push_str(&name(credit));
+        if i + 1 < credits.len() {
+            match credit.join.as_deref().map(str::trim) {
+                Some(",") => credit_string.push_str(", "),
+                Some(join) if !join.is_empty() => {
+                    credit_string.push(' ');
+                    credit_string.push_str(join);
+                    credit_string.push(' ');
                 }
-                credit_string.push_str(join);
-                credit_string.push(' ')
+                _ => credit_string.push(' '),
             }
         }
-        credit_string
     }
+    if !style.featuring {
+        return credit_string;
+    }
+    let featured: Vec<String> = extra
+        .iter()
+        .filter(|e| e.has_role(&RoleKind::Featuring))
+        .filter(|e| !credits.iter().any(|c| c.id == e.id))
+        .map(name)
+        .collect();
+    if let Some((last, rest)) = featured.split_last() {
+        if !credit_string.is_empty() {
+            credit_string.push_str(" feat. ");
+        }
+        if !rest.is_empty() {
+            credit_string.push_str(&rest.join(", "));
+            credit_string.push_str(" & ");
+        }
+        credit_string.push_str(last);
+    }
+    credit_string
+}
+
+/// Join phrases recognized by [`parse_credit_string`].
+pub const JOIN_PHRASES: &[&str] = &[
+    "&",
+    "+",
+    "/",
+    "x",
+    "X",
+    "And",
+    "and",
+    "Feat.",
+    "feat.",
+    "Featuring",
+    "featuring",
+    "Ft.",
+    "ft.",
+    "Meets",
+    "meets",
+    "Pres.",
+    "Presents",
+    "presents",
+    "Vs",
+    "vs",
+    "Vs.",
+    "vs.",
+    "Versus",
+    "versus",
+    "With",
+    "with",
+];
+
+/// Best-effort inverse of [`get_credit_string`]. Splits a display string like
+/// "A & B feat. C" into credits with names and joins. IDs are left as 0 since they
+/// can't be known, and names that contain a join phrase will be split incorrectly.
+///
+/// ```
+/// use disco_quick::artist_credit::parse_credit_string;
+///
+/// let credits = parse_credit_string("A , B, C & D");
+/// let names: Vec<_> = credits.iter().map(|c| c.name.as_str()).collect();
+/// let joins: Vec<_> = credits.iter().map(|c| c.join.as_deref()).collect();
+/// assert_eq!(names, ["A", "B", "C", "D"]);
+/// assert_eq!(joins, [Some(","), Some(","), Some("&"), None]);
+/// ```
+pub fn parse_credit_string(credit_string: &str) -> Vec<ArtistCredit> {
+    let mut credits = Vec::new();
+    let mut name = String::new();
+    let mut push = |name: &mut String, join: Option<&str>| {
+        if !name.is_empty() {
+            credits.push(ArtistCredit {
+                name: take(name),
+                join: join.map(str::to_string),
+                ..Default::default()
+            })
+        }
+    };
+    for word in credit_string.split_whitespace() {
+        if !name.is_empty() && JOIN_PHRASES.contains(&word) {
+            push(&mut name, Some(word));
+            continue;
+        }
+        let (word, comma) = match word.strip_suffix(',') {
+            Some(word) => (word, true),
+            None => (word, false),
+        };
+        if !word.is_empty() {
+            if !name.is_empty() {
+                name.push(' ');
+            }
+            name.push_str(word);
+        }
+        if comma {
+            push(&mut name, Some(","));
+        }
+    }
+    push(&mut name, None);
+    credits
 }
 
 impl Parser for ArtistCreditParser {
     type Item = ArtistCredit;
     fn new() -> Self {
-        Self {
-            state: ParserState::Artist,
-            current_item: ArtistCredit::default(),
-            item_ready: false,
-        }
+        Self::default()
     }
 
     fn take(&mut self) -> ArtistCredit {
         self.item_ready = false;
         take(&mut self.current_item)
     }
+
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
             ParserState::Artist => match ev {
-                Event::Start(e) => match e.local_name().as_ref() {
-                    b"artist" => ParserState::Artist,
-                    b"id" => ParserState::Id,
-                    b"name" => ParserState::Name,
-                    b"anv" => ParserState::Anv,
-                    b"join" => ParserState::Join,
-                    b"role" => ParserState::Role,
-                    b"tracks" => ParserState::Tracks,
+                Event::Start(e) => match Tag::of(e.local_name().as_ref()) {
+                    Some(Tag::Id) => ParserState::Id,
+                    Some(Tag::Name) => ParserState::Name,
+                    Some(Tag::Anv) => ParserState::Anv,
+                    Some(Tag::Join) => ParserState::Join,
+                    Some(Tag::Role) => ParserState::Role,
+                    Some(Tag::Tracks) => ParserState::Tracks,
                     _ => ParserState::Artist,
                 },
                 Event::End(e) if e.local_name().as_ref() == b"artist" => {
@@ -119,7 +296,7 @@ impl Parser for ArtistCreditParser {
 
             ParserState::Role => match ev {
                 Event::Text(e) => {
-                    self.current_item.role = Some(e.unescape()?.to_string());
+                    self.current_item.role = Some(intern(&e.unescape()?));
                     ParserState::Artist
                 }
                 _ => ParserState::Artist,
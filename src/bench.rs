@@ -0,0 +1,98 @@
+//! Measuring how fast dumps are parsed, so applications can notice at startup when
+//! something in their environment slows parsing down, e.g. a slow disk or a debug build.
+//!
+//! ```no_run
+//! use disco_quick::bench::quick_probe;
+//!
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! let probe = quick_probe("releases.xml.gz".as_ref(), 1000)?;
+//! if probe.debug_build || probe.ns_per_record() > 200_000.0 {
+//!     eprintln!("Parsing is slow: {probe}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::parser::Parser;
+use crate::reader::{Checkpoint, DiscogsReader, EntityReader, ReaderError};
+use crate::shared::EntityType;
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The timing of parsing the start of a dump.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbeReport {
+    pub entity: EntityType,
+    /// The number of records parsed, which is less than asked for if the dump is shorter.
+    pub records: u64,
+    /// The bytes of decompressed XML the records took up.
+    pub bytes: u64,
+    /// The time taken to open the dump and parse the records.
+    pub elapsed: Duration,
+    /// Whether the crate was compiled without optimizations, which makes parsing
+    /// many times slower.
+    pub debug_build: bool,
+}
+
+impl ProbeReport {
+    pub fn ns_per_record(&self) -> f64 {
+        if self.records == 0 {
+            return 0.0;
+        }
+        self.elapsed.as_nanos() as f64 / self.records as f64
+    }
+
+    pub fn records_per_sec(&self) -> f64 {
+        self.records as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    /// Megabytes of decompressed XML parsed per second.
+    pub fn mb_per_sec(&self) -> f64 {
+        self.bytes as f64 / 1e6 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+impl fmt::Display for ProbeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} records of {} in {:.1?} ({:.0} ns/record, {:.1} MB/s)",
+            self.records,
+            self.entity,
+            self.elapsed,
+            self.ns_per_record(),
+            self.mb_per_sec(),
+        )?;
+        if self.debug_build {
+            write!(f, " in a debug build")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the first `n` records of a dump and report how long it took.
+pub fn quick_probe(path: &Path, n: u64) -> Result<ProbeReport, ReaderError> {
+    let start = Instant::now();
+    let reader = DiscogsReader::from_path(path)?;
+    let (entity, checkpoint) = match reader {
+        DiscogsReader::Artists(r) => (EntityType::Artist, probe(*r, n)?),
+        DiscogsReader::Labels(r) => (EntityType::Label, probe(*r, n)?),
+        DiscogsReader::Masters(r) => (EntityType::Master, probe(*r, n)?),
+        DiscogsReader::Releases(r) => (EntityType::Release, probe(*r, n)?),
+        DiscogsReader::Custom(_) => return Err(ReaderError::InvalidStartTag),
+    };
+    Ok(ProbeReport {
+        entity,
+        records: checkpoint.records,
+        bytes: checkpoint.offset,
+        elapsed: start.elapsed(),
+        debug_build: cfg!(debug_assertions),
+    })
+}
+
+fn probe<P: Parser>(mut reader: EntityReader<P>, n: u64) -> Result<Checkpoint, ReaderError> {
+    for item in reader.by_ref().take(n as usize) {
+        item?;
+    }
+    Ok(reader.checkpoint())
+}
@@ -0,0 +1,764 @@
+//! Borrowed views of records for consumers that only need transient access.
+//!
+//! The decompressed XML is read into a buffer a chunk at a time, and each record is
+//! parsed once, in place, with the text of its fields borrowing from the buffer
+//! instead of being copied into owned strings. Text is only allocated when it
+//! contains escapes like `&amp;`. The views can't outlive the callback they're passed
+//! to, so copy out anything that needs to be kept.
+//!
+//! Only the text and nested credits, labels and tracks are included. Use the owning
+//! readers, e.g. [`ReleasesReader`](crate::ReleasesReader), for images, videos,
+//! formats, companies and identifiers.
+//!
+//! ```no_run
+//! use disco_quick::borrowed::for_each_release;
+//! use std::collections::HashMap;
+//!
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! let mut countries: HashMap<String, u32> = HashMap::new();
+//! for_each_release("releases.xml.gz".as_ref(), |release| {
+//!     if let Some(count) = countries.get_mut(release.country.as_ref()) {
+//!         *count += 1;
+//!     } else {
+//!         countries.insert(release.country.into_owned(), 1);
+//!     }
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::id::{ArtistId, LabelId, MasterId, ReleaseId};
+use crate::parser::ParserError;
+use crate::prolog::read_start_tag;
+use crate::reader::{get_xml_reader, ReaderError};
+use crate::shared::EntityType;
+use quick_xml::events::attributes::Attributes;
+use quick_xml::events::{BytesStart, Event};
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Clone, Debug, Default)]
+pub struct ArtistRef<'a> {
+    pub id: ArtistId,
+    pub name: Cow<'a, str>,
+    pub real_name: Option<Cow<'a, str>>,
+    pub profile: Option<Cow<'a, str>>,
+    pub data_quality: Cow<'a, str>,
+    pub name_variations: Vec<Cow<'a, str>>,
+    pub urls: Vec<Cow<'a, str>>,
+    pub aliases: Vec<InfoRef<'a, ArtistId>>,
+    pub members: Vec<InfoRef<'a, ArtistId>>,
+    pub groups: Vec<InfoRef<'a, ArtistId>>,
+}
+
+/// A reference to another artist or label by ID and name, with an [`ArtistId`] or
+/// a [`LabelId`].
+#[derive(Clone, Debug, Default)]
+pub struct InfoRef<'a, I> {
+    pub id: I,
+    pub name: Cow<'a, str>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LabelRef<'a> {
+    pub id: LabelId,
+    pub name: Cow<'a, str>,
+    pub contactinfo: Option<Cow<'a, str>>,
+    pub profile: Option<Cow<'a, str>>,
+    pub parent_label: Option<InfoRef<'a, LabelId>>,
+    pub sublabels: Vec<InfoRef<'a, LabelId>>,
+    pub urls: Vec<Cow<'a, str>>,
+    pub data_quality: Cow<'a, str>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MasterRef<'a> {
+    pub id: MasterId,
+    pub title: Cow<'a, str>,
+    pub main_release: ReleaseId,
+    pub year: i32,
+    pub notes: Option<Cow<'a, str>>,
+    pub genres: Vec<Cow<'a, str>>,
+    pub styles: Vec<Cow<'a, str>>,
+    pub genres_present: bool,
+    pub styles_present: bool,
+    pub data_quality: Cow<'a, str>,
+    pub artists: Vec<CreditRef<'a>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReleaseRef<'a> {
+    pub id: ReleaseId,
+    pub status: Cow<'a, str>,
+    pub title: Cow<'a, str>,
+    pub artists: Vec<CreditRef<'a>>,
+    pub country: Cow<'a, str>,
+    pub labels: Vec<ReleaseLabelRef<'a>>,
+    pub released: Cow<'a, str>,
+    pub notes: Option<Cow<'a, str>>,
+    pub genres: Vec<Cow<'a, str>>,
+    pub styles: Vec<Cow<'a, str>>,
+    pub genres_present: bool,
+    pub styles_present: bool,
+    pub master_id: Option<MasterId>,
+    pub is_main_release: bool,
+    pub data_quality: Cow<'a, str>,
+    pub extraartists: Vec<CreditRef<'a>>,
+    pub tracklist: Vec<TrackRef<'a>>,
+}
+
+/// A borrowed [`ArtistCredit`](crate::artist_credit::ArtistCredit).
+#[derive(Clone, Debug, Default)]
+pub struct CreditRef<'a> {
+    pub id: ArtistId,
+    pub name: Cow<'a, str>,
+    pub anv: Option<Cow<'a, str>>,
+    pub join: Option<Cow<'a, str>>,
+    pub role: Option<Cow<'a, str>>,
+    pub tracks: Option<Cow<'a, str>>,
+}
+
+/// A borrowed [`ReleaseLabel`](crate::shared::ReleaseLabel).
+#[derive(Clone, Debug, Default)]
+pub struct ReleaseLabelRef<'a> {
+    pub id: LabelId,
+    pub name: Cow<'a, str>,
+    pub catno: Option<Cow<'a, str>>,
+}
+
+/// A borrowed [`Track`](crate::track::Track).
+#[derive(Clone, Debug, Default)]
+pub struct TrackRef<'a> {
+    pub position: Cow<'a, str>,
+    pub title: Cow<'a, str>,
+    pub duration: Option<Cow<'a, str>>,
+    pub artists: Vec<CreditRef<'a>>,
+    pub extraartists: Vec<CreditRef<'a>>,
+    pub sub_tracks: Vec<TrackRef<'a>>,
+}
+
+/// An element that has just been started, and whether it's empty, i.e. `<name/>`.
+struct Element<'a> {
+    start: BytesStart<'a>,
+    empty: bool,
+    /// The content of the start tag, borrowed from the record.
+    raw: &'a str,
+    /// The offset of the `<` of the start tag in the XML being read.
+    offset: usize,
+}
+
+impl<'a> Element<'a> {
+    fn name(&self) -> &[u8] {
+        self.start.local_name().into_inner()
+    }
+
+    /// The value of an attribute, borrowed from the record.
+    fn attr(&self, key: &[u8]) -> Result<Option<Cow<'a, str>>, ParserError> {
+        for attr in Attributes::new(self.raw, self.start.name().as_ref().len()) {
+            let attr = attr?;
+            if attr.key.local_name().as_ref() == key {
+                return Ok(Some(attr.unescape_value()?));
+            }
+        }
+        Ok(None)
+    }
+
+    fn id_attr<T: std::str::FromStr<Err = std::num::ParseIntError>>(
+        &self,
+    ) -> Result<T, ParserError> {
+        Ok(self.attr(b"id")?.ok_or(ParserError::MissingAttr)?.parse()?)
+    }
+}
+
+/// Reads the elements of a single record, which may be followed by others.
+struct Record<'a> {
+    xml: &'a [u8],
+    reader: quick_xml::Reader<&'a [u8]>,
+    /// Whether the end of `xml` was reached, so the record may continue past it.
+    eof: bool,
+}
+
+impl<'a> Record<'a> {
+    fn new(xml: &'a [u8]) -> Self {
+        Self {
+            xml,
+            reader: quick_xml::Reader::from_reader(xml),
+            eof: false,
+        }
+    }
+
+    /// Wrap a start tag that was just read, which ends before `>` or `/>`.
+    fn element(&self, start: BytesStart<'a>, empty: bool) -> Result<Element<'a>, ParserError> {
+        let end = self.reader.buffer_position() - if empty { 2 } else { 1 };
+        let offset = end - start.len() - 1;
+        let raw = &self.xml[offset + 1..end];
+        let raw = std::str::from_utf8(raw).map_err(quick_xml::Error::from)?;
+        Ok(Element {
+            start,
+            empty,
+            raw,
+            offset,
+        })
+    }
+
+    /// Read up to the start of the record's root element, or None if the root
+    /// element of the dump ends first.
+    fn root(&mut self) -> Result<Option<Element<'a>>, ParserError> {
+        // The end tag of the dump's root element wasn't opened by this reader
+        self.reader.check_end_names(false);
+        let root = loop {
+            match self.reader.read_event()? {
+                Event::Start(start) => break Some(self.element(start, false)?),
+                Event::Empty(start) => break Some(self.element(start, true)?),
+                Event::End(_) => break None,
+                Event::Eof => {
+                    self.eof = true;
+                    break None;
+                }
+                _ => continue,
+            }
+        };
+        self.reader.check_end_names(true);
+        Ok(root)
+    }
+
+    /// Whether the record was read without reaching the end of the XML, so what was
+    /// read from it is all of it.
+    fn is_complete(&self) -> bool {
+        !self.eof
+    }
+
+    /// Read the text of an element up to its end tag.
+    fn text(&mut self, el: &Element) -> Result<Cow<'a, str>, ParserError> {
+        let mut text = Cow::Borrowed("");
+        if el.empty {
+            return Ok(text);
+        }
+        loop {
+            match self.reader.read_event()? {
+                Event::Text(e) if text.is_empty() => text = e.unescape()?,
+                Event::Text(e) => text.to_mut().push_str(&e.unescape()?),
+                Event::Start(e) => {
+                    self.reader.read_to_end(e.name())?;
+                }
+                Event::End(_) => return Ok(text),
+                Event::Eof => {
+                    self.eof = true;
+                    return Ok(text);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Like [`text`](Self::text), but None if the element has no text.
+    fn optional_text(&mut self, el: &Element) -> Result<Option<Cow<'a, str>>, ParserError> {
+        Ok(Some(self.text(el)?).filter(|t| !t.is_empty()))
+    }
+
+    /// Call `f` with each child element. It must read the child to its end tag, with
+    /// [`text`](Self::text), [`children`](Self::children) or [`skip`](Self::skip).
+    fn children<F>(&mut self, el: &Element, mut f: F) -> Result<(), ParserError>
+    where
+        F: FnMut(&mut Self, Element<'a>) -> Result<(), ParserError>,
+    {
+        if el.empty {
+            return Ok(());
+        }
+        loop {
+            match self.reader.read_event()? {
+                Event::Start(start) => {
+                    let el = self.element(start, false)?;
+                    f(self, el)?
+                }
+                Event::Empty(start) => {
+                    let el = self.element(start, true)?;
+                    f(self, el)?
+                }
+                Event::End(_) => return Ok(()),
+                Event::Eof => {
+                    self.eof = true;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn skip(&mut self, el: &Element) -> Result<(), ParserError> {
+        if !el.empty {
+            self.reader.read_to_end(el.start.name())?;
+        }
+        Ok(())
+    }
+
+    /// The text of every child element, e.g. the genres in `<genres>`.
+    fn text_list(&mut self, el: &Element) -> Result<Vec<Cow<'a, str>>, ParserError> {
+        let mut list = Vec::new();
+        self.children(el, |r, child| {
+            list.push(r.text(&child)?);
+            Ok(())
+        })?;
+        Ok(list)
+    }
+
+    /// Children like `<name id="1">Name</name>`.
+    fn info_list<I>(&mut self, el: &Element) -> Result<Vec<InfoRef<'a, I>>, ParserError>
+    where
+        I: std::str::FromStr<Err = std::num::ParseIntError>,
+    {
+        let mut list = Vec::new();
+        self.children(el, |r, child| {
+            let id = child.id_attr()?;
+            list.push(InfoRef {
+                id,
+                name: r.text(&child)?,
+            });
+            Ok(())
+        })?;
+        Ok(list)
+    }
+
+    fn credits(&mut self, el: &Element) -> Result<Vec<CreditRef<'a>>, ParserError> {
+        let mut credits = Vec::new();
+        self.children(el, |r, artist| {
+            let mut credit = CreditRef::default();
+            r.children(&artist, |r, field| {
+                match field.name() {
+                    b"id" => credit.id = r.text(&field)?.parse()?,
+                    b"name" => credit.name = r.text(&field)?,
+                    b"anv" => credit.anv = r.optional_text(&field)?,
+                    b"join" => credit.join = r.optional_text(&field)?,
+                    b"role" => credit.role = r.optional_text(&field)?,
+                    b"tracks" => credit.tracks = r.optional_text(&field)?,
+                    _ => r.skip(&field)?,
+                }
+                Ok(())
+            })?;
+            credits.push(credit);
+            Ok(())
+        })?;
+        Ok(credits)
+    }
+
+    fn tracks(&mut self, el: &Element) -> Result<Vec<TrackRef<'a>>, ParserError> {
+        let mut tracks = Vec::new();
+        self.children(el, |r, track_el| {
+            let mut track = TrackRef::default();
+            r.children(&track_el, |r, field| {
+                match field.name() {
+                    b"position" => track.position = r.text(&field)?,
+                    b"title" => track.title = r.text(&field)?,
+                    b"duration" => track.duration = r.optional_text(&field)?,
+                    b"artists" => track.artists = r.credits(&field)?,
+                    b"extraartists" => track.extraartists = r.credits(&field)?,
+                    b"sub_tracks" => track.sub_tracks = r.tracks(&field)?,
+                    _ => r.skip(&field)?,
+                }
+                Ok(())
+            })?;
+            tracks.push(track);
+            Ok(())
+        })?;
+        Ok(tracks)
+    }
+}
+
+impl<'a> ArtistRef<'a> {
+    /// Parse the XML of an `<artist>` record.
+    pub fn parse(xml: &'a [u8]) -> Result<Self, ParserError> {
+        let mut r = Record::new(xml);
+        let root = r.root()?.ok_or_else(missing_record)?;
+        Self::read(&mut r, root)
+    }
+
+    fn read(r: &mut Record<'a>, root: Element<'a>) -> Result<Self, ParserError> {
+        let mut artist = ArtistRef::default();
+        r.children(&root, |r, field| {
+            match field.name() {
+                b"id" => artist.id = r.text(&field)?.parse()?,
+                b"name" => artist.name = r.text(&field)?,
+                b"realname" => artist.real_name = r.optional_text(&field)?,
+                b"profile" => artist.profile = r.optional_text(&field)?,
+                b"data_quality" => artist.data_quality = r.text(&field)?,
+                b"namevariations" => artist.name_variations = r.text_list(&field)?,
+                b"urls" => artist.urls = r.text_list(&field)?,
+                b"aliases" => artist.aliases = r.info_list(&field)?,
+                b"groups" => artist.groups = r.info_list(&field)?,
+                b"members" => r.children(&field, |r, member| {
+                    // The <name> of each member is preceded by a redundant <id>
+                    if member.name() == b"name" {
+                        let id = member.id_attr()?;
+                        let name = r.text(&member)?;
+                        artist.members.push(InfoRef { id, name });
+                        Ok(())
+                    } else {
+                        r.skip(&member)
+                    }
+                })?,
+                _ => r.skip(&field)?,
+            }
+            Ok(())
+        })?;
+        Ok(artist)
+    }
+}
+
+impl<'a> LabelRef<'a> {
+    /// Parse the XML of a `<label>` record.
+    pub fn parse(xml: &'a [u8]) -> Result<Self, ParserError> {
+        let mut r = Record::new(xml);
+        let root = r.root()?.ok_or_else(missing_record)?;
+        Self::read(&mut r, root)
+    }
+
+    fn read(r: &mut Record<'a>, root: Element<'a>) -> Result<Self, ParserError> {
+        let mut label = LabelRef::default();
+        r.children(&root, |r, field| {
+            match field.name() {
+                b"id" => label.id = r.text(&field)?.parse()?,
+                b"name" => label.name = r.text(&field)?,
+                b"contactinfo" => label.contactinfo = r.optional_text(&field)?,
+                b"profile" => label.profile = r.optional_text(&field)?,
+                b"data_quality" => label.data_quality = r.text(&field)?,
+                b"urls" => label.urls = r.text_list(&field)?,
+                b"sublabels" => label.sublabels = r.info_list(&field)?,
+                b"parentLabel" => {
+                    let id = field.id_attr()?;
+                    let name = r.text(&field)?;
+                    label.parent_label = Some(InfoRef { id, name });
+                }
+                _ => r.skip(&field)?,
+            }
+            Ok(())
+        })?;
+        Ok(label)
+    }
+}
+
+impl<'a> MasterRef<'a> {
+    /// Parse the XML of a `<master>` record.
+    pub fn parse(xml: &'a [u8]) -> Result<Self, ParserError> {
+        let mut r = Record::new(xml);
+        let root = r.root()?.ok_or_else(missing_record)?;
+        Self::read(&mut r, root)
+    }
+
+    fn read(r: &mut Record<'a>, root: Element<'a>) -> Result<Self, ParserError> {
+        let mut master = MasterRef {
+            id: root.id_attr()?,
+            ..Default::default()
+        };
+        r.children(&root, |r, field| {
+            match field.name() {
+                b"main_release" => master.main_release = r.text(&field)?.parse()?,
+                b"title" => master.title = r.text(&field)?,
+                b"year" => master.year = r.text(&field)?.parse()?,
+                b"notes" => master.notes = r.optional_text(&field)?,
+                b"data_quality" => master.data_quality = r.text(&field)?,
+                b"genres" => {
+                    master.genres_present = true;
+                    master.genres = r.text_list(&field)?;
+                }
+                b"styles" => {
+                    master.styles_present = true;
+                    master.styles = r.text_list(&field)?;
+                }
+                b"artists" => master.artists = r.credits(&field)?,
+                _ => r.skip(&field)?,
+            }
+            Ok(())
+        })?;
+        Ok(master)
+    }
+}
+
+impl<'a> ReleaseRef<'a> {
+    /// Parse the XML of a `<release>` record.
+    pub fn parse(xml: &'a [u8]) -> Result<Self, ParserError> {
+        let mut r = Record::new(xml);
+        let root = r.root()?.ok_or_else(missing_record)?;
+        Self::read(&mut r, root)
+    }
+
+    fn read(r: &mut Record<'a>, root: Element<'a>) -> Result<Self, ParserError> {
+        let mut release = ReleaseRef {
+            id: root.id_attr()?,
+            status: root.attr(b"status")?.unwrap_or_default(),
+            ..Default::default()
+        };
+        r.children(&root, |r, field| {
+            match field.name() {
+                b"title" => release.title = r.text(&field)?,
+                b"country" => release.country = r.text(&field)?,
+                b"released" => release.released = r.text(&field)?,
+                b"notes" => release.notes = r.optional_text(&field)?,
+                b"data_quality" => release.data_quality = r.text(&field)?,
+                b"genres" => {
+                    release.genres_present = true;
+                    release.genres = r.text_list(&field)?;
+                }
+                b"styles" => {
+                    release.styles_present = true;
+                    release.styles = r.text_list(&field)?;
+                }
+                b"master_id" => {
+                    release.is_main_release = match field.attr(b"is_main_release")? {
+                        Some(value) => value.parse()?,
+                        None => false,
+                    };
+                    release.master_id = Some(r.text(&field)?.parse()?);
+                }
+                b"artists" => release.artists = r.credits(&field)?,
+                b"extraartists" => release.extraartists = r.credits(&field)?,
+                b"tracklist" => release.tracklist = r.tracks(&field)?,
+                b"labels" => r.children(&field, |r, label| {
+                    release.labels.push(ReleaseLabelRef {
+                        id: label.id_attr()?,
+                        name: label.attr(b"name")?.unwrap_or_default(),
+                        catno: label.attr(b"catno")?,
+                    });
+                    r.skip(&label)
+                })?,
+                _ => r.skip(&field)?,
+            }
+            Ok(())
+        })?;
+        Ok(release)
+    }
+}
+
+fn missing_record() -> ParserError {
+    quick_xml::Error::UnexpectedEof("record".into()).into()
+}
+
+/// How much of the decompressed XML is read at a time, unless a record is larger.
+const READ_SIZE: usize = 256 * 1024;
+
+/// Reads the records of a dump from a buffer of its decompressed XML, a chunk at a
+/// time. Each record is returned as it is in the dump, byte for byte, from the `<`
+/// of its start tag to the `>` of its end tag.
+///
+/// ```
+/// use disco_quick::borrowed::RecordReader;
+///
+/// # fn main() -> Result<(), disco_quick::ReaderError> {
+/// let record = r#"<label><id>1</id><!-- note --><name>A &amp; B</name ></label>"#;
+/// let path = std::env::temp_dir().join("disco-quick-record-reader.xml");
+/// std::fs::write(&path, format!("<labels>\n{record}\n</labels>"))?;
+/// let mut reader = RecordReader::from_path(&path)?;
+/// assert_eq!(reader.next_record()?, Some(record.as_bytes()));
+/// assert_eq!(reader.next_record()?, None);
+/// # std::fs::remove_file(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RecordReader {
+    input: Box<dyn BufRead>,
+    /// The decompressed XML read so far and not yet discarded.
+    data: Vec<u8>,
+    /// The offset in the decompressed XML of the start of `data`.
+    offset: u64,
+    /// The start of what hasn't been read from `data`.
+    pos: usize,
+    /// Whether all of the input is in `data`.
+    exhausted: bool,
+    entity_type: EntityType,
+    finished: bool,
+}
+
+impl RecordReader {
+    /// Open a dump, decompressing it if it's gzipped, and detect its entity type
+    /// from the root tag.
+    pub fn from_path(path: &Path) -> Result<Self, ReaderError> {
+        let mut reader = get_xml_reader(path)?;
+        let mut buf = Vec::with_capacity(4096);
+        let (start, _) = read_start_tag(&mut reader, &mut buf)?;
+        let tag = String::from_utf8_lossy(start.local_name().into_inner()).into_owned();
+        let entity_type = EntityType::ALL
+            .into_iter()
+            .find(|t| t.root_tag() == tag)
+            .ok_or(ReaderError::InvalidStartTag)?;
+        // The XML reader has consumed exactly the bytes up to the end of the root tag
+        let offset = reader.buffer_position() as u64;
+        Ok(Self {
+            input: reader.into_inner(),
+            data: Vec::with_capacity(READ_SIZE),
+            offset,
+            pos: 0,
+            exhausted: false,
+            entity_type,
+            finished: false,
+        })
+    }
+
+    pub fn entity_type(&self) -> EntityType {
+        self.entity_type
+    }
+
+    /// The offset in the decompressed XML that the next record is read from,
+    /// counting the whitespace before it.
+    pub fn position(&self) -> u64 {
+        self.offset + self.pos as u64
+    }
+
+    /// The XML of the next record, or None at the end of the dump. A dump that ends
+    /// part way through a record is an error.
+    pub fn next_record(&mut self) -> Result<Option<&[u8]>, ReaderError> {
+        match self.next_with(|r, root| r.skip(&root))? {
+            Some((range, ())) => Ok(Some(&self.data[range])),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the next record with `read`, which is given its root element and must
+    /// read it to its end tag. If the record continues past the XML read so far,
+    /// more is read and `read` is called again, so it should only use what it reads
+    /// once the record [`is_complete`](Record::is_complete). Returns the range of
+    /// the record in `data` and the result of `read`.
+    fn next_with<T>(
+        &mut self,
+        mut read: impl for<'a> FnMut(&mut Record<'a>, Element<'a>) -> Result<T, ParserError>,
+    ) -> Result<Option<(Range<usize>, T)>, ReaderError> {
+        if self.finished {
+            return Ok(None);
+        }
+        loop {
+            let xml = &self.data[self.pos..];
+            let mut r = Record::new(xml);
+            let mut started = false;
+            let result = r.root().and_then(|root| match root {
+                Some(root) => {
+                    started = true;
+                    let start = root.offset;
+                    read(&mut r, root).map(|t| Some((start, t)))
+                }
+                None => Ok(None),
+            });
+            let end = r.reader.buffer_position();
+            // A record cut off by the end of `xml` fails to parse if it's cut inside
+            // markup, which then has no `>` after the position of the error
+            let incomplete = match &result {
+                Ok(_) => !r.is_complete(),
+                Err(ParserError::Xml(quick_xml::Error::UnexpectedEof(_))) => true,
+                Err(_) => !r.is_complete() || !xml[end.min(xml.len())..].contains(&b'>'),
+            };
+            if incomplete && !self.exhausted {
+                self.read_more()?;
+                continue;
+            }
+            if incomplete && started {
+                self.finished = true;
+                let tag = self.entity_type.record_tag().to_string();
+                return Err(quick_xml::Error::UnexpectedEof(tag).into());
+            }
+            return match result {
+                Ok(Some((start, t))) => {
+                    let range = self.pos + start..self.pos + end;
+                    self.pos += end;
+                    Ok(Some((range, t)))
+                }
+                Ok(None) => {
+                    self.finished = true;
+                    Ok(None)
+                }
+                Err(e) => {
+                    self.finished = true;
+                    let tag = self.entity_type.record_tag().as_bytes();
+                    Err(e.with_context(None, tag, self.position()).into())
+                }
+            };
+        }
+    }
+
+    /// Discard what's been read from `data` and read at least [`READ_SIZE`] more
+    /// bytes, or as many as are left, doubling that for records larger than it.
+    fn read_more(&mut self) -> Result<(), ReaderError> {
+        self.data.drain(..self.pos);
+        self.offset += self.pos as u64;
+        self.pos = 0;
+        let wanted = self.data.len().max(READ_SIZE);
+        let mut read = 0;
+        while read < wanted {
+            let buf = self.input.fill_buf()?;
+            if buf.is_empty() {
+                self.exhausted = true;
+                break;
+            }
+            let len = buf.len();
+            self.data.extend_from_slice(buf);
+            self.input.consume(len);
+            read += len;
+        }
+        Ok(())
+    }
+
+    fn for_each(
+        mut self,
+        entity_type: EntityType,
+        mut read: impl for<'a> FnMut(&mut Record<'a>, Element<'a>) -> Result<(), ParserError>,
+    ) -> Result<(), ReaderError> {
+        if self.entity_type != entity_type {
+            return Err(ReaderError::InvalidStartTag);
+        }
+        while self.next_with(&mut read)?.is_some() {}
+        Ok(())
+    }
+}
+
+/// Call `f` with every artist in an artists dump.
+pub fn for_each_artist<F>(path: &Path, mut f: F) -> Result<(), ReaderError>
+where
+    F: FnMut(ArtistRef),
+{
+    RecordReader::from_path(path)?.for_each(EntityType::Artist, |r, root| {
+        let artist = ArtistRef::read(r, root)?;
+        if r.is_complete() {
+            f(artist);
+        }
+        Ok(())
+    })
+}
+
+/// Call `f` with every label in a labels dump.
+pub fn for_each_label<F>(path: &Path, mut f: F) -> Result<(), ReaderError>
+where
+    F: FnMut(LabelRef),
+{
+    RecordReader::from_path(path)?.for_each(EntityType::Label, |r, root| {
+        let label = LabelRef::read(r, root)?;
+        if r.is_complete() {
+            f(label);
+        }
+        Ok(())
+    })
+}
+
+/// Call `f` with every master in a masters dump.
+pub fn for_each_master<F>(path: &Path, mut f: F) -> Result<(), ReaderError>
+where
+    F: FnMut(MasterRef),
+{
+    RecordReader::from_path(path)?.for_each(EntityType::Master, |r, root| {
+        let master = MasterRef::read(r, root)?;
+        if r.is_complete() {
+            f(master);
+        }
+        Ok(())
+    })
+}
+
+/// Call `f` with every release in a releases dump.
+pub fn for_each_release<F>(path: &Path, mut f: F) -> Result<(), ReaderError>
+where
+    F: FnMut(ReleaseRef),
+{
+    RecordReader::from_path(path)?.for_each(EntityType::Release, |r, root| {
+        let release = ReleaseRef::read(r, root)?;
+        if r.is_complete() {
+            f(release);
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,341 @@
+//! A binary copy of a parsed dump, so it only has to be parsed once.
+//!
+//! [`Cache::build`] writes two files to the cache directory: the records, each
+//! serialized with bincode after its length, and an index of their offsets sorted by
+//! ID. A [`Cache`] iterates over the records or looks them up by ID without reading
+//! the XML again.
+//!
+//! Both files start with [`FORMAT_VERSION`] and the size and modification time of
+//! the dump they were built from. [`Cache::open`] rebuilds the cache if either
+//! doesn't match, e.g. after the crate's record types change or the dump is replaced.
+//!
+//! ```no_run
+//! use disco_quick::cache::Cache;
+//! use disco_quick::release::Release;
+//!
+//! # fn main() -> Result<(), disco_quick::cache::CacheError> {
+//! let cache: Cache<Release> = Cache::open("releases.xml.gz".as_ref(), "cache".as_ref())?;
+//! if let Some(release) = cache.get(1)? {
+//!     println!("{release}");
+//! }
+//! for release in cache.iter()? {
+//!     let release = release?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::Artist;
+use crate::label::Label;
+use crate::master::Master;
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::release::Release;
+use crate::shared::EntityType;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Error as IoError, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use thiserror::Error;
+
+/// The version of the cache files. It's increased whenever the layout of the files
+/// or the serialized fields of a record type change. The records have an extra field
+/// with the `extra-fields` feature, so its caches have a version of their own.
+pub const FORMAT_VERSION: u32 = if cfg!(feature = "extra-fields") {
+    2 | 1 << 16
+} else {
+    2
+};
+
+const MAGIC: &[u8; 8] = b"DQCACHE\0";
+const HEADER_LEN: u64 = 8 + 4 + 1 + 8 + 8;
+
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error(transparent)]
+    IoError(#[from] IoError),
+    #[error(transparent)]
+    ReaderError(#[from] ReaderError),
+    #[error(transparent)]
+    Bincode(#[from] bincode::Error),
+    #[error("Expected a dump of {expected} but found {found}")]
+    WrongEntity { expected: EntityType, found: String },
+    #[error("The cache is missing, out of date or from a different dump")]
+    Stale,
+}
+
+/// Records that can be cached.
+pub trait Cached: Serialize + DeserializeOwned {
+    const ENTITY: EntityType;
+
+    fn cache_id(&self) -> u32;
+
+    /// The records of the dump, or None if it holds a different entity.
+    fn records(
+        reader: DiscogsReader,
+    ) -> Option<Box<dyn Iterator<Item = Result<Self, ReaderError>>>>;
+}
+
+macro_rules! cached {
+    ($t:ty, $entity:ident, $variant:ident) => {
+        impl Cached for $t {
+            const ENTITY: EntityType = EntityType::$entity;
+
+            fn cache_id(&self) -> u32 {
+                self.id.get()
+            }
+
+            fn records(
+                reader: DiscogsReader,
+            ) -> Option<Box<dyn Iterator<Item = Result<Self, ReaderError>>>> {
+                match reader {
+                    DiscogsReader::$variant(records) => Some(records),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+cached!(Artist, Artist, Artists);
+cached!(Label, Label, Labels);
+cached!(Master, Master, Masters);
+cached!(Release, Release, Releases);
+
+/// The header of both cache files, identifying the dump they were built from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Header {
+    version: u32,
+    entity: u8,
+    source_len: u64,
+    source_modified: u64,
+}
+
+impl Header {
+    fn of_source(entity: EntityType, source: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(source)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Ok(Self {
+            version: FORMAT_VERSION,
+            entity: entity as u8,
+            source_len: metadata.len(),
+            source_modified: modified,
+        })
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&self.version.to_le_bytes())?;
+        out.write_all(&[self.entity])?;
+        out.write_all(&self.source_len.to_le_bytes())?;
+        out.write_all(&self.source_modified.to_le_bytes())
+    }
+
+    /// Read a header, or None if the file doesn't start with one.
+    fn read(input: &mut impl Read) -> io::Result<Option<Self>> {
+        let mut buf = [0; HEADER_LEN as usize];
+        match input.read_exact(&mut buf) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        if &buf[..8] != MAGIC {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            version: u32::from_le_bytes(buf[8..12].try_into().unwrap()),
+            entity: buf[12],
+            source_len: u64::from_le_bytes(buf[13..21].try_into().unwrap()),
+            source_modified: u64::from_le_bytes(buf[21..29].try_into().unwrap()),
+        }))
+    }
+}
+
+/// The parsed records of a dump, stored in a cache directory.
+pub struct Cache<T: Cached> {
+    records_path: PathBuf,
+    index: Vec<(u32, u64)>,
+    records: RefCell<BufReader<File>>,
+    _entity: PhantomData<T>,
+}
+
+impl<T: Cached> Cache<T> {
+    /// Open the cache of the dump at `source`, building it first if it's missing or
+    /// out of date.
+    pub fn open(source: &Path, cache_dir: &Path) -> Result<Self, CacheError> {
+        match Self::open_existing(source, cache_dir) {
+            Err(CacheError::Stale) => Self::build(source, cache_dir),
+            result => result,
+        }
+    }
+
+    /// Open the cache of the dump at `source`, failing with [`CacheError::Stale`]
+    /// instead of building it.
+    pub fn open_existing(source: &Path, cache_dir: &Path) -> Result<Self, CacheError> {
+        let expected = Header::of_source(T::ENTITY, source)?;
+        let (records_path, index_path) = paths(source, cache_dir);
+        let open = |path: &Path| match File::open(path) {
+            Ok(file) => Ok(BufReader::new(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(CacheError::Stale),
+            Err(e) => Err(e.into()),
+        };
+        let mut records = open(&records_path)?;
+        let mut index_file = open(&index_path)?;
+        if Header::read(&mut records)? != Some(expected)
+            || Header::read(&mut index_file)? != Some(expected)
+        {
+            return Err(CacheError::Stale);
+        }
+
+        let mut buf = [0; 8];
+        index_file.read_exact(&mut buf)?;
+        let len = u64::from_le_bytes(buf) as usize;
+        let mut index = Vec::with_capacity(len);
+        let mut entry = [0; 12];
+        for _ in 0..len {
+            index_file.read_exact(&mut entry)?;
+            let id = u32::from_le_bytes(entry[..4].try_into().unwrap());
+            let offset = u64::from_le_bytes(entry[4..].try_into().unwrap());
+            index.push((id, offset));
+        }
+        Ok(Self {
+            records_path,
+            index,
+            records: RefCell::new(records),
+            _entity: PhantomData,
+        })
+    }
+
+    /// Parse the dump at `source` and write its cache to `cache_dir`, which is created
+    /// if it doesn't exist. An existing cache of the dump is replaced.
+    pub fn build(source: &Path, cache_dir: &Path) -> Result<Self, CacheError> {
+        let reader = DiscogsReader::from_path(source)?;
+        let found = reader.to_string();
+        let records = T::records(reader).ok_or(CacheError::WrongEntity {
+            expected: T::ENTITY,
+            found,
+        })?;
+        fs::create_dir_all(cache_dir)?;
+        let header = Header::of_source(T::ENTITY, source)?;
+        let (records_path, index_path) = paths(source, cache_dir);
+
+        // Write to temporary files so an interrupted build never looks complete
+        let records_tmp = records_path.with_extension("records.tmp");
+        let mut out = BufWriter::new(File::create(&records_tmp)?);
+        header.write(&mut out)?;
+        let mut offset = HEADER_LEN;
+        let mut index = Vec::new();
+        let mut buf = Vec::new();
+        for record in records {
+            let record = record?;
+            buf.clear();
+            bincode::serialize_into(&mut buf, &record)?;
+            out.write_all(&(buf.len() as u32).to_le_bytes())?;
+            out.write_all(&buf)?;
+            index.push((record.cache_id(), offset));
+            offset += 4 + buf.len() as u64;
+        }
+        out.flush()?;
+        drop(out);
+        // Only the first record of an ID can be looked up
+        index.sort_by_key(|(id, _)| *id);
+        index.dedup_by_key(|(id, _)| *id);
+
+        let index_tmp = index_path.with_extension("index.tmp");
+        let mut out = BufWriter::new(File::create(&index_tmp)?);
+        header.write(&mut out)?;
+        out.write_all(&(index.len() as u64).to_le_bytes())?;
+        for (id, offset) in &index {
+            out.write_all(&id.to_le_bytes())?;
+            out.write_all(&offset.to_le_bytes())?;
+        }
+        out.flush()?;
+        drop(out);
+
+        fs::rename(records_tmp, &records_path)?;
+        fs::rename(index_tmp, &index_path)?;
+        Self::open_existing(source, cache_dir)
+    }
+
+    /// The number of records.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// The IDs of the records, in ascending order.
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.index.iter().map(|(id, _)| *id)
+    }
+
+    /// The record with `id`, or None if there isn't one.
+    pub fn get(&self, id: u32) -> Result<Option<T>, CacheError> {
+        let Ok(i) = self.index.binary_search_by_key(&id, |(id, _)| *id) else {
+            return Ok(None);
+        };
+        let mut records = self.records.borrow_mut();
+        records.seek(SeekFrom::Start(self.index[i].1))?;
+        read_record(&mut *records, &mut Vec::new())
+    }
+
+    /// Iterate over the records in the order of the dump.
+    pub fn iter(&self) -> Result<CacheIter<T>, CacheError> {
+        let mut reader = BufReader::new(File::open(&self.records_path)?);
+        reader.seek(SeekFrom::Start(HEADER_LEN))?;
+        Ok(CacheIter {
+            reader,
+            buf: Vec::new(),
+            _entity: PhantomData,
+        })
+    }
+}
+
+/// Iterator over the records of a [`Cache`].
+pub struct CacheIter<T> {
+    reader: BufReader<File>,
+    buf: Vec<u8>,
+    _entity: PhantomData<T>,
+}
+
+impl<T: Cached> Iterator for CacheIter<T> {
+    type Item = Result<T, CacheError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        read_record(&mut self.reader, &mut self.buf).transpose()
+    }
+}
+
+/// Read a length-prefixed record, or None at the end of the file.
+fn read_record<T: Cached>(
+    input: &mut impl Read,
+    buf: &mut Vec<u8>,
+) -> Result<Option<T>, CacheError> {
+    let mut len = [0; 4];
+    match input.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    buf.resize(u32::from_le_bytes(len) as usize, 0);
+    input.read_exact(buf)?;
+    Ok(Some(bincode::deserialize(buf)?))
+}
+
+/// The paths of the record and index files of a dump's cache.
+fn paths(source: &Path, cache_dir: &Path) -> (PathBuf, PathBuf) {
+    let name = source
+        .file_name()
+        .map_or("dump".into(), |n| n.to_string_lossy());
+    (
+        cache_dir.join(format!("{name}.records")),
+        cache_dir.join(format!("{name}.index")),
+    )
+}
@@ -3,11 +3,12 @@ use crate::shared::ReleaseLabel;
 use quick_xml::events::Event;
 use std::mem::take;
 
+/// Parses a `<company>` element of a release into a [`ReleaseLabel`].
 #[derive(Debug, Default)]
 pub struct CompanyParser {
     state: ParserState,
-    pub current_item: ReleaseLabel,
-    pub item_ready: bool,
+    current_item: ReleaseLabel,
+    item_ready: bool,
 }
 
 #[derive(Debug, Default)]
@@ -31,6 +32,10 @@ impl Parser for CompanyParser {
         self.item_ready = false;
         take(&mut self.current_item)
     }
+
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
             ParserState::Company => match ev {
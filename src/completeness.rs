@@ -0,0 +1,185 @@
+//! Scoring how complete the information of an entity is, for prioritizing curation.
+//!
+//! Each score is the weighted percentage of fields that are filled in, from 0 to 100.
+use crate::artist::Artist;
+use crate::label::Label;
+use crate::master::Master;
+use crate::release::Release;
+
+fn score(parts: &[(u32, bool)]) -> u8 {
+    // Summed as u64 so that large weights can't overflow
+    let total: u64 = parts.iter().map(|(weight, _)| *weight as u64).sum();
+    if total == 0 {
+        return 0;
+    }
+    let present: u64 = parts
+        .iter()
+        .filter(|(_, p)| *p)
+        .map(|(w, _)| *w as u64)
+        .sum();
+    (present as f64 / total as f64 * 100.0).round() as u8
+}
+
+fn has_text(text: &Option<String>) -> bool {
+    text.as_deref().is_some_and(|t| !t.trim().is_empty())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArtistWeights {
+    pub profile: u32,
+    pub images: u32,
+    pub urls: u32,
+    pub real_name: u32,
+    pub aliases: u32,
+    pub name_variations: u32,
+}
+
+impl Default for ArtistWeights {
+    fn default() -> Self {
+        Self {
+            profile: 30,
+            images: 25,
+            urls: 15,
+            real_name: 10,
+            aliases: 10,
+            name_variations: 10,
+        }
+    }
+}
+
+impl Artist {
+    pub fn completeness_score(&self) -> u8 {
+        self.completeness_score_with(&ArtistWeights::default())
+    }
+
+    pub fn completeness_score_with(&self, weights: &ArtistWeights) -> u8 {
+        score(&[
+            (weights.profile, has_text(&self.profile)),
+            (weights.images, !self.images.is_empty()),
+            (weights.urls, !self.urls.is_empty()),
+            (weights.real_name, has_text(&self.real_name)),
+            (weights.aliases, !self.aliases.is_empty()),
+            (weights.name_variations, !self.name_variations.is_empty()),
+        ])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelWeights {
+    pub profile: u32,
+    pub images: u32,
+    pub urls: u32,
+    pub contact_info: u32,
+}
+
+impl Default for LabelWeights {
+    fn default() -> Self {
+        Self {
+            profile: 35,
+            images: 25,
+            urls: 20,
+            contact_info: 20,
+        }
+    }
+}
+
+impl Label {
+    pub fn completeness_score(&self) -> u8 {
+        self.completeness_score_with(&LabelWeights::default())
+    }
+
+    pub fn completeness_score_with(&self, weights: &LabelWeights) -> u8 {
+        score(&[
+            (weights.profile, has_text(&self.profile)),
+            (weights.images, !self.images.is_empty()),
+            (weights.urls, !self.urls.is_empty()),
+            (weights.contact_info, has_text(&self.contactinfo)),
+        ])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MasterWeights {
+    pub images: u32,
+    pub videos: u32,
+    pub notes: u32,
+    pub styles: u32,
+    pub year: u32,
+}
+
+impl Default for MasterWeights {
+    fn default() -> Self {
+        Self {
+            images: 30,
+            videos: 20,
+            notes: 15,
+            styles: 15,
+            year: 20,
+        }
+    }
+}
+
+impl Master {
+    pub fn completeness_score(&self) -> u8 {
+        self.completeness_score_with(&MasterWeights::default())
+    }
+
+    pub fn completeness_score_with(&self, weights: &MasterWeights) -> u8 {
+        score(&[
+            (weights.images, !self.images.is_empty()),
+            (weights.videos, !self.videos.is_empty()),
+            (weights.notes, has_text(&self.notes)),
+            (weights.styles, !self.styles.is_empty()),
+            (weights.year, self.year > 0),
+        ])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReleaseWeights {
+    pub images: u32,
+    pub tracklist: u32,
+    pub released: u32,
+    pub country: u32,
+    pub styles: u32,
+    pub credits: u32,
+    pub identifiers: u32,
+    pub notes: u32,
+    pub videos: u32,
+}
+
+impl Default for ReleaseWeights {
+    fn default() -> Self {
+        Self {
+            images: 20,
+            tracklist: 20,
+            released: 10,
+            country: 5,
+            styles: 10,
+            credits: 15,
+            identifiers: 10,
+            notes: 5,
+            videos: 5,
+        }
+    }
+}
+
+impl Release {
+    pub fn completeness_score(&self) -> u8 {
+        self.completeness_score_with(&ReleaseWeights::default())
+    }
+
+    pub fn completeness_score_with(&self, weights: &ReleaseWeights) -> u8 {
+        score(&[
+            (weights.images, !self.images.is_empty()),
+            (weights.tracklist, !self.tracklist.is_empty()),
+            (weights.released, !self.released.is_empty()),
+            (weights.country, !self.country.is_empty()),
+            (weights.styles, !self.styles.is_empty()),
+            (weights.credits, !self.extraartists.is_empty()),
+            (weights.identifiers, !self.identifiers.is_empty()),
+            (weights.notes, has_text(&self.notes)),
+            (weights.videos, !self.videos.is_empty()),
+        ])
+    }
+}
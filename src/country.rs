@@ -0,0 +1,436 @@
+//! The countries of releases, with their ISO 3166 codes.
+//!
+//! The dumps name countries in English the way Discogs lists them, e.g. `UK`, `US`
+//! or `Germany`, and also use names of countries that no longer exist, like `USSR`,
+//! and regions that releases were issued for, like `Europe` or `UK & Ireland`. A
+//! [`Country`] keeps the name as it is in the dump, and maps it to a code for
+//! consumers that need one, like MusicBrainz.
+//!
+//! ```
+//! use disco_quick::country::{Country, CountryKind};
+//!
+//! let uk = Country::new("UK");
+//! assert_eq!(uk.iso_code(), Some("GB"));
+//! assert_eq!(Country::new("USSR").kind(), CountryKind::Former);
+//! assert!(Country::new("Europe").is_region());
+//! assert_eq!(Country::new("Europe").musicbrainz_code(), Some("XE"));
+//! assert_eq!(Country::new("Kosovo").iso_code(), None);
+//! assert_eq!(Country::new("Kosovo").musicbrainz_code(), Some("XK"));
+//! ```
+use crate::intern::{intern, Symbol};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+/// The countries Discogs lists, by name, with their ISO 3166-1 alpha-2 codes.
+const COUNTRIES: &[(&str, &str)] = &[
+    ("Afghanistan", "AF"),
+    ("Albania", "AL"),
+    ("Algeria", "DZ"),
+    ("American Samoa", "AS"),
+    ("Andorra", "AD"),
+    ("Angola", "AO"),
+    ("Anguilla", "AI"),
+    ("Antigua & Barbuda", "AG"),
+    ("Argentina", "AR"),
+    ("Armenia", "AM"),
+    ("Aruba", "AW"),
+    ("Australia", "AU"),
+    ("Austria", "AT"),
+    ("Azerbaijan", "AZ"),
+    ("Bahamas, The", "BS"),
+    ("Bahrain", "BH"),
+    ("Bangladesh", "BD"),
+    ("Barbados", "BB"),
+    ("Belarus", "BY"),
+    ("Belgium", "BE"),
+    ("Belize", "BZ"),
+    ("Benin", "BJ"),
+    ("Bermuda", "BM"),
+    ("Bhutan", "BT"),
+    ("Bolivia", "BO"),
+    ("Bosnia & Herzegovina", "BA"),
+    ("Botswana", "BW"),
+    ("Brazil", "BR"),
+    ("British Virgin Islands", "VG"),
+    ("Brunei", "BN"),
+    ("Bulgaria", "BG"),
+    ("Burkina Faso", "BF"),
+    ("Burundi", "BI"),
+    ("Cambodia", "KH"),
+    ("Cameroon", "CM"),
+    ("Canada", "CA"),
+    ("Cape Verde", "CV"),
+    ("Cayman Islands", "KY"),
+    ("Central African Republic", "CF"),
+    ("Chad", "TD"),
+    ("Chile", "CL"),
+    ("China", "CN"),
+    ("Colombia", "CO"),
+    ("Comoros", "KM"),
+    ("Congo, Democratic Republic of the", "CD"),
+    ("Congo, Republic of the", "CG"),
+    ("Cook Islands", "CK"),
+    ("Costa Rica", "CR"),
+    ("Croatia", "HR"),
+    ("Cuba", "CU"),
+    ("Curaçao", "CW"),
+    ("Cyprus", "CY"),
+    ("Czech Republic", "CZ"),
+    ("Denmark", "DK"),
+    ("Djibouti", "DJ"),
+    ("Dominica", "DM"),
+    ("Dominican Republic", "DO"),
+    ("Ecuador", "EC"),
+    ("Egypt", "EG"),
+    ("El Salvador", "SV"),
+    ("Equatorial Guinea", "GQ"),
+    ("Eritrea", "ER"),
+    ("Estonia", "EE"),
+    ("Ethiopia", "ET"),
+    ("Faroe Islands", "FO"),
+    ("Fiji", "FJ"),
+    ("Finland", "FI"),
+    ("France", "FR"),
+    ("French Guiana", "GF"),
+    ("French Polynesia", "PF"),
+    ("Gabon", "GA"),
+    ("Gambia, The", "GM"),
+    ("Georgia", "GE"),
+    ("Germany", "DE"),
+    ("Ghana", "GH"),
+    ("Gibraltar", "GI"),
+    ("Greece", "GR"),
+    ("Greenland", "GL"),
+    ("Grenada", "GD"),
+    ("Guadeloupe", "GP"),
+    ("Guam", "GU"),
+    ("Guatemala", "GT"),
+    ("Guernsey", "GG"),
+    ("Guinea", "GN"),
+    ("Guinea-Bissau", "GW"),
+    ("Guyana", "GY"),
+    ("Haiti", "HT"),
+    ("Honduras", "HN"),
+    ("Hong Kong", "HK"),
+    ("Hungary", "HU"),
+    ("Iceland", "IS"),
+    ("India", "IN"),
+    ("Indonesia", "ID"),
+    ("Iran", "IR"),
+    ("Iraq", "IQ"),
+    ("Ireland", "IE"),
+    ("Isle Of Man", "IM"),
+    ("Israel", "IL"),
+    ("Italy", "IT"),
+    ("Ivory Coast", "CI"),
+    ("Jamaica", "JM"),
+    ("Japan", "JP"),
+    ("Jersey", "JE"),
+    ("Jordan", "JO"),
+    ("Kazakhstan", "KZ"),
+    ("Kenya", "KE"),
+    ("Kuwait", "KW"),
+    ("Kyrgyzstan", "KG"),
+    ("Laos", "LA"),
+    ("Latvia", "LV"),
+    ("Lebanon", "LB"),
+    ("Lesotho", "LS"),
+    ("Liberia", "LR"),
+    ("Libya", "LY"),
+    ("Liechtenstein", "LI"),
+    ("Lithuania", "LT"),
+    ("Luxembourg", "LU"),
+    ("Macau", "MO"),
+    ("Madagascar", "MG"),
+    ("Malawi", "MW"),
+    ("Malaysia", "MY"),
+    ("Maldives", "MV"),
+    ("Mali", "ML"),
+    ("Malta", "MT"),
+    ("Martinique", "MQ"),
+    ("Mauritania", "MR"),
+    ("Mauritius", "MU"),
+    ("Mayotte", "YT"),
+    ("Mexico", "MX"),
+    ("Moldova, Republic of", "MD"),
+    ("Monaco", "MC"),
+    ("Mongolia", "MN"),
+    ("Montenegro", "ME"),
+    ("Montserrat", "MS"),
+    ("Morocco", "MA"),
+    ("Mozambique", "MZ"),
+    ("Myanmar", "MM"),
+    ("Namibia", "NA"),
+    ("Nepal", "NP"),
+    ("Netherlands", "NL"),
+    ("New Caledonia", "NC"),
+    ("New Zealand", "NZ"),
+    ("Nicaragua", "NI"),
+    ("Niger", "NE"),
+    ("Nigeria", "NG"),
+    ("North Korea", "KP"),
+    ("North Macedonia", "MK"),
+    ("Macedonia", "MK"),
+    ("Norway", "NO"),
+    ("Oman", "OM"),
+    ("Pakistan", "PK"),
+    ("Palestine", "PS"),
+    ("Panama", "PA"),
+    ("Papua New Guinea", "PG"),
+    ("Paraguay", "PY"),
+    ("Peru", "PE"),
+    ("Philippines", "PH"),
+    ("Poland", "PL"),
+    ("Portugal", "PT"),
+    ("Puerto Rico", "PR"),
+    ("Qatar", "QA"),
+    ("Reunion", "RE"),
+    ("Réunion", "RE"),
+    ("Romania", "RO"),
+    ("Russia", "RU"),
+    ("Rwanda", "RW"),
+    ("Saint Kitts and Nevis", "KN"),
+    ("Saint Lucia", "LC"),
+    ("Saint Vincent and the Grenadines", "VC"),
+    ("Samoa", "WS"),
+    ("San Marino", "SM"),
+    ("Saudi Arabia", "SA"),
+    ("Senegal", "SN"),
+    ("Serbia", "RS"),
+    ("Seychelles", "SC"),
+    ("Sierra Leone", "SL"),
+    ("Singapore", "SG"),
+    ("Slovakia", "SK"),
+    ("Slovenia", "SI"),
+    ("Solomon Islands", "SB"),
+    ("Somalia", "SO"),
+    ("South Africa", "ZA"),
+    ("South Korea", "KR"),
+    ("Spain", "ES"),
+    ("Sri Lanka", "LK"),
+    ("Sudan", "SD"),
+    ("Suriname", "SR"),
+    ("Swaziland", "SZ"),
+    ("Sweden", "SE"),
+    ("Switzerland", "CH"),
+    ("Syria", "SY"),
+    ("Taiwan", "TW"),
+    ("Tajikistan", "TJ"),
+    ("Tanzania", "TZ"),
+    ("Thailand", "TH"),
+    ("Togo", "TG"),
+    ("Tonga", "TO"),
+    ("Trinidad & Tobago", "TT"),
+    ("Tunisia", "TN"),
+    ("Turkey", "TR"),
+    ("Turkmenistan", "TM"),
+    ("Turks and Caicos Islands", "TC"),
+    ("UK", "GB"),
+    ("US", "US"),
+    ("US Virgin Islands", "VI"),
+    ("Uganda", "UG"),
+    ("Ukraine", "UA"),
+    ("United Arab Emirates", "AE"),
+    ("Uruguay", "UY"),
+    ("Uzbekistan", "UZ"),
+    ("Vanuatu", "VU"),
+    ("Vatican City", "VA"),
+    ("Venezuela", "VE"),
+    ("Vietnam", "VN"),
+    ("Yemen", "YE"),
+    ("Zambia", "ZM"),
+    ("Zimbabwe", "ZW"),
+];
+
+/// Countries that no longer exist, with the codes they had in ISO 3166-1 or, for
+/// those that were gone before it, the codes ISO 3166-3 reserves for them.
+const FORMER_COUNTRIES: &[(&str, &str)] = &[
+    ("Burma", "BU"),
+    ("Czechoslovakia", "CS"),
+    ("Dahomey", "DY"),
+    ("German Democratic Republic (GDR)", "DD"),
+    ("Netherlands Antilles", "AN"),
+    ("Rhodesia", "RH"),
+    ("Upper Volta", "HV"),
+    ("USSR", "SU"),
+    ("Yugoslavia", "YU"),
+    ("Zaire", "ZR"),
+];
+
+/// Names without an ISO 3166-1 code of their own. Kosovo isn't in ISO 3166-1, and
+/// the `XK` it's often given is a user-assigned code. Serbia and Montenegro had `CS`,
+/// which is left to Czechoslovakia, which had it before.
+const UNCODED: &[(&str, CountryKind)] = &[
+    ("Kosovo", CountryKind::Country),
+    ("Serbia and Montenegro", CountryKind::Former),
+];
+
+/// The regions Discogs lists. Other names joining several countries with `&` or
+/// commas, e.g. `UK & France`, are taken to be regions too.
+const REGIONS: &[&str] = &[
+    "Africa",
+    "Asia",
+    "Australasia",
+    "Benelux",
+    "Central America",
+    "Europe",
+    "Gulf Cooperation Council",
+    "Middle East",
+    "North America (inc Mexico)",
+    "Scandinavia",
+    "South America",
+    "South East Asia",
+    "South Pacific",
+    "Worldwide",
+];
+
+/// The codes MusicBrainz uses for the regions and countries that have no ISO 3166-1
+/// code of their own.
+const MUSICBRAINZ_CODES: &[(&str, &str)] = &[
+    ("Europe", "XE"),
+    ("Worldwide", "XW"),
+    ("Czechoslovakia", "XC"),
+    ("German Democratic Republic (GDR)", "XG"),
+    ("Kosovo", "XK"),
+    ("Serbia and Montenegro", "XS"),
+];
+
+/// What a country name of the dumps refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CountryKind {
+    /// A country, which has an ISO 3166-1 code except for `Kosovo`.
+    Country,
+    /// A country that no longer exists.
+    Former,
+    /// A region of several countries, e.g. `Europe`.
+    Region,
+    /// An empty name, or `Unknown`.
+    Unknown,
+    /// A name the crate doesn't know.
+    Unrecognized,
+}
+
+/// The country of a release, as named in the dump.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Country(Symbol);
+
+impl Country {
+    pub fn new(name: &str) -> Self {
+        Self(intern(name))
+    }
+
+    /// The name as written in the dump, e.g. `UK`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn kind(&self) -> CountryKind {
+        match lookup(self.as_str()) {
+            Some((_, kind)) => kind,
+            None if matches!(self.as_str().trim(), "" | "Unknown") => CountryKind::Unknown,
+            None if self.as_str().contains(['&', ',']) => CountryKind::Region,
+            None => CountryKind::Unrecognized,
+        }
+    }
+
+    /// The ISO 3166-1 alpha-2 code of the country, e.g. `GB` for `UK`. Former
+    /// countries have the codes they had or that ISO 3166-3 reserves for them, e.g.
+    /// `SU` for `USSR`. Regions and unknown countries have none, and neither do
+    /// `Kosovo` and `Serbia and Montenegro`, whose codes aren't ISO's or its own.
+    pub fn iso_code(&self) -> Option<&'static str> {
+        lookup(self.as_str()).and_then(|(code, _)| code)
+    }
+
+    /// The code of the country in MusicBrainz, which is its ISO code or one of the
+    /// codes MusicBrainz adds, e.g. `XE` for `Europe` and `XW` for `Worldwide`.
+    pub fn musicbrainz_code(&self) -> Option<&'static str> {
+        MUSICBRAINZ_CODES
+            .iter()
+            .find(|(name, _)| *name == self.as_str())
+            .map(|(_, code)| *code)
+            .or_else(|| self.iso_code())
+    }
+
+    pub fn is_region(&self) -> bool {
+        self.kind() == CountryKind::Region
+    }
+
+    pub fn is_former(&self) -> bool {
+        self.kind() == CountryKind::Former
+    }
+}
+
+/// The code and kind of a known name.
+fn lookup(name: &str) -> Option<(Option<&'static str>, CountryKind)> {
+    static NAMES: OnceLock<HashMap<&str, (Option<&str>, CountryKind)>> = OnceLock::new();
+    let names = NAMES.get_or_init(|| {
+        let countries = COUNTRIES
+            .iter()
+            .map(|(name, code)| (*name, (Some(*code), CountryKind::Country)));
+        let former = FORMER_COUNTRIES
+            .iter()
+            .map(|(name, code)| (*name, (Some(*code), CountryKind::Former)));
+        let uncoded = UNCODED.iter().map(|(name, kind)| (*name, (None, *kind)));
+        let regions = REGIONS
+            .iter()
+            .map(|name| (*name, (None, CountryKind::Region)));
+        countries
+            .chain(former)
+            .chain(uncoded)
+            .chain(regions)
+            .collect()
+    });
+    names.get(name.trim()).copied()
+}
+
+impl Deref for Country {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Country {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Country {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for Country {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<Symbol> for Country {
+    fn from(name: Symbol) -> Self {
+        Self(name)
+    }
+}
+
+impl PartialEq<str> for Country {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Country {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
@@ -0,0 +1,79 @@
+use crate::reader::XmlReader;
+use std::any::Any;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// An item produced by a [`CustomReader`]. Downcast it to the concrete type
+/// yielded by the registered parser.
+pub type CustomItem = Box<dyn Any>;
+
+type Factory =
+    Arc<dyn Fn(XmlReader, Vec<u8>) -> Box<dyn Iterator<Item = CustomItem>> + Send + Sync>;
+
+static REGISTRY: OnceLock<RwLock<Vec<(String, Factory)>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<Vec<(String, Factory)>> {
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Register a reader for files whose root element is `tag`, so that
+/// [`DiscogsReader::from_path`](crate::DiscogsReader::from_path) returns a
+/// [`DiscogsReader::Custom`](crate::DiscogsReader::Custom) for them.
+///
+/// The factory receives the XML reader positioned just after the root start tag,
+/// along with the buffer used to read it. Registering a tag that is already
+/// registered replaces the previous factory. The built-in tags can't be overridden.
+pub fn register_reader<F, I>(tag: &str, factory: F)
+where
+    F: Fn(XmlReader, Vec<u8>) -> I + Send + Sync + 'static,
+    I: Iterator<Item = CustomItem> + 'static,
+{
+    let factory: Factory = Arc::new(move |reader, buf| Box::new(factory(reader, buf)));
+    let mut registry = registry().write().unwrap();
+    if let Some(entry) = registry.iter_mut().find(|(t, _)| t == tag) {
+        entry.1 = factory;
+    } else {
+        registry.push((tag.to_string(), factory));
+    }
+}
+
+/// Remove the reader registered for `tag`. Returns false if there wasn't one.
+pub fn unregister_reader(tag: &str) -> bool {
+    let mut registry = registry().write().unwrap();
+    let len = registry.len();
+    registry.retain(|(t, _)| t != tag);
+    registry.len() != len
+}
+
+pub(crate) fn open(tag: &[u8], reader: XmlReader, buf: Vec<u8>) -> Option<CustomReader> {
+    // Called without the lock held, so a factory can register readers itself
+    let (tag, factory) = registry()
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(t, _)| t.as_bytes() == tag)
+        .map(|(tag, factory)| (tag.clone(), Arc::clone(factory)))?;
+    Some(CustomReader {
+        tag,
+        inner: factory(reader, buf),
+    })
+}
+
+/// Iterator over the items of a file handled by a registered custom reader.
+pub struct CustomReader {
+    tag: String,
+    inner: Box<dyn Iterator<Item = CustomItem>>,
+}
+
+impl CustomReader {
+    /// The root element name the reader was registered for.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+}
+
+impl Iterator for CustomReader {
+    type Item = CustomItem;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
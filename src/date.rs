@@ -0,0 +1,101 @@
+//! Parsing of the partial dates used for the `released` field of releases.
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A release date where any part may be unknown, e.g. "1989", "1992-00-00" or "2003-04".
+///
+/// Parsing is lenient: zeroes and out of range parts are treated as unknown, slashes
+/// and dots are accepted as separators, and anything that can't be parsed results in
+/// a date with no known parts. The original string is kept and returned by
+/// [`as_str`](Self::as_str).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReleaseDate {
+    pub year: Option<u16>,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+    raw: String,
+}
+
+impl ReleaseDate {
+    pub fn parse(s: &str) -> Self {
+        let mut date = Self {
+            raw: s.to_string(),
+            ..Default::default()
+        };
+        let s = s.trim();
+        // Compact dates like 19990312
+        if s.len() == 8 && s.bytes().all(|b| b.is_ascii_digit()) {
+            date.set_parts(&s[..4], Some(&s[4..6]), Some(&s[6..]));
+            return date;
+        }
+        let mut parts = s.split(['-', '/', '.']).map(str::trim);
+        if let Some(year) = parts.next() {
+            date.set_parts(year, parts.next(), parts.next());
+        }
+        date
+    }
+
+    fn set_parts(&mut self, year: &str, month: Option<&str>, day: Option<&str>) {
+        self.year = year.parse().ok().filter(|y| (1000..=9999).contains(y));
+        if self.year.is_none() {
+            return;
+        }
+        self.month = month
+            .and_then(|m| m.parse().ok())
+            .filter(|m| (1..=12).contains(m));
+        if self.month.is_none() {
+            return;
+        }
+        self.day = day
+            .and_then(|d| d.parse().ok())
+            .filter(|d| (1..=31).contains(d));
+    }
+
+    /// The string the date was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// Whether at least the year is known.
+    pub fn is_known(&self) -> bool {
+        self.year.is_some()
+    }
+
+    /// Whether the year, month and day are all known.
+    pub fn is_complete(&self) -> bool {
+        self.day.is_some()
+    }
+}
+
+impl From<&str> for ReleaseDate {
+    fn from(s: &str) -> Self {
+        Self::parse(s)
+    }
+}
+
+/// Dates are ordered by year, month and day, with an unknown part sorting before
+/// any known value, so "1999" comes before "1999-03". Dates with the same parts
+/// are ordered by their original string.
+impl Ord for ReleaseDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.year, self.month, self.day, &self.raw).cmp(&(
+            other.year,
+            other.month,
+            other.day,
+            &other.raw,
+        ))
+    }
+}
+
+impl PartialOrd for ReleaseDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for ReleaseDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
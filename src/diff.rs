@@ -0,0 +1,315 @@
+//! The changes between the dumps of two months.
+//!
+//! The records of a dump are in order of ID, so two dumps of the same entity can be
+//! compared by reading them side by side, in constant memory. Each dump is
+//! decompressed on a thread of its own. The result is a stream of [`Change`]s,
+//! which is enough to update a database loaded from the older dump rather than
+//! reloading it.
+//!
+//! Records are compared with [`SemanticEq`], so by default the fields that churn
+//! between dumps without the data changing don't make a record count as changed.
+//! The fields that did change can be found with [`Change::fields`].
+//!
+//! ```no_run
+//! use disco_quick::diff::{compare, Change};
+//!
+//! # fn main() -> Result<(), disco_quick::diff::DiffError> {
+//! let diff = compare(
+//!     "discogs_20240101_labels.xml.gz".as_ref(),
+//!     "discogs_20240201_labels.xml.gz".as_ref(),
+//! )?;
+//! for change in diff {
+//!     match change? {
+//!         Change::Added(label) => println!("+ {label}"),
+//!         Change::Removed(id) => println!("- {id}"),
+//!         Change::Changed { new, .. } => println!("~ {new}"),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+pub mod fields;
+
+use self::fields::FieldDiff;
+use crate::artist::Artist;
+use crate::entity::Entity;
+use crate::label::Label;
+use crate::master::Master;
+use crate::options::ReaderOptions;
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::release::Release;
+use crate::semantic::{SemanticEq, VolatileSet};
+use crate::shared::EntityType;
+use std::iter::Peekable;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DiffError {
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+    #[error("Can't compare a dump of {old} with a dump of {new}")]
+    DifferentEntities { old: String, new: String },
+    #[error("The {which} dump isn't in order of ID: {id} follows {previous}")]
+    OutOfOrder {
+        which: &'static str,
+        id: u32,
+        previous: u32,
+    },
+}
+
+/// A difference between two dumps.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change<T> {
+    /// A record only in the new dump.
+    Added(T),
+    /// The ID of a record only in the old dump.
+    Removed(u32),
+    /// A record in both dumps that differs.
+    Changed { id: u32, old: T, new: T },
+}
+
+impl<T> Change<T> {
+    pub fn id(&self) -> u32
+    where
+        T: Diffable,
+    {
+        match self {
+            Change::Added(record) => record.diff_id(),
+            Change::Removed(id) | Change::Changed { id, .. } => *id,
+        }
+    }
+
+    /// The fields that changed, if the record is in both dumps. All the
+    /// [`Volatile`](crate::semantic::Volatile) fields are ignored, as they are when
+    /// records are compared by a [`Diff`].
+    pub fn fields(&self) -> Option<T::Changes>
+    where
+        T: FieldDiff,
+    {
+        self.fields_with(VolatileSet::all())
+    }
+
+    /// The fields that changed, ignoring the fields in `ignore`.
+    pub fn fields_with(&self, ignore: VolatileSet) -> Option<T::Changes>
+    where
+        T: FieldDiff,
+    {
+        match self {
+            Change::Changed { old, new, .. } => Some(T::field_changes(old, new, ignore)),
+            _ => None,
+        }
+    }
+
+    fn map<U>(self, f: impl Fn(T) -> U) -> Change<U> {
+        match self {
+            Change::Added(record) => Change::Added(f(record)),
+            Change::Removed(id) => Change::Removed(id),
+            Change::Changed { id, old, new } => Change::Changed {
+                id,
+                old: f(old),
+                new: f(new),
+            },
+        }
+    }
+}
+
+/// Records that can be compared across dumps.
+pub trait Diffable: SemanticEq {
+    fn diff_id(&self) -> u32;
+}
+
+macro_rules! diffable {
+    ($($t:ty),*) => {
+        $(impl Diffable for $t {
+            fn diff_id(&self) -> u32 {
+                self.id.get()
+            }
+        })*
+    };
+}
+
+diffable!(Artist, Label, Master, Release);
+
+type Records<T> = Peekable<Box<dyn Iterator<Item = Result<T, ReaderError>>>>;
+
+/// The changes between two sequences of records in order of ID.
+pub struct Diff<T: Diffable> {
+    old: Records<T>,
+    new: Records<T>,
+    ignore: VolatileSet,
+    last_old: Option<u32>,
+    last_new: Option<u32>,
+    finished: bool,
+}
+
+impl<T: Diffable> Diff<T> {
+    /// Compare the records of two readers, e.g. [`ReleasesReader`](crate::ReleasesReader)s.
+    pub fn new(
+        old: impl Iterator<Item = Result<T, ReaderError>> + 'static,
+        new: impl Iterator<Item = Result<T, ReaderError>> + 'static,
+    ) -> Self {
+        let old: Box<dyn Iterator<Item = _>> = Box::new(old);
+        let new: Box<dyn Iterator<Item = _>> = Box::new(new);
+        Self {
+            old: old.peekable(),
+            new: new.peekable(),
+            ignore: VolatileSet::all(),
+            last_old: None,
+            last_new: None,
+            finished: false,
+        }
+    }
+
+    /// Only ignore the volatile fields in `fields` when comparing records. All of
+    /// them are ignored by default.
+    pub fn ignoring(mut self, fields: VolatileSet) -> Self {
+        self.ignore = fields;
+        self
+    }
+
+    /// Take the next record of the old or new dump, checking it's in order.
+    fn take(&mut self, old: bool) -> Result<Option<T>, DiffError> {
+        let (records, last, which) = if old {
+            (&mut self.old, &mut self.last_old, "old")
+        } else {
+            (&mut self.new, &mut self.last_new, "new")
+        };
+        let Some(record) = records.next().transpose()? else {
+            return Ok(None);
+        };
+        let id = record.diff_id();
+        if let Some(previous) = *last {
+            if id <= previous {
+                return Err(DiffError::OutOfOrder {
+                    which,
+                    id,
+                    previous,
+                });
+            }
+        }
+        *last = Some(id);
+        Ok(Some(record))
+    }
+
+    fn next_change(&mut self) -> Result<Option<Change<T>>, DiffError> {
+        loop {
+            let peek_id = |records: &mut Records<T>| match records.peek() {
+                Some(Ok(record)) => Some(Some(record.diff_id())),
+                // Let `take` return the error
+                Some(Err(_)) => Some(None),
+                None => None,
+            };
+            let change = match (peek_id(&mut self.old), peek_id(&mut self.new)) {
+                (None, None) => return Ok(None),
+                (Some(_), None) => self.take(true)?.map(|old| Change::Removed(old.diff_id())),
+                (None, Some(_)) => self.take(false)?.map(Change::Added),
+                (Some(None), _) => self.take(true)?.map(|old| Change::Removed(old.diff_id())),
+                (_, Some(None)) => self.take(false)?.map(Change::Added),
+                (Some(Some(old)), Some(Some(new))) if old < new => {
+                    self.take(true)?.map(|old| Change::Removed(old.diff_id()))
+                }
+                (Some(Some(old)), Some(Some(new))) if new < old => {
+                    self.take(false)?.map(Change::Added)
+                }
+                (Some(Some(id)), Some(Some(_))) => {
+                    let old = self.take(true)?;
+                    let new = self.take(false)?;
+                    match (old, new) {
+                        (Some(old), Some(new)) if !old.semantic_eq_with(&new, self.ignore) => {
+                            Some(Change::Changed { id, old, new })
+                        }
+                        _ => None,
+                    }
+                }
+            };
+            if change.is_some() {
+                return Ok(change);
+            }
+        }
+    }
+}
+
+impl<T: Diffable> Iterator for Diff<T> {
+    type Item = Result<Change<T>, DiffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = self.next_change().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.finished = true;
+        }
+        result
+    }
+}
+
+/// The changes between two dumps of the same entity, from [`compare`].
+pub enum DumpDiff {
+    Artists(Box<Diff<Artist>>),
+    Labels(Box<Diff<Label>>),
+    Masters(Box<Diff<Master>>),
+    Releases(Box<Diff<Release>>),
+}
+
+impl DumpDiff {
+    pub fn entity_type(&self) -> EntityType {
+        match self {
+            DumpDiff::Artists(_) => EntityType::Artist,
+            DumpDiff::Labels(_) => EntityType::Label,
+            DumpDiff::Masters(_) => EntityType::Master,
+            DumpDiff::Releases(_) => EntityType::Release,
+        }
+    }
+
+    /// See [`Diff::ignoring`].
+    pub fn ignoring(self, fields: VolatileSet) -> Self {
+        match self {
+            DumpDiff::Artists(d) => DumpDiff::Artists(Box::new(d.ignoring(fields))),
+            DumpDiff::Labels(d) => DumpDiff::Labels(Box::new(d.ignoring(fields))),
+            DumpDiff::Masters(d) => DumpDiff::Masters(Box::new(d.ignoring(fields))),
+            DumpDiff::Releases(d) => DumpDiff::Releases(Box::new(d.ignoring(fields))),
+        }
+    }
+}
+
+impl Iterator for DumpDiff {
+    type Item = Result<Change<Entity>, DiffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(match self {
+            DumpDiff::Artists(d) => d.next()?.map(|c| c.map(Entity::Artist)),
+            DumpDiff::Labels(d) => d.next()?.map(|c| c.map(Entity::Label)),
+            DumpDiff::Masters(d) => d.next()?.map(|c| c.map(Entity::Master)),
+            DumpDiff::Releases(d) => d.next()?.map(|c| c.map(Entity::Release)),
+        })
+    }
+}
+
+/// Compare the dumps of an entity from two months. Gzipped dumps are decompressed
+/// on separate threads.
+pub fn compare(old_path: &Path, new_path: &Path) -> Result<DumpDiff, DiffError> {
+    let open = |path| DiscogsReader::from_path_with_options(path, ReaderOptions::new().prefetch());
+    let diff = match (open(old_path)?, open(new_path)?) {
+        (DiscogsReader::Artists(old), DiscogsReader::Artists(new)) => {
+            DumpDiff::Artists(Box::new(Diff::new(*old, *new)))
+        }
+        (DiscogsReader::Labels(old), DiscogsReader::Labels(new)) => {
+            DumpDiff::Labels(Box::new(Diff::new(*old, *new)))
+        }
+        (DiscogsReader::Masters(old), DiscogsReader::Masters(new)) => {
+            DumpDiff::Masters(Box::new(Diff::new(*old, *new)))
+        }
+        (DiscogsReader::Releases(old), DiscogsReader::Releases(new)) => {
+            DumpDiff::Releases(Box::new(Diff::new(*old, *new)))
+        }
+        (old, new) => {
+            return Err(DiffError::DifferentEntities {
+                old: old.to_string(),
+                new: new.to_string(),
+            })
+        }
+    };
+    Ok(diff)
+}
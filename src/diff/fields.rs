@@ -0,0 +1,306 @@
+//! The fields that changed between two versions of a record.
+//!
+//! A [`Change::Changed`](super::Change::Changed) has both versions of a record, and
+//! [`Change::fields`](super::Change::fields) compares them field by field. Each
+//! field of the changeset is `None` if it's the same in both, or has the old and new
+//! values if not. The tracklist of a release is compared track by track in a
+//! [`TracklistDiff`], matching tracks by their position.
+//!
+//! ```no_run
+//! use disco_quick::diff::{compare, Change, DumpDiff};
+//!
+//! # fn main() -> Result<(), disco_quick::diff::DiffError> {
+//! let diff = compare(
+//!     "discogs_20240101_releases.xml.gz".as_ref(),
+//!     "discogs_20240201_releases.xml.gz".as_ref(),
+//! )?;
+//! let DumpDiff::Releases(diff) = diff else {
+//!     return Ok(());
+//! };
+//! for change in diff {
+//!     let change = change?;
+//!     if let Some(fields) = change.fields() {
+//!         if let Some((old, new)) = fields.title {
+//!             println!("{}: {old} -> {new}", change.id());
+//!         }
+//!         for (position, track) in &fields.tracklist.changed {
+//!             println!("{}: track {position} changed {:?}", change.id(), track.changed_fields());
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::{Artist, ArtistInfo};
+use crate::artist_credit::ArtistCredit;
+use crate::country::Country;
+use crate::id::{MasterId, ReleaseId};
+use crate::intern::Symbol;
+use crate::label::{Label, LabelInfo};
+use crate::master::Master;
+use crate::release::{Release, ReleaseFormat, ReleaseIdentifier, ReleaseStatus};
+use crate::semantic::{SemanticEq, VolatileSet};
+use crate::shared::{DataQuality, Image, ReleaseLabel};
+use crate::track::Track;
+use crate::video::Video;
+use std::collections::HashMap;
+
+/// The old and new values of a field, or None if it's unchanged.
+pub type FieldChange<T> = Option<(T, T)>;
+
+/// Records that can be compared field by field.
+pub trait FieldDiff: SemanticEq {
+    type Changes;
+
+    /// The fields that differ between `old` and `new`, ignoring the fields in
+    /// `ignore`.
+    fn field_changes(old: &Self, new: &Self, ignore: VolatileSet) -> Self::Changes;
+}
+
+fn field<T: Clone + PartialEq>(
+    old: &T,
+    new: &T,
+    old_cleared: &T,
+    new_cleared: &T,
+) -> FieldChange<T> {
+    (old_cleared != new_cleared).then(|| (old.clone(), new.clone()))
+}
+
+/// Declare the changeset of a record, with a [`FieldChange`] for each field listed.
+/// Fields listed after `diffed` have their own changeset, built by a function.
+macro_rules! changeset {
+    (
+        $(#[$doc:meta])*
+        $name:ident for $record:ty {
+            $($(#[$attr:meta])* $field:ident: $ty:ty),* $(,)?
+        }
+        $(diffed { $($dfield:ident: $dty:ty = $dfn:path),* $(,)? })?
+    ) => {
+        $(#[$doc])*
+        #[derive(Clone, Debug, Default, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $name {
+            $($(#[$attr])* pub $field: FieldChange<$ty>,)*
+            $($(pub $dfield: $dty,)*)?
+        }
+
+        impl $name {
+            fn between(old: &$record, new: &$record, a: &$record, b: &$record) -> Self {
+                Self {
+                    $($(#[$attr])* $field: field(&old.$field, &new.$field, &a.$field, &b.$field),)*
+                    $($($dfield: $dfn(&old.$dfield, &new.$dfield, &a.$dfield, &b.$dfield),)*)?
+                }
+            }
+
+            /// The names of the fields that changed.
+            pub fn changed_fields(&self) -> Vec<&'static str> {
+                let mut fields = Vec::new();
+                $(
+                    $(#[$attr])*
+                    if self.$field.is_some() {
+                        fields.push(stringify!($field));
+                    }
+                )*
+                $($(
+                    if !self.$dfield.is_empty() {
+                        fields.push(stringify!($dfield));
+                    }
+                )*)?
+                fields
+            }
+
+            /// Whether no field changed.
+            pub fn is_empty(&self) -> bool {
+                self.changed_fields().is_empty()
+            }
+        }
+    };
+}
+
+macro_rules! field_diff {
+    ($($record:ty => $changes:ty),*) => {
+        $(impl FieldDiff for $record {
+            type Changes = $changes;
+
+            fn field_changes(old: &Self, new: &Self, ignore: VolatileSet) -> $changes {
+                let (mut a, mut b) = (old.clone(), new.clone());
+                a.clear_volatile(ignore);
+                b.clear_volatile(ignore);
+                <$changes>::between(old, new, &a, &b)
+            }
+        })*
+    };
+}
+
+changeset! {
+    /// The fields of an artist that changed.
+    ArtistChange for Artist {
+        name: String,
+        real_name: Option<String>,
+        profile: Option<String>,
+        data_quality: DataQuality,
+        name_variations: Vec<String>,
+        urls: Vec<String>,
+        aliases: Vec<ArtistInfo>,
+        members: Vec<ArtistInfo>,
+        groups: Vec<ArtistInfo>,
+        images: Vec<Image>,
+        #[cfg(feature = "extra-fields")]
+        extra_fields: std::collections::BTreeMap<String, String>,
+    }
+}
+
+changeset! {
+    /// The fields of a label that changed.
+    LabelChange for Label {
+        name: String,
+        contactinfo: Option<String>,
+        profile: Option<String>,
+        parent_label: Option<LabelInfo>,
+        sublabels: Vec<LabelInfo>,
+        urls: Vec<String>,
+        data_quality: DataQuality,
+        images: Vec<Image>,
+        #[cfg(feature = "extra-fields")]
+        extra_fields: std::collections::BTreeMap<String, String>,
+    }
+}
+
+changeset! {
+    /// The fields of a master that changed.
+    MasterChange for Master {
+        title: String,
+        main_release: ReleaseId,
+        year: i32,
+        notes: Option<String>,
+        genres: Vec<Symbol>,
+        styles: Vec<Symbol>,
+        data_quality: DataQuality,
+        artists: Vec<ArtistCredit>,
+        images: Vec<Image>,
+        videos: Vec<Video>,
+        #[cfg(feature = "extra-fields")]
+        extra_fields: std::collections::BTreeMap<String, String>,
+    }
+}
+
+changeset! {
+    /// The fields of a release that changed.
+    ReleaseChange for Release {
+        status: ReleaseStatus,
+        title: String,
+        artists: Vec<ArtistCredit>,
+        country: Country,
+        labels: Vec<ReleaseLabel>,
+        released: String,
+        notes: Option<String>,
+        genres: Vec<Symbol>,
+        styles: Vec<Symbol>,
+        master_id: Option<MasterId>,
+        is_main_release: bool,
+        data_quality: DataQuality,
+        images: Vec<Image>,
+        videos: Vec<Video>,
+        extraartists: Vec<ArtistCredit>,
+        formats: Vec<ReleaseFormat>,
+        companies: Vec<ReleaseLabel>,
+        identifiers: Vec<ReleaseIdentifier>,
+        #[cfg(feature = "extra-fields")]
+        extra_fields: std::collections::BTreeMap<String, String>,
+    }
+    diffed {
+        tracklist: TracklistDiff = TracklistDiff::between,
+    }
+}
+
+changeset! {
+    /// The fields of a track that changed.
+    TrackChange for Track {
+        title: String,
+        duration: Option<String>,
+        artists: Vec<ArtistCredit>,
+        extraartists: Vec<ArtistCredit>,
+        sub_tracks: Vec<Track>,
+    }
+}
+
+field_diff!(
+    Artist => ArtistChange,
+    Label => LabelChange,
+    Master => MasterChange,
+    Release => ReleaseChange
+);
+
+/// The changes to a tracklist. Tracks are matched by position, and tracks that
+/// share a position, like headings without one, by the order they're in.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TracklistDiff {
+    /// The tracks only in the new tracklist.
+    pub added: Vec<Track>,
+    /// The tracks only in the old tracklist.
+    pub removed: Vec<Track>,
+    /// The positions of the tracks that changed, and their changes.
+    pub changed: Vec<(String, TrackChange)>,
+    /// Whether the tracks in both tracklists are in a different order.
+    pub reordered: bool,
+}
+
+impl TracklistDiff {
+    fn between(old: &[Track], new: &[Track], _: &[Track], _: &[Track]) -> Self {
+        Self::new(old, new)
+    }
+
+    /// Compare two tracklists.
+    pub fn new(old: &[Track], new: &[Track]) -> Self {
+        let mut diff = Self::default();
+        let old_keys = keys(old);
+        let new_keys = keys(new);
+        let by_key: HashMap<_, usize> = old_keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+        let mut matched = vec![false; old.len()];
+        let mut last = None;
+        for (track, key) in new.iter().zip(&new_keys) {
+            let Some(&i) = by_key.get(key) else {
+                diff.added.push(track.clone());
+                continue;
+            };
+            matched[i] = true;
+            if last.is_some_and(|last| i < last) {
+                diff.reordered = true;
+            }
+            last = Some(i);
+            let change = TrackChange::between(&old[i], track, &old[i], track);
+            if !change.is_empty() {
+                diff.changed.push((track.position.clone(), change));
+            }
+        }
+        diff.removed = old
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(track, _)| track.clone())
+            .collect();
+        diff
+    }
+
+    /// Whether the tracklists are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && !self.reordered
+    }
+}
+
+/// The position of each track, and how many tracks before it share it.
+fn keys(tracks: &[Track]) -> Vec<(&str, usize)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    tracks
+        .iter()
+        .map(|track| {
+            let count = seen.entry(track.position.as_str()).or_default();
+            *count += 1;
+            (track.position.as_str(), *count - 1)
+        })
+        .collect()
+}
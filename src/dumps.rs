@@ -0,0 +1,270 @@
+//! Working out which month a dump is from when its file name doesn't say.
+//!
+//! IDs are assigned in order, so the highest ID in a dump bounds when it was
+//! published. The estimate is narrowed further by the fields listed in
+//! [`schema::changes`](crate::schema::changes), which appear or disappear in a
+//! known month.
+//!
+//! [`DiscogsDump`] opens the dumps of a month from a directory. With the `http`
+//! feature, the `download` module finds the dumps that have been published and
+//! downloads them. With the `checksum` feature, `verify` checks a downloaded dump
+//! against its published checksum. With the `catalog` feature, `catalog` compares
+//! the number of records in a dump with an estimate for its month.
+use crate::borrowed::RecordReader;
+use crate::reader::ReaderError;
+use crate::schema::{changes, ChangeKind, DumpDate, SchemaChange};
+use crate::shared::EntityType;
+use quick_xml::events::Event;
+use std::collections::HashSet;
+use std::fmt;
+
+#[cfg(feature = "catalog")]
+pub mod catalog;
+#[cfg(feature = "checksum")]
+pub mod checksum;
+pub mod dir;
+#[cfg(feature = "http")]
+pub mod download;
+
+#[cfg(feature = "checksum")]
+pub use self::checksum::verify;
+pub use self::dir::DiscogsDump;
+
+/// The approximate highest IDs of artists, labels, masters and releases in the
+/// dumps of a month. They only need to be close, as the estimate is the range
+/// between two of them.
+const MAX_IDS: &[(DumpDate, [u32; 4])] = &[
+    (
+        DumpDate::new(2012, 1),
+        [2_600_000, 380_000, 450_000, 3_300_000],
+    ),
+    (
+        DumpDate::new(2014, 1),
+        [3_900_000, 600_000, 680_000, 5_200_000],
+    ),
+    (
+        DumpDate::new(2016, 1),
+        [4_900_000, 870_000, 960_000, 7_800_000],
+    ),
+    (
+        DumpDate::new(2018, 1),
+        [6_100_000, 1_170_000, 1_290_000, 11_300_000],
+    ),
+    (
+        DumpDate::new(2020, 1),
+        [7_550_000, 1_550_000, 1_670_000, 14_600_000],
+    ),
+    (
+        DumpDate::new(2022, 1),
+        [10_300_000, 2_320_000, 2_450_000, 21_500_000],
+    ),
+    (
+        DumpDate::new(2023, 1),
+        [11_700_000, 2_650_000, 2_800_000, 25_800_000],
+    ),
+    (
+        DumpDate::new(2024, 1),
+        [13_300_000, 3_050_000, 3_200_000, 29_600_000],
+    ),
+    (
+        DumpDate::new(2025, 1),
+        [14_800_000, 3_450_000, 3_600_000, 32_800_000],
+    ),
+];
+
+/// The range of months a dump may be from. Either end is None if it's unbounded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonthEstimate {
+    pub entity: EntityType,
+    pub records: u64,
+    pub max_id: u32,
+    pub earliest: Option<DumpDate>,
+    pub latest: Option<DumpDate>,
+    /// The month interpolated from the highest ID, within the range.
+    pub likely: Option<DumpDate>,
+}
+
+impl MonthEstimate {
+    pub fn contains(&self, date: DumpDate) -> bool {
+        self.earliest.is_none_or(|e| e <= date) && self.latest.is_none_or(|l| date <= l)
+    }
+}
+
+impl fmt::Display for MonthEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fmt_date = |d: Option<DumpDate>| d.map_or("?".to_string(), |d| d.to_string());
+        write!(
+            f,
+            "{} from {} to {}",
+            self.entity,
+            fmt_date(self.earliest),
+            fmt_date(self.latest)
+        )?;
+        if let Some(likely) = self.likely {
+            write!(f, ", likely {likely}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Estimate the month of a dump by reading all of it.
+pub fn infer_month(mut reader: RecordReader) -> Result<MonthEstimate, ReaderError> {
+    let entity = reader.entity_type();
+    let markers: Vec<&SchemaChange> = changes().iter().filter(|c| c.entity == entity).collect();
+    let mut wanted = HashSet::new();
+    for marker in &markers {
+        wanted.insert(marker.field.as_bytes());
+        if let Some((parent, _)) = marker.field.rsplit_once('/') {
+            wanted.insert(parent.as_bytes());
+        }
+    }
+
+    let mut seen: HashSet<Vec<u8>> = HashSet::new();
+    let mut records = 0;
+    let mut max_id = 0;
+    let mut path = Vec::new();
+    while let Some(xml) = reader.next_record()? {
+        records += 1;
+        let mut xml_reader = quick_xml::Reader::from_reader(xml);
+        // The length of the path before each open element
+        let mut lengths = Vec::new();
+        let mut id_element = false;
+        path.clear();
+        loop {
+            match xml_reader.read_event()? {
+                Event::Start(e) if lengths.is_empty() => {
+                    lengths.push(0);
+                    if let Some(id) = e.try_get_attribute("id")? {
+                        max_id = max_id.max(id.unescape_value()?.parse().unwrap_or(0));
+                    }
+                }
+                Event::Start(e) => {
+                    lengths.push(path.len());
+                    push_name(&mut path, e.local_name().as_ref());
+                    if wanted.contains(path.as_slice()) && !seen.contains(&path) {
+                        seen.insert(path.clone());
+                    }
+                    id_element = path == b"id";
+                }
+                Event::Empty(e) if !lengths.is_empty() => {
+                    let len = path.len();
+                    push_name(&mut path, e.local_name().as_ref());
+                    if wanted.contains(path.as_slice()) && !seen.contains(&path) {
+                        seen.insert(path.clone());
+                    }
+                    path.truncate(len);
+                }
+                Event::Text(e) if id_element => {
+                    max_id = max_id.max(e.unescape()?.trim().parse().unwrap_or(0));
+                }
+                Event::End(_) => {
+                    id_element = false;
+                    match lengths.pop() {
+                        Some(len) if !lengths.is_empty() => path.truncate(len),
+                        _ => break,
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+    }
+    Ok(estimate(entity, records, max_id, &markers, &seen))
+}
+
+/// Estimate the month of a dump from the paths of all the elements seen in its
+/// records, like `formats/format`, without the root element of each record.
+pub(crate) fn estimate_from_paths(
+    entity: EntityType,
+    records: u64,
+    max_id: u32,
+    seen: &HashSet<Vec<u8>>,
+) -> MonthEstimate {
+    let markers: Vec<&SchemaChange> = changes().iter().filter(|c| c.entity == entity).collect();
+    estimate(entity, records, max_id, &markers, seen)
+}
+
+pub(crate) fn push_name(path: &mut Vec<u8>, name: &[u8]) {
+    if !path.is_empty() {
+        path.push(b'/');
+    }
+    path.extend_from_slice(name);
+}
+
+fn estimate(
+    entity: EntityType,
+    records: u64,
+    max_id: u32,
+    markers: &[&SchemaChange],
+    seen: &HashSet<Vec<u8>>,
+) -> MonthEstimate {
+    let index = EntityType::ALL
+        .iter()
+        .position(|e| *e == entity)
+        .unwrap_or(0);
+    let ids = |i: usize| MAX_IDS[i].1[index];
+    let after = MAX_IDS.iter().position(|(_, max)| max[index] > max_id);
+    let (mut earliest, mut latest) = match after {
+        Some(0) => (None, Some(MAX_IDS[0].0)),
+        Some(i) => (Some(MAX_IDS[i - 1].0), Some(MAX_IDS[i].0)),
+        None => (MAX_IDS.last().map(|(date, _)| *date), None),
+    };
+    let likely = match after {
+        Some(i) if i > 0 => {
+            let (start, end) = (MAX_IDS[i - 1].0.months(), MAX_IDS[i].0.months());
+            let fraction = (max_id - ids(i - 1)) as f64 / (ids(i) - ids(i - 1)) as f64;
+            Some(DumpDate::from_months(
+                start + ((end - start) as f64 * fraction) as u32,
+            ))
+        }
+        _ => None,
+    };
+
+    // The schema markers are exact, so they take precedence over the IDs
+    let (mut schema_earliest, mut schema_latest) = (None, None);
+    for marker in markers {
+        let present = seen.contains(marker.field.as_bytes());
+        let parent_present = match marker.field.rsplit_once('/') {
+            Some((parent, _)) => seen.contains(parent.as_bytes()),
+            None => records > 0,
+        };
+        let (from, until) = match (marker.kind, present, parent_present) {
+            (ChangeKind::Added, true, _) | (ChangeKind::Removed, false, true) => {
+                (Some(marker.since), None)
+            }
+            (ChangeKind::Added, false, true) | (ChangeKind::Removed, true, _) => {
+                (None, Some(marker.since.previous()))
+            }
+            _ => (None, None),
+        };
+        schema_earliest = schema_earliest.max(from);
+        if let Some(until) = until {
+            schema_latest = Some(schema_latest.map_or(until, |l: DumpDate| l.min(until)));
+        }
+    }
+    let conflicts = schema_earliest.is_some_and(|e| latest.is_some_and(|l| l < e))
+        || schema_latest.is_some_and(|l| earliest.is_some_and(|e| l < e));
+    if conflicts {
+        earliest = schema_earliest;
+        latest = schema_latest;
+    } else {
+        earliest = earliest.max(schema_earliest);
+        latest = match (latest, schema_latest) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+    let likely = likely.map(|d| {
+        let d = earliest.map_or(d, |e| d.max(e));
+        latest.map_or(d, |l| d.min(l))
+    });
+    MonthEstimate {
+        entity,
+        records,
+        max_id,
+        earliest,
+        latest,
+        likely,
+    }
+}
@@ -0,0 +1,366 @@
+//! Estimates of the number of records the dumps of past months had, to catch
+//! incomplete dumps.
+//!
+//! A download that was cut short can still be a valid gzip file, and a dump that
+//! Discogs published incomplete parses without errors, so neither is noticed until
+//! the records are missed. The crate ships a table of estimates of the number of
+//! records in the dumps of some months, and [`check_count`] compares the number found
+//! in a dump with the estimate for its month. Months between those in the table are
+//! interpolated, and later months are extrapolated from the last two.
+//!
+//! The estimates weren't counted from the dumps, and can be several percent off:
+//! the 2023-10 dumps had 2,247,215 masters, about 5% more than the estimate
+//! interpolated for that month. So a count is only flagged when it's further from the
+//! estimate than a tolerance, [`DEFAULT_TOLERANCE`] unless given. To replace the
+//! estimates of a month with its counts, count its dumps with [`count_records`] and
+//! edit its row in `data/catalog.csv`.
+//!
+//! ```no_run
+//! use disco_quick::dumps::catalog::check_dump;
+//! use disco_quick::DiscogsDump;
+//!
+//! # fn main() -> Result<(), disco_quick::dumps::dir::DumpDirError> {
+//! let dump = DiscogsDump::open_dir("dumps".as_ref())?;
+//! for check in check_dump(&dump)? {
+//!     if !check.is_plausible() {
+//!         eprintln!("{check}");
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::borrowed::RecordReader;
+use crate::dumps::dir::{DiscogsDump, DumpDirError};
+use crate::reader::ReaderError;
+use crate::schema::{self, DumpDate};
+use crate::shared::EntityType;
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The default fraction of the estimate that a count may differ by.
+pub const DEFAULT_TOLERANCE: f64 = 0.1;
+
+/// The table of estimates, with a header and a row for each month: the month and the
+/// estimated counts of artists, labels, masters and releases, separated by commas.
+const CATALOG: &str = include_str!("../../data/catalog.csv");
+
+/// A feature of the structure of the dumps of a month.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SchemaFlag {
+    /// The members of artists have `id` elements as well as the `id` attribute of
+    /// their `name`.
+    MemberIds,
+}
+
+impl SchemaFlag {
+    pub const ALL: [SchemaFlag; 1] = [SchemaFlag::MemberIds];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MemberIds => "member_ids",
+        }
+    }
+
+    /// The entity and field of the [schema changes](crate::schema::changes) that
+    /// decide whether the dumps of a month have the flag.
+    pub fn field(&self) -> (EntityType, &'static str) {
+        match self {
+            Self::MemberIds => (EntityType::Artist, "members/id"),
+        }
+    }
+
+    /// Whether the dumps of `date` have the flag, which is every month while no
+    /// change to its field is registered.
+    pub fn is_set(&self, date: DumpDate) -> bool {
+        let (entity, field) = self.field();
+        schema::is_field_available(entity, field, date)
+    }
+}
+
+impl fmt::Display for SchemaFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The estimated counts and schema flags of the dumps of a month.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CatalogEntry {
+    pub date: DumpDate,
+    /// The estimated number of artists, labels, masters and releases, in that order.
+    pub estimates: [u64; 4],
+    /// The flags of the month, from [`flags`].
+    pub flags: Vec<SchemaFlag>,
+}
+
+impl CatalogEntry {
+    pub fn estimate(&self, entity: EntityType) -> u64 {
+        self.estimates[entity as usize]
+    }
+
+    pub fn has_flag(&self, flag: SchemaFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+}
+
+/// Where an estimate came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CountSource {
+    /// The month is in the table.
+    Table,
+    /// The month is between two in the table.
+    Interpolated,
+    /// The month is after the last in the table.
+    Extrapolated,
+}
+
+/// The estimated number of records in the dump of a month.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EstimatedCount {
+    pub count: u64,
+    pub source: CountSource,
+}
+
+/// How a count compares with the estimate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CountStatus {
+    /// Within the tolerance of the estimate.
+    Plausible,
+    /// Lower than estimated, as when a dump is truncated.
+    Low,
+    /// Higher than estimated, as when the dump is from a later month.
+    High,
+    /// The month is before the first in the table.
+    Unknown,
+}
+
+/// The number of records found in a dump, compared with the estimate.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CountCheck {
+    pub entity: EntityType,
+    pub date: DumpDate,
+    pub records: u64,
+    pub estimate: Option<EstimatedCount>,
+    pub status: CountStatus,
+}
+
+impl CountCheck {
+    pub fn is_plausible(&self) -> bool {
+        self.status == CountStatus::Plausible
+    }
+
+    /// The difference from the estimate as a fraction of it, e.g. -0.5 for a dump
+    /// with half the records estimated.
+    pub fn deviation(&self) -> Option<f64> {
+        let estimate = self.estimate?.count;
+        (estimate > 0).then(|| (self.records as f64 - estimate as f64) / estimate as f64)
+    }
+}
+
+impl fmt::Display for CountCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {}: {} records",
+            self.entity, self.date, self.records
+        )?;
+        let (Some(estimate), Some(deviation)) = (self.estimate, self.deviation()) else {
+            return write!(f, ", no estimate for the month");
+        };
+        let status = match self.status {
+            CountStatus::Plausible => "close to the estimate",
+            CountStatus::Low => "fewer than estimated",
+            CountStatus::High => "more than estimated",
+            CountStatus::Unknown => "unknown",
+        };
+        write!(
+            f,
+            ", {status} (about {}, {:+.1}%)",
+            estimate.count,
+            deviation * 100.0
+        )
+    }
+}
+
+/// The months in the table, in order.
+///
+/// The table is parsed the first time it's used, so this loads every row of it:
+///
+/// ```
+/// use disco_quick::dumps::catalog::{entries, flags};
+///
+/// let entries = entries();
+/// assert!(entries.windows(2).all(|e| e[0].date < e[1].date));
+/// assert!(entries.iter().all(|e| e.estimates.iter().all(|c| *c > 0)));
+/// assert!(entries.iter().all(|e| e.flags == flags(e.date)));
+/// ```
+pub fn entries() -> &'static [CatalogEntry] {
+    static ENTRIES: OnceLock<Vec<CatalogEntry>> = OnceLock::new();
+    ENTRIES.get_or_init(|| {
+        let mut entries: Vec<_> = CATALOG
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| parse_entry(line).unwrap_or_else(|| panic!("Invalid catalog entry {line}")))
+            .collect();
+        entries.sort_by_key(|e| e.date);
+        entries
+    })
+}
+
+fn parse_entry(line: &str) -> Option<CatalogEntry> {
+    let mut fields = line.split(',');
+    let date = fields.next()?.parse().ok()?;
+    let mut estimates = [0; 4];
+    for estimate in &mut estimates {
+        *estimate = fields.next()?.trim().parse().ok()?;
+    }
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(CatalogEntry {
+        date,
+        estimates,
+        flags: flags(date),
+    })
+}
+
+/// The entry of `date`, if the month is in the table.
+pub fn entry(date: DumpDate) -> Option<&'static CatalogEntry> {
+    entries().iter().find(|e| e.date == date)
+}
+
+/// The schema flags of the dumps of `date`.
+pub fn flags(date: DumpDate) -> Vec<SchemaFlag> {
+    SchemaFlag::ALL
+        .into_iter()
+        .filter(|flag| flag.is_set(date))
+        .collect()
+}
+
+/// The estimated number of records in the dump of `entity` of `date`, or None if
+/// the month is before the first in the table.
+///
+/// ```
+/// use disco_quick::dumps::catalog::{check_count, estimated_count, CountSource};
+/// use disco_quick::schema::DumpDate;
+/// use disco_quick::EntityType;
+///
+/// let date = DumpDate::new(2023, 10);
+/// let estimate = estimated_count(EntityType::Master, date).unwrap();
+/// assert_eq!(estimate.source, CountSource::Interpolated);
+/// // The masters counted in the dump of that month
+/// let check = check_count(EntityType::Master, date, 2_247_215);
+/// assert!(check.is_plausible());
+/// assert!((0.04..0.06).contains(&check.deviation().unwrap()));
+/// ```
+pub fn estimated_count(entity: EntityType, date: DumpDate) -> Option<EstimatedCount> {
+    let entries = entries();
+    let after = entries.partition_point(|e| e.date < date);
+    let (before, next) = match (after.checked_sub(1), entries.get(after)) {
+        (_, Some(next)) if next.date == date => {
+            return Some(EstimatedCount {
+                count: next.estimate(entity),
+                source: CountSource::Table,
+            })
+        }
+        (None, _) => return None,
+        (Some(i), Some(next)) => (&entries[i], next),
+        // Extrapolate from the last two
+        (Some(i), None) => {
+            let last = &entries[i];
+            let count = match i.checked_sub(1).map(|j| &entries[j]) {
+                Some(prev) => interpolate(prev, last, entity, date),
+                None => last.estimate(entity),
+            };
+            return Some(EstimatedCount {
+                count,
+                source: CountSource::Extrapolated,
+            });
+        }
+    };
+    Some(EstimatedCount {
+        count: interpolate(before, next, entity, date),
+        source: CountSource::Interpolated,
+    })
+}
+
+/// The estimate of `date` on the line through the estimates of `a` and `b`.
+fn interpolate(a: &CatalogEntry, b: &CatalogEntry, entity: EntityType, date: DumpDate) -> u64 {
+    let (x0, x1) = (a.date.months() as f64, b.date.months() as f64);
+    let (y0, y1) = (a.estimate(entity) as f64, b.estimate(entity) as f64);
+    let y = y0 + (y1 - y0) * (date.months() as f64 - x0) / (x1 - x0);
+    y.max(0.0).round() as u64
+}
+
+/// Compare the number of records found in the dump of `entity` of `date` with the
+/// estimate, allowing [`DEFAULT_TOLERANCE`].
+pub fn check_count(entity: EntityType, date: DumpDate, records: u64) -> CountCheck {
+    check_count_with(entity, date, records, DEFAULT_TOLERANCE)
+}
+
+/// Compare the number of records found in the dump of `entity` of `date` with the
+/// estimate, allowing them to differ by `tolerance` of the estimate.
+pub fn check_count_with(
+    entity: EntityType,
+    date: DumpDate,
+    records: u64,
+    tolerance: f64,
+) -> CountCheck {
+    let estimate = estimated_count(entity, date);
+    let status = match estimate {
+        None => CountStatus::Unknown,
+        Some(estimate) => {
+            let margin = estimate.count as f64 * tolerance;
+            match records as f64 - estimate.count as f64 {
+                d if d < -margin => CountStatus::Low,
+                d if d > margin => CountStatus::High,
+                _ => CountStatus::Plausible,
+            }
+        }
+    };
+    CountCheck {
+        entity,
+        date,
+        records,
+        estimate,
+        status,
+    }
+}
+
+/// Count the records of the dump at `path` without parsing them.
+pub fn count_records(path: &Path) -> Result<(EntityType, u64), ReaderError> {
+    let mut reader = RecordReader::from_path(path)?;
+    let mut records = 0;
+    while reader.next_record()?.is_some() {
+        records += 1;
+    }
+    Ok((reader.entity_type(), records))
+}
+
+/// Count the records of each dump of `dump` and compare them with the estimates for
+/// its month.
+pub fn check_dump(dump: &DiscogsDump) -> Result<Vec<CountCheck>, DumpDirError> {
+    let mut checks = Vec::new();
+    for entity in dump.entities() {
+        let path = dump
+            .path(entity)
+            .ok_or(DumpDirError::MissingFile(dump.date(), entity))?;
+        let (found, records) = count_records(path)?;
+        if found != entity {
+            return Err(DumpDirError::WrongEntity {
+                expected: entity,
+                found: found.to_string(),
+            });
+        }
+        checks.push(check_count(entity, dump.date(), records));
+    }
+    Ok(checks)
+}
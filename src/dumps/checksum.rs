@@ -0,0 +1,137 @@
+//! Checking dumps against the SHA-256 checksums published with them.
+//!
+//! Each month's dumps come with a `discogs_YYYYMMDD_CHECKSUM.txt` in the format
+//! written by `sha256sum`. Verifying a dump before parsing it finds a corrupt
+//! download in a few seconds, rather than as a gzip error hours into a run.
+//!
+//! ```no_run
+//! use disco_quick::dumps::verify;
+//!
+//! # fn main() -> Result<(), disco_quick::dumps::checksum::ChecksumError> {
+//! verify(
+//!     "discogs_20240101_releases.xml.gz".as_ref(),
+//!     "discogs_20240101_CHECKSUM.txt".as_ref(),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ChecksumError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("{0} isn't listed in the checksum file")]
+    NotListed(String),
+    #[error("{file} is corrupt: its SHA-256 is {actual} rather than {expected}")]
+    Mismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// The SHA-256 checksums of a month's dumps, by file name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checksums {
+    sums: HashMap<String, String>,
+}
+
+impl Checksums {
+    /// Parse lines of a hex checksum followed by a file name, ignoring any others.
+    pub fn parse(text: &str) -> Self {
+        let mut sums = HashMap::new();
+        for line in text.lines() {
+            let Some((sum, name)) = line.trim().split_once(char::is_whitespace) else {
+                continue;
+            };
+            // sha256sum marks files read in binary mode with a *
+            let name = name.trim_start().trim_start_matches('*');
+            if sum.len() == 64 && sum.bytes().all(|b| b.is_ascii_hexdigit()) && !name.is_empty() {
+                sums.insert(name.to_string(), sum.to_ascii_lowercase());
+            }
+        }
+        Self { sums }
+    }
+
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// The checksum of the file named `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.sums.get(name).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sums.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sums.is_empty()
+    }
+
+    /// Check the file at `path` against the checksum listed for its file name.
+    pub fn verify(&self, path: &Path) -> Result<(), ChecksumError> {
+        let name = file_name(path);
+        let expected = self
+            .get(&name)
+            .ok_or_else(|| ChecksumError::NotListed(name.clone()))?;
+        check(&name, expected, &sha256_file(path)?)
+    }
+}
+
+/// Check the dump at `path` against the checksum file published with it.
+pub fn verify(path: &Path, checksum_file: &Path) -> Result<(), ChecksumError> {
+    Checksums::from_path(checksum_file)?.verify(path)
+}
+
+/// The hex SHA-256 of the file at `path`.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    update_from(&mut hasher, File::open(path)?)?;
+    Ok(hex(hasher))
+}
+
+/// Feed everything `reader` produces to `hasher`.
+pub(crate) fn update_from(hasher: &mut Sha256, mut reader: impl Read) -> io::Result<()> {
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+pub(crate) fn hex(hasher: Sha256) -> String {
+    let mut hex = String::with_capacity(64);
+    for b in hasher.finalize() {
+        let _ = write!(hex, "{b:02x}");
+    }
+    hex
+}
+
+pub(crate) fn check(name: &str, expected: &str, actual: &str) -> Result<(), ChecksumError> {
+    if expected.eq_ignore_ascii_case(actual) {
+        return Ok(());
+    }
+    Err(ChecksumError::Mismatch {
+        file: name.to_string(),
+        expected: expected.to_string(),
+        actual: actual.to_string(),
+    })
+}
+
+pub(crate) fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
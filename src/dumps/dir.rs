@@ -0,0 +1,315 @@
+//! Opening the dumps of a month from a directory.
+//!
+//! The dumps are found by their file names as published, e.g.
+//! `discogs_20240101_artists.xml.gz`. Decompressed copies ending in `.xml` are found
+//! too, and preferred over the gzipped files as they're faster to read.
+//!
+//! ```no_run
+//! use disco_quick::DiscogsDump;
+//!
+//! # fn main() -> Result<(), disco_quick::dumps::dir::DumpDirError> {
+//! let dump = DiscogsDump::open_dir("dumps".as_ref())?;
+//! println!("Reading the dumps of {}", dump.date());
+//! for label in dump.labels()? {
+//!     let label = label?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::ArtistsReader;
+use crate::label::LabelsReader;
+use crate::master::MastersReader;
+use crate::options::ReaderOptions;
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::release::ReleasesReader;
+use crate::schema::DumpDate;
+use crate::shared::EntityType;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DumpDirError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+    #[cfg(feature = "checksum")]
+    #[error(transparent)]
+    Checksum(#[from] super::checksum::ChecksumError),
+    #[error("No dumps found in {0}")]
+    NoDumps(PathBuf),
+    #[error("No dumps of {1} found in {0}")]
+    NoDumpsOf(PathBuf, DumpDate),
+    #[error("The {1} dump of {0} is missing")]
+    MissingFile(DumpDate, EntityType),
+    #[error("The checksum file of {0} is missing")]
+    MissingChecksums(DumpDate),
+    #[error("Expected a dump of {expected} but found {found}")]
+    WrongEntity { expected: EntityType, found: String },
+}
+
+/// The dumps of a month found in a directory. Any of them may be missing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscogsDump {
+    date: DumpDate,
+    /// The artists, labels, masters and releases dumps, in that order.
+    paths: [Option<PathBuf>; 4],
+    /// The gzipped dumps as published, which the checksum file lists.
+    gzipped: [Option<PathBuf>; 4],
+    checksums: Option<PathBuf>,
+}
+
+/// A file of the dumps of a month.
+struct DumpFile {
+    date: DumpDate,
+    /// The entity of a dump, or None for the checksum file.
+    entity: Option<EntityType>,
+    /// Lower is preferred when there are several copies of a dump.
+    rank: u8,
+}
+
+impl DiscogsDump {
+    /// Open the dumps in `dir`. If it has the dumps of several months, the latest
+    /// month is opened.
+    pub fn open_dir(dir: &Path) -> Result<Self, DumpDirError> {
+        let date = Self::months_in(dir)?
+            .pop()
+            .ok_or_else(|| DumpDirError::NoDumps(dir.to_path_buf()))?;
+        Self::open_month(dir, date)
+    }
+
+    /// Open the dumps of the month `date` in `dir`.
+    pub fn open_month(dir: &Path, date: DumpDate) -> Result<Self, DumpDirError> {
+        let mut dump = Self {
+            date,
+            paths: Default::default(),
+            gzipped: Default::default(),
+            checksums: None,
+        };
+        let mut ranks = [u8::MAX; 4];
+        let mut found = false;
+        for (path, file) in files(dir)? {
+            if file.date != date {
+                continue;
+            }
+            found = true;
+            let Some(entity) = file.entity else {
+                dump.checksums = Some(path);
+                continue;
+            };
+            let i = index(entity);
+            if file.rank == GZIPPED_RANK {
+                dump.gzipped[i] = Some(path.clone());
+            }
+            if file.rank < ranks[i] {
+                ranks[i] = file.rank;
+                dump.paths[i] = Some(path);
+            }
+        }
+        if !found {
+            return Err(DumpDirError::NoDumpsOf(dir.to_path_buf(), date));
+        }
+        Ok(dump)
+    }
+
+    /// The months with dumps in `dir`, oldest first.
+    pub fn months_in(dir: &Path) -> Result<Vec<DumpDate>, DumpDirError> {
+        let months: BTreeMap<DumpDate, ()> = files(dir)?
+            .into_iter()
+            .filter(|(_, file)| file.entity.is_some())
+            .map(|(_, file)| (file.date, ()))
+            .collect();
+        Ok(months.into_keys().collect())
+    }
+
+    /// The month the dumps are from.
+    pub fn date(&self) -> DumpDate {
+        self.date
+    }
+
+    /// The path of the dump of `entity`, if it was found.
+    pub fn path(&self, entity: EntityType) -> Option<&Path> {
+        self.paths[index(entity)].as_deref()
+    }
+
+    /// The path of the checksum file, if it was found.
+    pub fn checksum_path(&self) -> Option<&Path> {
+        self.checksums.as_deref()
+    }
+
+    /// The entities whose dumps were found.
+    pub fn entities(&self) -> Vec<EntityType> {
+        EntityType::ALL
+            .into_iter()
+            .filter(|e| self.path(*e).is_some())
+            .collect()
+    }
+
+    /// Whether the dumps of all four entities were found.
+    pub fn is_complete(&self) -> bool {
+        self.paths.iter().all(Option::is_some)
+    }
+
+    pub fn reader(&self, entity: EntityType) -> Result<DiscogsReader, DumpDirError> {
+        self.reader_with_options(entity, ReaderOptions::default())
+    }
+
+    pub fn reader_with_options(
+        &self,
+        entity: EntityType,
+        options: ReaderOptions,
+    ) -> Result<DiscogsReader, DumpDirError> {
+        let path = self
+            .path(entity)
+            .ok_or(DumpDirError::MissingFile(self.date, entity))?;
+        let reader = DiscogsReader::from_path_with_options(path, options)?;
+        if reader.entity_type() != Some(entity) {
+            return Err(DumpDirError::WrongEntity {
+                expected: entity,
+                found: reader.to_string(),
+            });
+        }
+        Ok(reader)
+    }
+
+    pub fn artists(&self) -> Result<ArtistsReader, DumpDirError> {
+        match self.reader(EntityType::Artist)? {
+            DiscogsReader::Artists(reader) => Ok(*reader),
+            _ => unreachable!("the reader's entity was checked"),
+        }
+    }
+
+    pub fn labels(&self) -> Result<LabelsReader, DumpDirError> {
+        match self.reader(EntityType::Label)? {
+            DiscogsReader::Labels(reader) => Ok(*reader),
+            _ => unreachable!("the reader's entity was checked"),
+        }
+    }
+
+    pub fn masters(&self) -> Result<MastersReader, DumpDirError> {
+        match self.reader(EntityType::Master)? {
+            DiscogsReader::Masters(reader) => Ok(*reader),
+            _ => unreachable!("the reader's entity was checked"),
+        }
+    }
+
+    pub fn releases(&self) -> Result<ReleasesReader, DumpDirError> {
+        match self.reader(EntityType::Release)? {
+            DiscogsReader::Releases(reader) => Ok(*reader),
+            _ => unreachable!("the reader's entity was checked"),
+        }
+    }
+
+    /// Check the dumps that were found against the checksum file. It lists the
+    /// gzipped dumps, so those are checked even where a decompressed copy is read.
+    /// A dump with only a decompressed copy fails with
+    /// [`ChecksumError::NotListed`](super::checksum::ChecksumError::NotListed).
+    #[cfg(feature = "checksum")]
+    pub fn verify(&self) -> Result<(), DumpDirError> {
+        let path = self
+            .checksums
+            .as_deref()
+            .ok_or(DumpDirError::MissingChecksums(self.date))?;
+        let checksums = super::checksum::Checksums::from_path(path)?;
+        for (path, gzipped) in self.paths.iter().zip(&self.gzipped) {
+            if let Some(path) = gzipped.as_ref().or(path.as_ref()) {
+                checksums.verify(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<super::download::DumpPaths> for DiscogsDump {
+    fn from(paths: super::download::DumpPaths) -> Self {
+        let checksums = paths
+            .artists
+            .parent()
+            .map(|dir| dir.join(format!("discogs_{}_CHECKSUM.txt", stamp(paths.date))))
+            .filter(|path| path.is_file());
+        let date = paths.date;
+        let paths = [
+            Some(paths.artists),
+            Some(paths.labels),
+            Some(paths.masters),
+            Some(paths.releases),
+        ];
+        Self {
+            date,
+            gzipped: paths.clone(),
+            paths,
+            checksums,
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+fn stamp(date: DumpDate) -> String {
+    format!("{:04}{:02}01", date.year, date.month)
+}
+
+/// The rank of the gzipped dumps as published.
+const GZIPPED_RANK: u8 = 1;
+
+fn index(entity: EntityType) -> usize {
+    match entity {
+        EntityType::Artist => 0,
+        EntityType::Label => 1,
+        EntityType::Master => 2,
+        EntityType::Release => 3,
+    }
+}
+
+/// The files in `dir` named like the dumps.
+fn files(dir: &Path) -> io::Result<Vec<(PathBuf, DumpFile)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(file) = name.to_str().and_then(parse_name) else {
+            continue;
+        };
+        if entry.file_type()?.is_file() || entry.path().is_file() {
+            files.push((entry.path(), file));
+        }
+    }
+    Ok(files)
+}
+
+/// The month of a dump from its file name, like `discogs_20240101_artists.xml.gz`.
+pub(crate) fn date_from_name(name: &str) -> Option<DumpDate> {
+    parse_name(name).map(|file| file.date)
+}
+
+/// Parse a name like `discogs_20240101_artists.xml.gz`.
+fn parse_name(name: &str) -> Option<DumpFile> {
+    let rest = name.strip_prefix("discogs_")?;
+    let (stamp, rest) = rest.split_once('_')?;
+    if stamp.len() != 8 || !stamp.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let date = stamp.parse().ok()?;
+    let (kind, extension) = rest.split_once('.')?;
+    if kind == "CHECKSUM" && extension == "txt" {
+        return Some(DumpFile {
+            date,
+            entity: None,
+            rank: 0,
+        });
+    }
+    let entity = EntityType::ALL.into_iter().find(|e| e.root_tag() == kind)?;
+    let rank = match extension {
+        "xml" => 0,
+        "xml.gz" => GZIPPED_RANK,
+        _ => return None,
+    };
+    Some(DumpFile {
+        date,
+        entity: Some(entity),
+        rank,
+    })
+}
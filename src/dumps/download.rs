@@ -0,0 +1,402 @@
+//! Finding and downloading the dumps published in the Discogs S3 bucket.
+//!
+//! ```no_run
+//! use disco_quick::dumps::download::{dump_files, list_months};
+//!
+//! # fn main() -> Result<(), disco_quick::dumps::download::DownloadError> {
+//! let latest = *list_months()?.last().unwrap();
+//! let files = dump_files(latest)?;
+//! let paths = files.download("dumps".as_ref(), |p| eprintln!("{p}"))?;
+//! for reader in paths.readers()? {
+//!     let name = reader.to_string();
+//!     let n = reader.count();
+//!     println!("{name}: {n}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::dumps::checksum::{self, ChecksumError, Checksums};
+use crate::http::{self, HttpReader, DEFAULT_RETRIES};
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::schema::DumpDate;
+use crate::shared::EntityType;
+use log::warn;
+use quick_xml::events::Event;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// The bucket the dumps are published in, which is where data.discogs.com links to.
+pub const BUCKET_URL: &str = "https://discogs-data-dumps.s3.us-west-2.amazonaws.com";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Error, Debug)]
+pub enum DownloadError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+    #[error(transparent)]
+    Checksum(#[from] ChecksumError),
+    #[error("No dumps were published in {0}")]
+    NotFound(DumpDate),
+    #[error("The {1} dump of {0} is missing")]
+    MissingFile(DumpDate, EntityType),
+}
+
+/// The files of the dumps of a month.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DumpFiles {
+    pub date: DumpDate,
+    /// The date in the file names, e.g. "20240101".
+    pub stamp: String,
+    /// The keys of the files in the bucket, including the checksums.
+    pub keys: Vec<String>,
+}
+
+impl DumpFiles {
+    /// The key of the dump of `entity`, if it was published.
+    pub fn key(&self, entity: EntityType) -> Option<&str> {
+        let suffix = format!("_{}.xml.gz", entity.root_tag());
+        self.keys
+            .iter()
+            .find(|k| k.ends_with(&suffix))
+            .map(String::as_str)
+    }
+
+    pub fn url(&self, entity: EntityType) -> Option<String> {
+        self.key(entity).map(|key| format!("{BUCKET_URL}/{key}"))
+    }
+
+    /// The URL of the file of SHA-256 checksums of the dumps.
+    pub fn checksum_url(&self) -> Option<String> {
+        self.keys
+            .iter()
+            .find(|k| k.ends_with("_CHECKSUM.txt"))
+            .map(|key| format!("{BUCKET_URL}/{key}"))
+    }
+
+    /// The published checksums of the dumps, or None if there's no checksum file.
+    pub fn checksums(&self) -> Result<Option<Checksums>, DownloadError> {
+        let Some(url) = self.checksum_url() else {
+            return Ok(None);
+        };
+        let mut text = String::new();
+        HttpReader::open(&url)?.read_to_string(&mut text)?;
+        Ok(Some(Checksums::parse(&text)))
+    }
+
+    /// Stream the dump of `entity` without downloading it.
+    pub fn open(&self, entity: EntityType) -> Result<DiscogsReader, DownloadError> {
+        let url = self
+            .url(entity)
+            .ok_or(DownloadError::MissingFile(self.date, entity))?;
+        Ok(DiscogsReader::from_url(&url)?)
+    }
+
+    /// Download the four dumps to `dir`, continuing any that were partly downloaded.
+    /// Each file is checked against the published checksums as it's downloaded, and
+    /// files that are already there are checked before they're used.
+    pub fn download(
+        &self,
+        dir: &Path,
+        mut on_progress: impl FnMut(&DownloadProgress),
+    ) -> Result<DumpPaths, DownloadError> {
+        fs::create_dir_all(dir)?;
+        let checksums = self.checksums()?;
+        let mut paths = Vec::with_capacity(4);
+        for entity in EntityType::ALL {
+            let key = self
+                .key(entity)
+                .ok_or(DownloadError::MissingFile(self.date, entity))?;
+            let name = key.rsplit('/').next().unwrap_or(key);
+            let path = dir.join(name);
+            let url = format!("{BUCKET_URL}/{key}");
+            match checksums.as_ref().and_then(|c| c.get(name)) {
+                Some(sha256) => download_verified(&url, &path, sha256, &mut on_progress)?,
+                None => download(&url, &path, &mut on_progress)?,
+            }
+            paths.push(path);
+        }
+        let [artists, labels, masters, releases] = <[PathBuf; 4]>::try_from(paths)
+            .unwrap_or_else(|_| unreachable!("a path is added for each entity type"));
+        Ok(DumpPaths {
+            date: self.date,
+            artists,
+            labels,
+            masters,
+            releases,
+        })
+    }
+}
+
+/// The downloaded dumps of a month.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpPaths {
+    pub date: DumpDate,
+    pub artists: PathBuf,
+    pub labels: PathBuf,
+    pub masters: PathBuf,
+    pub releases: PathBuf,
+}
+
+impl DumpPaths {
+    pub fn path(&self, entity: EntityType) -> &Path {
+        match entity {
+            EntityType::Artist => &self.artists,
+            EntityType::Label => &self.labels,
+            EntityType::Master => &self.masters,
+            EntityType::Release => &self.releases,
+        }
+    }
+
+    pub fn reader(&self, entity: EntityType) -> Result<DiscogsReader, ReaderError> {
+        DiscogsReader::from_path(self.path(entity))
+    }
+
+    /// Readers of the artists, labels, masters and releases, in that order.
+    pub fn readers(&self) -> Result<Vec<DiscogsReader>, ReaderError> {
+        EntityType::ALL.iter().map(|e| self.reader(*e)).collect()
+    }
+}
+
+/// How far a download has got, passed to the progress callback about once a second.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadProgress {
+    pub url: String,
+    /// Bytes of the file downloaded, including those downloaded by earlier attempts.
+    pub bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub elapsed: Duration,
+    pub finished: bool,
+}
+
+impl DownloadProgress {
+    pub fn percent(&self) -> Option<f64> {
+        match self.total_bytes {
+            Some(total) if total > 0 => Some(self.bytes as f64 / total as f64 * 100.0),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DownloadProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.url.rsplit('/').next().unwrap_or(&self.url);
+        write!(f, "{name}: {:.1} MB", self.bytes as f64 / 1e6)?;
+        if let Some(percent) = self.percent() {
+            write!(f, " ({percent:.1}%)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The months that have dumps, oldest first.
+pub fn list_months() -> Result<Vec<DumpDate>, DownloadError> {
+    Ok(list_dumps()?.into_iter().map(|d| d.date).collect())
+}
+
+/// The dumps of every month, oldest first.
+pub fn list_dumps() -> Result<Vec<DumpFiles>, DownloadError> {
+    Ok(group_keys(list_keys("data/")?))
+}
+
+/// The dumps of a month.
+pub fn dump_files(date: DumpDate) -> Result<DumpFiles, DownloadError> {
+    let prefix = format!("data/{}/discogs_{}{:02}", date.year, date.year, date.month);
+    group_keys(list_keys(&prefix)?)
+        .into_iter()
+        .next()
+        .ok_or(DownloadError::NotFound(date))
+}
+
+/// Download `url` to `path`. The file is written with a `.part` extension until
+/// it's complete, and a partial file left by an earlier attempt is continued.
+pub fn download(
+    url: &str,
+    path: &Path,
+    on_progress: impl FnMut(&DownloadProgress),
+) -> Result<(), DownloadError> {
+    if path.exists() {
+        return Ok(());
+    }
+    fetch(url, path, None, on_progress)
+}
+
+/// Like [`download`], but the file must have the SHA-256 checksum `sha256`. It's
+/// hashed as it's downloaded, and deleted if it doesn't match. A file that's already
+/// at `path` is hashed and downloaded again if it doesn't match.
+pub fn download_verified(
+    url: &str,
+    path: &Path,
+    sha256: &str,
+    on_progress: impl FnMut(&DownloadProgress),
+) -> Result<(), DownloadError> {
+    if path.exists() {
+        match checksum::check(
+            &checksum::file_name(path),
+            sha256,
+            &checksum::sha256_file(path)?,
+        ) {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                warn!("Downloading {url} again: {e}");
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    fetch(url, path, Some(sha256), on_progress)
+}
+
+fn fetch(
+    url: &str,
+    path: &Path,
+    sha256: Option<&str>,
+    mut on_progress: impl FnMut(&DownloadProgress),
+) -> Result<(), DownloadError> {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let offset = fs::metadata(&part).map_or(0, |m| m.len());
+    let mut hasher = sha256.map(|_| Sha256::new());
+    if let (Some(hasher), true) = (&mut hasher, offset > 0) {
+        checksum::update_from(hasher, File::open(&part)?)?;
+    }
+    let start = Instant::now();
+    let reader = match HttpReader::starting_at(url, offset, DEFAULT_RETRIES) {
+        Ok(reader) => Some(reader),
+        // The partial file is already complete
+        Err(e) if offset > 0 && http::status(&e) == Some(416) => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut progress = DownloadProgress {
+        url: url.to_string(),
+        bytes: offset,
+        total_bytes: reader.as_ref().and_then(|r| r.content_length()),
+        elapsed: Duration::ZERO,
+        finished: false,
+    };
+    if let Some(mut reader) = reader {
+        let mut file = OpenOptions::new().create(true).append(true).open(&part)?;
+        let mut buf = vec![0; 1 << 16];
+        let mut last_progress = Instant::now();
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buf[..n])?;
+            if let Some(hasher) = &mut hasher {
+                hasher.update(&buf[..n]);
+            }
+            progress.bytes += n as u64;
+            if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                progress.elapsed = start.elapsed();
+                on_progress(&progress);
+                last_progress = Instant::now();
+            }
+        }
+        file.sync_all()?;
+    }
+    if let (Some(hasher), Some(expected)) = (hasher, sha256) {
+        if let Err(e) =
+            checksum::check(&checksum::file_name(path), expected, &checksum::hex(hasher))
+        {
+            fs::remove_file(&part)?;
+            return Err(e.into());
+        }
+    }
+    fs::rename(&part, path)?;
+    progress.elapsed = start.elapsed();
+    progress.finished = true;
+    on_progress(&progress);
+    Ok(())
+}
+
+/// The keys in the bucket starting with `prefix`, following the pages of the listing.
+fn list_keys(prefix: &str) -> Result<Vec<String>, DownloadError> {
+    let mut keys = Vec::new();
+    let mut token: Option<String> = None;
+    loop {
+        let mut url = format!("{BUCKET_URL}/?list-type=2&prefix={}", encode(prefix));
+        if let Some(token) = &token {
+            url.push_str("&continuation-token=");
+            url.push_str(&encode(token));
+        }
+        let mut body = String::new();
+        HttpReader::open(&url)?.read_to_string(&mut body)?;
+        token = parse_listing(&body, &mut keys)?;
+        if token.is_none() {
+            return Ok(keys);
+        }
+    }
+}
+
+/// Add the keys of a page of a `ListObjectsV2` response to `keys`, returning the
+/// token of the next page if there is one.
+fn parse_listing(xml: &str, keys: &mut Vec<String>) -> Result<Option<String>, DownloadError> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut element = Vec::new();
+    let mut token = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => element = e.local_name().as_ref().to_vec(),
+            Event::Text(e) => match element.as_slice() {
+                b"Key" => keys.push(e.unescape()?.into_owned()),
+                b"NextContinuationToken" => token = Some(e.unescape()?.into_owned()),
+                _ => {}
+            },
+            Event::End(_) => element.clear(),
+            Event::Eof => return Ok(token),
+            _ => {}
+        }
+    }
+}
+
+/// Group the keys of dump files by the date in their names, e.g.
+/// `data/2024/discogs_20240101_labels.xml.gz`. Other keys are ignored.
+fn group_keys(keys: Vec<String>) -> Vec<DumpFiles> {
+    let mut dumps: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for key in keys {
+        let name = key.rsplit('/').next().unwrap_or(&key);
+        let Some(stamp) = name
+            .strip_prefix("discogs_")
+            .and_then(|rest| rest.split('_').next())
+        else {
+            continue;
+        };
+        if stamp.len() == 8 && stamp.bytes().all(|b| b.is_ascii_digit()) {
+            dumps.entry(stamp.to_string()).or_default().push(key);
+        }
+    }
+    dumps
+        .into_iter()
+        .filter_map(|(stamp, keys)| {
+            let date = stamp.parse().ok()?;
+            Some(DumpFiles { date, stamp, keys })
+        })
+        .collect()
+}
+
+/// Percent-encode a query parameter.
+fn encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            b => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
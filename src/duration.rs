@@ -0,0 +1,72 @@
+//! Parsing of the free-form track durations in the releases dump.
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A track duration parsed from strings like "3:37", "1:02:10", "3.37" or "3'37".
+///
+/// Each part is in units of 60 of the next, so "3.37" is read as 3 minutes and 37
+/// seconds rather than a decimal. Surrounding whitespace and a trailing `"` are
+/// ignored. Out of range parts like the seconds of "3:75" are accepted as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackDuration {
+    secs: u32,
+}
+
+impl TrackDuration {
+    pub const fn from_secs(secs: u32) -> Self {
+        Self { secs }
+    }
+
+    /// Parse a duration, returning None if it's empty or not in a known format.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().trim_end_matches('"').trim_end();
+        if s.is_empty() {
+            return None;
+        }
+        let mut secs = 0u32;
+        for (i, part) in s.split([':', '.', '\'']).enumerate() {
+            let part = part.trim();
+            if i > 2 || part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            secs = secs.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+        }
+        Some(Self { secs })
+    }
+
+    pub fn secs(&self) -> u32 {
+        self.secs
+    }
+
+    pub fn as_duration(&self) -> Duration {
+        Duration::from_secs(self.secs as u64)
+    }
+}
+
+impl From<TrackDuration> for Duration {
+    fn from(duration: TrackDuration) -> Self {
+        duration.as_duration()
+    }
+}
+
+impl FromStr for TrackDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).ok_or_else(|| format!("Invalid duration {s}"))
+    }
+}
+
+/// Formats the duration like the dumps do, e.g. "3:07" or "1:02:10".
+impl fmt::Display for TrackDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (hours, mins, secs) = (self.secs / 3600, self.secs / 60 % 60, self.secs % 60);
+        if hours > 0 {
+            write!(f, "{hours}:{mins:02}:{secs:02}")
+        } else {
+            write!(f, "{mins}:{secs:02}")
+        }
+    }
+}
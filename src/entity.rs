@@ -0,0 +1,142 @@
+//! Handling the records of the four dumps the same way.
+//!
+//! [`DiscogsReader`](crate::DiscogsReader) is an iterator of [`Entity`], so a counter
+//! or loader can be written once instead of for each of its variants. Code that's
+//! generic over the record types can use the [`DiscogsEntity`] trait.
+//!
+//! ```no_run
+//! use disco_quick::entity::Entity;
+//! use disco_quick::DiscogsReader;
+//!
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! for entity in DiscogsReader::from_path("labels.xml.gz".as_ref())? {
+//!     let entity = entity?;
+//!     if let Some(id) = entity.id() {
+//!         println!("{id}: {entity}");
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::Artist;
+use crate::custom::CustomItem;
+use crate::filter::Media;
+use crate::label::Label;
+use crate::master::Master;
+use crate::release::Release;
+use crate::shared::{DataQuality, EntityType};
+use crate::summary::Identified;
+use std::fmt;
+
+/// The fields all four record types have. Their IDs are available through
+/// [`Identified`] and their images through [`Media`].
+pub trait DiscogsEntity: Identified + Media + fmt::Display {
+    fn data_quality(&self) -> &DataQuality;
+
+    fn entity_type(&self) -> EntityType {
+        Self::ENTITY_TYPE
+    }
+
+    /// The name of an artist or label, or the artists and title of a master or release.
+    fn display_title(&self) -> String {
+        self.to_string()
+    }
+}
+
+macro_rules! discogs_entity {
+    ($($t:ident),*) => {
+        $(impl DiscogsEntity for $t {
+            fn data_quality(&self) -> &DataQuality {
+                &self.data_quality
+            }
+        })*
+    };
+}
+
+discogs_entity!(Artist, Label, Master, Release);
+
+/// A record of a dump, or an item of a [custom reader](crate::custom).
+pub enum Entity {
+    Artist(Artist),
+    Label(Label),
+    Master(Master),
+    Release(Release),
+    Custom(CustomItem),
+}
+
+impl Entity {
+    /// The type of the record, or None for custom items.
+    pub fn entity_type(&self) -> Option<EntityType> {
+        match self {
+            Entity::Artist(_) => Some(EntityType::Artist),
+            Entity::Label(_) => Some(EntityType::Label),
+            Entity::Master(_) => Some(EntityType::Master),
+            Entity::Release(_) => Some(EntityType::Release),
+            Entity::Custom(_) => None,
+        }
+    }
+
+    /// The ID of the record, or None for custom items.
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            Entity::Artist(a) => Some(a.id()),
+            Entity::Label(l) => Some(l.id()),
+            Entity::Master(m) => Some(m.id()),
+            Entity::Release(r) => Some(r.id()),
+            Entity::Custom(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entity::Artist(a) => f.debug_tuple("Artist").field(a).finish(),
+            Entity::Label(l) => f.debug_tuple("Label").field(l).finish(),
+            Entity::Master(m) => f.debug_tuple("Master").field(m).finish(),
+            Entity::Release(r) => f.debug_tuple("Release").field(r).finish(),
+            Entity::Custom(_) => f.debug_tuple("Custom").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entity::Artist(a) => a.fmt(f),
+            Entity::Label(l) => l.fmt(f),
+            Entity::Master(m) => m.fmt(f),
+            Entity::Release(r) => r.fmt(f),
+            Entity::Custom(_) => write!(f, "custom item"),
+        }
+    }
+}
+
+/// The records are serialized as they are, without saying which type they are.
+/// Custom items can't be serialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Entity {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Entity::Artist(a) => a.serialize(serializer),
+            Entity::Label(l) => l.serialize(serializer),
+            Entity::Master(m) => m.serialize(serializer),
+            Entity::Release(r) => r.serialize(serializer),
+            Entity::Custom(_) => Err(serde::ser::Error::custom(
+                "custom items can't be serialized",
+            )),
+        }
+    }
+}
+
+macro_rules! from_record {
+    ($($t:ident),*) => {
+        $(impl From<$t> for Entity {
+            fn from(record: $t) -> Self {
+                Entity::$t(record)
+            }
+        })*
+    };
+}
+
+from_record!(Artist, Label, Master, Release);
@@ -0,0 +1,279 @@
+//! An alternative to [`ReleasesReader`] that reports the fields of each release as
+//! typed events, so code that handles a few fields can match on those and ignore
+//! the rest. The releases are still parsed in full.
+use crate::artist_credit::ArtistCredit;
+use crate::country::Country;
+use crate::id::{MasterId, ReleaseId};
+use crate::options::ReaderOptions;
+use crate::prolog::read_start_tag;
+use crate::reader::{get_xml_reader_with_options, ReaderError, XmlReader};
+use crate::release::{Release, ReleaseFormat, ReleaseIdentifier, ReleaseStatus, ReleasesReader};
+use crate::shared::{DataQuality, Image, ReleaseLabel};
+use crate::track::Track;
+use crate::video::Video;
+use std::path::Path;
+
+/// A field of a release, in the order the dumps list them. Every release begins
+/// with [`Start`](Self::Start) and finishes with [`End`](Self::End). Fields that
+/// are empty or missing are left out.
+#[derive(Clone, Copy, Debug)]
+pub enum ReleaseFieldEvent<'a> {
+    Start {
+        id: ReleaseId,
+        status: &'a ReleaseStatus,
+    },
+    Image(&'a Image),
+    Artist(&'a ArtistCredit),
+    Title(&'a str),
+    Label(&'a ReleaseLabel),
+    ExtraArtist(&'a ArtistCredit),
+    Format(&'a ReleaseFormat),
+    /// The release has a `<genres>` element, which may be empty. Its genres follow.
+    Genres,
+    Genre(&'a str),
+    /// The release has a `<styles>` element, which may be empty. Its styles follow.
+    Styles,
+    Style(&'a str),
+    Country(&'a Country),
+    Released(&'a str),
+    Notes(&'a str),
+    DataQuality(&'a DataQuality),
+    MasterId {
+        id: MasterId,
+        is_main_release: bool,
+    },
+    Track(&'a Track),
+    Identifier(&'a ReleaseIdentifier),
+    Video(&'a Video),
+    Company(&'a ReleaseLabel),
+    End {
+        id: ReleaseId,
+    },
+}
+
+/// The next field of the current release to report. Fields of lists hold the index
+/// of their next item.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Field {
+    /// Read the next release.
+    #[default]
+    Next,
+    Start,
+    Image(usize),
+    Artist(usize),
+    Title,
+    Label(usize),
+    ExtraArtist(usize),
+    Format(usize),
+    Genres,
+    Genre(usize),
+    Styles,
+    Style(usize),
+    Country,
+    Released,
+    Notes,
+    DataQuality,
+    MasterId,
+    Track(usize),
+    Identifier(usize),
+    Video(usize),
+    Company(usize),
+    End,
+}
+
+impl Field {
+    /// The first field after those of this kind.
+    fn next_kind(self) -> Self {
+        match self {
+            Field::Next => Field::Start,
+            Field::Start => Field::Image(0),
+            Field::Image(_) => Field::Artist(0),
+            Field::Artist(_) => Field::Title,
+            Field::Title => Field::Label(0),
+            Field::Label(_) => Field::ExtraArtist(0),
+            Field::ExtraArtist(_) => Field::Format(0),
+            Field::Format(_) => Field::Genres,
+            Field::Genres => Field::Genre(0),
+            Field::Genre(_) => Field::Styles,
+            Field::Styles => Field::Style(0),
+            Field::Style(_) => Field::Country,
+            Field::Country => Field::Released,
+            Field::Released => Field::Notes,
+            Field::Notes => Field::DataQuality,
+            Field::DataQuality => Field::MasterId,
+            Field::MasterId => Field::Track(0),
+            Field::Track(_) => Field::Identifier(0),
+            Field::Identifier(_) => Field::Video(0),
+            Field::Video(_) => Field::Company(0),
+            Field::Company(_) => Field::End,
+            Field::End => Field::Next,
+        }
+    }
+
+    /// The next item of a list, or the next kind of field.
+    fn next_item(self) -> Self {
+        match self {
+            Field::Image(i) => Field::Image(i + 1),
+            Field::Artist(i) => Field::Artist(i + 1),
+            Field::Label(i) => Field::Label(i + 1),
+            Field::ExtraArtist(i) => Field::ExtraArtist(i + 1),
+            Field::Format(i) => Field::Format(i + 1),
+            Field::Genre(i) => Field::Genre(i + 1),
+            Field::Style(i) => Field::Style(i + 1),
+            Field::Track(i) => Field::Track(i + 1),
+            Field::Identifier(i) => Field::Identifier(i + 1),
+            Field::Video(i) => Field::Video(i + 1),
+            Field::Company(i) => Field::Company(i + 1),
+            field => field.next_kind(),
+        }
+    }
+
+    /// The event of the field of `release`, or None if it doesn't have it.
+    fn event(self, release: &Release) -> Option<ReleaseFieldEvent<'_>> {
+        Some(match self {
+            Field::Next => return None,
+            Field::Start => ReleaseFieldEvent::Start {
+                id: release.id,
+                status: &release.status,
+            },
+            Field::Image(i) => ReleaseFieldEvent::Image(release.images.get(i)?),
+            Field::Artist(i) => ReleaseFieldEvent::Artist(release.artists.get(i)?),
+            Field::Title => ReleaseFieldEvent::Title(text(&release.title)?),
+            Field::Label(i) => ReleaseFieldEvent::Label(release.labels.get(i)?),
+            Field::ExtraArtist(i) => ReleaseFieldEvent::ExtraArtist(release.extraartists.get(i)?),
+            Field::Format(i) => ReleaseFieldEvent::Format(release.formats.get(i)?),
+            Field::Genres if release.genres_present || !release.genres.is_empty() => {
+                ReleaseFieldEvent::Genres
+            }
+            Field::Genres => return None,
+            Field::Genre(i) => ReleaseFieldEvent::Genre(release.genres.get(i)?),
+            Field::Styles if release.styles_present || !release.styles.is_empty() => {
+                ReleaseFieldEvent::Styles
+            }
+            Field::Styles => return None,
+            Field::Style(i) => ReleaseFieldEvent::Style(release.styles.get(i)?),
+            Field::Country if release.country.is_empty() => return None,
+            Field::Country => ReleaseFieldEvent::Country(&release.country),
+            Field::Released => ReleaseFieldEvent::Released(text(&release.released)?),
+            Field::Notes => ReleaseFieldEvent::Notes(release.notes.as_deref()?),
+            Field::DataQuality => ReleaseFieldEvent::DataQuality(&release.data_quality),
+            Field::MasterId => ReleaseFieldEvent::MasterId {
+                id: release.master_id?,
+                is_main_release: release.is_main_release,
+            },
+            Field::Track(i) => ReleaseFieldEvent::Track(release.tracklist.get(i)?),
+            Field::Identifier(i) => ReleaseFieldEvent::Identifier(release.identifiers.get(i)?),
+            Field::Video(i) => ReleaseFieldEvent::Video(release.videos.get(i)?),
+            Field::Company(i) => ReleaseFieldEvent::Company(release.companies.get(i)?),
+            Field::End => ReleaseFieldEvent::End { id: release.id },
+        })
+    }
+}
+
+fn text(text: &str) -> Option<&str> {
+    Some(text).filter(|t| !t.is_empty())
+}
+
+/// Reads a releases dump as a stream of [`ReleaseFieldEvent`]s.
+///
+/// Each release is parsed by a [`ReleasesReader`], so the fields are the same as
+/// those of the [`Release`]s it yields, including the effects of its
+/// [`ReaderOptions`]. The events of a release are reported once it's been read, in
+/// the order the dump lists its fields.
+///
+/// The events borrow from the reader, so it can't be an [`Iterator`]. Use
+/// [`next_event`](Self::next_event) in a `while let` loop, or [`for_each`](Self::for_each).
+///
+/// ```
+/// use disco_quick::events::{ReleaseEvents, ReleaseFieldEvent};
+/// use disco_quick::release::Release;
+/// use disco_quick::ReleasesReader;
+///
+/// let release = Release {
+///     id: 1.into(),
+///     title: "Title".to_string(),
+///     ..Default::default()
+/// };
+/// let mut titles = Vec::new();
+/// ReleaseEvents::from_releases(ReleasesReader::from_items([release]))
+///     .for_each(|ev| {
+///         if let ReleaseFieldEvent::Title(title) = ev {
+///             titles.push(title.to_string());
+///         }
+///     })
+///     .unwrap();
+/// assert_eq!(titles, ["Title"]);
+/// ```
+pub struct ReleaseEvents {
+    releases: ReleasesReader,
+    release: Release,
+    field: Field,
+}
+
+impl ReleaseEvents {
+    /// Create a reader from an XML reader that's positioned after the root start tag.
+    pub fn new(reader: XmlReader, buf: Vec<u8>) -> Self {
+        Self::with_options(reader, buf, ReaderOptions::default())
+    }
+
+    pub fn with_options(reader: XmlReader, buf: Vec<u8>, options: ReaderOptions) -> Self {
+        Self::from_releases(ReleasesReader::with_options(reader, buf, options))
+    }
+
+    /// Report the fields of the releases of `releases`.
+    pub fn from_releases(releases: ReleasesReader) -> Self {
+        Self {
+            releases,
+            release: Release::default(),
+            field: Field::Next,
+        }
+    }
+
+    /// Open a releases dump, decompressing it if it's gzipped.
+    pub fn from_path(path: &Path) -> Result<Self, ReaderError> {
+        Self::from_path_with_options(path, ReaderOptions::default())
+    }
+
+    pub fn from_path_with_options(
+        path: &Path,
+        options: ReaderOptions,
+    ) -> Result<Self, ReaderError> {
+        let mut reader = get_xml_reader_with_options(path, &options)?;
+        let mut buf = Vec::with_capacity(4096);
+        let (start, _) = read_start_tag(&mut reader, &mut buf)?;
+        if start.local_name().as_ref() != b"releases" {
+            return Err(ReaderError::InvalidStartTag);
+        }
+        Ok(Self::with_options(reader, buf, options))
+    }
+
+    /// Read the next field, or None at the end of the dump.
+    pub fn next_event(&mut self) -> Result<Option<ReleaseFieldEvent<'_>>, ReaderError> {
+        let field = loop {
+            if self.field == Field::Next {
+                match self.releases.next() {
+                    Some(release) => self.release = release?,
+                    None => return Ok(None),
+                }
+            }
+            let field = self.field;
+            if field.event(&self.release).is_some() {
+                self.field = field.next_item();
+                break field;
+            }
+            self.field = field.next_kind();
+        };
+        Ok(field.event(&self.release))
+    }
+
+    /// Call `f` with every field of the dump.
+    pub fn for_each<F>(mut self, mut f: F) -> Result<(), ReaderError>
+    where
+        F: FnMut(ReleaseFieldEvent),
+    {
+        while let Some(ev) = self.next_event()? {
+            f(ev);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+//! Conversion of parsed entities into formats used by other tools.
+pub mod aggregate;
+pub mod csv;
+#[cfg(feature = "jsonl")]
+pub mod jsonl;
+pub mod manifest;
+#[cfg(feature = "jsonl")]
+pub mod migrate;
+pub mod musicbrainz;
+pub mod ordering;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "tokio")]
+pub mod sink;
@@ -0,0 +1,274 @@
+//! Counts from the dumps that are safe to publish.
+//!
+//! A count of 1 or 2 in a table of, say, releases by country and genre can point at
+//! a single record. [`Disclosure`] applies the usual rules of statistical disclosure
+//! control before tables are published:
+//!
+//! - Counts below a threshold are suppressed and published as `<threshold`.
+//! - Counts below a second limit are rounded to a multiple of a base, so differences
+//!   between two publications don't reveal single records.
+//! - If only one count of a table is suppressed, the next smallest is too, so it
+//!   can't be worked out from the total.
+//!
+//! The totals of the tables are rounded the same way.
+//!
+//! ```no_run
+//! use disco_quick::export::aggregate::Disclosure;
+//! use disco_quick::stats::DumpStats;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let stats = DumpStats::default();
+//! let disclosure = Disclosure::new().threshold(10).round_below(100, 5);
+//! let mut out = std::fs::File::create("stats.csv")?;
+//! for table in disclosure.dump_stats(&stats) {
+//!     table.write_csv(&mut out)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::export::csv::write_field;
+use crate::stats::{DumpStats, DurationReport, ScriptAnalysis, ScriptCounts};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+/// The default threshold below which counts are suppressed.
+pub const DEFAULT_THRESHOLD: u64 = 10;
+/// The default limit below which counts are rounded.
+pub const DEFAULT_ROUND_BELOW: u64 = 100;
+/// The default base that small counts are rounded to a multiple of.
+pub const DEFAULT_ROUNDING_BASE: u64 = 5;
+
+/// A published count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cell {
+    Count(u64),
+    /// A count that was below the threshold, or suppressed so another can't be
+    /// worked out.
+    Suppressed,
+}
+
+/// A table of counts with the disclosure rules applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PublishedTable {
+    pub name: String,
+    pub rows: Vec<(String, Cell)>,
+    pub total: Cell,
+    /// The threshold counts were suppressed below, for labelling suppressed cells.
+    pub threshold: u64,
+}
+
+impl PublishedTable {
+    /// The number of suppressed counts.
+    pub fn suppressed(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|(_, cell)| *cell == Cell::Suppressed)
+            .count()
+    }
+
+    /// Write the rows and the total as CSV lines of the table name, key and count.
+    pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        for (key, cell) in self.rows.iter().chain([&("total".to_string(), self.total)]) {
+            write_field(writer, &self.name)?;
+            writer.write_all(b",")?;
+            write_field(writer, key)?;
+            writeln!(writer, ",{}", self.display(*cell))?;
+        }
+        Ok(())
+    }
+
+    /// Replace an empty key, e.g. the country of releases without one.
+    fn with_empty_key(mut self, key: &str) -> Self {
+        for row in &mut self.rows {
+            if row.0.is_empty() {
+                row.0 = key.to_string();
+            }
+        }
+        self
+    }
+
+    /// A cell as it's published, with suppressed counts shown as `<threshold`.
+    pub fn display(&self, cell: Cell) -> impl fmt::Display {
+        match cell {
+            Cell::Count(count) => count.to_string(),
+            Cell::Suppressed => format!("<{}", self.threshold),
+        }
+    }
+}
+
+/// The rules applied to counts before they're published.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disclosure {
+    threshold: u64,
+    round_below: u64,
+    base: u64,
+    secondary: bool,
+}
+
+impl Default for Disclosure {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            round_below: DEFAULT_ROUND_BELOW,
+            base: DEFAULT_ROUNDING_BASE,
+            secondary: true,
+        }
+    }
+}
+
+impl Disclosure {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Suppress counts below `threshold`.
+    pub fn threshold(mut self, threshold: u64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Round counts below `limit` to the nearest multiple of `base`. A base of 0 or
+    /// 1 leaves them as they are.
+    pub fn round_below(mut self, limit: u64, base: u64) -> Self {
+        self.round_below = limit;
+        self.base = base;
+        self
+    }
+
+    /// Whether to suppress a second count when a table has only one suppressed.
+    /// It's on by default, and can be turned off for tables that are published
+    /// without a total.
+    pub fn secondary_suppression(mut self, secondary: bool) -> Self {
+        self.secondary = secondary;
+        self
+    }
+
+    /// A single count as it would be published.
+    pub fn cell(&self, count: u64) -> Cell {
+        if count < self.threshold {
+            return Cell::Suppressed;
+        }
+        if count >= self.round_below || self.base <= 1 {
+            return Cell::Count(count);
+        }
+        let rounded = (count + self.base / 2) / self.base * self.base;
+        Cell::Count(rounded.max(self.threshold))
+    }
+
+    /// Apply the rules to a table of counts by key.
+    pub fn table<K: fmt::Display>(
+        &self,
+        name: &str,
+        counts: impl IntoIterator<Item = (K, u64)>,
+    ) -> PublishedTable {
+        let counts: Vec<(String, u64)> = counts
+            .into_iter()
+            .map(|(key, count)| (key.to_string(), count))
+            .collect();
+        let total = counts.iter().map(|(_, count)| count).sum();
+        let mut rows: Vec<(String, Cell)> = counts
+            .iter()
+            .map(|(key, count)| (key.clone(), self.cell(*count)))
+            .collect();
+        let suppressed = rows.iter().filter(|(_, c)| *c == Cell::Suppressed).count();
+        if self.secondary && suppressed == 1 {
+            let smallest = counts
+                .iter()
+                .zip(&rows)
+                .enumerate()
+                .filter(|(_, (_, (_, cell)))| *cell != Cell::Suppressed)
+                .min_by_key(|(_, ((_, count), _))| *count)
+                .map(|(i, _)| i);
+            if let Some(i) = smallest {
+                rows[i].1 = Cell::Suppressed;
+            }
+        }
+        PublishedTable {
+            name: name.to_string(),
+            rows,
+            total: self.cell(total),
+            threshold: self.threshold,
+        }
+    }
+
+    /// The entity counts and the releases by country, genre, style, decade and format.
+    pub fn dump_stats(&self, stats: &DumpStats) -> Vec<PublishedTable> {
+        let entities = [
+            ("artists", stats.artists.count),
+            ("labels", stats.labels.count),
+            ("masters", stats.masters.count),
+            ("releases", stats.releases.count),
+        ];
+        vec![
+            // The entities don't add up to anything, so there's nothing to work out
+            self.secondary_suppression(false)
+                .table("entities", entities),
+            self.table("releases_by_country", counts(&stats.releases_by_country))
+                .with_empty_key("unknown"),
+            self.table("releases_by_genre", counts(&stats.releases_by_genre)),
+            self.table("releases_by_style", counts(&stats.releases_by_style)),
+            self.table("releases_by_decade", counts(&stats.releases_by_decade))
+                .with_empty_key("unknown"),
+            self.table("releases_by_format", counts(&stats.releases_by_format)),
+        ]
+    }
+
+    /// The number of tracks by whole minute of their duration, overall and by style
+    /// and format.
+    pub fn durations(&self, report: &DurationReport) -> Vec<PublishedTable> {
+        let histogram = |name: &str, histogram: &[u64]| {
+            let last = histogram.len().saturating_sub(1);
+            let rows = histogram.iter().enumerate().map(|(minute, count)| {
+                let key = if minute == last {
+                    format!("{minute}+")
+                } else {
+                    minute.to_string()
+                };
+                (key, *count)
+            });
+            self.table(name, rows)
+        };
+        let mut tables = vec![histogram("track_minutes", &report.all.histogram)];
+        for (style, distribution) in &report.by_style {
+            let name = format!("track_minutes_by_style/{style}");
+            tables.push(histogram(&name, &distribution.histogram));
+        }
+        for (format, distribution) in &report.by_format {
+            let name = format!("track_minutes_by_format/{format}");
+            tables.push(histogram(&name, &distribution.histogram));
+        }
+        tables
+    }
+
+    /// The number of releases using each script, overall and by country. Releases
+    /// can use several scripts, so the totals are of uses rather than releases.
+    /// The tables by label aren't included, as most labels have few releases.
+    pub fn scripts(&self, analysis: &ScriptAnalysis) -> Vec<PublishedTable> {
+        let scripts = |name: &str, counts: &ScriptCounts| {
+            self.table(
+                name,
+                counts
+                    .scripts
+                    .iter()
+                    .map(|(script, count)| (format!("{script:?}"), *count)),
+            )
+        };
+        let mut tables = vec![scripts("scripts", &analysis.all)];
+        for (country, counts) in &analysis.by_country {
+            let country = if country.is_empty() {
+                "unknown"
+            } else {
+                country
+            };
+            tables.push(scripts(&format!("scripts_by_country/{country}"), counts));
+        }
+        tables
+    }
+}
+
+fn counts(map: &BTreeMap<String, u64>) -> impl Iterator<Item = (&String, u64)> {
+    map.iter().map(|(key, count)| (key, *count))
+}
@@ -0,0 +1,632 @@
+//! CSV files in the layout of [discogs-xml2db](https://github.com/philipmat/discogs-xml2db),
+//! so databases loaded by its import scripts can be loaded from these files instead.
+//!
+//! Each table is written to a file named after it, e.g. `release_track.csv`, with a
+//! header row and the columns listed in [`TABLES`]. Missing values are empty, and
+//! booleans are `t` or `f` as expected by PostgreSQL's `COPY`.
+use crate::artist::Artist;
+use crate::artist_credit::ArtistCredit;
+use crate::export::ordering::{ChildOrder, SortChildren};
+use crate::hierarchy::LabelHierarchy;
+use crate::id::ReleaseId;
+use crate::label::Label;
+use crate::links::LinkStatuses;
+use crate::master::Master;
+use crate::release::Release;
+use crate::shared::EntityType;
+use crate::shared::Image;
+use crate::supplement::Supplement;
+use crate::track::Track;
+use crate::video::Video;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The tables and their columns, in the order they're written.
+pub const TABLES: &[(&str, &[&str])] = &[
+    (
+        "artist",
+        &["id", "name", "realname", "profile", "data_quality"],
+    ),
+    ("artist_alias", &["artist_id", "alias_name"]),
+    ("artist_namevariation", &["artist_id", "name"]),
+    ("artist_url", &["artist_id", "url"]),
+    (
+        "group_member",
+        &["group_artist_id", "member_artist_id", "member_name"],
+    ),
+    ("artist_image", &["artist_id", "type", "width", "height"]),
+    (
+        "label",
+        &[
+            "id",
+            "name",
+            "contact_info",
+            "profile",
+            "parent_id",
+            "parent_name",
+            "data_quality",
+        ],
+    ),
+    ("label_url", &["label_id", "url"]),
+    ("label_image", &["label_id", "type", "width", "height"]),
+    (
+        "master",
+        &["id", "title", "year", "main_release", "data_quality"],
+    ),
+    (
+        "master_artist",
+        &[
+            "master_id",
+            "artist_id",
+            "artist_name",
+            "anv",
+            "position",
+            "join_string",
+            "role",
+        ],
+    ),
+    (
+        "master_video",
+        &["master_id", "duration", "title", "description", "uri"],
+    ),
+    ("master_genre", &["master_id", "genre"]),
+    ("master_style", &["master_id", "style"]),
+    ("master_image", &["master_id", "type", "width", "height"]),
+    (
+        "release",
+        &[
+            "id",
+            "title",
+            "released",
+            "country",
+            "notes",
+            "data_quality",
+            "master_id",
+            "status",
+        ],
+    ),
+    (
+        "release_artist",
+        &[
+            "release_id",
+            "artist_id",
+            "artist_name",
+            "extra",
+            "anv",
+            "position",
+            "join_string",
+            "role",
+            "tracks",
+        ],
+    ),
+    ("release_label", &["release_id", "label", "catno"]),
+    ("release_genre", &["release_id", "genre"]),
+    ("release_style", &["release_id", "style"]),
+    (
+        "release_format",
+        &["release_id", "name", "qty", "text_string", "descriptions"],
+    ),
+    (
+        "release_track",
+        &[
+            "release_id",
+            "sequence",
+            "position",
+            "parent",
+            "title",
+            "duration",
+            "track_id",
+        ],
+    ),
+    (
+        "release_track_artist",
+        &[
+            "release_id",
+            "track_sequence",
+            "track_id",
+            "artist_id",
+            "artist_name",
+            "extra",
+            "anv",
+            "position",
+            "join_string",
+            "role",
+            "tracks",
+        ],
+    ),
+    (
+        "release_identifier",
+        &["release_id", "description", "type", "value"],
+    ),
+    (
+        "release_video",
+        &["release_id", "duration", "title", "description", "uri"],
+    ),
+    (
+        "release_company",
+        &[
+            "release_id",
+            "company_id",
+            "company_name",
+            "entity_type",
+            "entity_type_name",
+            "uri",
+        ],
+    ),
+    ("release_image", &["release_id", "type", "width", "height"]),
+];
+
+/// The columns added to the label table by [`CsvExport::label_hierarchy`].
+pub const LABEL_HIERARCHY_COLUMNS: &[&str] = &["root_id", "root_name", "depth", "label_code"];
+
+/// The columns added to the `artist_url` and `label_url` tables by
+/// [`CsvExport::link_statuses`].
+pub const LINK_STATUS_COLUMNS: &[&str] = &["link_status", "http_status", "location"];
+
+/// The columns a table can have after those listed in [`TABLES`], depending on the
+/// options of the export.
+pub fn optional_columns(table: &str) -> &'static [&'static str] {
+    match table {
+        "label" => LABEL_HIERARCHY_COLUMNS,
+        "artist_url" | "label_url" => LINK_STATUS_COLUMNS,
+        _ => &[],
+    }
+}
+
+/// The columns of a table, or None if it isn't one of [`TABLES`].
+pub fn columns(table: &str) -> Option<&'static [&'static str]> {
+    TABLES
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, columns)| *columns)
+}
+
+struct Table {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+/// Writes entities to one CSV file per table in a directory. Files are created
+/// when the first row of their table is written.
+pub struct CsvExport {
+    dir: PathBuf,
+    tables: BTreeMap<&'static str, Table>,
+    field: String,
+    order: Option<ChildOrder>,
+    supplements: BTreeMap<&'static str, Arc<Supplement>>,
+    hierarchy: Option<Arc<LabelHierarchy>>,
+    link_statuses: Option<Arc<LinkStatuses>>,
+}
+
+impl CsvExport {
+    /// Export to `dir`, which is created if it doesn't exist.
+    pub fn new(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            tables: BTreeMap::new(),
+            field: String::new(),
+            order: None,
+            supplements: BTreeMap::new(),
+            hierarchy: None,
+            link_statuses: None,
+        })
+    }
+
+    /// Sort the child collections of each record before writing its rows.
+    pub fn sort_children(mut self, order: ChildOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Add the columns of `supplement` to the table of `entity`, joined by ID. They
+    /// follow the columns listed in [`TABLES`], and are empty for records that
+    /// aren't in the supplement.
+    pub fn supplement(mut self, entity: EntityType, supplement: Arc<Supplement>) -> Self {
+        self.supplements.insert(entity.record_tag(), supplement);
+        self
+    }
+
+    /// Add the columns of [`LABEL_HIERARCHY_COLUMNS`] to the label table: the ID and
+    /// name of the top-level label above each label, or the label itself if it has no
+    /// parent, the number of labels between them, and the Label Code from the profile.
+    pub fn label_hierarchy(mut self, hierarchy: Arc<LabelHierarchy>) -> Self {
+        self.hierarchy = Some(hierarchy);
+        self
+    }
+
+    /// Add the columns of [`LINK_STATUS_COLUMNS`] to the `artist_url` and
+    /// `label_url` tables: the state of each URL, e.g. `dead`, and the HTTP status
+    /// and redirect location of its response. URLs that aren't in `statuses` are
+    /// `unchecked`.
+    pub fn link_statuses(mut self, statuses: Arc<LinkStatuses>) -> Self {
+        self.link_statuses = Some(statuses);
+        self
+    }
+
+    fn row(&mut self, table: &'static str, values: &[&dyn fmt::Display]) -> io::Result<()> {
+        let t = match self.tables.get_mut(table) {
+            Some(t) => t,
+            None => {
+                let path = self.dir.join(format!("{table}.csv"));
+                let mut writer = BufWriter::new(File::create(&path)?);
+                let header = columns(table).expect("unknown table").join(",");
+                write!(writer, "{header}")?;
+                if table == "label" && self.hierarchy.is_some() {
+                    write!(writer, ",{}", LABEL_HIERARCHY_COLUMNS.join(","))?;
+                }
+                if table.ends_with("_url") && self.link_statuses.is_some() {
+                    write!(writer, ",{}", LINK_STATUS_COLUMNS.join(","))?;
+                }
+                for column in self.supplements.get(table).iter().flat_map(|s| s.columns()) {
+                    writer.write_all(b",")?;
+                    write_field(&mut writer, column)?;
+                }
+                writeln!(writer)?;
+                self.tables.entry(table).or_insert(Table { path, writer })
+            }
+        };
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                t.writer.write_all(b",")?;
+            }
+            self.field.clear();
+            let _ = write!(self.field, "{value}");
+            write_field(&mut t.writer, &self.field)?;
+        }
+        t.writer.write_all(b"\n")
+    }
+
+    /// Write the row of an entity's own table, followed by its supplement if it has one.
+    fn entity_row(
+        &mut self,
+        table: &'static str,
+        id: i64,
+        values: &[&dyn fmt::Display],
+    ) -> io::Result<()> {
+        let Some(supplement) = self.supplements.get(table).cloned() else {
+            return self.row(table, values);
+        };
+        let row = u32::try_from(id).ok().and_then(|id| supplement.row(id));
+        let mut values = values.to_vec();
+        for i in 0..supplement.columns().len() {
+            values.push(row.map_or(&"", |r| &r[i]));
+        }
+        self.row(table, &values)
+    }
+
+    /// Write the row of a URL, followed by its status if statuses were given.
+    fn url_row(&mut self, table: &'static str, id: &dyn fmt::Display, url: &str) -> io::Result<()> {
+        let Some(statuses) = self.link_statuses.clone() else {
+            return self.row(table, &[id, &url]);
+        };
+        let status = statuses.status(url);
+        let http_status = Opt(status.http_status);
+        let location = opt(&status.location);
+        self.row(table, &[id, &url, &status.state, &http_status, &location])
+    }
+
+    pub fn write_artist(&mut self, artist: &Artist) -> io::Result<()> {
+        let artist = artist.sorted(self.order);
+        let id = &artist.id;
+        self.entity_row(
+            "artist",
+            artist.id.into(),
+            &[
+                id,
+                &artist.name,
+                &opt(&artist.real_name),
+                &opt(&artist.profile),
+                &artist.data_quality,
+            ],
+        )?;
+        for alias in &artist.aliases {
+            self.row("artist_alias", &[id, &alias.name])?;
+        }
+        for name in &artist.name_variations {
+            self.row("artist_namevariation", &[id, name])?;
+        }
+        for url in &artist.urls {
+            self.url_row("artist_url", id, url)?;
+        }
+        for member in &artist.members {
+            self.row("group_member", &[id, &member.id, &member.name])?;
+        }
+        self.images("artist_image", id, &artist.images)
+    }
+
+    pub fn write_label(&mut self, label: &Label) -> io::Result<()> {
+        let label = label.sorted(self.order);
+        let id = &label.id;
+        let parent = label.parent_label.as_ref();
+        let parent_name = parent.map(|p| p.name.as_str());
+        let values: [&dyn fmt::Display; 7] = [
+            id,
+            &label.name,
+            &opt(&label.contactinfo),
+            &opt(&label.profile),
+            &Opt(parent.map(|p| p.id)),
+            &opt(&parent_name),
+            &label.data_quality,
+        ];
+        match self.hierarchy.clone() {
+            Some(hierarchy) => {
+                let ancestors = hierarchy.ancestors(label.id);
+                let (root_id, root_name) = ancestors
+                    .last()
+                    .map_or((label.id, label.name.as_str()), |r| (r.id, &r.name));
+                let depth = ancestors.len();
+                let code = label.label_code();
+                let code = opt(&code);
+                let mut values = values.to_vec();
+                values.extend([&root_id as &dyn fmt::Display, &root_name, &depth, &code]);
+                self.entity_row("label", label.id.into(), &values)?;
+            }
+            None => self.entity_row("label", label.id.into(), &values)?,
+        }
+        for url in &label.urls {
+            self.url_row("label_url", id, url)?;
+        }
+        self.images("label_image", id, &label.images)
+    }
+
+    pub fn write_master(&mut self, master: &Master) -> io::Result<()> {
+        let master = master.sorted(self.order);
+        let id = &master.id;
+        self.entity_row(
+            "master",
+            master.id.into(),
+            &[
+                id,
+                &master.title,
+                &master.year,
+                &master.main_release,
+                &master.data_quality,
+            ],
+        )?;
+        for (i, credit) in master.artists.iter().enumerate() {
+            self.row(
+                "master_artist",
+                &[
+                    id,
+                    &credit.id,
+                    &credit.name,
+                    &opt(&credit.anv),
+                    &(i + 1),
+                    &opt(&credit.join),
+                    &opt(&credit.role),
+                ],
+            )?;
+        }
+        self.videos("master_video", id, &master.videos)?;
+        for genre in &master.genres {
+            self.row("master_genre", &[id, genre])?;
+        }
+        for style in &master.styles {
+            self.row("master_style", &[id, style])?;
+        }
+        self.images("master_image", id, &master.images)
+    }
+
+    pub fn write_release(&mut self, release: &Release) -> io::Result<()> {
+        let release = release.sorted(self.order);
+        let id = &release.id;
+        self.entity_row(
+            "release",
+            release.id.into(),
+            &[
+                id,
+                &release.title,
+                &release.released,
+                &release.country,
+                &opt(&release.notes),
+                &release.data_quality,
+                &Opt(release.master_id),
+                &release.status,
+            ],
+        )?;
+        self.release_credits("release_artist", id, None, &release.artists, false)?;
+        self.release_credits("release_artist", id, None, &release.extraartists, true)?;
+        for label in &release.labels {
+            self.row("release_label", &[id, &label.name, &opt(&label.catno)])?;
+        }
+        for genre in &release.genres {
+            self.row("release_genre", &[id, genre])?;
+        }
+        for style in &release.styles {
+            self.row("release_style", &[id, style])?;
+        }
+        for format in &release.formats {
+            self.row(
+                "release_format",
+                &[
+                    id,
+                    &format.name,
+                    &format.qty,
+                    &opt(&format.text),
+                    &format.descriptions.join("; "),
+                ],
+            )?;
+        }
+        let mut sequence = 0;
+        for track in &release.tracklist {
+            self.release_track(id, track, None, &mut sequence)?;
+        }
+        for identifier in &release.identifiers {
+            self.row(
+                "release_identifier",
+                &[
+                    id,
+                    &identifier.description,
+                    &identifier.r#type,
+                    &opt(&identifier.value),
+                ],
+            )?;
+        }
+        self.videos("release_video", id, &release.videos)?;
+        for company in &release.companies {
+            self.row(
+                "release_company",
+                &[
+                    id,
+                    &company.id,
+                    &company.name,
+                    &company.entity_type,
+                    &company.entity_type_name,
+                    &"",
+                ],
+            )?;
+        }
+        self.images("release_image", id, &release.images)
+    }
+
+    /// Write a track and its sub-tracks, which refer to it by its sequence number.
+    fn release_track(
+        &mut self,
+        id: &ReleaseId,
+        track: &Track,
+        parent: Option<u32>,
+        sequence: &mut u32,
+    ) -> io::Result<()> {
+        *sequence += 1;
+        let this = *sequence;
+        self.row(
+            "release_track",
+            &[
+                id,
+                &this,
+                &track.position,
+                &Opt(parent),
+                &track.title,
+                &opt(&track.duration),
+                &"",
+            ],
+        )?;
+        self.release_credits(
+            "release_track_artist",
+            id,
+            Some(this),
+            &track.artists,
+            false,
+        )?;
+        self.release_credits(
+            "release_track_artist",
+            id,
+            Some(this),
+            &track.extraartists,
+            true,
+        )?;
+        for sub_track in &track.sub_tracks {
+            self.release_track(id, sub_track, Some(this), sequence)?;
+        }
+        Ok(())
+    }
+
+    fn release_credits(
+        &mut self,
+        table: &'static str,
+        id: &ReleaseId,
+        track: Option<u32>,
+        credits: &[ArtistCredit],
+        extra: bool,
+    ) -> io::Result<()> {
+        let extra = if extra { "t" } else { "f" };
+        for (i, credit) in credits.iter().enumerate() {
+            let position = i + 1;
+            let mut values: Vec<&dyn fmt::Display> = vec![id];
+            let track = Opt(track);
+            if track.0.is_some() {
+                values.extend([&track as &dyn fmt::Display, &""]);
+            }
+            let anv = opt(&credit.anv);
+            let join = opt(&credit.join);
+            let role = opt(&credit.role);
+            let tracks = opt(&credit.tracks);
+            values.extend([
+                &credit.id as &dyn fmt::Display,
+                &credit.name,
+                &extra,
+                &anv,
+                &position,
+                &join,
+                &role,
+                &tracks,
+            ]);
+            self.row(table, &values)?;
+        }
+        Ok(())
+    }
+
+    fn videos(
+        &mut self,
+        table: &'static str,
+        id: &dyn fmt::Display,
+        videos: &[Video],
+    ) -> io::Result<()> {
+        for video in videos {
+            self.row(
+                table,
+                &[
+                    id,
+                    &video.duration,
+                    &video.title,
+                    &video.description,
+                    &video.src,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn images(
+        &mut self,
+        table: &'static str,
+        id: &dyn fmt::Display,
+        images: &[Image],
+    ) -> io::Result<()> {
+        for image in images {
+            self.row(table, &[id, &image.r#type, &image.width, &image.height])?;
+        }
+        Ok(())
+    }
+
+    /// Flush all files, returning the paths of the tables that were written.
+    pub fn finish(self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::with_capacity(self.tables.len());
+        for (_, mut table) in self.tables {
+            table.writer.flush()?;
+            paths.push(table.path);
+        }
+        Ok(paths)
+    }
+}
+
+/// An optional value, displayed as an empty string when missing.
+struct Opt<T>(Option<T>);
+
+impl<T: fmt::Display> fmt::Display for Opt<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Some(value) => value.fmt(f),
+            None => Ok(()),
+        }
+    }
+}
+
+fn opt<T: AsRef<str>>(value: &Option<T>) -> &str {
+    value.as_ref().map_or("", |v| v.as_ref())
+}
+
+pub(crate) fn write_field(writer: &mut impl Write, field: &str) -> io::Result<()> {
+    if field.contains(['"', ',', '\n', '\r']) {
+        writer.write_all(b"\"")?;
+        writer.write_all(field.replace('"', "\"\"").as_bytes())?;
+        writer.write_all(b"\"")
+    } else {
+        writer.write_all(field.as_bytes())
+    }
+}
@@ -0,0 +1,269 @@
+//! Records as [JSON Lines](https://jsonlines.org/), one JSON object per line.
+//!
+//! [`write`](fn@write) streams records to any writer, and [`write_parts`] splits
+//! them into numbered files with a [`PartWriter`] so they can be listed in a
+//! manifest.
+//!
+//! Each record is written with a `schema_version` field before its own fields,
+//! holding the [`FIELD_SCHEMA_VERSION`] it was written with.
+use crate::artist::Artist;
+use crate::export::manifest::{ManifestFile, PartWriter, FIELD_SCHEMA_VERSION};
+use crate::export::ordering::{ChildOrder, SortChildren};
+use crate::label::Label;
+use crate::links::LinkStatuses;
+use crate::master::Master;
+use crate::release::Release;
+use crate::schema::DumpDate;
+use crate::shared::EntityType;
+use crate::supplement::Supplement;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// How records are written.
+#[derive(Clone, Debug, Default)]
+pub struct JsonlOptions {
+    pub(crate) compact: bool,
+    pub(crate) gzip: bool,
+    pub(crate) records_per_part: Option<u64>,
+    pub(crate) order: Option<ChildOrder>,
+    pub(crate) supplement: Option<Arc<Supplement>>,
+    pub(crate) link_statuses: Option<Arc<LinkStatuses>>,
+}
+
+impl JsonlOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave out fields that are null, empty strings, or empty lists or objects.
+    /// Records written this way can't be deserialized without filling them back in.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Gzip the output.
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Sort the child collections of each record before writing it.
+    pub fn sort_children(mut self, order: ChildOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Add the columns of `supplement` to each record that's in it, as string
+    /// fields named after them. Fields the record already has aren't replaced.
+    pub fn supplement(mut self, supplement: Arc<Supplement>) -> Self {
+        self.supplement = Some(supplement);
+        self
+    }
+
+    /// Add a `url_statuses` field to records with `urls`, with an object for each
+    /// URL in the same order holding the `url` and its [`LinkStatus`]. URLs that
+    /// aren't in `statuses` are `Unchecked`.
+    ///
+    /// [`LinkStatus`]: crate::links::LinkStatus
+    pub fn link_statuses(mut self, statuses: Arc<LinkStatuses>) -> Self {
+        self.link_statuses = Some(statuses);
+        self
+    }
+
+    /// Start a new file after every `n` records. Only used by [`write_parts`].
+    pub fn records_per_part(mut self, n: u64) -> Self {
+        self.records_per_part = Some(n);
+        self
+    }
+}
+
+/// A record that can be written, by value or by reference.
+pub trait JsonRecord {
+    type Record: Serialize + SortChildren;
+
+    fn record(&self) -> &Self::Record;
+}
+
+macro_rules! json_record {
+    ($($t:ty),*) => {
+        $(impl JsonRecord for $t {
+            type Record = $t;
+
+            fn record(&self) -> &$t {
+                self
+            }
+        })*
+    };
+}
+
+json_record!(Artist, Label, Master, Release);
+
+impl<T: JsonRecord> JsonRecord for &T {
+    type Record = T::Record;
+
+    fn record(&self) -> &T::Record {
+        (*self).record()
+    }
+}
+
+/// Write each record as a line of JSON, returning the number of records written.
+pub fn write<W, T>(
+    out: W,
+    records: impl IntoIterator<Item = T>,
+    options: &JsonlOptions,
+) -> io::Result<u64>
+where
+    W: Write,
+    T: JsonRecord,
+{
+    if options.gzip {
+        let mut gz = GzEncoder::new(out, Compression::default());
+        let written = write_lines(&mut gz, records, options)?;
+        gz.finish()?.flush()?;
+        Ok(written)
+    } else {
+        let mut out = out;
+        let written = write_lines(&mut out, records, options)?;
+        out.flush()?;
+        Ok(written)
+    }
+}
+
+fn write_lines<T: JsonRecord>(
+    out: &mut impl Write,
+    records: impl IntoIterator<Item = T>,
+    options: &JsonlOptions,
+) -> io::Result<u64> {
+    let mut line = Vec::new();
+    let mut written = 0;
+    for record in records {
+        line.clear();
+        to_line(&mut line, &record, options)?;
+        out.write_all(&line)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Write the records to numbered files in `dir`, named as described in
+/// [`manifest`](crate::export::manifest), returning the descriptions of the files.
+pub fn write_parts<T: JsonRecord>(
+    dir: &Path,
+    entity: EntityType,
+    dump_date: DumpDate,
+    records: impl IntoIterator<Item = T>,
+    options: &JsonlOptions,
+) -> io::Result<Vec<ManifestFile>> {
+    let mut writer = PartWriter::new(dir, entity, dump_date).gzip(options.gzip);
+    if let Some(n) = options.records_per_part {
+        writer = writer.max_records_per_part(n);
+    }
+    let mut line = Vec::new();
+    for record in records {
+        line.clear();
+        to_line(&mut line, &record, options)?;
+        writer.write_record(&line)?;
+    }
+    writer.finish()
+}
+
+pub(crate) fn to_line<T: JsonRecord>(
+    line: &mut Vec<u8>,
+    record: &T,
+    options: &JsonlOptions,
+) -> io::Result<()> {
+    let record = record.record().sorted(options.order);
+    let start = line.len();
+    if options.compact || options.supplement.is_some() || options.link_statuses.is_some() {
+        let mut value = serde_json::to_value(&record)?;
+        if let Some(supplement) = &options.supplement {
+            join(&mut value, supplement);
+        }
+        if let Some(statuses) = &options.link_statuses {
+            add_link_statuses(&mut value, statuses)?;
+        }
+        if options.compact {
+            prune(&mut value);
+        }
+        serde_json::to_writer(&mut *line, &value)?;
+    } else {
+        serde_json::to_writer(&mut *line, &record)?;
+    }
+    add_version(line, start);
+    line.push(b'\n');
+    Ok(())
+}
+
+/// Add the schema version as the first field of the object written from `start`.
+fn add_version(line: &mut Vec<u8>, start: usize) {
+    if line.get(start) != Some(&b'{') {
+        return;
+    }
+    let mut field = format!("{{\"schema_version\":{FIELD_SCHEMA_VERSION}");
+    if line.get(start + 1) != Some(&b'}') {
+        field.push(',');
+    }
+    line.splice(start..start + 1, field.into_bytes());
+}
+
+fn join(value: &mut Value, supplement: &Supplement) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    let id = map.get("id").and_then(Value::as_u64);
+    let Some(row) = id.and_then(|id| supplement.row(u32::try_from(id).ok()?)) else {
+        return;
+    };
+    for (column, field) in supplement.columns().iter().zip(row) {
+        if !map.contains_key(column) {
+            map.insert(column.clone(), Value::String(field.clone()));
+        }
+    }
+}
+
+fn add_link_statuses(value: &mut Value, statuses: &LinkStatuses) -> io::Result<()> {
+    let Value::Object(map) = value else {
+        return Ok(());
+    };
+    let Some(Value::Array(urls)) = map.get("urls") else {
+        return Ok(());
+    };
+    let mut url_statuses = Vec::with_capacity(urls.len());
+    for url in urls.iter().filter_map(Value::as_str) {
+        let mut status = serde_json::to_value(statuses.status(url))?;
+        if let Value::Object(fields) = &mut status {
+            fields.insert("url".to_string(), Value::String(url.to_string()));
+        }
+        url_statuses.push(status);
+    }
+    map.insert("url_statuses".to_string(), Value::Array(url_statuses));
+    Ok(())
+}
+
+/// Remove the empty fields of objects, recursively.
+fn prune(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.values_mut().for_each(prune);
+            map.retain(|_, v| !is_empty(v));
+        }
+        Value::Array(values) => values.iter_mut().for_each(prune),
+        _ => {}
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        Value::Array(a) => a.is_empty(),
+        Value::Object(o) => o.is_empty(),
+        _ => false,
+    }
+}
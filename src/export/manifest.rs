@@ -0,0 +1,306 @@
+//! Writing exported records to numbered files, and describing them in a manifest
+//! so that downstream ingestion can be automated.
+//!
+//! A [`PartWriter`] names its files with a template in which `{entity}`,
+//! `{dump_date}` and `{part}` are replaced, e.g. `{entity}_{dump_date}_{part}`
+//! gives `releases_2023-10_001.jsonl`. The [`Manifest`] lists every file with its
+//! record count, size and CRC-32 checksum, and is written as JSON.
+use crate::schema::DumpDate;
+use crate::shared::EntityType;
+use flate2::write::GzEncoder;
+use flate2::{Compression, Crc};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The default template for naming output files.
+pub const DEFAULT_NAMING: &str = "{entity}_{dump_date}_{part}";
+
+/// The version of the exported fields. It's increased whenever a field is added,
+/// removed or changes meaning, so consumers can detect incompatible exports.
+///
+/// - 1: The first version.
+/// - 2: Records exported as JSON Lines have a `schema_version` field.
+/// - 3: Release identifiers have a `kind`, e.g. `barcode` or `matrix_runout`.
+///
+/// Older JSON Lines exports can be upgraded with
+/// [`migrate::jsonl`](crate::export::migrate::jsonl).
+pub const FIELD_SCHEMA_VERSION: u32 = 3;
+
+/// Render a file name template for a part of an export.
+pub fn render_name(template: &str, entity: EntityType, dump_date: DumpDate, part: u32) -> String {
+    template
+        .replace("{entity}", entity.root_tag())
+        .replace("{dump_date}", &dump_date.to_string())
+        .replace("{part}", &format!("{part:03}"))
+}
+
+/// A file listed in a [`Manifest`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManifestFile {
+    /// The file name, relative to the manifest.
+    pub path: String,
+    pub entity: EntityType,
+    pub part: u32,
+    pub records: u64,
+    pub bytes: u64,
+    pub crc32: u32,
+}
+
+/// A description of the files produced by an export.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Manifest {
+    pub dump_date: DumpDate,
+    pub schema_version: u32,
+    pub generator: String,
+    pub files: Vec<ManifestFile>,
+}
+
+impl Manifest {
+    pub fn new(dump_date: DumpDate) -> Self {
+        Self {
+            dump_date,
+            schema_version: FIELD_SCHEMA_VERSION,
+            generator: concat!("disco-quick ", env!("CARGO_PKG_VERSION")).to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn add_files(&mut self, files: impl IntoIterator<Item = ManifestFile>) {
+        self.files.extend(files);
+    }
+
+    /// The total number of records of `entity` across all files.
+    pub fn records(&self, entity: EntityType) -> u64 {
+        self.files
+            .iter()
+            .filter(|f| f.entity == entity)
+            .map(|f| f.records)
+            .sum()
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\n");
+        let _ = writeln!(json, "  \"dump_date\": \"{}\",", self.dump_date);
+        let _ = writeln!(json, "  \"schema_version\": {},", self.schema_version);
+        let _ = writeln!(json, "  \"generator\": {},", json_string(&self.generator));
+        json.push_str("  \"files\": [");
+        for (i, file) in self.files.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "\n    {{\"path\": {}, \"entity\": \"{}\", \"part\": {}, \"records\": {}, \
+                 \"bytes\": {}, \"crc32\": \"{:08x}\"}}",
+                json_string(&file.path),
+                file.entity,
+                file.part,
+                file.records,
+                file.bytes,
+                file.crc32,
+            );
+        }
+        if !self.files.is_empty() {
+            json.push_str("\n  ");
+        }
+        json.push_str("]\n}\n");
+        json
+    }
+
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Keeps the checksum and size of what's written to a file.
+struct Checksummed {
+    file: File,
+    crc: Crc,
+    bytes: u64,
+}
+
+impl Write for Checksummed {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.crc.update(&buf[..n]);
+        self.bytes += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+enum PartSink {
+    Plain(BufWriter<Checksummed>),
+    Gzip(GzEncoder<BufWriter<Checksummed>>),
+}
+
+impl PartSink {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Plain(w) => w.write_all(buf),
+            Self::Gzip(w) => w.write_all(buf),
+        }
+    }
+
+    fn finish(self) -> io::Result<Checksummed> {
+        let buffered = match self {
+            Self::Plain(w) => w,
+            Self::Gzip(w) => w.finish()?,
+        };
+        buffered.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+struct OpenPart {
+    writer: PartSink,
+    file: ManifestFile,
+}
+
+/// Writes the records of an export to one or more files in a directory,
+/// starting a new part when the current one reaches the record limit.
+pub struct PartWriter {
+    dir: PathBuf,
+    entity: EntityType,
+    dump_date: DumpDate,
+    naming: String,
+    extension: String,
+    max_records: Option<u64>,
+    gzip: bool,
+    current: Option<OpenPart>,
+    files: Vec<ManifestFile>,
+}
+
+impl PartWriter {
+    pub fn new(dir: &Path, entity: EntityType, dump_date: DumpDate) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            entity,
+            dump_date,
+            naming: DEFAULT_NAMING.to_string(),
+            extension: "jsonl".to_string(),
+            max_records: None,
+            gzip: false,
+            current: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Name files with `template` instead of [`DEFAULT_NAMING`].
+    pub fn naming(mut self, template: &str) -> Self {
+        self.naming = template.to_string();
+        self
+    }
+
+    /// The extension added to file names, "jsonl" by default.
+    pub fn extension(mut self, extension: &str) -> Self {
+        self.extension = extension.trim_start_matches('.').to_string();
+        self
+    }
+
+    /// Start a new file after every `n` records.
+    pub fn max_records_per_part(mut self, n: u64) -> Self {
+        self.max_records = Some(n.max(1));
+        self
+    }
+
+    /// Gzip each file, adding ".gz" to its name. The size and checksum in the
+    /// manifest are of the compressed file.
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Write a record, which should include any separator such as a trailing newline.
+    pub fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let full = match (&self.current, self.max_records) {
+            (Some(part), Some(max)) => part.file.records >= max,
+            _ => false,
+        };
+        if full {
+            self.close_part()?;
+        }
+        let part = match &mut self.current {
+            Some(part) => part,
+            None => self.current.insert(self.open_part()?),
+        };
+        part.writer.write_all(record)?;
+        part.file.records += 1;
+        Ok(())
+    }
+
+    fn open_part(&self) -> io::Result<OpenPart> {
+        let part = self.files.len() as u32 + 1;
+        let mut name = render_name(&self.naming, self.entity, self.dump_date, part);
+        if !self.extension.is_empty() {
+            name.push('.');
+            name.push_str(&self.extension);
+        }
+        if self.gzip {
+            name.push_str(".gz");
+        }
+        let file = BufWriter::new(Checksummed {
+            file: File::create(self.dir.join(&name))?,
+            crc: Crc::new(),
+            bytes: 0,
+        });
+        let writer = match self.gzip {
+            true => PartSink::Gzip(GzEncoder::new(file, Compression::default())),
+            false => PartSink::Plain(file),
+        };
+        Ok(OpenPart {
+            writer,
+            file: ManifestFile {
+                path: name,
+                entity: self.entity,
+                part,
+                records: 0,
+                bytes: 0,
+                crc32: 0,
+            },
+        })
+    }
+
+    fn close_part(&mut self) -> io::Result<()> {
+        if let Some(mut part) = self.current.take() {
+            let written = part.writer.finish()?;
+            part.file.bytes = written.bytes;
+            part.file.crc32 = written.crc.sum();
+            self.files.push(part.file);
+        }
+        Ok(())
+    }
+
+    /// Flush and close the last file, returning the descriptions of all files written.
+    pub fn finish(mut self) -> io::Result<Vec<ManifestFile>> {
+        self.close_part()?;
+        Ok(self.files)
+    }
+}
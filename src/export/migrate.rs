@@ -0,0 +1,210 @@
+//! Upgrading records exported by older versions of the crate.
+//!
+//! Records exported as JSON Lines carry the [`FIELD_SCHEMA_VERSION`] they were
+//! written with in a `schema_version` field, except those of version 1, which
+//! didn't have one. [`jsonl`] plans the steps from one version to another, and the
+//! [`JsonlMigration`] applies them to each line of an export, so that it doesn't
+//! have to be exported again from the dumps.
+//!
+//! ```no_run
+//! use disco_quick::export::manifest::FIELD_SCHEMA_VERSION;
+//! use disco_quick::export::migrate;
+//!
+//! # fn main() -> Result<(), migrate::MigrateError> {
+//! let migration = migrate::jsonl(1, FIELD_SCHEMA_VERSION)?;
+//! let lines = migration.run_path(
+//!     "releases_2023-10_001.jsonl.gz".as_ref(),
+//!     "upgraded/releases_2023-10_001.jsonl.gz".as_ref(),
+//! )?;
+//! println!("Upgraded {lines} records");
+//! # Ok(())
+//! # }
+//! ```
+use crate::export::manifest::FIELD_SCHEMA_VERSION;
+use crate::identifiers::IdentifierKind;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MigrateError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Line {line} isn't valid JSON: {source}")]
+    Json {
+        line: u64,
+        source: serde_json::Error,
+    },
+    #[error("Line {0} isn't a JSON object")]
+    NotAnObject(u64),
+    #[error("Unknown schema version {0}, the latest is {FIELD_SCHEMA_VERSION}")]
+    UnknownVersion(u32),
+    #[error("Can't migrate from schema version {from} back to {to}")]
+    Downgrade { from: u32, to: u32 },
+    #[error("Line {line} has schema version {found} but {expected} was expected")]
+    WrongVersion {
+        line: u64,
+        expected: u32,
+        found: u32,
+    },
+}
+
+/// A change to the exported fields, from one version to the next.
+struct Step {
+    from: u32,
+    apply: fn(&mut Map<String, Value>),
+}
+
+/// The steps from each version to the next, in order.
+const STEPS: &[Step] = &[
+    Step {
+        from: 1,
+        // The version is added to every migrated record
+        apply: |_| {},
+    },
+    Step {
+        from: 2,
+        apply: add_identifier_kinds,
+    },
+];
+
+/// Plan the migration of JSON Lines exports from schema version `from` to `to`.
+pub fn jsonl(from: u32, to: u32) -> Result<JsonlMigration, MigrateError> {
+    for version in [from, to] {
+        if version == 0 || version > FIELD_SCHEMA_VERSION {
+            return Err(MigrateError::UnknownVersion(version));
+        }
+    }
+    if from > to {
+        return Err(MigrateError::Downgrade { from, to });
+    }
+    let steps = STEPS
+        .iter()
+        .filter(|step| step.from >= from && step.from < to)
+        .collect();
+    Ok(JsonlMigration { from, to, steps })
+}
+
+/// The steps that upgrade records from one schema version to another.
+pub struct JsonlMigration {
+    from: u32,
+    to: u32,
+    steps: Vec<&'static Step>,
+}
+
+impl JsonlMigration {
+    pub fn from_version(&self) -> u32 {
+        self.from
+    }
+
+    pub fn to_version(&self) -> u32 {
+        self.to
+    }
+
+    /// Upgrade a record. Its version is checked against the one migrated from, with
+    /// records without one taken to be version 1.
+    pub fn migrate_record(&self, record: &mut Map<String, Value>) -> Result<(), MigrateError> {
+        let found = record_version(record);
+        if found != self.from {
+            return Err(MigrateError::WrongVersion {
+                line: 0,
+                expected: self.from,
+                found,
+            });
+        }
+        for step in &self.steps {
+            (step.apply)(record);
+        }
+        if self.to > 1 {
+            record.insert("schema_version".to_string(), self.to.into());
+        }
+        Ok(())
+    }
+
+    /// Upgrade each line of `input`, writing them to `output` and returning the
+    /// number of records. Empty lines are left out, and the fields of the records
+    /// are written in alphabetical order.
+    pub fn run(&self, input: impl BufRead, mut output: impl Write) -> Result<u64, MigrateError> {
+        let mut records = 0;
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let number = i as u64 + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value = serde_json::from_str(&line).map_err(|source| MigrateError::Json {
+                line: number,
+                source,
+            })?;
+            let Value::Object(mut record) = value else {
+                return Err(MigrateError::NotAnObject(number));
+            };
+            self.migrate_record(&mut record).map_err(|e| match e {
+                MigrateError::WrongVersion {
+                    expected, found, ..
+                } => MigrateError::WrongVersion {
+                    line: number,
+                    expected,
+                    found,
+                },
+                e => e,
+            })?;
+            serde_json::to_writer(&mut output, &record).map_err(io::Error::from)?;
+            output.write_all(b"\n")?;
+            records += 1;
+        }
+        output.flush()?;
+        Ok(records)
+    }
+
+    /// Upgrade the file at `input`, writing it to `output`. Files ending in `.gz`
+    /// are read and written gzipped.
+    pub fn run_path(&self, input: &Path, output: &Path) -> Result<u64, MigrateError> {
+        let file = File::open(input)?;
+        let reader: Box<dyn BufRead> = if is_gzip(input) {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        let writer = BufWriter::new(File::create(output)?);
+        if is_gzip(output) {
+            let mut gz = GzEncoder::new(writer, Compression::default());
+            let records = self.run(reader, &mut gz)?;
+            gz.finish()?.flush()?;
+            Ok(records)
+        } else {
+            self.run(reader, writer)
+        }
+    }
+}
+
+fn add_identifier_kinds(record: &mut Map<String, Value>) {
+    let Some(Value::Array(identifiers)) = record.get_mut("identifiers") else {
+        return;
+    };
+    for identifier in identifiers {
+        let Value::Object(identifier) = identifier else {
+            continue;
+        };
+        let r#type = identifier.get("type").and_then(Value::as_str).unwrap_or("");
+        let kind = IdentifierKind::from(r#type).key();
+        identifier.insert("kind".to_string(), kind.into());
+    }
+}
+
+/// The schema version of an exported record.
+pub fn record_version(record: &Map<String, Value>) -> u32 {
+    record
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(1, |v| v as u32)
+}
+
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gz")
+}
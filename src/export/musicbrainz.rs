@@ -0,0 +1,194 @@
+//! Seeding data for the MusicBrainz release editor.
+//!
+//! See <https://musicbrainz.org/doc/Development/Release_Editor_Seeding>. The fields must be
+//! POSTed to the editor, so [`seed_form`] wraps them in an HTML form that can be opened in
+//! a browser and submitted.
+use crate::artist_credit::ArtistCredit;
+use crate::identifiers::validate_ean_upc;
+use crate::release::{Release, ReleaseFormat};
+use log::warn;
+use std::fmt::Write;
+
+pub const RELEASE_EDITOR_URL: &str = "https://musicbrainz.org/release/add";
+
+/// The MusicBrainz URL relationship type for Discogs links.
+const DISCOGS_LINK_TYPE: &str = "76";
+
+/// Build the release editor seeding fields for a release, in the order they should be submitted.
+pub fn seed_fields(release: &Release) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut add = |key: String, value: &str| {
+        if !value.is_empty() {
+            fields.push((key, value.to_string()))
+        }
+    };
+    add("name".into(), &release.title);
+    add_credits(&mut add, "artist_credit", &release.artists);
+
+    let mut date = release.released.split('-');
+    for part in ["year", "month", "day"] {
+        match date.next().map(|d| d.trim_start_matches('0')) {
+            Some(d) if !d.is_empty() => add(format!("events.0.date.{part}"), d),
+            _ => break,
+        }
+    }
+    if let Some(code) = release.country.musicbrainz_code() {
+        add("events.0.country".into(), code);
+    }
+
+    for (i, label) in release.labels.iter().enumerate() {
+        add(format!("labels.{i}.name"), strip_suffix(&label.name));
+        match label.catno.as_deref() {
+            Some("none") => add(format!("labels.{i}.catalog_number"), "[none]"),
+            Some(catno) => add(format!("labels.{i}.catalog_number"), catno),
+            None => {}
+        }
+    }
+
+    // Prefer a barcode with a valid check digit, but seed an invalid one rather than none
+    let mut barcodes = release.identifiers.iter().filter(|i| i.is_barcode());
+    let barcode = barcodes
+        .clone()
+        .find(|i| i.is_valid_barcode())
+        .or_else(|| barcodes.next());
+    if let Some(barcode) = barcode.and_then(|i| i.value.as_deref()) {
+        if !validate_ean_upc(barcode) {
+            warn!("Release {} has an invalid barcode: {barcode}", release.id);
+        }
+        let digits: String = barcode.chars().filter(|c| c.is_ascii_digit()).collect();
+        add("barcode".into(), &digits);
+    }
+
+    let format = release
+        .formats
+        .first()
+        .map(medium_format)
+        .unwrap_or_default();
+    let mut medium = 0;
+    let mut number = 0;
+    for track in release.all_tracks() {
+        if track.position.is_empty() {
+            continue;
+        }
+        if let Some(m) = medium_index(&track.position) {
+            if m != medium && number > 0 {
+                number = 0;
+            }
+            medium = m;
+        }
+        if number == 0 {
+            add(format!("mediums.{medium}.format"), &format);
+        }
+        let prefix = format!("mediums.{medium}.track.{number}");
+        add(format!("{prefix}.name"), &track.title);
+        add(format!("{prefix}.number"), &track.position);
+        if let Some(duration) = &track.duration {
+            add(format!("{prefix}.length"), duration);
+        }
+        add_credits(&mut add, &format!("{prefix}.artist_credit"), &track.artists);
+        number += 1;
+    }
+
+    add(
+        "urls.0.url".into(),
+        &format!("https://www.discogs.com/release/{}", release.id),
+    );
+    add("urls.0.link_type".into(), DISCOGS_LINK_TYPE);
+    add(
+        "edit_note".into(),
+        &format!(
+            "Imported from https://www.discogs.com/release/{}",
+            release.id
+        ),
+    );
+    fields
+}
+
+/// Render an HTML page with a form that seeds the release editor when submitted.
+pub fn seed_form(release: &Release) -> String {
+    let mut html = String::new();
+    let title = escape(&release.to_string());
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">"
+    )
+    .unwrap();
+    writeln!(html, "<title>{title}</title></head><body>").unwrap();
+    writeln!(
+        html,
+        "<form method=\"post\" action=\"{RELEASE_EDITOR_URL}\">"
+    )
+    .unwrap();
+    for (name, value) in seed_fields(release) {
+        let (name, value) = (escape(&name), escape(&value));
+        writeln!(
+            html,
+            "<input type=\"hidden\" name=\"{name}\" value=\"{value}\">"
+        )
+        .unwrap();
+    }
+    writeln!(
+        html,
+        "<button type=\"submit\">Add {title} to MusicBrainz</button>"
+    )
+    .unwrap();
+    html.push_str("</form></body></html>\n");
+    html
+}
+
+fn add_credits(add: &mut impl FnMut(String, &str), prefix: &str, credits: &[ArtistCredit]) {
+    for (i, credit) in credits.iter().enumerate() {
+        let name = strip_suffix(&credit.name);
+        let credited = credit
+            .anv
+            .as_deref()
+            .filter(|a| !a.is_empty())
+            .unwrap_or(name);
+        add(format!("{prefix}.names.{i}.name"), credited);
+        add(format!("{prefix}.names.{i}.artist.name"), name);
+        if i + 1 < credits.len() {
+            let join = match credit.join.as_deref() {
+                Some(",") => ", ".to_string(),
+                Some(join) if !join.is_empty() => format!(" {join} "),
+                _ => " ".to_string(),
+            };
+            add(format!("{prefix}.names.{i}.join_phrase"), &join);
+        }
+    }
+}
+
+/// Remove the numeric suffix Discogs uses to disambiguate names, e.g. "Svek (2)".
+fn strip_suffix(name: &str) -> &str {
+    if let Some((base, suffix)) = name.rsplit_once(" (") {
+        let num = suffix.trim_end_matches(')');
+        if num.len() + 1 == suffix.len() && num.chars().all(|c| c.is_ascii_digit()) {
+            return base;
+        }
+    }
+    name
+}
+
+/// The zero-based medium of a position like "2-14" or "CD2-3".
+fn medium_index(position: &str) -> Option<usize> {
+    let (medium, _) = position.split_once('-')?;
+    let digits = medium.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    digits.parse::<usize>().ok()?.checked_sub(1)
+}
+
+fn medium_format(format: &ReleaseFormat) -> String {
+    let size = ["12\"", "10\"", "7\""]
+        .into_iter()
+        .find(|s| format.descriptions.iter().any(|d| d == s));
+    match (format.name.as_str(), size) {
+        ("Vinyl", Some(size)) => format!("{size} Vinyl"),
+        ("File", _) => "Digital Media".to_string(),
+        (name, _) => name.to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,152 @@
+//! Sorting the child collections of records, so exports of different months only
+//! differ where the data does.
+//!
+//! The dumps list images, identifiers, URLs and the like in whatever order the
+//! database returns them, which changes between months. With [`SortChildren`] they
+//! are sorted by their values, compared byte by byte so the order doesn't depend on
+//! the locale. Lists whose order means something are left alone: tracklists,
+//! formats, genres and styles, the labels of releases, whose first is taken as the
+//! main label, and the main artist credits, whose join strings depend on their
+//! order. Extra artist credits are sorted only if asked for with
+//! [`CreditOrder::Sort`].
+use crate::artist::{Artist, ArtistInfo};
+use crate::artist_credit::ArtistCredit;
+use crate::label::{Label, LabelInfo};
+use crate::master::Master;
+use crate::release::Release;
+use crate::shared::{Image, ReleaseLabel};
+use crate::track::Track;
+use crate::video::Video;
+use std::borrow::Cow;
+
+/// How the extra artist credits of releases and tracks are ordered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CreditOrder {
+    /// Keep the order of the dump, which is usually the order shown on Discogs.
+    #[default]
+    Preserve,
+    /// Sort by role, then artist ID and name.
+    Sort,
+}
+
+/// Which child collections are sorted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChildOrder {
+    pub credits: CreditOrder,
+}
+
+impl ChildOrder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn credits(mut self, credits: CreditOrder) -> Self {
+        self.credits = credits;
+        self
+    }
+}
+
+/// Records whose child collections can be sorted.
+pub trait SortChildren: Clone {
+    fn sort_children(&mut self, order: ChildOrder);
+
+    /// The record with its children sorted, or the record itself if `order` is None.
+    fn sorted(&self, order: Option<ChildOrder>) -> Cow<'_, Self> {
+        match order {
+            Some(order) => {
+                let mut sorted = self.clone();
+                sorted.sort_children(order);
+                Cow::Owned(sorted)
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
+}
+
+impl SortChildren for Artist {
+    fn sort_children(&mut self, _order: ChildOrder) {
+        self.name_variations.sort_unstable();
+        self.urls.sort_unstable();
+        sort_artist_info(&mut self.aliases);
+        sort_artist_info(&mut self.members);
+        sort_artist_info(&mut self.groups);
+        sort_images(&mut self.images);
+    }
+}
+
+impl SortChildren for Label {
+    fn sort_children(&mut self, _order: ChildOrder) {
+        self.urls.sort_unstable();
+        sort_label_info(&mut self.sublabels);
+        sort_images(&mut self.images);
+    }
+}
+
+impl SortChildren for Master {
+    fn sort_children(&mut self, _order: ChildOrder) {
+        sort_images(&mut self.images);
+        sort_videos(&mut self.videos);
+    }
+}
+
+impl SortChildren for Release {
+    fn sort_children(&mut self, order: ChildOrder) {
+        sort_images(&mut self.images);
+        sort_videos(&mut self.videos);
+        sort_release_labels(&mut self.companies);
+        self.identifiers.sort_by(|a, b| {
+            (&a.r#type, &a.value, &a.description).cmp(&(&b.r#type, &b.value, &b.description))
+        });
+        if order.credits == CreditOrder::Sort {
+            sort_credits(&mut self.extraartists);
+            for track in &mut self.tracklist {
+                sort_track_credits(track);
+            }
+        }
+    }
+}
+
+fn sort_track_credits(track: &mut Track) {
+    sort_credits(&mut track.extraartists);
+    for sub_track in &mut track.sub_tracks {
+        sort_track_credits(sub_track);
+    }
+}
+
+fn sort_credits(credits: &mut [ArtistCredit]) {
+    credits.sort_by(|a, b| {
+        (a.role.as_deref(), a.id, &a.name, &a.anv, &a.tracks).cmp(&(
+            b.role.as_deref(),
+            b.id,
+            &b.name,
+            &b.anv,
+            &b.tracks,
+        ))
+    });
+}
+
+fn sort_artist_info(info: &mut [ArtistInfo]) {
+    info.sort_by(|a, b| (a.id, &a.name).cmp(&(b.id, &b.name)));
+}
+
+fn sort_label_info(info: &mut [LabelInfo]) {
+    info.sort_by(|a, b| (a.id, &a.name).cmp(&(b.id, &b.name)));
+}
+
+fn sort_release_labels(labels: &mut [ReleaseLabel]) {
+    labels.sort_by(|a, b| {
+        (a.entity_type, a.id, &a.name, &a.catno).cmp(&(b.entity_type, b.id, &b.name, &b.catno))
+    });
+}
+
+fn sort_images(images: &mut [Image]) {
+    images.sort_by(|a, b| {
+        (&a.r#type, a.width, a.height, &a.uri).cmp(&(&b.r#type, b.width, b.height, &b.uri))
+    });
+}
+
+fn sort_videos(videos: &mut [Video]) {
+    videos.sort_by(|a, b| (&a.src, &a.title).cmp(&(&b.src, &b.title)));
+}
@@ -0,0 +1,195 @@
+//! Loading exports into PostgreSQL with `COPY`, which is far faster than inserting
+//! rows one at a time.
+//!
+//! The tables are those of [`csv::TABLES`](crate::export::csv::TABLES), written by a
+//! [`CsvExport`](crate::export::csv::CsvExport). [`schema_sql`] creates them, with
+//! the [optional columns](crate::export::csv::optional_columns) that some options of
+//! the export add, and [`load_script`] is a psql script that copies the files into
+//! them in a single transaction. Each file can also be sent to a client library's
+//! `COPY ... FROM STDIN` support with the statement from [`copy_statement`]. The
+//! columns copied are read from the header of each file, so the optional columns
+//! are left empty when they weren't exported. Columns added by a
+//! [`Supplement`](crate::supplement::Supplement) aren't in the schema and need to be
+//! added to their table before loading.
+//!
+//! No keys or indexes are created, as loading is faster without them. Add them once
+//! the data is in.
+//!
+//! A long load that fails partway through can be run again with the script from
+//! [`resumable_load_script`], which commits each table separately and records the
+//! last entity ID committed in [`PROGRESS_TABLE`].
+//!
+//! ```no_run
+//! use disco_quick::export::csv::CsvExport;
+//! use disco_quick::export::postgres::{load_script, schema_sql};
+//! use disco_quick::DiscogsReader;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut export = CsvExport::new("out".as_ref())?;
+//! if let DiscogsReader::Releases(releases) = DiscogsReader::from_path("releases.xml.gz".as_ref())? {
+//!     for release in *releases {
+//!         export.write_release(&release?)?;
+//!     }
+//! }
+//! let paths = export.finish()?;
+//! std::fs::write("out/schema.sql", schema_sql())?;
+//! std::fs::write("out/load.sql", load_script(&paths)?)?;
+//! // psql -f out/schema.sql && psql -f out/load.sql
+//! # Ok(())
+//! # }
+//! ```
+use crate::export::csv::{columns, optional_columns, TABLES};
+use crate::util::CsvRecords;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+/// The table in which [`resumable_load_script`] records the last entity ID loaded
+/// into each table.
+pub const PROGRESS_TABLE: &str = "disco_quick_load";
+
+/// The PostgreSQL type of a column of [`TABLES`].
+pub fn column_type(table: &str, column: &str) -> &'static str {
+    match (table, column) {
+        // Track positions and durations are text like "A1" and "4:45"
+        ("release_track", "position" | "duration") => "text",
+        (_, "extra") => "boolean",
+        (_, c) => integer_or_text(c),
+    }
+}
+
+fn integer_or_text(column: &str) -> &'static str {
+    match column {
+        "id" | "year" | "main_release" | "width" | "height" | "duration" | "position"
+        | "sequence" | "parent" | "track_sequence" | "entity_type" | "depth" | "http_status" => {
+            "integer"
+        }
+        c if c.ends_with("_id") && c != "track_id" => "integer",
+        _ => "text",
+    }
+}
+
+/// `CREATE TABLE` statements for all of [`TABLES`], including their optional columns.
+pub fn schema_sql() -> String {
+    let mut sql = String::new();
+    for (table, columns) in TABLES {
+        let columns: Vec<&str> = columns
+            .iter()
+            .chain(optional_columns(table))
+            .copied()
+            .collect();
+        let _ = writeln!(sql, "CREATE TABLE IF NOT EXISTS {table} (");
+        for (i, column) in columns.iter().enumerate() {
+            let separator = if i + 1 < columns.len() { "," } else { "" };
+            let _ = writeln!(
+                sql,
+                "    {column} {}{separator}",
+                column_type(table, column)
+            );
+        }
+        sql.push_str(");\n");
+    }
+    sql
+}
+
+/// The `COPY` statement that reads the CSV file at `path` from standard input, with
+/// the columns of its header, or None if the file isn't named after one of [`TABLES`].
+pub fn copy_statement(path: &Path) -> io::Result<Option<String>> {
+    let Some((table, columns)) = table_of(path)? else {
+        return Ok(None);
+    };
+    let columns = columns.join(", ");
+    Ok(Some(format!(
+        "COPY {table} ({columns}) FROM STDIN WITH (FORMAT csv, HEADER true)"
+    )))
+}
+
+/// A psql script that copies the files written by a
+/// [`CsvExport`](crate::export::csv::CsvExport) into their tables. Files that
+/// aren't named after one of [`TABLES`] are skipped.
+pub fn load_script(paths: &[PathBuf]) -> io::Result<String> {
+    let mut script = String::from("BEGIN;\n");
+    for path in paths {
+        let Some((table, columns)) = table_of(path)? else {
+            continue;
+        };
+        let _ = writeln!(
+            script,
+            "\\copy {table} ({}) FROM '{}' WITH (FORMAT csv, HEADER true)",
+            columns.join(", "),
+            quote_path(path),
+        );
+    }
+    script.push_str("COMMIT;\n");
+    Ok(script)
+}
+
+/// Like [`load_script`], but it can be run again after it fails without duplicating
+/// rows. Each table is loaded in its own transaction through a staging table:
+///
+/// - rows of entities up to the ID recorded in [`PROGRESS_TABLE`] are skipped,
+/// - rows already in the table for the remaining entities are replaced,
+/// - and the highest entity ID is recorded when the transaction commits.
+///
+/// The entity ID is the first column of each table, e.g. `release_id`. Truncate
+/// [`PROGRESS_TABLE`] before loading a different export into the same tables.
+///
+/// Unlike [`load_script`], the load as a whole isn't atomic: the tables committed
+/// before a failure stay loaded, and other sessions can see some tables of the
+/// export before the others are loaded. Run it when nothing else reads the tables,
+/// or use [`load_script`] if a failed load must leave them untouched.
+pub fn resumable_load_script(paths: &[PathBuf]) -> io::Result<String> {
+    let mut script = String::from("\\set ON_ERROR_STOP on\n");
+    let _ = writeln!(
+        script,
+        "CREATE TABLE IF NOT EXISTS {PROGRESS_TABLE} (table_name text PRIMARY KEY, last_id integer NOT NULL);"
+    );
+    for path in paths {
+        let Some((table, columns)) = table_of(path)? else {
+            continue;
+        };
+        let key = &columns[0];
+        let columns = columns.join(", ");
+        let staging = format!("staging_{table}");
+        let _ = write!(
+            script,
+            "BEGIN;
+CREATE TEMP TABLE {staging} (LIKE {table}) ON COMMIT DROP;
+\\copy {staging} ({columns}) FROM '{path}' WITH (FORMAT csv, HEADER true)
+DELETE FROM {staging} WHERE {key} <= (SELECT last_id FROM {PROGRESS_TABLE} WHERE table_name = '{table}');
+DELETE FROM {table} t USING (SELECT DISTINCT {key} FROM {staging}) s WHERE t.{key} = s.{key};
+INSERT INTO {table} ({columns}) SELECT {columns} FROM {staging};
+INSERT INTO {PROGRESS_TABLE} (table_name, last_id) SELECT '{table}', max({key}) FROM {staging} HAVING count(*) > 0
+    ON CONFLICT (table_name) DO UPDATE SET last_id = greatest({PROGRESS_TABLE}.last_id, excluded.last_id);
+COMMIT;
+",
+            path = quote_path(path),
+        );
+    }
+    Ok(script)
+}
+
+/// The table of a file written by a [`CsvExport`](crate::export::csv::CsvExport) and
+/// the columns of its header.
+fn table_of(path: &Path) -> io::Result<Option<(&str, Vec<String>)>> {
+    let Some(table) = path.file_stem().and_then(|s| s.to_str()) else {
+        return Ok(None);
+    };
+    if columns(table).is_none() {
+        return Ok(None);
+    }
+    let mut records = CsvRecords::new(BufReader::new(File::open(path)?));
+    match records.next_record() {
+        Ok(Some(header)) => Ok(Some((table, header))),
+        Ok(None) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} has no header", path.display()),
+        )),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+fn quote_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\'', "''")
+}
@@ -0,0 +1,249 @@
+//! Exporting async streams of records to async destinations.
+//!
+//! An [`AsyncExporter`] reads records from a stream, like those of the
+//! [`stream`](crate::stream) readers, and sends them in batches to an [`AsyncSink`],
+//! e.g. an async database driver or a multipart upload. Records are read while the
+//! sink is busy with earlier batches, up to a bound on the batches in flight. Once
+//! that's reached, reading waits for the sink, so a slow destination holds back the
+//! reader rather than batches piling up in memory. Reading and sending share a task,
+//! so nothing has to be spawned on the runtime.
+//!
+//! [`JsonlSink`] writes the batches as JSON Lines to any `AsyncWrite`, and
+//! [`from_fn`] turns an async closure into a sink.
+//!
+//! ```no_run
+//! use disco_quick::export::sink::{self, AsyncExporter};
+//! use disco_quick::stream::AsyncDiscogsReader;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let AsyncDiscogsReader::Labels(labels) =
+//!     AsyncDiscogsReader::from_path("labels.xml.gz".as_ref()).await?
+//! else {
+//!     return Ok(());
+//! };
+//! let mut db = sink::from_fn(|batch: Vec<_>| async move {
+//!     // Insert the batch with an async driver
+//!     println!("Inserting {} labels", batch.len());
+//!     Ok::<_, std::io::Error>(())
+//! });
+//! let exporter = AsyncExporter::new().batch_size(500).max_in_flight(2);
+//! let stats = exporter.run(labels.into_stream(), &mut db).await?;
+//! println!("Exported {} labels in {} batches", stats.records, stats.batches);
+//! # Ok(())
+//! # }
+//! ```
+use crate::reader::ReaderError;
+use futures_util::future::try_join;
+use futures_util::stream::{Stream, StreamExt};
+use std::future::Future;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// The default number of records in a batch.
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+/// The default number of batches read ahead of the sink.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+#[derive(Error, Debug)]
+pub enum SinkError<E> {
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+    #[error("The sink failed: {0}")]
+    Sink(E),
+}
+
+/// A destination for batches of records.
+pub trait AsyncSink<T> {
+    type Error;
+
+    /// Write a batch of records. The exporter doesn't send another batch until the
+    /// future returned is done.
+    fn send(&mut self, batch: Vec<T>) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Called once every batch has been sent, e.g. to flush a writer or complete
+    /// an upload.
+    fn finish(&mut self) -> impl Future<Output = Result<(), Self::Error>> {
+        async { Ok(()) }
+    }
+}
+
+/// The number of records and batches exported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExportStats {
+    pub records: u64,
+    pub batches: u64,
+}
+
+/// Sends the records of a stream to an [`AsyncSink`] in batches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AsyncExporter {
+    batch_size: usize,
+    max_in_flight: usize,
+}
+
+impl Default for AsyncExporter {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+        }
+    }
+}
+
+impl AsyncExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send the records in batches of `n`. The last batch may be smaller.
+    pub fn batch_size(mut self, n: usize) -> Self {
+        self.batch_size = n.max(1);
+        self
+    }
+
+    /// Read at most `n` batches ahead of the one the sink is writing.
+    pub fn max_in_flight(mut self, n: usize) -> Self {
+        self.max_in_flight = n.max(1);
+        self
+    }
+
+    /// Export the records of `records` to `sink`, and finish the sink once they've
+    /// all been sent. An error from either stops the export without finishing it.
+    pub async fn run<T, E, S>(
+        &self,
+        records: impl Stream<Item = Result<T, E>>,
+        sink: &mut S,
+    ) -> Result<ExportStats, SinkError<S::Error>>
+    where
+        S: AsyncSink<T>,
+        ReaderError: From<E>,
+    {
+        let (sender, mut receiver) = mpsc::channel::<Vec<T>>(self.max_in_flight);
+        let read = async move {
+            let mut records = std::pin::pin!(records);
+            let mut batch = Vec::with_capacity(self.batch_size);
+            while let Some(record) = records.next().await {
+                batch.push(record.map_err(|e| SinkError::Reader(e.into()))?);
+                if batch.len() == self.batch_size {
+                    let full = std::mem::replace(&mut batch, Vec::with_capacity(self.batch_size));
+                    if sender.send(full).await.is_err() {
+                        // The sink failed, and its error is returned instead
+                        return Ok(());
+                    }
+                }
+            }
+            if !batch.is_empty() {
+                let _ = sender.send(batch).await;
+            }
+            Ok::<_, SinkError<S::Error>>(())
+        };
+        let send = async {
+            let mut stats = ExportStats::default();
+            while let Some(batch) = receiver.recv().await {
+                stats.records += batch.len() as u64;
+                stats.batches += 1;
+                sink.send(batch).await.map_err(SinkError::Sink)?;
+            }
+            sink.finish().await.map_err(SinkError::Sink)?;
+            Ok(stats)
+        };
+        let ((), stats) = try_join(read, send).await?;
+        Ok(stats)
+    }
+}
+
+/// A sink that calls an async function with each batch.
+pub struct FnSink<F> {
+    f: F,
+}
+
+/// Make a sink of an async function called with each batch.
+pub fn from_fn<T, E, F, Fut>(f: F) -> FnSink<F>
+where
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    FnSink { f }
+}
+
+impl<T, E, F, Fut> AsyncSink<T> for FnSink<F>
+where
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    type Error = E;
+
+    fn send(&mut self, batch: Vec<T>) -> impl Future<Output = Result<(), E>> {
+        (self.f)(batch)
+    }
+}
+
+#[cfg(feature = "jsonl")]
+pub use self::jsonl::JsonlSink;
+
+#[cfg(feature = "jsonl")]
+mod jsonl {
+    use super::AsyncSink;
+    use crate::export::jsonl::{to_line, JsonRecord, JsonlOptions};
+    use async_compression::tokio::write::GzipEncoder;
+    use std::io;
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+    /// Writes batches of records as JSON Lines. Gzipped output is compressed as it's
+    /// written.
+    pub struct JsonlSink<W> {
+        out: Output<W>,
+        options: JsonlOptions,
+        buf: Vec<u8>,
+    }
+
+    enum Output<W> {
+        Plain(W),
+        Gzip(GzipEncoder<W>),
+    }
+
+    impl<W: AsyncWrite + Unpin> JsonlSink<W> {
+        pub fn new(out: W, options: JsonlOptions) -> Self {
+            let out = if options.gzip {
+                Output::Gzip(GzipEncoder::new(out))
+            } else {
+                Output::Plain(out)
+            };
+            Self {
+                out,
+                options,
+                buf: Vec::new(),
+            }
+        }
+
+        /// The writer the records are written to.
+        pub fn into_inner(self) -> W {
+            match self.out {
+                Output::Plain(out) => out,
+                Output::Gzip(gz) => gz.into_inner(),
+            }
+        }
+    }
+
+    impl<T: JsonRecord, W: AsyncWrite + Unpin> AsyncSink<T> for JsonlSink<W> {
+        type Error = io::Error;
+
+        async fn send(&mut self, batch: Vec<T>) -> io::Result<()> {
+            self.buf.clear();
+            for record in &batch {
+                to_line(&mut self.buf, record, &self.options)?;
+            }
+            match &mut self.out {
+                Output::Plain(out) => out.write_all(&self.buf).await,
+                Output::Gzip(gz) => gz.write_all(&self.buf).await,
+            }
+        }
+
+        async fn finish(&mut self) -> io::Result<()> {
+            match &mut self.out {
+                Output::Plain(out) => out.flush().await,
+                Output::Gzip(gz) => gz.shutdown().await,
+            }
+        }
+    }
+}
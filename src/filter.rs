@@ -0,0 +1,270 @@
+//! Predicates on parsed records, for readers that should only yield some of them.
+//!
+//! A [`Filter`] wraps a closure and can be combined with [`and`](Filter::and),
+//! [`or`](Filter::or) and [`not`](Filter::not). Pass one to
+//! [`EntityReader::filter_records`](crate::reader::EntityReader::filter_records) to
+//! have records that don't match dropped before they're yielded.
+//!
+//! ```no_run
+//! use disco_quick::filter::{min_images, video_longer_than};
+//! use disco_quick::{DiscogsReader, ReaderError};
+//!
+//! # fn main() -> Result<(), ReaderError> {
+//! if let DiscogsReader::Releases(releases) = DiscogsReader::from_path("releases.xml.gz".as_ref())? {
+//!     let media_rich = releases.filter_records(video_longer_than(600).and(min_images(3)));
+//!     for release in media_rich {
+//!         println!("{}", release?);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A filter is checked once a record has been parsed. To pick out a few records of a
+//! large dump, an [`EarlyFilter`] on their IDs is much faster: it's checked as soon as
+//! the ID is read, and records that don't match are skipped without being parsed. Pass
+//! one to [`EntityReader::filter_early`](crate::reader::EntityReader::filter_early),
+//! or use [`EntityReader::filter_ids`](crate::reader::EntityReader::filter_ids).
+//!
+//! Filters can also be written as expressions like `country == "UK" && year >= 1990`
+//! with the [`expr`] module, which picks out the parts that can be checked early.
+pub mod expr;
+
+use crate::artist::Artist;
+use crate::label::Label;
+use crate::master::Master;
+use crate::release::{Release, ReleaseStatus};
+use crate::shared::Image;
+use crate::video::Video;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// A predicate on records of type `T`.
+pub struct Filter<T> {
+    predicate: Arc<dyn Fn(&T) -> bool + Send + Sync>,
+}
+
+impl<T> Clone for Filter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            predicate: self.predicate.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Filter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Filter").finish_non_exhaustive()
+    }
+}
+
+impl<T> Filter<T> {
+    pub fn matches(&self, item: &T) -> bool {
+        (self.predicate)(item)
+    }
+}
+
+impl<T: 'static> Filter<T> {
+    pub fn new(predicate: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    pub fn and(self, other: impl Into<Filter<T>>) -> Self {
+        let other = other.into();
+        Self::new(move |item| self.matches(item) && other.matches(item))
+    }
+
+    pub fn or(self, other: impl Into<Filter<T>>) -> Self {
+        let other = other.into();
+        Self::new(move |item| self.matches(item) || other.matches(item))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self::new(move |item| !self.matches(item))
+    }
+}
+
+impl<T: 'static, F> From<F> for Filter<T>
+where
+    F: Fn(&T) -> bool + Send + Sync + 'static,
+{
+    fn from(predicate: F) -> Self {
+        Self::new(predicate)
+    }
+}
+
+type EarlyPredicate = Arc<dyn Fn(u32, Option<&str>) -> bool + Send + Sync>;
+
+/// A predicate on the ID of a record and its status, which only releases have.
+#[derive(Clone)]
+pub struct EarlyFilter {
+    predicate: EarlyPredicate,
+}
+
+impl fmt::Debug for EarlyFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EarlyFilter").finish_non_exhaustive()
+    }
+}
+
+impl EarlyFilter {
+    pub fn new(predicate: impl Fn(u32, Option<&str>) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    /// Records with one of `ids`.
+    pub fn ids(ids: HashSet<u32>) -> Self {
+        Self::new(move |id, _| ids.contains(&id))
+    }
+
+    /// Releases with the status [`Accepted`](ReleaseStatus::Accepted), which are
+    /// skipped before they're parsed otherwise. Records of other entities match.
+    pub fn accepted() -> Self {
+        Self::new(|_, status| status.is_none_or(|s| ReleaseStatus::from(s).is_accepted()))
+    }
+
+    pub fn matches(&self, id: u32, status: Option<&str>) -> bool {
+        (self.predicate)(id, status)
+    }
+
+    pub fn and(self, other: impl Into<EarlyFilter>) -> Self {
+        let other = other.into();
+        Self::new(move |id, status| self.matches(id, status) && other.matches(id, status))
+    }
+
+    pub fn or(self, other: impl Into<EarlyFilter>) -> Self {
+        let other = other.into();
+        Self::new(move |id, status| self.matches(id, status) || other.matches(id, status))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self::new(move |id, status| !self.matches(id, status))
+    }
+}
+
+impl<F> From<F> for EarlyFilter
+where
+    F: Fn(u32, Option<&str>) -> bool + Send + Sync + 'static,
+{
+    fn from(predicate: F) -> Self {
+        Self::new(predicate)
+    }
+}
+
+/// What a reader does with a record once its ID is known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EarlyDecision {
+    Parse,
+    Skip,
+    /// The record is past the end of the ID range, so the rest of the dump is too.
+    Stop,
+}
+
+/// The checks a reader makes as soon as a record's ID is read.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EarlyChecks {
+    pub(crate) filter: Option<EarlyFilter>,
+    pub(crate) id_range: Option<(Bound<u32>, Bound<u32>)>,
+}
+
+impl EarlyChecks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.filter.is_none() && self.id_range.is_none()
+    }
+
+    pub(crate) fn check(&self, id: u32, status: Option<&str>) -> EarlyDecision {
+        if let Some((start, end)) = &self.id_range {
+            let past_end = match end {
+                Bound::Included(end) => id > *end,
+                Bound::Excluded(end) => id >= *end,
+                Bound::Unbounded => false,
+            };
+            if past_end {
+                return EarlyDecision::Stop;
+            }
+            let before_start = match start {
+                Bound::Included(start) => id < *start,
+                Bound::Excluded(start) => id <= *start,
+                Bound::Unbounded => false,
+            };
+            if before_start {
+                return EarlyDecision::Skip;
+            }
+        }
+        match &self.filter {
+            Some(filter) if !filter.matches(id, status) => EarlyDecision::Skip,
+            _ => EarlyDecision::Parse,
+        }
+    }
+}
+
+/// Records that have images and videos.
+pub trait Media {
+    fn images(&self) -> &[Image];
+
+    fn videos(&self) -> &[Video] {
+        &[]
+    }
+}
+
+impl Media for Artist {
+    fn images(&self) -> &[Image] {
+        &self.images
+    }
+}
+
+impl Media for Label {
+    fn images(&self) -> &[Image] {
+        &self.images
+    }
+}
+
+impl Media for Master {
+    fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    fn videos(&self) -> &[Video] {
+        &self.videos
+    }
+}
+
+impl Media for Release {
+    fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    fn videos(&self) -> &[Video] {
+        &self.videos
+    }
+}
+
+/// Records with at least `count` images.
+pub fn min_images<T: Media + 'static>(count: usize) -> Filter<T> {
+    Filter::new(move |item: &T| item.images().len() >= count)
+}
+
+/// Records with at least `count` videos.
+pub fn min_videos<T: Media + 'static>(count: usize) -> Filter<T> {
+    Filter::new(move |item: &T| item.videos().len() >= count)
+}
+
+/// Records with at least one video longer than `secs` seconds.
+pub fn video_longer_than<T: Media + 'static>(secs: u32) -> Filter<T> {
+    Filter::new(move |item: &T| item.videos().iter().any(|v| v.duration > secs))
+}
+
+/// Records whose videos last at least `secs` seconds in total.
+pub fn total_video_secs<T: Media + 'static>(secs: u64) -> Filter<T> {
+    Filter::new(move |item: &T| {
+        item.videos().iter().map(|v| v.duration as u64).sum::<u64>() >= secs
+    })
+}
@@ -0,0 +1,746 @@
+//! Filters written as expressions, e.g. `country == "UK" && year >= 1990 && format has "LP"`,
+//! so they can be given on a command line or in a config file instead of as closures.
+//!
+//! An expression compares the fields of a record with strings or integers, and
+//! combines the comparisons with `&&`, `||`, `!` and parentheses:
+//!
+//! | Operator | Meaning |
+//! | --- | --- |
+//! | `==`, `!=` | equal or not |
+//! | `<`, `<=`, `>`, `>=` | less or greater than |
+//! | `has` | a list field has an item equal to the string, or a string field contains it |
+//!
+//! Strings are always compared ignoring case, by their lowercase characters, so
+//! `country == "uk"` matches releases from the UK and `title < "b"` matches titles
+//! that start with "a" or "A". `!` binds tighter than `&&`, which binds tighter
+//! than `||`.
+//!
+//! The fields of each entity are listed by [`Queryable::FIELDS`]. A [`Query`] is
+//! checked against them when it's parsed, so a misspelled field or a comparison of
+//! a number with a string is an error rather than a filter that never matches.
+//!
+//! Comparisons of the `id`, and of the `status` of releases, are known before a
+//! record is parsed. When they're required for the whole expression to match, e.g.
+//! `id < 1000 && country == "UK"`, [`EntityReader::filter_query`] turns them into
+//! an [`EarlyFilter`], so records that fail them are skipped without being parsed.
+//!
+//! ```no_run
+//! use disco_quick::filter::expr::Query;
+//! use disco_quick::DiscogsReader;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let query = Query::parse(r#"status == "Accepted" && country == "UK" && format has "LP""#)?;
+//! if let DiscogsReader::Releases(releases) = DiscogsReader::from_path("releases.xml.gz".as_ref())? {
+//!     for release in releases.filter_query(query) {
+//!         println!("{}", release?);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`EntityReader::filter_query`]: crate::reader::EntityReader::filter_query
+use super::{EarlyFilter, Filter};
+use crate::artist::Artist;
+use crate::date::ReleaseDate;
+use crate::label::Label;
+use crate::master::Master;
+use crate::release::Release;
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ExprError {
+    #[error("Unexpected character {found:?} at {position}")]
+    UnexpectedChar { position: usize, found: char },
+    #[error("Unterminated string starting at {position}")]
+    UnterminatedString { position: usize },
+    #[error("Invalid number {found:?} at {position}")]
+    InvalidNumber { position: usize, found: String },
+    #[error("Expected {expected} at {position}, found {found}")]
+    UnexpectedToken {
+        position: usize,
+        expected: &'static str,
+        found: String,
+    },
+    #[error("{entity} have no field named {field:?}")]
+    UnknownField { entity: &'static str, field: String },
+    #[error("{field} is {ty}, which can't be compared with {op} {value}")]
+    TypeMismatch {
+        field: String,
+        ty: FieldType,
+        op: CmpOp,
+        value: String,
+    },
+}
+
+/// The type of a field that expressions can use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    Int,
+    Str,
+    /// A list of strings, e.g. the genres of a release.
+    List,
+}
+
+/// A field's value in a record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value<'a> {
+    Int(i64),
+    Str(&'a str),
+    List(Vec<&'a str>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Has,
+}
+
+/// Records whose fields can be used in expressions.
+pub trait Queryable {
+    /// The plural name of the entity, for errors.
+    const ENTITY: &'static str;
+    /// The fields and their types.
+    const FIELDS: &'static [(&'static str, FieldType)];
+    /// The fields known before the record is parsed.
+    const EARLY_FIELDS: &'static [&'static str] = &["id"];
+
+    /// The value of `field`, which is one of [`FIELDS`](Self::FIELDS).
+    fn value(&self, field: &str) -> Value<'_>;
+}
+
+/// A parsed expression that filters records of type `T`.
+pub struct Query<T> {
+    expr: Arc<Expr>,
+    early: Option<Arc<Expr>>,
+    source: String,
+    entity: PhantomData<fn(&T)>,
+}
+
+impl<T> Clone for Query<T> {
+    fn clone(&self) -> Self {
+        Self {
+            expr: self.expr.clone(),
+            early: self.early.clone(),
+            source: self.source.clone(),
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Query<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Query").field(&self.source).finish()
+    }
+}
+
+impl<T> fmt::Display for Query<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<T: Queryable> Query<T> {
+    /// Parse `source` and check its fields against those of `T`.
+    ///
+    /// ```
+    /// use disco_quick::filter::expr::{ExprError, Query};
+    /// use disco_quick::release::Release;
+    ///
+    /// let error = Query::<Release>::parse("colour == \"red\"").unwrap_err();
+    /// assert!(matches!(error, ExprError::UnknownField { .. }));
+    /// let error = Query::<Release>::parse("year == \"1990\"").unwrap_err();
+    /// assert!(matches!(error, ExprError::TypeMismatch { .. }));
+    /// let error = Query::<Release>::parse("genre == \"Rock\"").unwrap_err();
+    /// assert!(matches!(error, ExprError::TypeMismatch { .. }));
+    /// let error = Query::<Release>::parse("(year > 1990").unwrap_err();
+    /// assert_eq!(error.to_string(), "Expected ) at 12, found the end");
+    /// let error = Query::<Release>::parse("title == \"Blue").unwrap_err();
+    /// assert_eq!(error, ExprError::UnterminatedString { position: 9 });
+    /// let error = Query::<Release>::parse("year > 19x0").unwrap_err();
+    /// assert!(matches!(error, ExprError::InvalidNumber { .. }));
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let tokens = tokenize(source)?;
+        let mut parser = ExprParser {
+            tokens: &tokens,
+            next: 0,
+            end: source.len(),
+        };
+        let expr = parser.or::<T>()?;
+        if let Some((position, token)) = parser.tokens.get(parser.next) {
+            return Err(ExprError::UnexpectedToken {
+                position: *position,
+                expected: "&&, || or the end",
+                found: token.to_string(),
+            });
+        }
+        let mut early = Vec::new();
+        expr.early_conjuncts(T::EARLY_FIELDS, &mut early);
+        let early = early
+            .into_iter()
+            .cloned()
+            .reduce(|a, b| Expr::And(Box::new(a), Box::new(b)));
+        Ok(Self {
+            expr: Arc::new(expr),
+            early: early.map(Arc::new),
+            source: source.to_string(),
+            entity: PhantomData,
+        })
+    }
+
+    /// ```
+    /// use disco_quick::filter::expr::Query;
+    /// use disco_quick::release::Release;
+    ///
+    /// let release = Release {
+    ///     title: "Blue Lines".to_string(),
+    ///     country: "UK".into(),
+    ///     genres: vec!["Electronic".into()],
+    ///     ..Default::default()
+    /// };
+    /// let matches = |source| Query::parse(source).unwrap().matches(&release);
+    /// // Strings are compared ignoring case
+    /// assert!(matches(r#"country == "uk""#));
+    /// assert!(matches(r#"title > "b" && title < "C""#));
+    /// assert!(matches(r#"genre has "ELECTRONIC" && title has "lines""#));
+    /// // && binds tighter than ||, and ! than both
+    /// assert!(matches(r#"country == "US" && year > 2000 || country == "UK""#));
+    /// assert!(!matches(r#"country == "US" && (year > 2000 || country == "UK")"#));
+    /// assert!(matches(r#"!country == "US" && !genre has "Rock""#));
+    /// ```
+    pub fn matches(&self, record: &T) -> bool {
+        self.expr.eval(&|field| record.value(field))
+    }
+
+    /// The parts of the expression that can be checked before a record is parsed
+    /// and must match for the whole expression to, if there are any.
+    ///
+    /// ```
+    /// use disco_quick::filter::expr::Query;
+    /// use disco_quick::release::Release;
+    ///
+    /// let early = |source| Query::<Release>::parse(source).unwrap().early_filter();
+    /// let filter = early(r#"id < 1000 && country == "UK" && status == "accepted""#).unwrap();
+    /// assert!(filter.matches(1, Some("Accepted")));
+    /// assert!(!filter.matches(1, Some("Draft")));
+    /// assert!(!filter.matches(1000, Some("Accepted")));
+    /// // Only comparisons required by the whole expression are checked early
+    /// assert!(early(r#"id < 1000 || country == "UK""#).is_none());
+    /// let filter = early(r#"(id < 10 || id > 20) && !country == "UK""#).unwrap();
+    /// assert!(!filter.matches(15, None));
+    /// ```
+    pub fn early_filter(&self) -> Option<EarlyFilter> {
+        let early = self.early.clone()?;
+        Some(EarlyFilter::new(move |id, status| {
+            early.eval(&|field| match field {
+                "id" => Value::Int(id.into()),
+                _ => Value::Str(status.unwrap_or("")),
+            })
+        }))
+    }
+}
+
+impl<T: Queryable + 'static> From<Query<T>> for Filter<T> {
+    fn from(query: Query<T>) -> Self {
+        Filter::new(move |record| query.matches(record))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp {
+        field: &'static str,
+        op: CmpOp,
+        value: Literal,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Literal {
+    Int(i64),
+    /// A string, and its lowercase form for comparisons that ignore case.
+    Str(String, String),
+}
+
+impl Expr {
+    fn eval<'a>(&self, value: &impl Fn(&str) -> Value<'a>) -> bool {
+        match self {
+            Self::And(a, b) => a.eval(value) && b.eval(value),
+            Self::Or(a, b) => a.eval(value) || b.eval(value),
+            Self::Not(a) => !a.eval(value),
+            Self::Cmp {
+                field,
+                op,
+                value: literal,
+            } => compare(&value(field), *op, literal),
+        }
+    }
+
+    fn is_early(&self, fields: &[&str]) -> bool {
+        match self {
+            Self::And(a, b) | Self::Or(a, b) => a.is_early(fields) && b.is_early(fields),
+            Self::Not(a) => a.is_early(fields),
+            Self::Cmp { field, .. } => fields.contains(field),
+        }
+    }
+
+    /// Add the early parts of the top-level conjunction to `early`.
+    fn early_conjuncts<'a>(&'a self, fields: &[&str], early: &mut Vec<&'a Expr>) {
+        match self {
+            Self::And(a, b) => {
+                a.early_conjuncts(fields, early);
+                b.early_conjuncts(fields, early);
+            }
+            expr if expr.is_early(fields) => early.push(expr),
+            _ => {}
+        }
+    }
+}
+
+fn compare(value: &Value, op: CmpOp, literal: &Literal) -> bool {
+    match (value, literal) {
+        (Value::Int(a), Literal::Int(b)) => match op {
+            CmpOp::Eq => a == b,
+            CmpOp::Ne => a != b,
+            CmpOp::Lt => a < b,
+            CmpOp::Le => a <= b,
+            CmpOp::Gt => a > b,
+            CmpOp::Ge => a >= b,
+            CmpOp::Has => false,
+        },
+        (Value::Str(a), Literal::Str(b, lower)) => match op {
+            CmpOp::Eq => eq_ignore_case(a, b),
+            CmpOp::Ne => !eq_ignore_case(a, b),
+            CmpOp::Lt => cmp_ignore_case(a, lower).is_lt(),
+            CmpOp::Le => cmp_ignore_case(a, lower).is_le(),
+            CmpOp::Gt => cmp_ignore_case(a, lower).is_gt(),
+            CmpOp::Ge => cmp_ignore_case(a, lower).is_ge(),
+            CmpOp::Has => a.to_lowercase().contains(lower.as_str()),
+        },
+        (Value::List(items), Literal::Str(b, _)) => {
+            op == CmpOp::Has && items.iter().any(|item| eq_ignore_case(item, b))
+        }
+        _ => false,
+    }
+}
+
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+        || (!a.is_ascii()
+            && a.chars()
+                .flat_map(char::to_lowercase)
+                .eq(b.chars().flat_map(char::to_lowercase)))
+}
+
+/// Compare `a` with `lower`, which is already lowercase, ignoring the case of `a`.
+fn cmp_ignore_case(a: &str, lower: &str) -> Ordering {
+    a.chars().flat_map(char::to_lowercase).cmp(lower.chars())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Cmp(CmpOp),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ident(name) => write!(f, "{name:?}"),
+            Self::Str(s) => write!(f, "the string {s:?}"),
+            Self::Int(n) => write!(f, "the number {n}"),
+            Self::Cmp(op) => write!(f, "{op}"),
+            Self::And => f.write_str("&&"),
+            Self::Or => f.write_str("||"),
+            Self::Not => f.write_str("!"),
+            Self::Open => f.write_str("("),
+            Self::Close => f.write_str(")"),
+        }
+    }
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Has => "has",
+        })
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Int => "a number",
+            Self::Str => "a string",
+            Self::List => "a list",
+        })
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Int(n) => write!(f, "{n}"),
+            Self::Str(s, _) => write!(f, "{s:?}"),
+        }
+    }
+}
+
+/// Split an expression into tokens with their byte offsets.
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((position, c)) = chars.next() {
+        let mut followed_by = |next: char| chars.next_if(|(_, c)| *c == next).is_some();
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if followed_by('&') => Token::And,
+            '|' if followed_by('|') => Token::Or,
+            '=' if followed_by('=') => Token::Cmp(CmpOp::Eq),
+            '!' if followed_by('=') => Token::Cmp(CmpOp::Ne),
+            '!' => Token::Not,
+            '<' if followed_by('=') => Token::Cmp(CmpOp::Le),
+            '<' => Token::Cmp(CmpOp::Lt),
+            '>' if followed_by('=') => Token::Cmp(CmpOp::Ge),
+            '>' => Token::Cmp(CmpOp::Gt),
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => s.push(c),
+                            None => return Err(ExprError::UnterminatedString { position }),
+                        },
+                        Some((_, c)) => s.push(c),
+                        None => return Err(ExprError::UnterminatedString { position }),
+                    }
+                }
+                Token::Str(s)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut end = position + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric()) {
+                    end = i + c.len_utf8();
+                }
+                let found = &source[position..end];
+                let n = found.parse().map_err(|_| ExprError::InvalidNumber {
+                    position,
+                    found: found.to_string(),
+                })?;
+                Token::Int(n)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = position + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    end = i + c.len_utf8();
+                }
+                match &source[position..end] {
+                    "has" => Token::Cmp(CmpOp::Has),
+                    name => Token::Ident(name.to_string()),
+                }
+            }
+            found => return Err(ExprError::UnexpectedChar { position, found }),
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser, with `||` binding looser than `&&`.
+struct ExprParser<'a> {
+    tokens: &'a [(usize, Token)],
+    next: usize,
+    /// The length of the source, for errors at the end.
+    end: usize,
+}
+
+impl ExprParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, t)| t)
+    }
+
+    fn advance(&mut self, expected: &'static str) -> Result<(usize, &Token), ExprError> {
+        match self.tokens.get(self.next) {
+            Some((position, token)) => {
+                self.next += 1;
+                Ok((*position, token))
+            }
+            None => Err(ExprError::UnexpectedToken {
+                position: self.end,
+                expected,
+                found: "the end".to_string(),
+            }),
+        }
+    }
+
+    fn or<T: Queryable>(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.and::<T>()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and::<T>()?));
+        }
+        Ok(expr)
+    }
+
+    fn and<T: Queryable>(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.unary::<T>()?;
+        while self.peek() == Some(&Token::And) {
+            self.next += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary::<T>()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary<T: Queryable>(&mut self) -> Result<Expr, ExprError> {
+        let (position, token) = self.advance("a field, ! or (")?;
+        match token {
+            Token::Not => Ok(Expr::Not(Box::new(self.unary::<T>()?))),
+            Token::Open => {
+                let expr = self.or::<T>()?;
+                match self.advance(")")? {
+                    (_, Token::Close) => Ok(expr),
+                    (position, found) => Err(ExprError::UnexpectedToken {
+                        position,
+                        expected: ")",
+                        found: found.to_string(),
+                    }),
+                }
+            }
+            Token::Ident(name) => {
+                let name = name.clone();
+                self.comparison::<T>(&name)
+            }
+            found => Err(ExprError::UnexpectedToken {
+                position,
+                expected: "a field, ! or (",
+                found: found.to_string(),
+            }),
+        }
+    }
+
+    fn comparison<T: Queryable>(&mut self, name: &str) -> Result<Expr, ExprError> {
+        let Some(&(field, ty)) = T::FIELDS.iter().find(|(f, _)| *f == name) else {
+            return Err(ExprError::UnknownField {
+                entity: T::ENTITY,
+                field: name.to_string(),
+            });
+        };
+        let op = match self.advance("a comparison")? {
+            (_, Token::Cmp(op)) => *op,
+            (position, found) => {
+                return Err(ExprError::UnexpectedToken {
+                    position,
+                    expected: "a comparison",
+                    found: found.to_string(),
+                })
+            }
+        };
+        let value = match self.advance("a string or number")? {
+            (_, Token::Int(n)) => Literal::Int(*n),
+            (_, Token::Str(s)) => Literal::Str(s.clone(), s.to_lowercase()),
+            (position, found) => {
+                return Err(ExprError::UnexpectedToken {
+                    position,
+                    expected: "a string or number",
+                    found: found.to_string(),
+                })
+            }
+        };
+        let valid = match (ty, &value) {
+            (FieldType::Int, Literal::Int(_)) => op != CmpOp::Has,
+            (FieldType::Str, Literal::Str(..)) => true,
+            (FieldType::List, Literal::Str(..)) => op == CmpOp::Has,
+            _ => false,
+        };
+        if !valid {
+            return Err(ExprError::TypeMismatch {
+                field: field.to_string(),
+                ty,
+                op,
+                value: value.to_string(),
+            });
+        }
+        Ok(Expr::Cmp { field, op, value })
+    }
+}
+
+impl Queryable for Artist {
+    const ENTITY: &'static str = "Artists";
+    const FIELDS: &'static [(&'static str, FieldType)] = &[
+        ("id", FieldType::Int),
+        ("name", FieldType::Str),
+        ("real_name", FieldType::Str),
+        ("data_quality", FieldType::Str),
+        ("name_variation", FieldType::List),
+        ("alias", FieldType::List),
+        ("member", FieldType::List),
+        ("group", FieldType::List),
+        ("url", FieldType::List),
+        ("images", FieldType::Int),
+    ];
+
+    fn value(&self, field: &str) -> Value<'_> {
+        match field {
+            "id" => Value::Int(self.id.into()),
+            "name" => Value::Str(&self.name),
+            "real_name" => Value::Str(self.real_name.as_deref().unwrap_or("")),
+            "data_quality" => Value::Str(self.data_quality.as_str()),
+            "name_variation" => Value::List(self.name_variations.iter().map(|n| &**n).collect()),
+            "alias" => Value::List(self.aliases.iter().map(|a| &*a.name).collect()),
+            "member" => Value::List(self.members.iter().map(|a| &*a.name).collect()),
+            "group" => Value::List(self.groups.iter().map(|a| &*a.name).collect()),
+            "url" => Value::List(self.urls.iter().map(|u| &**u).collect()),
+            "images" => Value::Int(self.images.len() as i64),
+            _ => unreachable!("unknown artist field {field}"),
+        }
+    }
+}
+
+impl Queryable for Label {
+    const ENTITY: &'static str = "Labels";
+    const FIELDS: &'static [(&'static str, FieldType)] = &[
+        ("id", FieldType::Int),
+        ("name", FieldType::Str),
+        ("parent", FieldType::Str),
+        ("data_quality", FieldType::Str),
+        ("sublabel", FieldType::List),
+        ("url", FieldType::List),
+        ("images", FieldType::Int),
+    ];
+
+    fn value(&self, field: &str) -> Value<'_> {
+        match field {
+            "id" => Value::Int(self.id.into()),
+            "name" => Value::Str(&self.name),
+            "parent" => Value::Str(self.parent_label.as_ref().map_or("", |p| &p.name)),
+            "data_quality" => Value::Str(self.data_quality.as_str()),
+            "sublabel" => Value::List(self.sublabels.iter().map(|l| &*l.name).collect()),
+            "url" => Value::List(self.urls.iter().map(|u| &**u).collect()),
+            "images" => Value::Int(self.images.len() as i64),
+            _ => unreachable!("unknown label field {field}"),
+        }
+    }
+}
+
+impl Queryable for Master {
+    const ENTITY: &'static str = "Masters";
+    const FIELDS: &'static [(&'static str, FieldType)] = &[
+        ("id", FieldType::Int),
+        ("title", FieldType::Str),
+        ("year", FieldType::Int),
+        ("main_release", FieldType::Int),
+        ("data_quality", FieldType::Str),
+        ("genre", FieldType::List),
+        ("style", FieldType::List),
+        ("artist", FieldType::List),
+        ("images", FieldType::Int),
+        ("videos", FieldType::Int),
+    ];
+
+    fn value(&self, field: &str) -> Value<'_> {
+        match field {
+            "id" => Value::Int(self.id.into()),
+            "title" => Value::Str(&self.title),
+            "year" => Value::Int(self.year.into()),
+            "main_release" => Value::Int(self.main_release.into()),
+            "data_quality" => Value::Str(self.data_quality.as_str()),
+            "genre" => Value::List(self.genres.iter().map(|g| &**g).collect()),
+            "style" => Value::List(self.styles.iter().map(|s| &**s).collect()),
+            "artist" => Value::List(self.artists.iter().map(|a| &*a.name).collect()),
+            "images" => Value::Int(self.images.len() as i64),
+            "videos" => Value::Int(self.videos.len() as i64),
+            _ => unreachable!("unknown master field {field}"),
+        }
+    }
+}
+
+impl Queryable for Release {
+    const ENTITY: &'static str = "Releases";
+    const FIELDS: &'static [(&'static str, FieldType)] = &[
+        ("id", FieldType::Int),
+        ("status", FieldType::Str),
+        ("title", FieldType::Str),
+        ("country", FieldType::Str),
+        ("released", FieldType::Str),
+        ("year", FieldType::Int),
+        ("master_id", FieldType::Int),
+        ("data_quality", FieldType::Str),
+        ("genre", FieldType::List),
+        ("style", FieldType::List),
+        ("format", FieldType::List),
+        ("label", FieldType::List),
+        ("catno", FieldType::List),
+        ("artist", FieldType::List),
+        ("tracks", FieldType::Int),
+        ("images", FieldType::Int),
+        ("videos", FieldType::Int),
+    ];
+    const EARLY_FIELDS: &'static [&'static str] = &["id", "status"];
+
+    /// `year` is 0 if the release date has no year, as is `master_id` if the release
+    /// has no master. `format` has the names and descriptions of the formats, e.g.
+    /// `Vinyl` and `LP`.
+    fn value(&self, field: &str) -> Value<'_> {
+        match field {
+            "id" => Value::Int(self.id.into()),
+            "status" => Value::Str(self.status.as_str()),
+            "title" => Value::Str(&self.title),
+            "country" => Value::Str(&self.country),
+            "released" => Value::Str(&self.released),
+            "year" => Value::Int(ReleaseDate::parse(&self.released).year.unwrap_or(0).into()),
+            "master_id" => Value::Int(self.master_id.map_or(0, i64::from)),
+            "data_quality" => Value::Str(self.data_quality.as_str()),
+            "genre" => Value::List(self.genres.iter().map(|g| &**g).collect()),
+            "style" => Value::List(self.styles.iter().map(|s| &**s).collect()),
+            "format" => Value::List(
+                self.formats
+                    .iter()
+                    .flat_map(|f| {
+                        std::iter::once(&*f.name).chain(f.descriptions.iter().map(|d| &**d))
+                    })
+                    .collect(),
+            ),
+            "label" => Value::List(self.labels.iter().map(|l| &*l.name).collect()),
+            "catno" => Value::List(
+                self.labels
+                    .iter()
+                    .filter_map(|l| l.catno.as_deref())
+                    .collect(),
+            ),
+            "artist" => Value::List(self.artists.iter().map(|a| &*a.name).collect()),
+            "tracks" => Value::Int(self.tracklist.len() as i64),
+            "images" => Value::Int(self.images.len() as i64),
+            "videos" => Value::Int(self.videos.len() as i64),
+            _ => unreachable!("unknown release field {field}"),
+        }
+    }
+}
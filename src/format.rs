@@ -0,0 +1,167 @@
+//! The media and descriptions of release formats, parsed from their names.
+//!
+//! The dumps give each format of a release as a quantity, a name like `Vinyl` and
+//! descriptions like `LP` or `45 RPM`, all as text. [`ReleaseFormat`] keeps the
+//! text as it is, and its [`media`](ReleaseFormat::media) and
+//! [`description_flags`](ReleaseFormat::description_flags) parse it into the types
+//! here. Values the crate doesn't know are kept as `Other`.
+//!
+//! ```
+//! use disco_quick::format::{FormatDescription, MediaType};
+//! use disco_quick::release::ReleaseFormat;
+//!
+//! let format = ReleaseFormat {
+//!     qty: "2".into(),
+//!     name: "Vinyl".into(),
+//!     text: None,
+//!     descriptions: vec!["LP".into(), "Album".into(), "33 ⅓ RPM".into()],
+//! };
+//! assert_eq!(format.qty_parsed(), Some(2));
+//! assert_eq!(format.media(), MediaType::Vinyl);
+//! assert!(format.has_description(&FormatDescription::Lp));
+//! assert_eq!(format.rpm(), Some(33.33));
+//! ```
+use crate::release::ReleaseFormat;
+use crate::shared::string_enum;
+
+string_enum! {
+    /// The kind of media of a format, parsed from its name.
+    pub enum MediaType {
+        Vinyl => "Vinyl",
+        Acetate => "Acetate",
+        FlexiDisc => "Flexi-disc",
+        LatheCut => "Lathe Cut",
+        Shellac => "Shellac",
+        Cd => "CD",
+        Cdr => "CDr",
+        Sacd => "SACD",
+        Dvd => "DVD",
+        Dvdr => "DVDr",
+        BluRay => "Blu-ray",
+        Minidisc => "Minidisc",
+        Cassette => "Cassette",
+        Microcassette => "Microcassette",
+        EightTrack => "8-Track Cartridge",
+        ReelToReel => "Reel-To-Reel",
+        Dat => "DAT",
+        Vhs => "VHS",
+        Laserdisc => "Laserdisc",
+        FloppyDisk => "Floppy Disk",
+        /// Digital files.
+        File => "File",
+        BoxSet => "Box Set",
+        /// A release with media of several kinds.
+        AllMedia => "All Media",
+    }
+    /// A name the crate doesn't know, or an empty string if the format had none.
+    else Other(String);
+}
+
+string_enum! {
+    /// A description of a format, e.g. its size, speed or the kind of release.
+    pub enum FormatDescription {
+        Lp => "LP",
+        Ep => "EP",
+        Single => "Single",
+        MaxiSingle => "Maxi-Single",
+        Album => "Album",
+        MiniAlbum => "Mini-Album",
+        Compilation => "Compilation",
+        Reissue => "Reissue",
+        Repress => "Repress",
+        Remastered => "Remastered",
+        Promo => "Promo",
+        LimitedEdition => "Limited Edition",
+        Numbered => "Numbered",
+        TestPressing => "Test Pressing",
+        WhiteLabel => "White Label",
+        Unofficial => "Unofficial Release",
+        Mixed => "Mixed",
+        Stereo => "Stereo",
+        Mono => "Mono",
+        Quadraphonic => "Quadraphonic",
+        Inch7 => "7\"",
+        Inch10 => "10\"",
+        Inch12 => "12\"",
+        Rpm16 => "16 ⅔ RPM",
+        Rpm33 => "33 ⅓ RPM",
+        Rpm45 => "45 RPM",
+        Rpm78 => "78 RPM",
+    }
+    /// A description the crate doesn't know.
+    else Other(String);
+}
+
+impl MediaType {
+    /// Whether the media is a record cut or pressed with a groove, e.g. vinyl or
+    /// shellac.
+    pub fn is_disc_record(&self) -> bool {
+        matches!(
+            self,
+            Self::Vinyl | Self::Acetate | Self::FlexiDisc | Self::LatheCut | Self::Shellac
+        )
+    }
+
+    pub fn is_physical(&self) -> bool {
+        !matches!(self, Self::File | Self::Other(_))
+    }
+}
+
+impl FormatDescription {
+    /// The speed in revolutions per minute of a speed description, e.g. 33.33 for
+    /// `33 ⅓ RPM`.
+    pub fn rpm(&self) -> Option<f32> {
+        match self {
+            Self::Rpm16 => Some(16.67),
+            Self::Rpm33 => Some(33.33),
+            Self::Rpm45 => Some(45.0),
+            Self::Rpm78 => Some(78.0),
+            _ => None,
+        }
+    }
+
+    /// The diameter in inches of a size description, e.g. 7 for `7"`.
+    pub fn size_inches(&self) -> Option<u8> {
+        match self {
+            Self::Inch7 => Some(7),
+            Self::Inch10 => Some(10),
+            Self::Inch12 => Some(12),
+            _ => None,
+        }
+    }
+}
+
+impl ReleaseFormat {
+    /// The number of items of the format, or None if the quantity isn't a number.
+    pub fn qty_parsed(&self) -> Option<u32> {
+        self.qty.trim().parse().ok()
+    }
+
+    /// The kind of media, parsed from the name.
+    pub fn media(&self) -> MediaType {
+        MediaType::from(&*self.name)
+    }
+
+    /// The descriptions, parsed.
+    pub fn description_flags(&self) -> impl Iterator<Item = FormatDescription> + '_ {
+        self.descriptions
+            .iter()
+            .map(|d| FormatDescription::from(&**d))
+    }
+
+    pub fn has_description(&self, description: &FormatDescription) -> bool {
+        self.descriptions
+            .iter()
+            .any(|d| d.trim() == description.as_str())
+    }
+
+    /// The speed of the first speed description, e.g. 45.0 for `45 RPM`.
+    pub fn rpm(&self) -> Option<f32> {
+        self.description_flags().find_map(|d| d.rpm())
+    }
+
+    /// The size of the first size description, e.g. 12 for `12"`.
+    pub fn size_inches(&self) -> Option<u8> {
+        self.description_flags().find_map(|d| d.size_inches())
+    }
+}
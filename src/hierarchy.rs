@@ -0,0 +1,94 @@
+//! The parent chains of labels, for grouping labels under their top-level label.
+//!
+//! A label in the labels dump only names its immediate parent. A [`LabelHierarchy`]
+//! is built from the whole dump in a first pass, and then gives the ancestors of any
+//! label. Pass one to [`CsvExport::label_hierarchy`](crate::export::csv::CsvExport::label_hierarchy)
+//! to have each label's root and depth written with it.
+//!
+//! ```no_run
+//! use disco_quick::hierarchy::LabelHierarchy;
+//! use disco_quick::id::LabelId;
+//!
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! let hierarchy = LabelHierarchy::from_path("labels.xml.gz".as_ref())?;
+//! let id = LabelId(1);
+//! if let Some(root) = hierarchy.root(id) {
+//!     println!("{id} is {} levels below {}", hierarchy.depth(id), root.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::id::LabelId;
+use crate::label::{Label, LabelInfo};
+use crate::reader::{DiscogsReader, ReaderError};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The parent of each label that has one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LabelHierarchy {
+    parents: HashMap<LabelId, LabelInfo>,
+}
+
+impl LabelHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the parents of all the labels in a labels dump.
+    pub fn from_path(path: &Path) -> Result<Self, ReaderError> {
+        let DiscogsReader::Labels(labels) = DiscogsReader::from_path(path)? else {
+            return Err(ReaderError::InvalidStartTag);
+        };
+        let mut hierarchy = Self::new();
+        for label in *labels {
+            hierarchy.add(&label?);
+        }
+        Ok(hierarchy)
+    }
+
+    pub fn add(&mut self, label: &Label) {
+        if let Some(parent) = &label.parent_label {
+            self.parents.insert(label.id, parent.clone());
+        }
+    }
+
+    /// The number of labels that have a parent.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    pub fn parent(&self, id: LabelId) -> Option<&LabelInfo> {
+        self.parents.get(&id)
+    }
+
+    /// The parent of the label, its parent, and so on up to the top-level label. It
+    /// stops before any label that has already been listed, in case the dump has a
+    /// cycle of parents.
+    pub fn ancestors(&self, id: LabelId) -> Vec<&LabelInfo> {
+        let mut ancestors: Vec<&LabelInfo> = Vec::new();
+        let mut current = id;
+        while let Some(parent) = self.parents.get(&current) {
+            if parent.id == id || ancestors.iter().any(|a| a.id == parent.id) {
+                break;
+            }
+            ancestors.push(parent);
+            current = parent.id;
+        }
+        ancestors
+    }
+
+    /// The top-level label above the label, or None if it has no parent.
+    pub fn root(&self, id: LabelId) -> Option<&LabelInfo> {
+        self.ancestors(id).pop()
+    }
+
+    /// The number of labels above the label, which is 0 for top-level labels.
+    pub fn depth(&self, id: LabelId) -> usize {
+        self.ancestors(id).len()
+    }
+}
@@ -0,0 +1,178 @@
+//! Reading dumps over HTTP(S) without downloading them first.
+//!
+//! [`DiscogsReader::from_url`](crate::DiscogsReader::from_url) streams a dump and
+//! decompresses it on the fly. The connection is read through an [`HttpReader`],
+//! which picks up where it left off with a range request if it drops, so a long
+//! read doesn't have to start over.
+//!
+//! ```no_run
+//! use disco_quick::DiscogsReader;
+//!
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! let url = "https://discogs-data-dumps.s3.us-west-2.amazonaws.com/data/2024/discogs_20240101_labels.xml.gz";
+//! for label in DiscogsReader::from_url(url)?.take(10) {
+//!     println!("{}", label?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use log::warn;
+use std::io::{self, Read};
+use std::thread::sleep;
+use std::time::Duration;
+
+/// The number of times in a row a request is retried before giving up.
+pub const DEFAULT_RETRIES: u32 = 5;
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Reads the body of a URL, reconnecting with a range request for the rest of the
+/// body when the connection fails.
+pub struct HttpReader {
+    agent: ureq::Agent,
+    url: String,
+    body: Box<dyn Read + Send + Sync>,
+    offset: u64,
+    len: Option<u64>,
+    max_retries: u32,
+    /// The number of failed attempts since data was last read.
+    failures: u32,
+}
+
+impl HttpReader {
+    pub fn open(url: &str) -> io::Result<Self> {
+        Self::with_retries(url, DEFAULT_RETRIES)
+    }
+
+    /// Open `url`, retrying failed requests up to `max_retries` times in a row with
+    /// an increasing delay between them.
+    pub fn with_retries(url: &str, max_retries: u32) -> io::Result<Self> {
+        Self::starting_at(url, 0, max_retries)
+    }
+
+    /// Open `url` at byte `offset` of the body, e.g. to continue a partial download.
+    pub fn starting_at(url: &str, offset: u64, max_retries: u32) -> io::Result<Self> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(30))
+            .timeout_read(Duration::from_secs(60))
+            .build();
+        let mut reader = Self {
+            agent,
+            url: url.to_string(),
+            body: Box::new(io::empty()),
+            offset,
+            len: None,
+            max_retries,
+            failures: 0,
+        };
+        if let Err(e) = reader.request() {
+            reader.resume(e)?;
+        }
+        Ok(reader)
+    }
+
+    /// The length of the body, if the server sent it.
+    pub fn content_length(&self) -> Option<u64> {
+        self.len
+    }
+
+    /// The number of bytes of the body read so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Request the body from the current offset again after `error`, waiting longer
+    /// before each attempt. Fails once there have been too many failures in a row.
+    fn resume(&mut self, mut error: io::Error) -> io::Result<()> {
+        while self.failures < self.max_retries && is_retryable(&error) {
+            let delay = FIRST_RETRY_DELAY
+                .saturating_mul(1 << self.failures.min(16))
+                .min(MAX_RETRY_DELAY);
+            self.failures += 1;
+            warn!(
+                "Reading {} failed at byte {}, retrying in {delay:?} ({}/{}): {error}",
+                self.url, self.offset, self.failures, self.max_retries
+            );
+            sleep(delay);
+            match self.request() {
+                Ok(()) => return Ok(()),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    fn request(&mut self) -> io::Result<()> {
+        let mut request = self.agent.get(&self.url);
+        if self.offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", self.offset));
+        }
+        let response = request.call().map_err(io::Error::other)?;
+        if self.offset > 0 && response.status() != 206 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} can't be resumed without range requests", self.url),
+            ));
+        }
+        if self.len.is_none() {
+            self.len = response
+                .header("Content-Length")
+                .and_then(|len| len.parse::<u64>().ok())
+                .map(|len| len + self.offset);
+        }
+        self.body = response.into_reader();
+        Ok(())
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.len.is_some_and(|len| self.offset < len)
+    }
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let error = match self.body.read(buf) {
+                Ok(0) if self.is_truncated() => {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed early")
+                }
+                Ok(n) => {
+                    self.offset += n as u64;
+                    if n > 0 {
+                        self.failures = 0;
+                    }
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => e,
+            };
+            self.resume(error)?;
+        }
+    }
+}
+
+/// The HTTP status of a failed request, if the server responded.
+pub(crate) fn status(error: &io::Error) -> Option<u16> {
+    match error.get_ref()?.downcast_ref::<ureq::Error>()? {
+        ureq::Error::Status(status, _) => Some(*status),
+        ureq::Error::Transport(_) => None,
+    }
+}
+
+/// Whether a failed request might succeed if it's tried again.
+fn is_retryable(error: &io::Error) -> bool {
+    let Some(error) = error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<ureq::Error>())
+    else {
+        return error.kind() != io::ErrorKind::Unsupported;
+    };
+    match error {
+        ureq::Error::Status(status, _) => *status == 429 || *status >= 500,
+        ureq::Error::Transport(_) => true,
+    }
+}
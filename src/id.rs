@@ -0,0 +1,116 @@
+//! Typed IDs of artists, labels, masters and releases.
+//!
+//! Each entity has IDs of its own, so the same number can be an artist, a label, a
+//! master and a release. The fields of records that hold IDs have a type for the
+//! entity they refer to, e.g. the `id` of an [`ArtistCredit`](crate::artist_credit::ArtistCredit)
+//! is an [`ArtistId`] and that of a [`ReleaseLabel`](crate::shared::ReleaseLabel) a
+//! [`LabelId`], so one can't be looked up as the other by mistake. They're
+//! serialized as plain numbers.
+//!
+//! ```
+//! use disco_quick::id::{ArtistId, LabelId};
+//!
+//! let artist = ArtistId::new(1);
+//! assert_eq!(artist.get(), 1);
+//! assert_eq!(artist, 1);
+//! assert_eq!(u32::from(LabelId::from(5)), 5);
+//! assert_eq!("42".parse::<ArtistId>(), Ok(ArtistId(42)));
+//! ```
+use crate::shared::EntityType;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+macro_rules! discogs_id {
+    ($($(#[$doc:meta])* $t:ident => $entity:ident),*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+            #[cfg_attr(
+                feature = "serde",
+                derive(serde::Serialize, serde::Deserialize),
+                serde(transparent)
+            )]
+            pub struct $t(pub u32);
+
+            impl $t {
+                /// The entity the ID is of.
+                pub const ENTITY: EntityType = EntityType::$entity;
+
+                pub const fn new(id: u32) -> Self {
+                    Self(id)
+                }
+
+                pub const fn get(self) -> u32 {
+                    self.0
+                }
+
+                /// Whether the ID refers to a record. Credits of artists and labels
+                /// that aren't in the database have an ID of 0.
+                pub const fn is_valid(self) -> bool {
+                    self.0 != 0
+                }
+
+                /// The ID, or None if it's 0.
+                pub fn valid(self) -> Option<Self> {
+                    self.is_valid().then_some(self)
+                }
+            }
+
+            impl From<u32> for $t {
+                fn from(id: u32) -> Self {
+                    Self(id)
+                }
+            }
+
+            impl From<$t> for u32 {
+                fn from(id: $t) -> u32 {
+                    id.0
+                }
+            }
+
+            impl From<$t> for u64 {
+                fn from(id: $t) -> u64 {
+                    id.0.into()
+                }
+            }
+
+            impl From<$t> for i64 {
+                fn from(id: $t) -> i64 {
+                    id.0.into()
+                }
+            }
+
+            impl PartialEq<u32> for $t {
+                fn eq(&self, other: &u32) -> bool {
+                    self.0 == *other
+                }
+            }
+
+            impl fmt::Display for $t {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    self.0.fmt(f)
+                }
+            }
+
+            impl FromStr for $t {
+                type Err = ParseIntError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    s.trim().parse().map(Self)
+                }
+            }
+        )*
+    };
+}
+
+discogs_id!(
+    /// The ID of an artist.
+    ArtistId => Artist,
+    /// The ID of a label or company.
+    LabelId => Label,
+    /// The ID of a master.
+    MasterId => Master,
+    /// The ID of a release.
+    ReleaseId => Release
+);
@@ -0,0 +1,93 @@
+//! The kinds of release identifiers, and validation of the barcodes among them.
+use crate::shared::string_enum;
+
+string_enum! {
+    /// The type of a release identifier, parsed from the `type` attribute.
+    pub enum IdentifierKind {
+        Barcode => "Barcode",
+        LabelCode => "Label Code",
+        MatrixRunout => "Matrix / Runout",
+        RightsSociety => "Rights Society",
+        PriceCode => "Price Code",
+        SparsCode => "SPARS Code",
+        MasteringSidCode => "Mastering SID Code",
+        MouldSidCode => "Mould SID Code",
+        Isrc => "ISRC",
+        Asin => "ASIN",
+        DepositoLegal => "Depósito Legal",
+        /// Identifiers of the type `Other`, whose description says what they are.
+        Other => "Other",
+    }
+    /// A type the crate doesn't know, as written in the dump.
+    else Unknown(String);
+    serde = key;
+}
+
+impl IdentifierKind {
+    /// A name for the kind in snake case, e.g. `matrix_runout`, as used when it's
+    /// serialized. Unknown kinds are `unknown`. As the type it was parsed from is
+    /// kept alongside it, the kind isn't deserialized but parsed again from that.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Barcode => "barcode",
+            Self::LabelCode => "label_code",
+            Self::MatrixRunout => "matrix_runout",
+            Self::RightsSociety => "rights_society",
+            Self::PriceCode => "price_code",
+            Self::SparsCode => "spars_code",
+            Self::MasteringSidCode => "mastering_sid_code",
+            Self::MouldSidCode => "mould_sid_code",
+            Self::Isrc => "isrc",
+            Self::Asin => "asin",
+            Self::DepositoLegal => "deposito_legal",
+            Self::Other => "other",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+}
+
+/// The digits of a barcode with the spaces and hyphens used to group them removed,
+/// or None if it contains anything else.
+pub fn barcode_digits(value: &str) -> Option<String> {
+    let mut digits = String::with_capacity(value.len());
+    for c in value.trim().chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '\u{a0}' => {}
+            _ => return None,
+        }
+    }
+    Some(digits)
+}
+
+/// Whether `value` is an EAN-8, UPC-A, EAN-13 or GTIN-14 with a correct check digit.
+/// Spaces and hyphens between digits are allowed.
+pub fn validate_ean_upc(value: &str) -> bool {
+    match barcode_digits(value) {
+        Some(digits) if matches!(digits.len(), 8 | 12 | 13 | 14) => check_digit_ok(&digits),
+        _ => false,
+    }
+}
+
+/// The barcode as a 14 digit GTIN, padded with leading zeros, if it's valid.
+pub fn gtin14(value: &str) -> Option<String> {
+    if !validate_ean_upc(value) {
+        return None;
+    }
+    let digits = barcode_digits(value)?;
+    Some(format!("{digits:0>14}"))
+}
+
+/// Check the last digit against the GS1 checksum of the others, which weights
+/// digits alternately by 3 and 1 starting from the right.
+fn check_digit_ok(digits: &str) -> bool {
+    let values: Vec<u32> = digits.bytes().map(|b| (b - b'0') as u32).collect();
+    let (check, body) = values.split_last().expect("barcode has digits");
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+    (10 - sum % 10) % 10 == *check
+}
@@ -0,0 +1,542 @@
+//! Fetching records by ID without reading the whole dump.
+//!
+//! [`DumpIndex::build`] scans a dump once and records where each record starts in
+//! the decompressed XML. Plain XML files can then be read from that offset directly.
+//!
+//! A gzip stream can't be read from the middle, so for gzipped dumps the index also
+//! records where each gzip member starts, and a record is read by decompressing its
+//! member from the start. The dumps from Discogs are a single member, which makes
+//! this as slow as reading the dump up to the record. [`write_seekable`] recompresses
+//! a dump with a new member every few records, so only a small part has to be
+//! decompressed. The result is still an ordinary gzip file that any reader accepts.
+//! [`write_bgzf`] instead writes [BGZF](https://samtools.github.io/hts-specs/SAMv1.pdf),
+//! the blocked gzip of bioinformatics tools, whose members hold at most 64 KiB.
+//!
+//! [`DumpIndex::reader_from`] also uses the index to resume reading a dump from a
+//! record, e.g. after an interrupted import.
+//!
+//! ```no_run
+//! use disco_quick::index::DumpIndex;
+//!
+//! # fn main() -> Result<(), disco_quick::index::IndexError> {
+//! let dump = "releases.xml.gz".as_ref();
+//! let index = DumpIndex::open(dump, "releases.xml.gz.idx".as_ref())?;
+//! if let Some(release) = index.get_release(40299)? {
+//!     println!("{release}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::Artist;
+use crate::borrowed::RecordReader;
+use crate::label::Label;
+use crate::master::Master;
+use crate::options::ReaderOptions;
+use crate::parser::{ArtistParser, LabelParser, MasterParser, Parser, ReleaseParser};
+use crate::reader::{open_dump, DiscogsReader, EntityReader, ReaderError};
+use crate::release::Release;
+use crate::shared::EntityType;
+use flate2::bufread::{GzDecoder, MultiGzDecoder};
+use flate2::write::GzEncoder;
+use flate2::{Compression, GzBuilder};
+use quick_xml::events::{BytesStart, Event};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Error as IoError, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use thiserror::Error;
+
+/// The version of the index file format.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The default number of records in each gzip member written by [`write_seekable`].
+pub const DEFAULT_RECORDS_PER_MEMBER: usize = 1000;
+
+const MAGIC: &[u8; 8] = b"DQINDEX\0";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The most decompressed data in a BGZF block, as written by htslib.
+const BGZF_BLOCK_DATA: usize = 0xff00;
+/// The empty block that ends a BGZF file.
+const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error(transparent)]
+    IoError(#[from] IoError),
+    #[error(transparent)]
+    ReaderError(#[from] ReaderError),
+    #[error("The index is of a dump of {found}, not {expected}")]
+    WrongEntity {
+        expected: EntityType,
+        found: EntityType,
+    },
+    #[error("The index is missing, out of date or from a different dump")]
+    Stale,
+}
+
+impl From<quick_xml::Error> for IndexError {
+    fn from(e: quick_xml::Error) -> Self {
+        Self::ReaderError(e.into())
+    }
+}
+
+/// The offsets of the records of a dump by ID.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpIndex {
+    source: PathBuf,
+    entity: EntityType,
+    gzip: bool,
+    source_len: u64,
+    source_modified: u64,
+    /// The compressed and decompressed offsets of the start of each gzip member.
+    members: Vec<(u64, u64)>,
+    /// The decompressed offset of each record, sorted by ID.
+    records: Vec<(u32, u64)>,
+}
+
+impl DumpIndex {
+    /// Load the index of `source` from `index_path`, building and saving it first if
+    /// it's missing or out of date.
+    pub fn open(source: &Path, index_path: &Path) -> Result<Self, IndexError> {
+        match Self::load(source, index_path) {
+            Err(IndexError::Stale) => {
+                let index = Self::build(source)?;
+                index.save(index_path)?;
+                Ok(index)
+            }
+            result => result,
+        }
+    }
+
+    /// Scan a dump, which may be gzipped, and index its records.
+    pub fn build(source: &Path) -> Result<Self, IndexError> {
+        let (source_len, source_modified) = source_identity(source)?;
+        let mut file = BufReader::new(File::open(source)?);
+        let gzip = file.fill_buf()?.starts_with(&GZIP_MAGIC);
+        let (entity, mut records, members) = if gzip {
+            let members = Members {
+                decoder: Some(GzDecoder::new(file)),
+                decompressed: 0,
+                starts: vec![(0, 0)],
+            };
+            let mut reader = quick_xml::Reader::from_reader(BufReader::new(members));
+            let (entity, records) = scan(&mut reader)?;
+            (entity, records, reader.into_inner().into_inner().starts)
+        } else {
+            let mut reader = quick_xml::Reader::from_reader(file);
+            let (entity, records) = scan(&mut reader)?;
+            (entity, records, Vec::new())
+        };
+        // Only the first record of an ID can be fetched
+        records.sort_by_key(|(id, _)| *id);
+        records.dedup_by_key(|(id, _)| *id);
+        Ok(Self {
+            source: source.to_path_buf(),
+            entity,
+            gzip,
+            source_len,
+            source_modified,
+            members,
+            records,
+        })
+    }
+
+    /// Load a saved index, failing with [`IndexError::Stale`] if it's missing, cut
+    /// short, or `source` has changed since it was built.
+    ///
+    /// ```
+    /// use disco_quick::index::{DumpIndex, IndexError};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir();
+    /// let (source, index_path) = (dir.join("dq-load.xml"), dir.join("dq-load.xml.idx"));
+    /// std::fs::write(&source, "<labels></labels>")?;
+    /// for saved in [&b""[..], b"DQINDEX\0\x01"] {
+    ///     std::fs::write(&index_path, saved)?;
+    ///     let result = DumpIndex::load(&source, &index_path);
+    ///     assert!(matches!(result, Err(IndexError::Stale)));
+    /// }
+    /// # std::fs::remove_file(&source)?;
+    /// # std::fs::remove_file(&index_path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load(source: &Path, index_path: &Path) -> Result<Self, IndexError> {
+        let input = match File::open(index_path) {
+            Ok(file) => BufReader::new(file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(IndexError::Stale),
+            Err(e) => return Err(e.into()),
+        };
+        match Self::read_from(source, input) {
+            // An empty or truncated file, e.g. from an interrupted save
+            Err(IndexError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                Err(IndexError::Stale)
+            }
+            result => result,
+        }
+    }
+
+    fn read_from(source: &Path, mut input: impl Read) -> Result<Self, IndexError> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut input)? != FORMAT_VERSION {
+            return Err(IndexError::Stale);
+        }
+        let gzip = read_u8(&mut input)? != 0;
+        let entity = EntityType::ALL
+            .get(read_u8(&mut input)? as usize)
+            .copied()
+            .ok_or(IndexError::Stale)?;
+        let (source_len, source_modified) = (read_u64(&mut input)?, read_u64(&mut input)?);
+        if source_identity(source)? != (source_len, source_modified) {
+            return Err(IndexError::Stale);
+        }
+        let members = (0..read_u64(&mut input)?)
+            .map(|_| Ok((read_u64(&mut input)?, read_u64(&mut input)?)))
+            .collect::<io::Result<_>>()?;
+        let records = (0..read_u64(&mut input)?)
+            .map(|_| Ok((read_u32(&mut input)?, read_u64(&mut input)?)))
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            source: source.to_path_buf(),
+            entity,
+            gzip,
+            source_len,
+            source_modified,
+            members,
+            records,
+        })
+    }
+
+    pub fn save(&self, index_path: &Path) -> io::Result<()> {
+        let tmp = index_path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        out.write_all(&[self.gzip as u8, self.entity as u8])?;
+        out.write_all(&self.source_len.to_le_bytes())?;
+        out.write_all(&self.source_modified.to_le_bytes())?;
+        out.write_all(&(self.members.len() as u64).to_le_bytes())?;
+        for (compressed, decompressed) in &self.members {
+            out.write_all(&compressed.to_le_bytes())?;
+            out.write_all(&decompressed.to_le_bytes())?;
+        }
+        out.write_all(&(self.records.len() as u64).to_le_bytes())?;
+        for (id, offset) in &self.records {
+            out.write_all(&id.to_le_bytes())?;
+            out.write_all(&offset.to_le_bytes())?;
+        }
+        out.flush()?;
+        drop(out);
+        fs::rename(tmp, index_path)
+    }
+
+    pub fn entity_type(&self) -> EntityType {
+        self.entity
+    }
+
+    /// The number of records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The number of gzip members, or 0 if the dump isn't gzipped.
+    pub fn members(&self) -> usize {
+        self.members.len()
+    }
+
+    /// The compressed offset of the gzip member containing the record with `id`, and
+    /// the record's offset in the decompressed XML. The first is 0 if the dump isn't
+    /// gzipped.
+    pub fn offsets(&self, id: u32) -> Option<(u64, u64)> {
+        let offset = self.offset(id)?;
+        Some((self.member(offset).0, offset))
+    }
+
+    pub fn get_artist(&self, id: u32) -> Result<Option<Artist>, IndexError> {
+        self.get::<ArtistParser>(EntityType::Artist, id)
+    }
+
+    pub fn get_label(&self, id: u32) -> Result<Option<Label>, IndexError> {
+        self.get::<LabelParser>(EntityType::Label, id)
+    }
+
+    pub fn get_master(&self, id: u32) -> Result<Option<Master>, IndexError> {
+        self.get::<MasterParser>(EntityType::Master, id)
+    }
+
+    pub fn get_release(&self, id: u32) -> Result<Option<Release>, IndexError> {
+        self.get::<ReleaseParser>(EntityType::Release, id)
+    }
+
+    fn offset(&self, id: u32) -> Option<u64> {
+        let i = self.records.binary_search_by_key(&id, |(id, _)| *id).ok()?;
+        Some(self.records[i].1)
+    }
+
+    fn member(&self, offset: u64) -> (u64, u64) {
+        let i = self.members.partition_point(|(_, start)| *start <= offset);
+        self.members
+            .get(i.wrapping_sub(1))
+            .copied()
+            .unwrap_or((0, 0))
+    }
+
+    fn get<P: Parser>(&self, entity: EntityType, id: u32) -> Result<Option<P::Item>, IndexError> {
+        if self.entity != entity {
+            return Err(IndexError::WrongEntity {
+                expected: entity,
+                found: self.entity,
+            });
+        }
+        let Some(offset) = self.offset(id) else {
+            return Ok(None);
+        };
+        let input = self.open_at(offset)?;
+        let mut reader = EntityReader::<P>::new(quick_xml::Reader::from_reader(input), Vec::new());
+        Ok(reader.next().transpose()?)
+    }
+
+    /// Read the dump from the record with `id` to the end, or None if there's no
+    /// such record. Records are read in the order of the dump, not by ID. The
+    /// throughput limit, prefetch and progress options are ignored.
+    pub fn reader_from(
+        &self,
+        id: u32,
+        options: ReaderOptions,
+    ) -> Result<Option<DiscogsReader>, IndexError> {
+        let Some(offset) = self.offset(id) else {
+            return Ok(None);
+        };
+        // Put back the root tag so the rest of the dump is well-formed
+        let root = format!("<{}>", self.entity.root_tag()).into_bytes();
+        let input = io::Cursor::new(root).chain(self.open_at(offset)?);
+        let reader = DiscogsReader::from_reader_with_options(input, Some(self.entity), options)?;
+        Ok(Some(reader))
+    }
+
+    /// Open the dump at an offset in the decompressed XML.
+    fn open_at(&self, offset: u64) -> io::Result<Box<dyn BufRead>> {
+        let mut file = BufReader::new(File::open(&self.source)?);
+        if self.gzip {
+            let (compressed, decompressed) = self.member(offset);
+            file.seek(SeekFrom::Start(compressed))?;
+            let mut gz = BufReader::new(MultiGzDecoder::new(file));
+            io::copy(&mut (&mut gz).take(offset - decompressed), &mut io::sink())?;
+            Ok(Box::new(gz))
+        } else {
+            file.seek(SeekFrom::Start(offset))?;
+            Ok(Box::new(file))
+        }
+    }
+}
+
+/// Reads the members of a gzip file one after another, noting where each starts.
+struct Members<R> {
+    decoder: Option<GzDecoder<R>>,
+    decompressed: u64,
+    starts: Vec<(u64, u64)>,
+}
+
+impl<R: BufRead + Seek> Read for Members<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(decoder) = &mut self.decoder {
+            let n = decoder.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                self.decompressed += n as u64;
+                return Ok(n);
+            }
+            let mut inner = self.decoder.take().unwrap().into_inner();
+            if inner.fill_buf()?.is_empty() {
+                break;
+            }
+            self.starts
+                .push((inner.stream_position()?, self.decompressed));
+            self.decoder = Some(GzDecoder::new(inner));
+        }
+        Ok(0)
+    }
+}
+
+/// Find the entity type and the ID and offset of each record.
+fn scan<R: BufRead>(
+    reader: &mut quick_xml::Reader<R>,
+) -> Result<(EntityType, Vec<(u32, u64)>), IndexError> {
+    let mut buf = Vec::new();
+    let mut entity = None;
+    let mut records = Vec::new();
+    let mut depth = 0;
+    let mut record_start = 0;
+    let mut in_id = false;
+    let mut has_id = false;
+    loop {
+        let position = reader.buffer_position() as u64;
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if depth == 0 => {
+                let tag = e.local_name();
+                entity = EntityType::ALL
+                    .into_iter()
+                    .find(|t| t.root_tag().as_bytes() == tag.as_ref());
+                if entity.is_none() {
+                    return Err(ReaderError::InvalidStartTag.into());
+                }
+                depth = 1;
+            }
+            Event::Start(e) if depth == 1 => {
+                record_start = position;
+                has_id = false;
+                // Releases and masters have their ID as an attribute
+                if let Some(id) = attr_id(&e)? {
+                    records.push((id, record_start));
+                    has_id = true;
+                }
+                depth += 1;
+            }
+            Event::Empty(e) if depth == 1 => {
+                if let Some(id) = attr_id(&e)? {
+                    records.push((id, position));
+                }
+            }
+            Event::Start(e) => {
+                in_id = depth == 2 && !has_id && e.local_name().as_ref() == b"id";
+                depth += 1;
+            }
+            Event::Text(e) if in_id => {
+                if let Ok(id) = e.unescape()?.trim().parse::<u32>() {
+                    records.push((id, record_start));
+                    has_id = true;
+                }
+            }
+            Event::End(_) => {
+                in_id = false;
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let entity = entity.ok_or(ReaderError::NoStartTag)?;
+    Ok((entity, records))
+}
+
+fn attr_id(e: &BytesStart) -> Result<Option<u32>, IndexError> {
+    match e.try_get_attribute("id")? {
+        Some(id) => Ok(id.unescape_value()?.trim().parse().ok()),
+        None => Ok(None),
+    }
+}
+
+/// Recompress a dump into a gzip file with a new member every `records_per_member`
+/// records, so an index of it can fetch records quickly. Returns the number of
+/// records written.
+pub fn write_seekable(
+    source: &Path,
+    out_path: &Path,
+    records_per_member: usize,
+) -> Result<u64, ReaderError> {
+    let mut reader = RecordReader::from_path(source)?;
+    let root = reader.entity_type().root_tag();
+    let records_per_member = records_per_member.max(1);
+    let mut gz = GzEncoder::new(
+        BufWriter::new(File::create(out_path)?),
+        Compression::default(),
+    );
+    writeln!(gz, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    write!(gz, "<{root}>")?;
+    let mut written = 0;
+    while let Some(record) = reader.next_record()? {
+        if written > 0 && written % records_per_member as u64 == 0 {
+            let out = gz.finish()?;
+            gz = GzEncoder::new(out, Compression::default());
+        }
+        gz.write_all(record)?;
+        written += 1;
+    }
+    writeln!(gz, "</{root}>")?;
+    gz.finish()?.flush()?;
+    Ok(written)
+}
+
+/// Recompress a dump, which may be gzipped, as BGZF. Returns the number of blocks
+/// written, not counting the empty block at the end.
+pub fn write_bgzf(source: &Path, out_path: &Path) -> io::Result<u64> {
+    let mut input = open_dump(source, &ReaderOptions::default())?;
+    let mut out = BufWriter::new(File::create(out_path)?);
+    let mut data = vec![0; BGZF_BLOCK_DATA];
+    let mut block = Vec::new();
+    let mut blocks = 0;
+    loop {
+        let len = read_full(&mut input, &mut data)?;
+        if len == 0 {
+            break;
+        }
+        block.clear();
+        // The extra field holds the size of the block, which is filled in after
+        let mut gz = GzBuilder::new()
+            .extra(vec![b'B', b'C', 2, 0, 0, 0])
+            .write(&mut block, Compression::default());
+        gz.write_all(&data[..len])?;
+        gz.finish()?;
+        let size = u16::try_from(block.len() - 1)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "BGZF block too large"))?;
+        block[16..18].copy_from_slice(&size.to_le_bytes());
+        out.write_all(&block)?;
+        blocks += 1;
+    }
+    out.write_all(&BGZF_EOF)?;
+    out.flush()?;
+    Ok(blocks)
+}
+
+/// Fill `buf` unless the input ends first, returning the number of bytes read.
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn source_identity(source: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(source)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    Ok((metadata.len(), modified))
+}
+
+fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    input.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
@@ -0,0 +1,262 @@
+//! Sharing the strings of small vocabularies, like genres, styles and countries,
+//! between records instead of allocating them for every record.
+//!
+//! Fields with these values are [`Symbol`]s, which are reference counted strings
+//! taken from a per-thread pool. Enable the `plain-strings` feature to make
+//! [`Symbol`] an alias of [`String`] instead.
+//!
+//! Each thread has its own pool, so records parsed on different threads, e.g. by a
+//! [`ParallelReader`](crate::parallel::ParallelReader), hold separate copies of the
+//! same strings. Call [`enable_shared_pool`] before reading to have all threads
+//! take their strings from one process-wide pool. The per-thread pools are still
+//! checked first, so the shared pool is only locked for strings a thread hasn't
+//! seen yet.
+#[cfg(not(feature = "plain-strings"))]
+use std::cell::RefCell;
+#[cfg(not(feature = "plain-strings"))]
+use std::collections::HashSet;
+#[cfg(not(feature = "plain-strings"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(feature = "plain-strings"))]
+use std::sync::{Arc, PoisonError, RwLock};
+#[cfg(not(feature = "plain-strings"))]
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+/// The most strings kept in the pool of each thread. Values seen after it's full are
+/// allocated as usual, so free text that ends up in an interned field can't make
+/// the pool grow without bound.
+pub const MAX_INTERNED: usize = 1 << 16;
+
+#[cfg(not(feature = "plain-strings"))]
+thread_local! {
+    static POOL: RefCell<HashSet<Arc<str>>> = RefCell::new(HashSet::new());
+}
+
+#[cfg(not(feature = "plain-strings"))]
+static SHARED_ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(not(feature = "plain-strings"))]
+static SHARED: RwLock<SharedPool> = RwLock::new(SharedPool {
+    strings: None,
+    capacity: 0,
+});
+
+#[cfg(not(feature = "plain-strings"))]
+struct SharedPool {
+    strings: Option<HashSet<Arc<str>>>,
+    capacity: usize,
+}
+
+/// A string from a small vocabulary, shared between the records that use it.
+#[cfg(not(feature = "plain-strings"))]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(Arc<str>);
+
+#[cfg(feature = "plain-strings")]
+pub type Symbol = String;
+
+/// Get the pooled copy of `s`, adding it to the pool if it's not full.
+#[cfg(not(feature = "plain-strings"))]
+pub fn intern(s: &str) -> Symbol {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if let Some(interned) = pool.get(s) {
+            return Symbol(interned.clone());
+        }
+        let interned = if SHARED_ENABLED.load(Ordering::Relaxed) {
+            shared_intern(s)
+        } else {
+            Arc::from(s)
+        };
+        if pool.len() < MAX_INTERNED {
+            pool.insert(interned.clone());
+        }
+        Symbol(interned)
+    })
+}
+
+/// Get the copy of `s` in the shared pool, adding it if the pool isn't full.
+#[cfg(not(feature = "plain-strings"))]
+fn shared_intern(s: &str) -> Arc<str> {
+    let shared = SHARED.read().unwrap_or_else(PoisonError::into_inner);
+    if let Some(interned) = shared.strings.as_ref().and_then(|strings| strings.get(s)) {
+        return interned.clone();
+    }
+    drop(shared);
+    let mut shared = SHARED.write().unwrap_or_else(PoisonError::into_inner);
+    let capacity = shared.capacity;
+    let Some(strings) = &mut shared.strings else {
+        return Arc::from(s);
+    };
+    // Another thread may have added it since the read lock was released
+    if let Some(interned) = strings.get(s) {
+        return interned.clone();
+    }
+    let interned: Arc<str> = Arc::from(s);
+    if strings.len() < capacity {
+        strings.insert(interned.clone());
+    }
+    interned
+}
+
+/// Share interned strings between all threads, keeping up to `capacity` strings in
+/// the shared pool. Strings interned by a thread before it's enabled aren't shared.
+#[cfg(not(feature = "plain-strings"))]
+pub fn enable_shared_pool(capacity: usize) {
+    let mut shared = SHARED.write().unwrap_or_else(PoisonError::into_inner);
+    shared.capacity = capacity;
+    shared.strings.get_or_insert_with(HashSet::new);
+    SHARED_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stop sharing strings between threads and empty the shared pool. Symbols already
+/// taken from it are unaffected.
+#[cfg(not(feature = "plain-strings"))]
+pub fn disable_shared_pool() {
+    SHARED_ENABLED.store(false, Ordering::Relaxed);
+    let mut shared = SHARED.write().unwrap_or_else(PoisonError::into_inner);
+    shared.strings = None;
+}
+
+/// The number of strings in the shared pool.
+#[cfg(not(feature = "plain-strings"))]
+pub fn shared_pool_size() -> usize {
+    let shared = SHARED.read().unwrap_or_else(PoisonError::into_inner);
+    shared.strings.as_ref().map_or(0, |strings| strings.len())
+}
+
+#[cfg(feature = "plain-strings")]
+pub fn intern(s: &str) -> Symbol {
+    s.to_string()
+}
+
+/// The number of strings in the current thread's pool.
+#[cfg(not(feature = "plain-strings"))]
+pub fn pool_size() -> usize {
+    POOL.with(|pool| pool.borrow().len())
+}
+
+#[cfg(feature = "plain-strings")]
+pub fn pool_size() -> usize {
+    0
+}
+
+#[cfg(feature = "plain-strings")]
+pub fn enable_shared_pool(_capacity: usize) {}
+
+#[cfg(feature = "plain-strings")]
+pub fn disable_shared_pool() {}
+
+#[cfg(feature = "plain-strings")]
+pub fn shared_pool_size() -> usize {
+    0
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl Default for Symbol {
+    fn default() -> Self {
+        intern("")
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        intern(s)
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl From<String> for Symbol {
+    fn from(s: String) -> Self {
+        intern(&s)
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl From<Symbol> for String {
+    fn from(s: Symbol) -> Self {
+        s.0.to_string()
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+#[cfg(not(feature = "plain-strings"))]
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+// Symbols are serialized as plain strings, so the output is the same either way
+#[cfg(all(feature = "serde", not(feature = "plain-strings")))]
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(all(feature = "serde", not(feature = "plain-strings")))]
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        Ok(intern(&s))
+    }
+}
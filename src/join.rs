@@ -0,0 +1,500 @@
+//! Joining artists and labels to the releases that credit them.
+//!
+//! Releases refer to their artists and labels, but not the other way round, so
+//! listing the releases of every artist means reading the whole releases dump and
+//! holding a pair of IDs for each credit until the end. A full dump has hundreds of
+//! millions of credits, more than fit in memory on many machines. A [`ReleaseJoin`]
+//! holds pairs up to a memory cap, then sorts them and writes them to a run file.
+//! The runs are merged as the join is read, so memory use is bounded by the cap
+//! rather than the dump.
+//!
+//! ```no_run
+//! use disco_quick::join::{JoinOn, ReleaseJoin};
+//! use disco_quick::DiscogsReader;
+//!
+//! # fn main() -> Result<(), disco_quick::join::JoinError> {
+//! let DiscogsReader::Releases(releases) = DiscogsReader::from_path("releases.xml.gz".as_ref())?
+//! else {
+//!     return Ok(());
+//! };
+//! let join = ReleaseJoin::new(JoinOn::AllArtists)
+//!     .memory_cap(1 << 30)
+//!     .spill_dir("tmp".as_ref());
+//! for row in join.join(*releases)? {
+//!     let (artist_id, release_ids) = row?;
+//!     println!("{artist_id}: {} releases", release_ids.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::id::{ArtistId, LabelId};
+use crate::linker::{artist_ids, label_ids};
+use crate::reader::ReaderError;
+use crate::release::Release;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::Infallible;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::vec;
+use thiserror::Error;
+
+/// The default number of bytes of pairs held in memory before they're written to a
+/// run.
+pub const DEFAULT_MEMORY_CAP: usize = 512 << 20;
+
+/// The most runs merged at once. When there are more, they're first merged into
+/// larger runs, so a small cap doesn't need a file handle for each.
+pub const MAX_MERGE_WIDTH: usize = 64;
+
+/// The size of a pair in memory and in a run.
+const PAIR_SIZE: usize = mem::size_of::<(u32, u32)>();
+
+/// Distinguishes the runs of joins in the same process.
+static JOINS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Error, Debug)]
+pub enum JoinError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+}
+
+impl From<Infallible> for JoinError {
+    fn from(e: Infallible) -> Self {
+        match e {}
+    }
+}
+
+/// The records a release is joined to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum JoinOn {
+    /// The main artists of the release.
+    #[default]
+    Artists,
+    /// The artists credited anywhere on the release, including the extra artists and
+    /// the artists of its tracks.
+    AllArtists,
+    /// The labels of the release.
+    Labels,
+    /// The labels and companies of the release.
+    AllLabels,
+}
+
+impl JoinOn {
+    /// The IDs of the records `release` is joined to, which may repeat.
+    fn ids<'a>(&self, release: &'a Release) -> Box<dyn Iterator<Item = u32> + 'a> {
+        match self {
+            Self::Artists => Box::new(
+                release
+                    .artists
+                    .iter()
+                    .map(|credit| credit.id.get())
+                    .filter(|id| *id != 0),
+            ),
+            Self::AllArtists => Box::new(artist_ids(release).map(ArtistId::get)),
+            Self::Labels => Box::new(
+                release
+                    .labels
+                    .iter()
+                    .map(|label| label.id.get())
+                    .filter(|id| *id != 0),
+            ),
+            Self::AllLabels => Box::new(label_ids(release).map(LabelId::get)),
+        }
+    }
+}
+
+/// Joins the artists or labels of releases to the IDs of the releases, spilling to
+/// disk once the pairs exceed a memory cap.
+#[derive(Clone, Debug)]
+pub struct ReleaseJoin {
+    on: JoinOn,
+    memory_cap: usize,
+    spill_dir: PathBuf,
+}
+
+impl Default for ReleaseJoin {
+    fn default() -> Self {
+        Self::new(JoinOn::default())
+    }
+}
+
+impl ReleaseJoin {
+    pub fn new(on: JoinOn) -> Self {
+        Self {
+            on,
+            memory_cap: DEFAULT_MEMORY_CAP,
+            spill_dir: std::env::temp_dir(),
+        }
+    }
+
+    /// Write the pairs held to a run once they take `bytes` of memory. Each pair
+    /// takes 8 bytes.
+    ///
+    /// The result is the same whatever the cap, even when the pairs of a release
+    /// end up in different runs:
+    ///
+    /// ```
+    /// use disco_quick::artist_credit::ArtistCredit;
+    /// use disco_quick::join::{JoinOn, ReleaseJoin};
+    /// use disco_quick::release::Release;
+    /// use std::convert::Infallible;
+    ///
+    /// let release = |id: u32, artists: &[u32]| Release {
+    ///     id: id.into(),
+    ///     artists: artists
+    ///         .iter()
+    ///         .map(|a| ArtistCredit { id: (*a).into(), ..Default::default() })
+    ///         .collect(),
+    ///     ..Default::default()
+    /// };
+    /// let releases = [release(100, &[5]), release(200, &[5, 5]), release(300, &[6])];
+    /// let join = ReleaseJoin::new(JoinOn::Artists).memory_cap(8);
+    /// let rows = join
+    ///     .join(releases.map(Ok::<_, Infallible>))
+    ///     .unwrap()
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    /// assert_eq!(rows, [(5, vec![100, 200]), (6, vec![300])]);
+    /// ```
+    pub fn memory_cap(mut self, bytes: usize) -> Self {
+        self.memory_cap = bytes.max(PAIR_SIZE);
+        self
+    }
+
+    /// Write runs to `dir` instead of the system's temporary directory.
+    pub fn spill_dir(mut self, dir: &Path) -> Self {
+        self.spill_dir = dir.to_path_buf();
+        self
+    }
+
+    /// Read all of `releases` and return the IDs of the releases of each artist or
+    /// label, in order of its ID. The release IDs are sorted, and each is listed
+    /// once however often the release credits the artist or label.
+    pub fn join<E>(
+        &self,
+        releases: impl IntoIterator<Item = Result<Release, E>>,
+    ) -> Result<Joined, JoinError>
+    where
+        JoinError: From<E>,
+    {
+        let mut spill = Spill::new(self);
+        for release in releases {
+            let release = release?;
+            if !release.id.is_valid() {
+                continue;
+            }
+            for id in self.on.ids(&release) {
+                spill.push((id, release.id.get()))?;
+            }
+        }
+        Ok(spill.finish()?)
+    }
+}
+
+/// The pairs held in memory and the runs written so far.
+struct Spill {
+    held: Vec<(u32, u32)>,
+    max_held: usize,
+    runs: Runs,
+    dir: PathBuf,
+    join: usize,
+    written: usize,
+}
+
+impl Spill {
+    fn new(options: &ReleaseJoin) -> Self {
+        Self {
+            held: Vec::new(),
+            max_held: options.memory_cap / PAIR_SIZE,
+            runs: Runs::default(),
+            dir: options.spill_dir.clone(),
+            join: JOINS.fetch_add(1, Ordering::Relaxed),
+            written: 0,
+        }
+    }
+
+    fn push(&mut self, pair: (u32, u32)) -> io::Result<()> {
+        if self.held.len() == self.held.capacity() {
+            // Grow by hand, as doubling could take up to twice the cap
+            let room = self.max_held - self.held.len();
+            let extra = self.held.capacity().max(1024).min(room);
+            self.held.reserve_exact(extra.max(1));
+        }
+        self.held.push(pair);
+        if self.held.len() >= self.max_held {
+            sort(&mut self.held);
+            let path = self.next_path()?;
+            self.runs.write(0, path, self.held.drain(..).map(Ok))?;
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn next_path(&mut self) -> io::Result<PathBuf> {
+        if self.written == 0 {
+            fs::create_dir_all(&self.dir)?;
+        }
+        let name = format!(
+            "disco-quick-join-{}-{}-{}.run",
+            std::process::id(),
+            self.join,
+            self.written
+        );
+        self.written += 1;
+        Ok(self.dir.join(name))
+    }
+
+    /// Merge each tier that's full into one run of the next, so a pair is rewritten
+    /// once per tier rather than each time the runs pile up.
+    fn compact(&mut self) -> io::Result<()> {
+        let mut tier = 0;
+        while tier < self.runs.tiers.len() {
+            if self.runs.tiers[tier].len() >= MAX_MERGE_WIDTH {
+                self.merge_tier(tier)?;
+            }
+            tier += 1;
+        }
+        Ok(())
+    }
+
+    /// Merge the runs of `tier` into one run of the tier above.
+    fn merge_tier(&mut self, tier: usize) -> io::Result<()> {
+        // Dropped once merged, which deletes the runs
+        let merged = Runs {
+            tiers: vec![mem::take(&mut self.runs.tiers[tier])],
+        };
+        let path = self.next_path()?;
+        let pairs = Merge::new(&merged.tiers[0], Vec::new())?;
+        self.runs.write(tier + 1, path, pairs)
+    }
+
+    fn finish(mut self) -> io::Result<Joined> {
+        sort(&mut self.held);
+        // Leave room for the pairs still held, which are merged as one more run
+        while self.runs.len() >= MAX_MERGE_WIDTH {
+            let Some(tier) = self.runs.tiers.iter().position(|runs| runs.len() > 1) else {
+                break;
+            };
+            self.merge_tier(tier)?;
+        }
+        let source = if self.runs.is_empty() {
+            Source::Memory(self.held.into_iter().peekable())
+        } else {
+            Source::Merge {
+                merge: Merge::new(&self.runs.paths(), self.held)?,
+                _runs: self.runs,
+            }
+        };
+        Ok(Joined {
+            source,
+            failed: false,
+        })
+    }
+}
+
+/// An iterator over the IDs of the releases of each artist or label, from
+/// [`ReleaseJoin::join`]. It ends after the first error, as the rows that follow
+/// could be missing the pairs of the run that failed.
+pub struct Joined {
+    source: Source,
+    failed: bool,
+}
+
+enum Source {
+    Memory(std::iter::Peekable<vec::IntoIter<(u32, u32)>>),
+    Merge {
+        merge: Merge,
+        /// Kept so the runs are deleted when the join is dropped.
+        _runs: Runs,
+    },
+}
+
+impl Source {
+    fn peek_key(&mut self) -> Option<u32> {
+        match self {
+            Source::Memory(pairs) => pairs.peek().map(|(key, _)| *key),
+            Source::Merge { merge, .. } => merge.peek().map(|(key, _)| key),
+        }
+    }
+
+    fn next_pair(&mut self) -> io::Result<Option<(u32, u32)>> {
+        match self {
+            Source::Memory(pairs) => Ok(pairs.next()),
+            Source::Merge { merge, .. } => merge.next().transpose(),
+        }
+    }
+}
+
+impl Iterator for Joined {
+    type Item = io::Result<(u32, Vec<u32>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let key = self.source.peek_key()?;
+        let mut release_ids = Vec::new();
+        while self.source.peek_key() == Some(key) {
+            match self.source.next_pair() {
+                Ok(Some((_, id))) => release_ids.push(id),
+                Ok(None) => break,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        Some(Ok((key, release_ids)))
+    }
+}
+
+/// A merge of sorted runs and the sorted pairs still in memory, without repeats.
+struct Merge {
+    heap: BinaryHeap<Reverse<((u32, u32), usize)>>,
+    readers: Vec<BufReader<File>>,
+    memory: vec::IntoIter<(u32, u32)>,
+}
+
+impl Merge {
+    fn new(paths: &[PathBuf], memory: Vec<(u32, u32)>) -> io::Result<Self> {
+        let mut heap = BinaryHeap::new();
+        let mut readers = Vec::with_capacity(paths.len());
+        for (i, path) in paths.iter().enumerate() {
+            let mut reader = BufReader::new(File::open(path)?);
+            if let Some(pair) = read_pair(&mut reader)? {
+                heap.push(Reverse((pair, i)));
+            }
+            readers.push(reader);
+        }
+        // The pairs still held are merged as one more run
+        let mut memory = memory.into_iter();
+        if let Some(pair) = memory.next() {
+            heap.push(Reverse((pair, readers.len())));
+        }
+        Ok(Self {
+            heap,
+            readers,
+            memory,
+        })
+    }
+
+    fn peek(&self) -> Option<(u32, u32)> {
+        self.heap.peek().map(|Reverse((pair, _))| *pair)
+    }
+
+    /// The next pair of any run. Its repeats in other runs are dropped too, so the
+    /// pair [`peek`](Self::peek) shows next is always a different one.
+    fn pop(&mut self) -> io::Result<Option<(u32, u32)>> {
+        let Some(pair) = self.advance()? else {
+            return Ok(None);
+        };
+        while self.peek() == Some(pair) {
+            self.advance()?;
+        }
+        Ok(Some(pair))
+    }
+
+    /// Take the first pair off the heap and put the next of its run in its place.
+    fn advance(&mut self) -> io::Result<Option<(u32, u32)>> {
+        let Some(Reverse((pair, run))) = self.heap.pop() else {
+            return Ok(None);
+        };
+        let next = match self.readers.get_mut(run) {
+            Some(reader) => read_pair(reader)?,
+            None => self.memory.next(),
+        };
+        if let Some(next) = next {
+            self.heap.push(Reverse((next, run)));
+        }
+        Ok(Some(pair))
+    }
+}
+
+impl Iterator for Merge {
+    type Item = io::Result<(u32, u32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop().transpose()
+    }
+}
+
+/// The run files written so far by tier, deleted when dropped. A run of tier `n + 1`
+/// is a merge of [`MAX_MERGE_WIDTH`] runs of tier `n`.
+#[derive(Default)]
+struct Runs {
+    tiers: Vec<Vec<PathBuf>>,
+}
+
+impl Runs {
+    fn write(
+        &mut self,
+        tier: usize,
+        path: PathBuf,
+        pairs: impl Iterator<Item = io::Result<(u32, u32)>>,
+    ) -> io::Result<()> {
+        if self.tiers.len() <= tier {
+            self.tiers.resize_with(tier + 1, Vec::new);
+        }
+        self.tiers[tier].push(path.clone());
+        let mut out = BufWriter::new(File::create(&path)?);
+        for pair in pairs {
+            write_pair(&mut out, pair?)?;
+        }
+        out.flush()
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        self.tiers.iter().flatten().cloned().collect()
+    }
+
+    fn len(&self) -> usize {
+        self.tiers.iter().map(Vec::len).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in self.tiers.iter().flatten() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn sort(held: &mut Vec<(u32, u32)>) {
+    held.sort_unstable();
+    held.dedup();
+}
+
+fn write_pair(out: &mut impl Write, (key, id): (u32, u32)) -> io::Result<()> {
+    out.write_all(&key.to_le_bytes())?;
+    out.write_all(&id.to_le_bytes())
+}
+
+/// Read a pair written by [`write_pair`], or None at the end of the run. A run that
+/// ends part way through a pair is an error rather than its end.
+fn read_pair(input: &mut impl Read) -> io::Result<Option<(u32, u32)>> {
+    let mut buf = [0; PAIR_SIZE];
+    let mut filled = 0;
+    while filled < PAIR_SIZE {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let key = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let id = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
+    Ok(Some((key, id)))
+}
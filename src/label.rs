@@ -1,73 +1,77 @@
+use crate::id::LabelId;
+use crate::options::ReaderOptions;
 use crate::parser::{Parser, ParserError};
-use crate::reader::XmlReader;
-use crate::shared::Image;
+use crate::reader::EntityReader;
+#[cfg(feature = "extra-fields")]
+use crate::shared::UnknownElement;
+use crate::shared::{normalize_in_place, DataQuality, Image};
 use crate::util::get_attr_id;
 use log::debug;
 use quick_xml::events::Event;
+#[cfg(feature = "extra-fields")]
+use std::collections::BTreeMap;
 use std::fmt;
 use std::mem::take;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Label {
-    pub id: u32,
+    pub id: LabelId,
     pub name: String,
     pub contactinfo: Option<String>,
     pub profile: Option<String>,
     pub parent_label: Option<LabelInfo>,
     pub sublabels: Vec<LabelInfo>,
     pub urls: Vec<String>,
-    pub data_quality: String,
+    pub data_quality: DataQuality,
     pub images: Vec<Image>,
+    /// The text of elements the parser doesn't know, by name, so data isn't lost when
+    /// Discogs adds elements before the crate supports them. Elements with children
+    /// aren't kept.
+    #[cfg(feature = "extra-fields")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_fields: BTreeMap<String, String>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LabelInfo {
-    pub id: u32,
+    pub id: LabelId,
     pub name: String,
 }
 
+impl Label {
+    /// The Label Code from the profile, e.g. "LC 01234" for "LC-1234". Codes of 4 or 5
+    /// digits are recognised, and padded to 5.
+    pub fn label_code(&self) -> Option<String> {
+        find_label_code(self.profile.as_deref()?)
+    }
+}
+
 impl fmt::Display for Label {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.name)
     }
 }
 
-pub struct LabelsReader {
-    buf: Vec<u8>,
-    reader: XmlReader,
-    parser: LabelParser,
-}
-
-impl LabelsReader {
-    pub fn new(reader: XmlReader, buf: Vec<u8>) -> Self {
-        Self {
-            buf,
-            reader,
-            parser: LabelParser::new(),
+fn find_label_code(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    for (i, _) in text.match_indices("LC") {
+        if i > 0 && bytes[i - 1].is_ascii_alphanumeric() {
+            continue;
         }
-    }
-}
-
-impl Iterator for LabelsReader {
-    type Item = Label;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.reader.read_event_into(&mut self.buf).unwrap() {
-                Event::Eof => {
-                    return None;
-                }
-                ev => self.parser.process(ev).unwrap(),
-            };
-            if self.parser.item_ready {
-                return Some(self.parser.take());
-            }
-            self.buf.clear();
+        let rest = text[i + 2..].trim_start_matches([' ', '-', ':', '.']);
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if (4..=5).contains(&digits) {
+            return Some(format!("LC {:0>5}", &rest[..digits]));
         }
     }
+    None
 }
 
+/// Iterator over the labels in a labels dump.
+pub type LabelsReader = EntityReader<LabelParser>;
+
 #[derive(Debug, Default)]
 enum ParserState {
     #[default]
@@ -82,15 +86,21 @@ enum ParserState {
     Sublabel,
     Urls,
     DataQuality,
+    #[cfg(feature = "extra-fields")]
+    Unknown,
 }
 
+/// Parses a `<label>` element of the labels dump into a [`Label`].
 #[derive(Debug, Default)]
 pub struct LabelParser {
     state: ParserState,
     current_item: Label,
-    current_sublabel_id: Option<u32>,
-    current_parent_id: Option<u32>,
+    current_sublabel_id: Option<LabelId>,
+    current_parent_id: Option<LabelId>,
     item_ready: bool,
+    normalize_text: bool,
+    #[cfg(feature = "extra-fields")]
+    unknown: UnknownElement,
 }
 
 impl Parser for LabelParser {
@@ -98,13 +108,41 @@ impl Parser for LabelParser {
     fn new() -> Self {
         Self::default()
     }
+
+    fn with_options(options: &ReaderOptions) -> Self {
+        Self {
+            normalize_text: options.normalize_text,
+            ..Self::default()
+        }
+    }
+
     fn take(&mut self) -> Self::Item {
         self.item_ready = false;
         take(&mut self.current_item)
     }
 
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
+
+    fn current_id(&self) -> Option<u32> {
+        Some(self.current_item.id.get()).filter(|id| *id != 0)
+    }
+
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
+            #[cfg(feature = "extra-fields")]
+            ParserState::Unknown => {
+                if self
+                    .unknown
+                    .process(ev, &mut self.current_item.extra_fields)?
+                {
+                    ParserState::Label
+                } else {
+                    ParserState::Unknown
+                }
+            }
+
             ParserState::Label => match ev {
                 Event::Start(e) if e.local_name().as_ref() == b"label" => ParserState::Label,
 
@@ -114,16 +152,25 @@ impl Parser for LabelParser {
                     b"contactinfo" => ParserState::Contactinfo,
                     b"profile" => ParserState::Profile,
                     b"parentLabel" => {
-                        self.current_parent_id = Some(get_attr_id(e));
+                        self.current_parent_id = Some(get_attr_id(e)?);
                         ParserState::ParentLabel
                     }
                     b"sublabels" => ParserState::Sublabels,
                     b"urls" => ParserState::Urls,
                     b"images" => ParserState::Images,
                     b"data_quality" => ParserState::DataQuality,
+                    #[cfg(feature = "extra-fields")]
+                    _ => {
+                        self.unknown.start(&e);
+                        ParserState::Unknown
+                    }
+                    #[cfg(not(feature = "extra-fields"))]
                     _ => ParserState::Label,
                 },
                 Event::End(e) if e.local_name().as_ref() == b"label" => {
+                    if self.normalize_text {
+                        self.current_item.profile.as_mut().map(normalize_in_place);
+                    }
                     self.item_ready = true;
                     ParserState::Label
                 }
@@ -150,7 +197,7 @@ impl Parser for LabelParser {
 
             ParserState::Images => match ev {
                 Event::Empty(e) if e.local_name().as_ref() == b"image" => {
-                    let image = Image::from_event(e);
+                    let image = Image::from_event(e)?;
                     self.current_item.images.push(image);
                     ParserState::Images
                 }
@@ -190,7 +237,7 @@ impl Parser for LabelParser {
 
             ParserState::Sublabels => match ev {
                 Event::Start(e) if e.local_name().as_ref() == b"label" => {
-                    self.current_sublabel_id = Some(get_attr_id(e));
+                    self.current_sublabel_id = Some(get_attr_id(e)?);
                     ParserState::Sublabel
                 }
                 Event::End(e) if e.local_name().as_ref() == b"sublabels" => ParserState::Label,
@@ -223,7 +270,7 @@ impl Parser for LabelParser {
 
             ParserState::DataQuality => match ev {
                 Event::Text(e) => {
-                    self.current_item.data_quality = e.unescape()?.to_string();
+                    self.current_item.data_quality = DataQuality::from(&*e.unescape()?);
                     ParserState::DataQuality
                 }
                 _ => ParserState::Label,
@@ -1,17 +1,80 @@
 #![doc = include_str!("../README.md")]
 pub mod artist;
 pub mod artist_credit;
+pub mod bench;
+pub mod borrowed;
+#[cfg(feature = "cache")]
+pub mod cache;
 pub mod company;
+pub mod completeness;
+pub mod country;
+pub mod custom;
+pub mod date;
+pub mod diff;
+pub mod dumps;
+pub mod duration;
+pub mod entity;
+pub mod events;
+pub mod export;
+pub mod filter;
+pub mod format;
+pub mod hierarchy;
+
+#[cfg(feature = "http")]
+pub mod http;
+pub mod id;
+pub mod identifiers;
+pub mod index;
+pub mod intern;
+pub mod join;
 pub mod label;
+pub mod linker;
+pub mod links;
 pub mod master;
-mod parser;
+mod options;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod parser;
+pub mod position;
+mod prefetch;
+pub mod progress;
+pub mod prolog;
 pub mod reader;
 pub mod release;
+pub mod report;
+pub mod role;
+pub mod schema;
+pub mod semantic;
 pub mod shared;
+pub mod slice;
+pub mod split;
+pub mod stats;
+pub mod store;
+#[cfg(feature = "tokio")]
+pub mod stream;
+pub mod summary;
+pub mod supplement;
+mod tags;
+pub mod title;
 pub mod track;
+pub mod translations;
 mod util;
+pub mod validate;
+pub mod verify;
+pub mod versions;
 pub mod video;
+pub mod warning;
+pub mod writer;
 
+pub use crate::dumps::DiscogsDump;
+pub use crate::entity::Entity;
+pub use crate::options::{
+    ErrorPolicy, Field, FieldSet, ReaderOptions, Sample, SkippedRecord, ERROR_RATE_MIN_RECORDS,
+};
+pub use crate::progress::Progress;
 pub use crate::reader::{
-    ArtistsReader, DiscogsReader, LabelsReader, MastersReader, ReaderError, ReleasesReader,
+    ArtistsReader, Checkpoint, DiscogsReader, LabelsReader, MastersReader, ReaderError,
+    ReleasesReader,
 };
+pub use crate::shared::{DataQuality, EntityType};
+pub use crate::warning::ParseWarning;
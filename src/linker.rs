@@ -0,0 +1,331 @@
+//! Resolving the artists, labels and masters that releases refer to.
+//!
+//! A release only has the IDs and names of its artists, labels and master. A
+//! [`Linker`] looks them up in stores built from the other dumps and returns each
+//! release as a [`LinkedRelease`] with the full records.
+//!
+//! The stores can be kept in memory, which needs the referenced records of the
+//! other dumps to fit. Reading the releases twice keeps that to what's needed: the
+//! first pass collects the IDs they refer to in [`References`], and only those
+//! records are loaded for the second. Otherwise, [`Linker::indexed`] looks records up
+//! in [`DumpIndex`]es and keeps the most used in memory up to a budget. Gzipped dumps
+//! should be recompressed with [`write_seekable`](crate::index::write_seekable) first,
+//! or each lookup reads a dump from the start.
+//!
+//! ```no_run
+//! use disco_quick::linker::{Linker, References};
+//! use disco_quick::DiscogsDump;
+//!
+//! # fn main() -> Result<(), disco_quick::linker::LinkError> {
+//! let dump = DiscogsDump::open_dir("dumps".as_ref())?;
+//! let mut references = References::new();
+//! for release in dump.releases()? {
+//!     references.add(&release?);
+//! }
+//! let linker = Linker::in_memory(&dump, Some(&references))?;
+//! for release in linker.link_all(dump.releases()?) {
+//!     let release = release?;
+//!     for credit in &release.release.artists {
+//!         if let Some(artist) = release.artist(credit.id) {
+//!             println!("{} is credited as {}", artist.name, credit.credited_name());
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::Artist;
+use crate::dumps::dir::{DiscogsDump, DumpDirError};
+use crate::id::{ArtistId, LabelId, MasterId};
+use crate::index::{DumpIndex, IndexError};
+use crate::label::Label;
+use crate::master::Master;
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::release::Release;
+use crate::shared::EntityType;
+use crate::store::{CachedStore, Store};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LinkError {
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+    #[error(transparent)]
+    Index(#[from] IndexError),
+    #[cfg(feature = "cache")]
+    #[error(transparent)]
+    Cache(#[from] crate::cache::CacheError),
+    #[error(transparent)]
+    Dump(#[from] DumpDirError),
+}
+
+impl From<Infallible> for LinkError {
+    fn from(e: Infallible) -> Self {
+        match e {}
+    }
+}
+
+/// The IDs of the artists, labels and masters referred to by releases.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct References {
+    pub artists: HashSet<ArtistId>,
+    pub labels: HashSet<LabelId>,
+    pub masters: HashSet<MasterId>,
+}
+
+impl References {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the IDs referred to by `release`, including those of its tracks and
+    /// companies.
+    pub fn add(&mut self, release: &Release) {
+        self.artists.extend(artist_ids(release));
+        self.labels.extend(label_ids(release));
+        self.masters.extend(master_id(release));
+    }
+
+    pub fn len(&self) -> usize {
+        self.artists.len() + self.labels.len() + self.masters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A release with the records it refers to that could be found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkedRelease {
+    pub release: Release,
+    pub artists: HashMap<ArtistId, Arc<Artist>>,
+    pub labels: HashMap<LabelId, Arc<Label>>,
+    pub master: Option<Arc<Master>>,
+}
+
+impl LinkedRelease {
+    pub fn artist(&self, id: ArtistId) -> Option<&Artist> {
+        self.artists.get(&id).map(Arc::as_ref)
+    }
+
+    pub fn label(&self, id: LabelId) -> Option<&Label> {
+        self.labels.get(&id).map(Arc::as_ref)
+    }
+
+    /// The IDs of the artists the release refers to that weren't found.
+    pub fn missing_artists(&self) -> Vec<ArtistId> {
+        let mut missing: Vec<ArtistId> = artist_ids(&self.release)
+            .filter(|id| !self.artists.contains_key(id))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
+    /// The IDs of the labels and companies the release refers to that weren't found.
+    pub fn missing_labels(&self) -> Vec<LabelId> {
+        let mut missing: Vec<LabelId> = label_ids(&self.release)
+            .filter(|id| !self.labels.contains_key(id))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
+    /// Whether every record the release refers to was found.
+    pub fn is_complete(&self) -> bool {
+        self.missing_artists().is_empty()
+            && self.missing_labels().is_empty()
+            && master_id(&self.release).is_none() == self.master.is_none()
+    }
+}
+
+pub type MemoryStore<T> = HashMap<u32, Arc<T>>;
+
+/// A linker with all its records in memory.
+pub type MemoryLinker = Linker<MemoryStore<Artist>, MemoryStore<Label>, MemoryStore<Master>>;
+
+/// A linker that looks records up in the indexes of the dumps.
+pub type IndexedLinker = Linker<
+    CachedStore<DumpIndex, Artist>,
+    CachedStore<DumpIndex, Label>,
+    CachedStore<DumpIndex, Master>,
+>;
+
+/// Looks up the records that releases refer to.
+pub struct Linker<A, L, M> {
+    artists: A,
+    labels: L,
+    masters: M,
+}
+
+impl<A, L, M> Linker<A, L, M>
+where
+    A: Store<Arc<Artist>>,
+    L: Store<Arc<Label>>,
+    M: Store<Arc<Master>>,
+    LinkError: From<A::Error> + From<L::Error> + From<M::Error>,
+{
+    pub fn new(artists: A, labels: L, masters: M) -> Self {
+        Self {
+            artists,
+            labels,
+            masters,
+        }
+    }
+
+    /// Look up the records `release` refers to.
+    pub fn link(&self, release: Release) -> Result<LinkedRelease, LinkError> {
+        let mut artists = HashMap::new();
+        for id in artist_ids(&release) {
+            if artists.contains_key(&id) {
+                continue;
+            }
+            if let Some(artist) = self.artists.fetch(id.get())? {
+                artists.insert(id, artist);
+            }
+        }
+        let mut labels = HashMap::new();
+        for id in label_ids(&release) {
+            if labels.contains_key(&id) {
+                continue;
+            }
+            if let Some(label) = self.labels.fetch(id.get())? {
+                labels.insert(id, label);
+            }
+        }
+        let master = match master_id(&release) {
+            Some(id) => self.masters.fetch(id.get())?,
+            None => None,
+        };
+        Ok(LinkedRelease {
+            release,
+            artists,
+            labels,
+            master,
+        })
+    }
+
+    /// Link each release of `releases`, e.g. a [`ReleasesReader`](crate::ReleasesReader).
+    pub fn link_all<I, E>(
+        &self,
+        releases: I,
+    ) -> impl Iterator<Item = Result<LinkedRelease, LinkError>> + '_
+    where
+        I: IntoIterator<Item = Result<Release, E>>,
+        I::IntoIter: 'static,
+        LinkError: From<E>,
+    {
+        releases
+            .into_iter()
+            .map(|release| self.link(release.map_err(LinkError::from)?))
+    }
+
+    pub fn artists(&self) -> &A {
+        &self.artists
+    }
+
+    pub fn labels(&self) -> &L {
+        &self.labels
+    }
+
+    pub fn masters(&self) -> &M {
+        &self.masters
+    }
+}
+
+impl MemoryLinker {
+    /// Load the artists, labels and masters of `dump` into memory, or only those in
+    /// `wanted` if it's given. The stores of dumps that are missing are left empty.
+    pub fn in_memory(dump: &DiscogsDump, wanted: Option<&References>) -> Result<Self, LinkError> {
+        let mut linker = Self::new(HashMap::new(), HashMap::new(), HashMap::new());
+        for entity in dump.entities() {
+            let reader = dump.reader(entity)?;
+            match reader {
+                DiscogsReader::Artists(artists) => {
+                    let wanted = wanted.map(|w| &w.artists);
+                    for artist in *artists {
+                        let artist = artist?;
+                        if wanted.is_none_or(|w| w.contains(&artist.id)) {
+                            linker.artists.insert(artist.id.get(), Arc::new(artist));
+                        }
+                    }
+                }
+                DiscogsReader::Labels(labels) => {
+                    let wanted = wanted.map(|w| &w.labels);
+                    for label in *labels {
+                        let label = label?;
+                        if wanted.is_none_or(|w| w.contains(&label.id)) {
+                            linker.labels.insert(label.id.get(), Arc::new(label));
+                        }
+                    }
+                }
+                DiscogsReader::Masters(masters) => {
+                    let wanted = wanted.map(|w| &w.masters);
+                    for master in *masters {
+                        let master = master?;
+                        if wanted.is_none_or(|w| w.contains(&master.id)) {
+                            linker.masters.insert(master.id.get(), Arc::new(master));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(linker)
+    }
+}
+
+impl IndexedLinker {
+    /// Look records up in the indexes of the artists, labels and masters of `dump`,
+    /// keeping up to `budget` of each in memory. The indexes are read from next to
+    /// the dumps, e.g. `discogs_20240101_artists.xml.gz.idx`, and built first if
+    /// they're missing or out of date.
+    pub fn indexed(dump: &DiscogsDump, budget: usize) -> Result<Self, LinkError> {
+        let open = |entity: EntityType| -> Result<DumpIndex, LinkError> {
+            let path = dump
+                .path(entity)
+                .ok_or(DumpDirError::MissingFile(dump.date(), entity))?;
+            let mut index_path = PathBuf::from(path).into_os_string();
+            index_path.push(".idx");
+            Ok(DumpIndex::open(path, index_path.as_ref())?)
+        };
+        Ok(Self::new(
+            CachedStore::new(open(EntityType::Artist)?, budget),
+            CachedStore::new(open(EntityType::Label)?, budget),
+            CachedStore::new(open(EntityType::Master)?, budget),
+        ))
+    }
+}
+
+/// The IDs of the artists credited on a release and its tracks.
+pub(crate) fn artist_ids(release: &Release) -> impl Iterator<Item = ArtistId> + '_ {
+    let track_credits = release
+        .all_tracks()
+        .flat_map(|track| track.artists.iter().chain(&track.extraartists));
+    release
+        .artists
+        .iter()
+        .chain(&release.extraartists)
+        .chain(track_credits)
+        .map(|credit| credit.id)
+        .filter(|id| id.is_valid())
+}
+
+pub(crate) fn label_ids(release: &Release) -> impl Iterator<Item = LabelId> + '_ {
+    release
+        .labels
+        .iter()
+        .chain(&release.companies)
+        .map(|label| label.id)
+        .filter(|id| id.is_valid())
+}
+
+fn master_id(release: &Release) -> Option<MasterId> {
+    release.master_id.and_then(MasterId::valid)
+}
@@ -0,0 +1,164 @@
+//! Whether the URLs of artists and labels still work, so exports can flag dead links.
+//!
+//! Many of the URLs in the dumps point at sites that have since gone, and sites built
+//! on the dumps often want to hide them. The statuses of URLs are kept in
+//! [`LinkStatuses`], which the exporters can join onto the URLs they write:
+//! [`CsvExport::link_statuses`](crate::export::csv::CsvExport::link_statuses) adds
+//! columns to the `artist_url` and `label_url` tables, and `JsonlOptions::link_statuses`
+//! adds a `url_statuses` field with the `jsonl` feature.
+//!
+//! The statuses are found by sending HEAD requests with a
+//! [`LinkChecker`](check::LinkChecker) with the `links` feature. It's async and
+//! limits the rate of requests, but the requests themselves are sent by an
+//! implementation of [`UrlChecker`](check::UrlChecker), so any HTTP client can be used.
+#[cfg(feature = "links")]
+pub mod check;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// What became of a request for a URL.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkState {
+    /// The server answered with a success status.
+    Live,
+    /// The server answered with a redirect, which isn't followed.
+    Redirected,
+    /// The server answered that the page doesn't exist, or the host couldn't be
+    /// found.
+    Dead,
+    /// The request failed in a way that may not last, e.g. a timeout, a server
+    /// error, or a status some servers send to HEAD requests for pages that exist.
+    Unknown,
+    /// The URL wasn't checked, e.g. because it isn't an HTTP(S) URL.
+    #[default]
+    Unchecked,
+}
+
+/// The status of a URL, with the details of the response if there was one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkStatus {
+    pub state: LinkState,
+    /// The HTTP status of the response.
+    pub http_status: Option<u16>,
+    /// The `Location` header of a redirect.
+    pub location: Option<String>,
+}
+
+/// The statuses of URLs by URL.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkStatuses {
+    statuses: HashMap<String, LinkStatus>,
+}
+
+impl LinkState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Live => "live",
+            Self::Redirected => "redirected",
+            Self::Dead => "dead",
+            Self::Unknown => "unknown",
+            Self::Unchecked => "unchecked",
+        }
+    }
+}
+
+impl fmt::Display for LinkState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl LinkStatus {
+    /// The status of a URL that wasn't checked.
+    pub const UNCHECKED: Self = Self {
+        state: LinkState::Unchecked,
+        http_status: None,
+        location: None,
+    };
+
+    /// The status of a URL from the status and `Location` header of the response to
+    /// a request for it. 401, 403, 405 and 429 are often sent to HEAD requests from
+    /// scripts for pages that exist, so they're unknown rather than dead, as are
+    /// server errors.
+    pub fn from_response(http_status: u16, location: Option<String>) -> Self {
+        let state = match http_status {
+            200..=299 => LinkState::Live,
+            300..=399 => LinkState::Redirected,
+            401 | 403 | 405 | 429 => LinkState::Unknown,
+            400..=499 => LinkState::Dead,
+            _ => LinkState::Unknown,
+        };
+        Self {
+            state,
+            http_status: Some(http_status),
+            location: location.filter(|_| state == LinkState::Redirected),
+        }
+    }
+
+    /// The status of a URL whose request failed without a response. A host that
+    /// couldn't be found is dead, while other failures may not last.
+    pub fn failed(host_not_found: bool) -> Self {
+        Self {
+            state: match host_not_found {
+                true => LinkState::Dead,
+                false => LinkState::Unknown,
+            },
+            http_status: None,
+            location: None,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.state == LinkState::Dead
+    }
+}
+
+impl LinkStatuses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, url: &str) -> Option<&LinkStatus> {
+        self.statuses.get(url)
+    }
+
+    /// The status of `url`, or [`LinkStatus::UNCHECKED`] if it hasn't been checked.
+    pub fn status(&self, url: &str) -> &LinkStatus {
+        self.get(url).unwrap_or(&LinkStatus::UNCHECKED)
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.statuses.contains_key(url)
+    }
+
+    pub fn insert(&mut self, url: String, status: LinkStatus) {
+        self.statuses.insert(url, status);
+    }
+
+    pub fn len(&self) -> usize {
+        self.statuses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LinkStatus)> {
+        self.statuses
+            .iter()
+            .map(|(url, status)| (url.as_str(), status))
+    }
+
+    /// The number of URLs in each state.
+    pub fn counts(&self) -> BTreeMap<LinkState, usize> {
+        let mut counts = BTreeMap::new();
+        for status in self.statuses.values() {
+            *counts.entry(status.state).or_default() += 1;
+        }
+        counts
+    }
+}
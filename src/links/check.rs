@@ -0,0 +1,219 @@
+//! Checking URLs with rate-limited HEAD requests.
+//!
+//! A [`LinkChecker`] sends a request for each URL it hasn't checked before, several
+//! at a time, with at least a minimum interval between requests and a longer one
+//! between requests to the same host. The dumps have hundreds of thousands of URLs
+//! on the same few sites, so without the limit per host a check would soon be
+//! blocked by them. The requests are sent by an implementation of [`UrlChecker`]
+//! around an async HTTP client.
+//!
+//! The requests for the URLs passed to [`LinkChecker::check`] are sent concurrently,
+//! so it's quicker to check the URLs of many records at once than record by record.
+//!
+//! ```no_run
+//! use disco_quick::links::check::{LinkChecker, UrlChecker};
+//! use disco_quick::links::LinkStatus;
+//! use disco_quick::DiscogsReader;
+//! use std::time::Duration;
+//!
+//! struct Client;
+//!
+//! impl UrlChecker for Client {
+//!     async fn head(&self, url: &str) -> LinkStatus {
+//!         // Send a HEAD request with an async HTTP client
+//!         LinkStatus::from_response(200, None)
+//!     }
+//! }
+//!
+//! # async fn run() -> Result<(), disco_quick::ReaderError> {
+//! let DiscogsReader::Artists(artists) = DiscogsReader::from_path("artists.xml.gz".as_ref())?
+//! else {
+//!     return Ok(());
+//! };
+//! let mut checker = LinkChecker::new(Client)
+//!     .requests_per_second(20.0)
+//!     .host_interval(Duration::from_secs(2));
+//! let mut urls = Vec::new();
+//! for artist in artists.take(1000) {
+//!     urls.extend(artist?.urls);
+//! }
+//! checker.check(&urls).await;
+//! let statuses = checker.finish();
+//! println!("{} dead links", statuses.iter().filter(|(_, s)| s.is_dead()).count());
+//! # Ok(())
+//! # }
+//! ```
+use super::{LinkStatus, LinkStatuses};
+use futures_util::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{sleep_until, Instant};
+
+/// The default number of requests in flight at once.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+/// The default limit on requests per second, to all hosts together.
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 10.0;
+/// The default minimum interval between requests to the same host.
+pub const DEFAULT_HOST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sends the request for a URL, e.g. with an async HTTP client.
+pub trait UrlChecker {
+    /// Send a HEAD request for `url` without following redirects, and return its
+    /// status with [`LinkStatus::from_response`], or [`LinkStatus::failed`] if
+    /// there was no response. Only HTTP(S) URLs are passed to it.
+    fn head(&self, url: &str) -> impl Future<Output = LinkStatus>;
+}
+
+/// Checks URLs with a [`UrlChecker`] and keeps their statuses, so each URL is only
+/// requested once.
+pub struct LinkChecker<C> {
+    checker: C,
+    concurrency: usize,
+    interval: Duration,
+    host_interval: Duration,
+    statuses: LinkStatuses,
+    schedule: Mutex<Schedule>,
+}
+
+/// The times at which the next requests may be sent.
+struct Schedule {
+    next: Instant,
+    hosts: HashMap<String, Instant>,
+}
+
+impl<C: UrlChecker> LinkChecker<C> {
+    pub fn new(checker: C) -> Self {
+        Self {
+            checker,
+            concurrency: DEFAULT_CONCURRENCY,
+            interval: Duration::from_secs_f64(1.0 / DEFAULT_REQUESTS_PER_SECOND),
+            host_interval: DEFAULT_HOST_INTERVAL,
+            statuses: LinkStatuses::new(),
+            schedule: Mutex::new(Schedule {
+                next: Instant::now(),
+                hosts: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Send at most `n` requests at once.
+    pub fn concurrency(mut self, n: usize) -> Self {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    /// Send at most `n` requests per second to all hosts together.
+    pub fn requests_per_second(mut self, n: f64) -> Self {
+        self.interval = match n > 0.0 {
+            true => Duration::from_secs_f64(1.0 / n),
+            false => Duration::ZERO,
+        };
+        self
+    }
+
+    /// Wait at least `interval` between requests to the same host.
+    pub fn host_interval(mut self, interval: Duration) -> Self {
+        self.host_interval = interval;
+        self
+    }
+
+    /// Start with the statuses of an earlier check, whose URLs won't be requested
+    /// again.
+    pub fn statuses(mut self, statuses: LinkStatuses) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    /// Check the URLs that haven't been checked yet. URLs that aren't HTTP(S) are
+    /// marked unchecked without a request.
+    pub async fn check<S: AsRef<str>>(&mut self, urls: impl IntoIterator<Item = S>) {
+        let mut pending = Vec::new();
+        let mut seen = HashSet::new();
+        for url in urls {
+            let url = url.as_ref();
+            if self.statuses.contains(url) || !seen.insert(url.to_string()) {
+                continue;
+            }
+            match http_host(url) {
+                Some(host) => pending.push((url.to_string(), host)),
+                None => self.statuses.insert(url.to_string(), LinkStatus::UNCHECKED),
+            }
+        }
+        if pending.is_empty() {
+            return;
+        }
+        // Take the hosts in turn, so requests to the same host don't hold up the rest
+        let mut occurrences = HashMap::new();
+        let mut pending: Vec<_> = pending
+            .into_iter()
+            .map(|(url, host)| {
+                let n = occurrences.entry(host.clone()).or_insert(0);
+                *n += 1;
+                (*n, url, host)
+            })
+            .collect();
+        pending.sort_by_key(|(n, _, _)| *n);
+        let now = Instant::now();
+        self.schedule
+            .get_mut()
+            .expect("the schedule isn't poisoned")
+            .hosts
+            .retain(|_, next| *next > now);
+        let (checker, schedule) = (&self.checker, &self.schedule);
+        let (interval, host_interval) = (self.interval, self.host_interval);
+        let mut checks = stream::iter(pending)
+            .map(|(_, url, host)| async move {
+                let at = {
+                    let mut schedule = schedule.lock().expect("the schedule isn't poisoned");
+                    let slot = schedule.next.max(Instant::now());
+                    schedule.next = slot + interval;
+                    let at = match schedule.hosts.get(&host) {
+                        Some(next) => slot.max(*next),
+                        None => slot,
+                    };
+                    schedule.hosts.insert(host, at + host_interval);
+                    at
+                };
+                sleep_until(at).await;
+                let status = checker.head(&url).await;
+                (url, status)
+            })
+            .buffer_unordered(self.concurrency);
+        while let Some((url, status)) = checks.next().await {
+            self.statuses.insert(url, status);
+        }
+    }
+
+    /// The statuses of the URLs checked so far.
+    pub fn checked(&self) -> &LinkStatuses {
+        &self.statuses
+    }
+
+    pub fn finish(self) -> LinkStatuses {
+        self.statuses
+    }
+}
+
+/// The host of an HTTP(S) URL in lowercase, without any user or port, or None if
+/// it isn't one.
+fn http_host(url: &str) -> Option<String> {
+    let url = url.trim();
+    let scheme_end = url.find("://")?;
+    let scheme = &url[..scheme_end];
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let rest = &url[scheme_end + 3..];
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = match host.rfind(':') {
+        Some(i) if !host.ends_with(']') => &host[..i],
+        _ => host,
+    };
+    match host.is_empty() {
+        true => None,
+        false => Some(host.to_ascii_lowercase()),
+    }
+}
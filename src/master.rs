@@ -1,71 +1,74 @@
 use crate::artist_credit::{get_credit_string, ArtistCredit, ArtistCreditParser};
+use crate::id::{MasterId, ReleaseId};
+use crate::intern::{intern, Symbol};
+use crate::options::ReaderOptions;
 use crate::parser::{Parser, ParserError};
-use crate::reader::XmlReader;
-use crate::shared::Image;
+use crate::reader::EntityReader;
+#[cfg(feature = "extra-fields")]
+use crate::shared::UnknownElement;
+use crate::shared::{normalize_in_place, DataQuality, Image};
+use crate::translations::Translations;
 use crate::util::get_attr_id;
 use crate::video::{Video, VideoParser};
 use log::debug;
 use quick_xml::events::Event;
+#[cfg(feature = "extra-fields")]
+use std::collections::BTreeMap;
 use std::fmt;
 use std::mem::take;
+use std::sync::Arc;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Master {
-    pub id: u32,
+    pub id: MasterId,
     pub title: String,
-    pub main_release: i32,
+    pub main_release: ReleaseId,
     pub year: i32,
     pub notes: Option<String>,
-    pub genres: Vec<String>,
-    pub styles: Vec<String>,
-    pub data_quality: String,
+    pub genres: Vec<Symbol>,
+    pub styles: Vec<Symbol>,
+    /// Whether the record has a `<genres>` element, even an empty one. Use
+    /// [`tagged_genres`](Self::tagged_genres) to tell untagged records from unknown ones.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub genres_present: bool,
+    /// Whether the record has a `<styles>` element, even an empty one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub styles_present: bool,
+    pub data_quality: DataQuality,
     pub artists: Vec<ArtistCredit>,
     pub images: Vec<Image>,
     pub videos: Vec<Video>,
+    /// The text of elements the parser doesn't know, by name, so data isn't lost when
+    /// Discogs adds elements before the crate supports them. Elements with children
+    /// aren't kept.
+    #[cfg(feature = "extra-fields")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_fields: BTreeMap<String, String>,
 }
 
-impl fmt::Display for Master {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let artist_credit = get_credit_string(&self.artists);
-        write!(f, "{} - {}", artist_credit, self.title)
+impl Master {
+    /// The genres, or None if the record has no `<genres>` element at all.
+    pub fn tagged_genres(&self) -> Option<&[Symbol]> {
+        self.genres_present.then_some(self.genres.as_slice())
     }
-}
 
-pub struct MastersReader {
-    buf: Vec<u8>,
-    reader: XmlReader,
-    parser: MasterParser,
-}
-
-impl MastersReader {
-    pub fn new(reader: XmlReader, buf: Vec<u8>) -> Self {
-        Self {
-            buf,
-            reader,
-            parser: MasterParser::new(),
-        }
+    /// The styles, or None if the record has no `<styles>` element at all.
+    pub fn tagged_styles(&self) -> Option<&[Symbol]> {
+        self.styles_present.then_some(self.styles.as_slice())
     }
 }
 
-impl Iterator for MastersReader {
-    type Item = Master;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.reader.read_event_into(&mut self.buf).unwrap() {
-                Event::Eof => {
-                    return None;
-                }
-                ev => self.parser.process(ev).unwrap(),
-            };
-            if self.parser.item_ready {
-                return Some(self.parser.take());
-            }
-            self.buf.clear();
-        }
+impl fmt::Display for Master {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let artist_credit = get_credit_string(&self.artists);
+        write!(f, "{} - {}", artist_credit, self.title)
     }
 }
 
+/// Iterator over the masters in a masters dump.
+pub type MastersReader = EntityReader<MasterParser>;
+
 #[derive(Debug, Default)]
 enum ParserState {
     #[default]
@@ -80,15 +83,22 @@ enum ParserState {
     Genres,
     Year,
     Videos,
+    #[cfg(feature = "extra-fields")]
+    Unknown,
 }
 
+/// Parses a `<master>` element of the masters dump into a [`Master`].
 #[derive(Debug, Default)]
 pub struct MasterParser {
     state: ParserState,
     current_item: Master,
     artist_parser: ArtistCreditParser,
-    videos_parser: VideoParser,
+    video_parser: VideoParser,
     item_ready: bool,
+    translations: Option<Arc<Translations>>,
+    normalize_text: bool,
+    #[cfg(feature = "extra-fields")]
+    unknown: UnknownElement,
 }
 
 impl Parser for MasterParser {
@@ -97,16 +107,44 @@ impl Parser for MasterParser {
         Self::default()
     }
 
+    fn with_options(options: &ReaderOptions) -> Self {
+        Self {
+            translations: options.translations.clone(),
+            normalize_text: options.normalize_text,
+            ..Self::default()
+        }
+    }
+
     fn take(&mut self) -> Master {
         self.item_ready = false;
         take(&mut self.current_item)
     }
 
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
+
+    fn current_id(&self) -> Option<u32> {
+        Some(self.current_item.id.get()).filter(|id| *id != 0)
+    }
+
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
+            #[cfg(feature = "extra-fields")]
+            ParserState::Unknown => {
+                if self
+                    .unknown
+                    .process(ev, &mut self.current_item.extra_fields)?
+                {
+                    ParserState::Master
+                } else {
+                    ParserState::Unknown
+                }
+            }
+
             ParserState::Master => match ev {
                 Event::Start(e) if e.local_name().as_ref() == b"master" => {
-                    self.current_item.id = get_attr_id(e);
+                    self.current_item.id = get_attr_id(e)?;
                     debug!("Began parsing Master {}", self.current_item.id);
                     ParserState::Master
                 }
@@ -117,15 +155,45 @@ impl Parser for MasterParser {
                     b"artists" => ParserState::Artists,
                     b"data_quality" => ParserState::DataQuality,
                     b"images" => ParserState::Images,
-                    b"styles" => ParserState::Styles,
-                    b"genres" => ParserState::Genres,
+                    b"styles" => {
+                        self.current_item.styles_present = true;
+                        ParserState::Styles
+                    }
+                    b"genres" => {
+                        self.current_item.genres_present = true;
+                        ParserState::Genres
+                    }
                     b"notes" => ParserState::Notes,
                     b"year" => ParserState::Year,
                     b"videos" => ParserState::Videos,
+                    #[cfg(feature = "extra-fields")]
+                    _ => {
+                        self.unknown.start(&e);
+                        ParserState::Unknown
+                    }
+                    #[cfg(not(feature = "extra-fields"))]
                     _ => ParserState::Master,
                 },
+                Event::Empty(e) => {
+                    match e.local_name().as_ref() {
+                        b"genres" => self.current_item.genres_present = true,
+                        b"styles" => self.current_item.styles_present = true,
+                        _ => {}
+                    }
+                    ParserState::Master
+                }
 
                 Event::End(e) if e.local_name().as_ref() == b"master" => {
+                    if let Some(translations) = &self.translations {
+                        translations.apply_master(&mut self.current_item);
+                    }
+                    if self.normalize_text {
+                        let master = &mut self.current_item;
+                        master.notes.as_mut().map(normalize_in_place);
+                        for video in &mut master.videos {
+                            normalize_in_place(&mut video.description);
+                        }
+                    }
                     self.item_ready = true;
                     ParserState::Master
                 }
@@ -146,7 +214,7 @@ impl Parser for MasterParser {
 
                 ev => {
                     self.artist_parser.process(ev)?;
-                    if self.artist_parser.item_ready {
+                    if self.artist_parser.item_ready() {
                         self.current_item.artists.push(self.artist_parser.take());
                     }
                     ParserState::Artists
@@ -163,7 +231,7 @@ impl Parser for MasterParser {
 
             ParserState::DataQuality => match ev {
                 Event::Text(e) => {
-                    self.current_item.data_quality = e.unescape()?.to_string();
+                    self.current_item.data_quality = DataQuality::from(&*e.unescape()?);
                     ParserState::DataQuality
                 }
                 _ => ParserState::Master,
@@ -171,7 +239,7 @@ impl Parser for MasterParser {
 
             ParserState::Images => match ev {
                 Event::Empty(e) if e.local_name().as_ref() == b"image" => {
-                    let image = Image::from_event(e);
+                    let image = Image::from_event(e)?;
                     self.current_item.images.push(image);
                     ParserState::Images
                 }
@@ -183,8 +251,9 @@ impl Parser for MasterParser {
             ParserState::Genres => match ev {
                 Event::End(e) if e.local_name().as_ref() == b"genres" => ParserState::Master,
 
+                Event::Text(e) if e.iter().all(|b| b.is_ascii_whitespace()) => ParserState::Genres,
                 Event::Text(e) => {
-                    self.current_item.genres.push(e.unescape()?.to_string());
+                    self.current_item.genres.push(intern(&e.unescape()?));
                     ParserState::Genres
                 }
                 _ => ParserState::Genres,
@@ -193,8 +262,9 @@ impl Parser for MasterParser {
             ParserState::Styles => match ev {
                 Event::End(e) if e.local_name().as_ref() == b"styles" => ParserState::Master,
 
+                Event::Text(e) if e.iter().all(|b| b.is_ascii_whitespace()) => ParserState::Styles,
                 Event::Text(e) => {
-                    self.current_item.styles.push(e.unescape()?.to_string());
+                    self.current_item.styles.push(intern(&e.unescape()?));
                     ParserState::Styles
                 }
                 _ => ParserState::Styles,
@@ -220,9 +290,9 @@ impl Parser for MasterParser {
                 Event::End(e) if e.local_name().as_ref() == b"videos" => ParserState::Master,
 
                 ev => {
-                    self.videos_parser.process(ev)?;
-                    if self.videos_parser.item_ready {
-                        self.current_item.videos.push(self.videos_parser.take());
+                    self.video_parser.process(ev)?;
+                    if self.video_parser.item_ready() {
+                        self.current_item.videos.push(self.video_parser.take());
                     }
                     ParserState::Videos
                 }
@@ -0,0 +1,504 @@
+use crate::filter::EarlyDecision;
+use crate::prefetch::{DEFAULT_CHUNKS, DEFAULT_CHUNK_SIZE};
+use crate::progress::{ByteCounter, Progress};
+use crate::reader::ReaderError;
+use crate::translations::Translations;
+use crate::video::DurationBounds;
+use crate::warning::ParseWarning;
+use log::{Level, LevelFilter};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The default initial capacity of the event buffer.
+pub const DEFAULT_BUFFER_CAPACITY: usize = 4096;
+/// The number of records parsed before [`ReaderOptions::max_error_rate`] is applied, so
+/// an error in one of the first few records doesn't abort.
+pub const ERROR_RATE_MIN_RECORDS: u64 = 1000;
+
+/// An element of a record that can be skipped with [`ReaderOptions::skip`].
+/// Fields that a record type doesn't have are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum Field {
+    Images = 1,
+    Videos = 1 << 1,
+    Notes = 1 << 2,
+    Profile = 1 << 3,
+    Tracklist = 1 << 4,
+    ExtraArtists = 1 << 5,
+    Companies = 1 << 6,
+    Identifiers = 1 << 7,
+    Urls = 1 << 8,
+    NameVariations = 1 << 9,
+    Aliases = 1 << 10,
+    Members = 1 << 11,
+    Groups = 1 << 12,
+    SubLabels = 1 << 13,
+}
+
+impl Field {
+    pub const ALL: [Field; 14] = [
+        Field::Images,
+        Field::Videos,
+        Field::Notes,
+        Field::Profile,
+        Field::Tracklist,
+        Field::ExtraArtists,
+        Field::Companies,
+        Field::Identifiers,
+        Field::Urls,
+        Field::NameVariations,
+        Field::Aliases,
+        Field::Members,
+        Field::Groups,
+        Field::SubLabels,
+    ];
+
+    /// The name of the element in the dumps.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Field::Images => "images",
+            Field::Videos => "videos",
+            Field::Notes => "notes",
+            Field::Profile => "profile",
+            Field::Tracklist => "tracklist",
+            Field::ExtraArtists => "extraartists",
+            Field::Companies => "companies",
+            Field::Identifiers => "identifiers",
+            Field::Urls => "urls",
+            Field::NameVariations => "namevariations",
+            Field::Aliases => "aliases",
+            Field::Members => "members",
+            Field::Groups => "groups",
+            Field::SubLabels => "sublabels",
+        }
+    }
+}
+
+/// Define a set of the flags of a `#[repr]` enum with an `ALL` array of its
+/// variants, which are single bits, along with `|` to build it.
+macro_rules! flag_set {
+    ($(#[$doc:meta])* $set:ident($repr:ty) of $flag:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+        pub struct $set($repr);
+
+        impl $set {
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            pub const fn all() -> Self {
+                let mut bits = 0;
+                let mut i = 0;
+                while i < $flag::ALL.len() {
+                    bits |= $flag::ALL[i] as $repr;
+                    i += 1;
+                }
+                Self(bits)
+            }
+
+            pub fn contains(&self, flag: $flag) -> bool {
+                self.0 & flag as $repr != 0
+            }
+
+            pub fn insert(&mut self, flag: $flag) {
+                self.0 |= flag as $repr;
+            }
+
+            pub fn remove(&mut self, flag: $flag) {
+                self.0 &= !(flag as $repr);
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            pub fn iter(&self) -> impl Iterator<Item = $flag> + '_ {
+                $flag::ALL.into_iter().filter(|f| self.contains(*f))
+            }
+        }
+
+        impl From<$flag> for $set {
+            fn from(flag: $flag) -> Self {
+                Self(flag as $repr)
+            }
+        }
+
+        impl std::ops::BitOr for $flag {
+            type Output = $set;
+            fn bitor(self, rhs: $flag) -> $set {
+                $set(self as $repr | rhs as $repr)
+            }
+        }
+
+        impl std::ops::BitOr<$flag> for $set {
+            type Output = $set;
+            fn bitor(self, rhs: $flag) -> $set {
+                $set(self.0 | rhs as $repr)
+            }
+        }
+
+        impl std::ops::BitOr for $set {
+            type Output = $set;
+            fn bitor(self, rhs: $set) -> $set {
+                $set(self.0 | rhs.0)
+            }
+        }
+    };
+}
+pub(crate) use flag_set;
+
+flag_set! {
+    /// A set of [`Field`]s, built by combining them with `|`.
+    FieldSet(u16) of Field
+}
+
+impl FieldSet {
+    /// Whether the set contains the field with the element name `tag`.
+    fn contains_tag(&self, tag: &[u8]) -> bool {
+        !self.is_empty() && self.iter().any(|f| f.tag().as_bytes() == tag)
+    }
+}
+
+/// What a reader does when a record can't be parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Yield the error and stop reading.
+    #[default]
+    Abort,
+    /// Skip to the next record, reporting the failure to the
+    /// [`on_skip`](ReaderOptions::on_skip) callback. Errors in the XML
+    /// syntax itself can't be recovered from and are still yielded.
+    SkipRecord,
+}
+
+/// A record that was skipped due to a parsing error.
+#[derive(Debug)]
+pub struct SkippedRecord<'a> {
+    /// The ID of the record, if it was parsed before the error occurred.
+    pub id: Option<u32>,
+    /// The position in the decompressed XML at which the error occurred.
+    pub offset: u64,
+    pub error: &'a ReaderError,
+}
+
+/// Which records of a dump are parsed, set with [`ReaderOptions::sample`] to cut a
+/// dump down to a smaller one that's representative of it. The records that aren't
+/// sampled are skipped without being parsed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sample {
+    /// Every `n`th record, starting with the first.
+    Every(u64),
+    /// Each record with the probability given, between 0 and 1. The records are
+    /// chosen pseudo-randomly from their position in the dump, so the same records
+    /// are sampled each time it's read with the same
+    /// [`sample_seed`](ReaderOptions::sample_seed).
+    Probability(f64),
+    /// The first `n` records, after which the reader stops.
+    FirstN(u64),
+}
+
+impl Sample {
+    /// Whether to parse the record at `index` in the dump, counting from 0.
+    pub(crate) fn decide(&self, index: u64, seed: u64) -> EarlyDecision {
+        let keep = match *self {
+            Sample::Every(n) => index.is_multiple_of(n.max(1)),
+            Sample::Probability(p) => {
+                // The top 53 bits as a fraction in [0, 1)
+                let fraction = (splitmix64(seed ^ index) >> 11) as f64 / (1u64 << 53) as f64;
+                fraction < p
+            }
+            Sample::FirstN(n) if index >= n => return EarlyDecision::Stop,
+            Sample::FirstN(_) => true,
+        };
+        match keep {
+            true => EarlyDecision::Parse,
+            false => EarlyDecision::Skip,
+        }
+    }
+}
+
+/// A well-mixed hash of `x`, from the SplitMix64 generator.
+pub(crate) fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+type SkipCallback = Arc<dyn Fn(&SkippedRecord) + Send + Sync>;
+type WarningCallback = Arc<dyn Fn(&ParseWarning) + Send + Sync>;
+type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+#[derive(Clone)]
+pub(crate) struct ProgressOptions {
+    pub(crate) callback: ProgressCallback,
+    pub(crate) interval: Duration,
+    pub(crate) counter: Arc<ByteCounter>,
+}
+
+/// Settings that control how a dump is read.
+#[derive(Clone, Default)]
+pub struct ReaderOptions {
+    pub(crate) max_bytes_per_sec: Option<u64>,
+    pub(crate) record_delay: Option<Duration>,
+    pub(crate) translations: Option<Arc<Translations>>,
+    pub(crate) on_error: ErrorPolicy,
+    pub(crate) on_skip: Option<SkipCallback>,
+    pub(crate) prefetch: Option<(usize, usize)>,
+    pub(crate) collect_warnings: bool,
+    pub(crate) on_warning: Option<WarningCallback>,
+    pub(crate) progress: Option<ProgressOptions>,
+    pub(crate) trim_text: Option<bool>,
+    pub(crate) normalize_text: bool,
+    pub(crate) video_duration_bounds: Option<DurationBounds>,
+    pub(crate) check_end_names: Option<bool>,
+    pub(crate) buffer_capacity: Option<usize>,
+    pub(crate) skip: FieldSet,
+    pub(crate) log_level: Option<LevelFilter>,
+    pub(crate) sample: Option<Sample>,
+    pub(crate) sample_seed: u64,
+    pub(crate) max_errors: Option<u64>,
+    pub(crate) max_error_rate: Option<f64>,
+}
+
+impl ReaderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the rate at which the file is read from disk. For gzipped dumps this
+    /// limits the compressed bytes, so the parsing rate will be several times higher.
+    pub fn max_bytes_per_sec(mut self, limit: u64) -> Self {
+        self.max_bytes_per_sec = Some(limit);
+        self
+    }
+
+    /// Sleep for `delay` after each record is parsed, to leave CPU time for other
+    /// processes. A zero delay yields to the scheduler instead of sleeping.
+    pub fn record_delay(mut self, delay: Duration) -> Self {
+        self.record_delay = Some(delay);
+        self
+    }
+
+    /// Rename genres and styles of releases and masters as they're parsed.
+    pub fn translations(mut self, translations: Translations) -> Self {
+        self.translations = Some(Arc::new(translations));
+        self
+    }
+
+    /// Decompress gzipped dumps on a separate thread, which feeds the parser
+    /// with chunks of 1 MiB through a buffer of 8 chunks.
+    pub fn prefetch(self) -> Self {
+        self.prefetch_with(DEFAULT_CHUNK_SIZE, DEFAULT_CHUNKS)
+    }
+
+    /// Decompress gzipped dumps on a separate thread with a buffer of up to `chunks`
+    /// chunks of `chunk_size` bytes each.
+    pub fn prefetch_with(mut self, chunk_size: usize, chunks: usize) -> Self {
+        self.prefetch = Some((chunk_size, chunks));
+        self
+    }
+
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.on_error = policy;
+        self
+    }
+
+    /// Call `callback` for each record skipped with [`ErrorPolicy::SkipRecord`].
+    pub fn on_skip(mut self, callback: impl Fn(&SkippedRecord) + Send + Sync + 'static) -> Self {
+        self.on_skip = Some(Arc::new(callback));
+        self
+    }
+
+    /// With [`ErrorPolicy::SkipRecord`], stop with [`ReaderError::TooManyErrors`] once
+    /// more than `n` records have been skipped. Parallel readers ignore this, as each
+    /// of their chunks is read separately.
+    pub fn max_errors(mut self, n: u64) -> Self {
+        self.max_errors = Some(n);
+        self
+    }
+
+    /// With [`ErrorPolicy::SkipRecord`], stop with [`ReaderError::TooManyErrors`] once
+    /// the fraction of the records parsed that were skipped is above `rate`, e.g. 0.01
+    /// for 1%. Records left out by sampling or early filters aren't counted. The rate
+    /// is only checked after [`ERROR_RATE_MIN_RECORDS`] records. Parallel readers
+    /// ignore this.
+    pub fn max_error_rate(mut self, rate: f64) -> Self {
+        self.max_error_rate = Some(rate);
+        self
+    }
+
+    /// Keep the non-fatal issues found while parsing, to be retrieved with
+    /// [`EntityReader::warnings`](crate::reader::EntityReader::warnings). They're kept
+    /// until taken, so drain them regularly when reading a whole dump.
+    pub fn collect_warnings(mut self) -> Self {
+        self.collect_warnings = true;
+        self
+    }
+
+    /// Call `callback` for each non-fatal issue found while parsing.
+    pub fn on_warning(mut self, callback: impl Fn(&ParseWarning) + Send + Sync + 'static) -> Self {
+        self.on_warning = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call `callback` with the reader's progress about once a second, and when the
+    /// end of the dump is reached. The percentage and ETA are only available for
+    /// dumps opened from a path, where the file size is known.
+    pub fn progress(self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.progress_with(Duration::from_secs(1), callback)
+    }
+
+    /// Like [`progress`](Self::progress) with a custom minimum interval between calls.
+    pub fn progress_with(
+        mut self,
+        interval: Duration,
+        callback: impl Fn(&Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(ProgressOptions {
+            callback: Arc::new(callback),
+            interval,
+            counter: Arc::default(),
+        });
+        self
+    }
+
+    /// Trim whitespace around text. The dumps don't contain any formatting whitespace,
+    /// so this only matters for other XML files with the same structure.
+    pub fn trim_text(mut self, trim: bool) -> Self {
+        self.trim_text = Some(trim);
+        self
+    }
+
+    /// Normalize the whitespace of notes, profiles and video descriptions with
+    /// [`normalize_text`](crate::shared::normalize_text), so they're the same
+    /// whichever platform they were entered on.
+    pub fn normalize_text(mut self, normalize: bool) -> Self {
+        self.normalize_text = normalize;
+        self
+    }
+
+    /// The video durations outside of which a warning is reported, instead of
+    /// [`DurationBounds::DEFAULT`]. Warnings are only checked for when they're
+    /// collected or there's a callback for them.
+    pub fn video_duration_bounds(mut self, bounds: DurationBounds) -> Self {
+        self.video_duration_bounds = Some(bounds);
+        self
+    }
+
+    /// Check that end tags match their start tags, which is enabled by default.
+    /// Disabling it speeds up parsing slightly.
+    pub fn check_end_names(mut self, check: bool) -> Self {
+        self.check_end_names = Some(check);
+        self
+    }
+
+    /// The initial capacity of the buffer that events are read into.
+    pub fn buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// Skip the images of records without parsing them, leaving them empty.
+    pub fn skip_images(self, skip: bool) -> Self {
+        self.skip_field(Field::Images, skip)
+    }
+
+    /// Skip the videos of releases and masters without parsing them, leaving them empty.
+    pub fn skip_videos(self, skip: bool) -> Self {
+        self.skip_field(Field::Videos, skip)
+    }
+
+    /// Jump past the elements of `fields` without tokenizing their contents, leaving
+    /// them empty or None in the parsed records. This speeds up parsing considerably
+    /// when only a few fields are needed, e.g. `skip(Field::Videos | Field::Notes)`.
+    pub fn skip(mut self, fields: impl Into<FieldSet>) -> Self {
+        self.skip = self.skip | fields.into();
+        self
+    }
+
+    fn skip_field(mut self, field: Field, skip: bool) -> Self {
+        if skip {
+            self.skip.insert(field);
+        } else {
+            self.skip.remove(field);
+        }
+        self
+    }
+
+    /// Only log the reader's messages about skipped records and warnings at or
+    /// above `level`. Messages from the parsers themselves aren't affected.
+    pub fn log_level(mut self, level: LevelFilter) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// Only parse a sample of the records, e.g. `sample(Sample::Probability(0.01))` for
+    /// about 1% of them. Parallel readers split the dump into chunks, so they ignore
+    /// this.
+    pub fn sample(mut self, sample: Sample) -> Self {
+        self.sample = Some(sample);
+        self
+    }
+
+    /// The seed that [`Sample::Probability`] chooses records with. Defaults to 0.
+    pub fn sample_seed(mut self, seed: u64) -> Self {
+        self.sample_seed = seed;
+        self
+    }
+
+    /// Whether skipping `errors` of the first `records` records is over the limits.
+    pub(crate) fn too_many_errors(&self, errors: u64, records: u64) -> bool {
+        let over_rate = self.max_error_rate.is_some_and(|rate| {
+            records >= ERROR_RATE_MIN_RECORDS && errors as f64 / records as f64 > rate
+        });
+        over_rate || self.max_errors.is_some_and(|max| errors > max)
+    }
+
+    pub(crate) fn log_enabled(&self, level: Level) -> bool {
+        self.log_level.is_none_or(|filter| level <= filter)
+    }
+
+    /// Whether the child element `name` of a record should be skipped.
+    pub(crate) fn skips(&self, name: &[u8]) -> bool {
+        self.skip.contains_tag(name)
+    }
+
+    pub(crate) fn buffer(&self) -> Vec<u8> {
+        Vec::with_capacity(self.buffer_capacity.unwrap_or(DEFAULT_BUFFER_CAPACITY))
+    }
+
+    pub(crate) fn inspect(&self) -> bool {
+        self.collect_warnings || self.on_warning.is_some()
+    }
+}
+
+impl fmt::Debug for ReaderOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReaderOptions")
+            .field("max_bytes_per_sec", &self.max_bytes_per_sec)
+            .field("record_delay", &self.record_delay)
+            .field("translations", &self.translations)
+            .field("on_error", &self.on_error)
+            .field("on_skip", &self.on_skip.is_some())
+            .field("prefetch", &self.prefetch)
+            .field("collect_warnings", &self.collect_warnings)
+            .field("on_warning", &self.on_warning.is_some())
+            .field("progress", &self.progress.as_ref().map(|p| p.interval))
+            .field("trim_text", &self.trim_text)
+            .field("normalize_text", &self.normalize_text)
+            .field("video_duration_bounds", &self.video_duration_bounds)
+            .field("check_end_names", &self.check_end_names)
+            .field("buffer_capacity", &self.buffer_capacity)
+            .field("skip", &self.skip.iter().collect::<Vec<_>>())
+            .field("log_level", &self.log_level)
+            .field("sample", &self.sample)
+            .field("sample_seed", &self.sample_seed)
+            .field("max_errors", &self.max_errors)
+            .field("max_error_rate", &self.max_error_rate)
+            .finish()
+    }
+}
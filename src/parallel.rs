@@ -0,0 +1,315 @@
+//! Parsing a dump on multiple threads.
+//!
+//! The decompressed dump is split into chunks at record boundaries on one thread, and
+//! the chunks are parsed by a pool of worker threads. Items are yielded in the order of
+//! the dump unless [`ParallelOptions::unordered`] is used.
+use crate::options::{ErrorPolicy, ReaderOptions};
+use crate::parser::{ArtistParser, LabelParser, MasterParser, Parser, ReleaseParser};
+use crate::reader::{open_dump, EntityReader, ReaderError, XmlReader};
+use crate::shared::EntityType;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Cursor, Read};
+use std::path::Path;
+use std::thread;
+use std::vec;
+
+const DEFAULT_CHUNK_SIZE: usize = 4 << 20;
+
+/// A parser for the records of a top-level dump.
+pub trait RecordParser: Parser {
+    const ENTITY: EntityType;
+
+    /// The bytes that begin each record, which don't occur anywhere else in the dump.
+    fn record_start() -> &'static [u8] {
+        match Self::ENTITY {
+            EntityType::Artist => b"<artist>",
+            EntityType::Label => b"<label>",
+            EntityType::Master => b"<master ",
+            EntityType::Release => b"<release ",
+        }
+    }
+}
+
+impl RecordParser for ArtistParser {
+    const ENTITY: EntityType = EntityType::Artist;
+}
+
+impl RecordParser for LabelParser {
+    const ENTITY: EntityType = EntityType::Label;
+}
+
+impl RecordParser for MasterParser {
+    const ENTITY: EntityType = EntityType::Master;
+}
+
+impl RecordParser for ReleaseParser {
+    const ENTITY: EntityType = EntityType::Release;
+}
+
+#[derive(Clone, Debug)]
+pub struct ParallelOptions {
+    threads: usize,
+    chunk_size: usize,
+    ordered: bool,
+    reader: ReaderOptions,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(4, |n| n.get()),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            ordered: true,
+            reader: ReaderOptions::default(),
+        }
+    }
+}
+
+impl ParallelOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of parsing threads. Defaults to the available parallelism.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// The approximate size of the decompressed chunks given to each thread. Defaults to 4 MiB.
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Yield items as soon as they're parsed rather than in the order of the dump.
+    pub fn unordered(mut self) -> Self {
+        self.ordered = false;
+        self
+    }
+
+    /// Options used to read the file and by each parsing thread. Byte offsets
+    /// reported to the `on_skip` callback are relative to the chunk being parsed.
+    pub fn reader_options(mut self, options: ReaderOptions) -> Self {
+        self.reader = options;
+        self
+    }
+}
+
+type Chunk<T> = (u64, Vec<Result<T, ReaderError>>);
+
+/// Iterator over the items of a dump parsed on multiple threads.
+pub struct ParallelReader<P: RecordParser> {
+    results: Receiver<Chunk<P::Item>>,
+    pending: BTreeMap<u64, Vec<Result<P::Item, ReaderError>>>,
+    current: vec::IntoIter<Result<P::Item, ReaderError>>,
+    next_seq: u64,
+    ordered: bool,
+    abort_on_error: bool,
+    finished: bool,
+}
+
+pub type ParallelArtistsReader = ParallelReader<ArtistParser>;
+pub type ParallelLabelsReader = ParallelReader<LabelParser>;
+pub type ParallelMastersReader = ParallelReader<MasterParser>;
+pub type ParallelReleasesReader = ParallelReader<ReleaseParser>;
+
+impl<P> ParallelReader<P>
+where
+    P: RecordParser + 'static,
+    P::Item: Send + 'static,
+{
+    pub fn from_path(path: &Path, options: ParallelOptions) -> Result<Self, ReaderError> {
+        let source = open_dump(path, &options.reader)?;
+        Ok(Self::from_reader(source, options))
+    }
+
+    /// Parse a decompressed dump from any source.
+    pub fn from_reader(source: impl BufRead + Send + 'static, options: ParallelOptions) -> Self {
+        let (chunk_tx, chunk_rx) = bounded::<(u64, Vec<u8>)>(options.threads);
+        let (result_tx, result_rx) = bounded(options.threads * 2);
+        for _ in 0..options.threads {
+            let chunk_rx = chunk_rx.clone();
+            let result_tx = result_tx.clone();
+            let mut reader_options = options.reader.clone();
+            // Sampling and error limits count records from the start of each chunk,
+            // so they're ignored
+            reader_options.sample = None;
+            reader_options.max_errors = None;
+            reader_options.max_error_rate = None;
+            thread::spawn(move || {
+                for (seq, chunk) in chunk_rx {
+                    let items = parse_chunk::<P>(chunk, reader_options.clone());
+                    if result_tx.send((seq, items)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let chunk_size = options.chunk_size;
+        thread::spawn(move || split::<P>(source, chunk_size, chunk_tx, result_tx));
+        Self {
+            results: result_rx,
+            pending: BTreeMap::new(),
+            current: Vec::new().into_iter(),
+            next_seq: 0,
+            ordered: options.ordered,
+            abort_on_error: options.reader.on_error == ErrorPolicy::Abort,
+            finished: false,
+        }
+    }
+
+    fn next_chunk(&mut self) -> Option<Vec<Result<P::Item, ReaderError>>> {
+        if !self.ordered {
+            return self.results.recv().ok().map(|(_, items)| items);
+        }
+        loop {
+            if let Some(items) = self.pending.remove(&self.next_seq) {
+                self.next_seq += 1;
+                return Some(items);
+            }
+            match self.results.recv() {
+                Ok((seq, items)) => {
+                    self.pending.insert(seq, items);
+                }
+                // A chunk is only missing if reading failed, which is reported in its place
+                Err(_) => return self.pending.pop_first().map(|(_, items)| items),
+            }
+        }
+    }
+}
+
+impl<P> Iterator for ParallelReader<P>
+where
+    P: RecordParser + 'static,
+    P::Item: Send + 'static,
+{
+    type Item = Result<P::Item, ReaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            if let Some(item) = self.current.next() {
+                if item.is_err() && self.abort_on_error {
+                    self.finished = true;
+                }
+                return Some(item);
+            }
+            match self.next_chunk() {
+                Some(items) => self.current = items.into_iter(),
+                None => {
+                    self.finished = true;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+fn parse_chunk<P: RecordParser>(
+    chunk: Vec<u8>,
+    options: ReaderOptions,
+) -> Vec<Result<P::Item, ReaderError>> {
+    let source: Box<dyn BufRead> = Box::new(Cursor::new(chunk));
+    let mut xml_reader: XmlReader = quick_xml::Reader::from_reader(source);
+    // The last chunk ends with the closing root tag
+    xml_reader.check_end_names(false);
+    EntityReader::<P>::with_options(xml_reader, Vec::new(), options).collect()
+}
+
+/// Read `source` into chunks that each contain whole records and send them to the workers.
+fn split<P: RecordParser>(
+    mut source: impl BufRead,
+    chunk_size: usize,
+    chunks: Sender<(u64, Vec<u8>)>,
+    results: Sender<Chunk<P::Item>>,
+) {
+    let pattern = P::record_start();
+    let mut seq = 0;
+    let mut buf = Vec::with_capacity(chunk_size * 2);
+    let mut started = false;
+    let mut eof = false;
+    // The bytes at the start of `buf` already searched for the pattern. Only what's
+    // read after them and the overlap of a pattern they could end with are searched
+    // again, so a record much larger than a chunk isn't searched over and over.
+    let mut searched: usize = 0;
+    while !eof {
+        let len = buf.len();
+        // Fill up to a chunk, or double the buffer when a record is larger than that
+        let to_read = if len < chunk_size {
+            chunk_size - len
+        } else {
+            len
+        };
+        match (&mut source)
+            .take(to_read.max(pattern.len()) as u64)
+            .read_to_end(&mut buf)
+        {
+            Ok(0) => eof = true,
+            Ok(_) => {}
+            Err(e) => {
+                let _ = results.send((seq, vec![Err(e.into())]));
+                return;
+            }
+        }
+        if !started {
+            // Skip the prolog and root start tag
+            let from = searched.saturating_sub(pattern.len() - 1);
+            match find(&buf, pattern, from) {
+                Some(i) => {
+                    buf.drain(..i);
+                    started = true;
+                    searched = 0;
+                }
+                None if eof => return,
+                None => {
+                    searched = buf.len();
+                    continue;
+                }
+            }
+        }
+        if buf.len() < chunk_size && !eof {
+            continue;
+        }
+        let split_at = if eof {
+            buf.len()
+        } else {
+            let from = searched.saturating_sub(pattern.len() - 1).max(1);
+            match rfind(&buf, pattern, from) {
+                Some(i) => i,
+                // The chunk contains a single incomplete record so keep reading
+                None => {
+                    searched = buf.len();
+                    continue;
+                }
+            }
+        };
+        let rest = buf.split_off(split_at);
+        let chunk = std::mem::replace(&mut buf, rest);
+        // The record left over starts with the last pattern found, so no other does
+        searched = buf.len();
+        if chunks.send((seq, chunk)).is_err() {
+            return;
+        }
+        seq += 1;
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .rposition(|w| w == needle)
+        .map(|i| i + from)
+}
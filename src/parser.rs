@@ -1,13 +1,46 @@
+use crate::options::ReaderOptions;
 use quick_xml::events::Event;
 use thiserror::Error;
 
+pub use crate::artist::ArtistParser;
+pub use crate::artist_credit::ArtistCreditParser;
+pub use crate::company::CompanyParser;
+pub use crate::label::LabelParser;
+pub use crate::master::MasterParser;
+pub use crate::release::ReleaseParser;
+pub use crate::track::TrackParser;
+pub use crate::video::VideoParser;
+
+/// A state machine that builds an item from a stream of XML events.
+///
+/// Feed events to [`process`](Parser::process) until [`item_ready`](Parser::item_ready)
+/// returns true, then call [`take`](Parser::take) to get the item and reset the parser.
+/// The child parsers (e.g. [`TrackParser`]) expect the events of a single element
+/// and are used by the entity parsers to handle nested elements.
 pub trait Parser {
     type Item;
 
     fn new() -> Self;
 
+    /// Create a parser that applies the parse-time settings of `options`.
+    fn with_options(_options: &ReaderOptions) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new()
+    }
+
+    /// Take the parsed item, leaving a default in its place.
     fn take(&mut self) -> Self::Item;
 
+    /// Whether the closing tag of the current item has been processed.
+    fn item_ready(&self) -> bool;
+
+    /// The ID of the item being parsed, if it's known yet.
+    fn current_id(&self) -> Option<u32> {
+        None
+    }
+
     fn process(&mut self, ev: Event) -> Result<(), ParserError>;
 }
 
@@ -19,4 +52,45 @@ pub enum ParserError {
     Int(#[from] std::num::ParseIntError),
     #[error(transparent)]
     Bool(#[from] std::str::ParseBoolError),
+    #[error(transparent)]
+    Attr(#[from] quick_xml::events::attributes::AttrError),
+    #[error("Missing attribute")]
+    MissingAttr,
+    /// An error annotated with where in the dump it occurred.
+    #[error("Failed to parse <{element}>{} at byte {offset}: {source}", fmt_id(.id))]
+    WithContext {
+        /// The ID of the record, if it had been parsed before the error occurred.
+        id: Option<u32>,
+        /// The element the error occurred in.
+        element: String,
+        /// The position in the decompressed XML at which the error occurred.
+        offset: u64,
+        source: Box<ParserError>,
+    },
+}
+
+impl ParserError {
+    pub(crate) fn with_context(self, id: Option<u32>, element: &[u8], offset: u64) -> Self {
+        Self::WithContext {
+            id,
+            element: String::from_utf8_lossy(element).into_owned(),
+            offset,
+            source: Box::new(self),
+        }
+    }
+
+    /// The error without any context that was added to it.
+    pub fn root_cause(&self) -> &ParserError {
+        match self {
+            Self::WithContext { source, .. } => source.root_cause(),
+            e => e,
+        }
+    }
+}
+
+fn fmt_id(id: &Option<u32>) -> String {
+    match id {
+        Some(id) => format!(" of record {id}"),
+        None => String::new(),
+    }
 }
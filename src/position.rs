@@ -0,0 +1,241 @@
+//! Parsing of track positions like "A1", "B2.a", "2-14", "CD1-3" or "Video 1", and
+//! of the tracks credits are limited to, like "A1 to A3, B2".
+use std::fmt;
+
+/// Prefixes naming a medium rather than a vinyl side.
+const MEDIUM_PREFIXES: &[&str] = &[
+    "CD", "DVD", "BD", "SACD", "VHS", "MC", "Cass", "Disc", "Video", "File",
+];
+
+/// The parts of a track position.
+///
+/// A position has a medium number and prefix ("2-14", "CD1-3"), a side ("A1") or
+/// neither ("7"), followed by the track number and an optional sub-index for
+/// sub-tracks ("B2.a", "A1a", "3.1").
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackPosition {
+    /// A prefix naming the medium, e.g. "CD" or "Video".
+    pub medium_prefix: Option<String>,
+    pub medium: Option<u32>,
+    pub side: Option<String>,
+    pub number: Option<u32>,
+    pub sub_index: Option<String>,
+}
+
+impl TrackPosition {
+    /// Parse a position, returning None if it's empty or not in a known format.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            return None;
+        }
+        let mut position = Self::default();
+        let rest = match s.rsplit_once('-') {
+            Some((medium, rest)) => {
+                let (prefix, digits) = split_alpha(medium.trim());
+                position.medium_prefix = prefix.map(str::to_string);
+                position.medium = Some(digits.trim().parse().ok()?);
+                rest.trim()
+            }
+            None => s,
+        };
+
+        let (letters, rest) = split_alpha(rest);
+        let rest = match letters {
+            Some(letters) if is_medium_prefix(letters) || rest.starts_with(' ') => {
+                if position.medium_prefix.is_some() {
+                    return None;
+                }
+                position.medium_prefix = Some(letters.to_string());
+                rest.trim_start()
+            }
+            Some(letters) if letters.chars().all(|c| c.is_ascii_uppercase()) => {
+                position.side = Some(letters.to_string());
+                rest
+            }
+            Some(_) => return None,
+            None => rest,
+        };
+
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if end > 0 {
+            position.number = Some(rest[..end].parse().ok()?);
+        }
+        let sub_index = rest[end..].trim_start_matches(['.', ' ']);
+        if !sub_index.is_empty() {
+            if !sub_index.chars().all(|c| c.is_alphanumeric()) {
+                return None;
+            }
+            position.sub_index = Some(sub_index.to_string());
+        }
+        if position.number.is_none() && position.side.is_none() {
+            return None;
+        }
+        Some(position)
+    }
+
+    /// The number of the disc or record the track is on. For vinyl sides this
+    /// assumes two sides per record, so sides C and D are on the second.
+    pub fn disc(&self) -> Option<u32> {
+        if self.medium.is_some() {
+            return self.medium;
+        }
+        let side = self.side.as_deref()?;
+        let mut chars = side.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some((c as u32 - 'A' as u32) / 2 + 1),
+            _ => None,
+        }
+    }
+
+    pub fn is_sub_track(&self) -> bool {
+        self.sub_index.is_some()
+    }
+}
+
+impl fmt::Display for TrackPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(prefix) = &self.medium_prefix {
+            write!(f, "{prefix}")?;
+            if self.medium.is_none() {
+                write!(f, " ")?;
+            }
+        }
+        if let Some(medium) = self.medium {
+            write!(f, "{medium}-")?;
+        }
+        if let Some(side) = &self.side {
+            write!(f, "{side}")?;
+        }
+        if let Some(number) = self.number {
+            write!(f, "{number}")?;
+        }
+        if let Some(sub_index) = &self.sub_index {
+            write!(f, ".{sub_index}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The tracks a credit applies to, parsed from the `tracks` of an artist credit,
+/// e.g. "A1 to A3, B2".
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackSelection {
+    pub spans: Vec<TrackSpan>,
+}
+
+/// A single position or an inclusive range of positions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackSpan {
+    Single(String),
+    Range(String, String),
+}
+
+impl TrackSelection {
+    /// Parse a comma separated list of positions and ranges written as "A1 to A3".
+    /// Ranges written as "A1-A3" are recognised when both ends have a side, as
+    /// "1-3" is the third track of the first medium.
+    pub fn parse(s: &str) -> Self {
+        let mut spans = Vec::new();
+        for part in s.split([',', ';']).map(str::trim).filter(|p| !p.is_empty()) {
+            let span = match split_range(part) {
+                Some((start, end)) => TrackSpan::Range(start.to_string(), end.to_string()),
+                None => TrackSpan::Single(part.to_string()),
+            };
+            spans.push(span);
+        }
+        Self { spans }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Which of `positions`, in tracklist order, the selection covers. A range covers
+    /// the positions from its start to its end in the tracklist. If either end isn't
+    /// in the tracklist, the positions between them by number are covered instead.
+    pub fn covers<S: AsRef<str>>(&self, positions: &[S]) -> Vec<bool> {
+        let mut covered = vec![false; positions.len()];
+        let find = |target: &str, from: usize| {
+            positions
+                .iter()
+                .skip(from)
+                .position(|p| same_position(p.as_ref(), target))
+                .map(|i| i + from)
+        };
+        for span in &self.spans {
+            match span {
+                TrackSpan::Single(position) => {
+                    for (i, p) in positions.iter().enumerate() {
+                        covered[i] |= same_position(p.as_ref(), position);
+                    }
+                }
+                TrackSpan::Range(start, end) => {
+                    let first = find(start, 0);
+                    match first.and_then(|first| Some((first, find(end, first)?))) {
+                        Some((first, last)) => covered[first..=last].fill(true),
+                        None => {
+                            for (i, p) in positions.iter().enumerate() {
+                                covered[i] |= in_range(p.as_ref(), start, end);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        covered
+    }
+}
+
+/// Split "A1 to A3" or "A1-A3" into its ends.
+fn split_range(s: &str) -> Option<(&str, &str)> {
+    let lower = s.to_ascii_lowercase();
+    if let Some(i) = lower.find(" to ") {
+        return Some((s[..i].trim(), s[i + 4..].trim()));
+    }
+    let (start, end) = s.split_once('-')?;
+    let has_side = |p: &str| TrackPosition::parse(p).is_some_and(|p| p.side.is_some());
+    (has_side(start) && has_side(end)).then(|| (start.trim(), end.trim()))
+}
+
+fn same_position(a: &str, b: &str) -> bool {
+    let (a, b) = (a.trim(), b.trim());
+    if a.eq_ignore_ascii_case(b) {
+        return true;
+    }
+    match (TrackPosition::parse(a), TrackPosition::parse(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether `position` is between `start` and `end` on the same medium or side.
+fn in_range(position: &str, start: &str, end: &str) -> bool {
+    let parse = |s| TrackPosition::parse(s).filter(|p| p.number.is_some());
+    let (Some(p), Some(start), Some(end)) = (parse(position), parse(start), parse(end)) else {
+        return false;
+    };
+    let place = |p: &TrackPosition| (p.medium_prefix.clone(), p.medium, p.side.clone());
+    if place(&start) != place(&end) || place(&p) != place(&start) {
+        return false;
+    }
+    (start.number..=end.number).contains(&p.number)
+}
+
+fn is_medium_prefix(s: &str) -> bool {
+    MEDIUM_PREFIXES.iter().any(|p| p.eq_ignore_ascii_case(s))
+}
+
+/// Split leading letters from the rest of `s`.
+fn split_alpha(s: &str) -> (Option<&str>, &str) {
+    let end = s
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    let letters = (end > 0).then(|| &s[..end]);
+    (letters, &s[end..])
+}
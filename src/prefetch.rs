@@ -0,0 +1,76 @@
+use std::io::{self, BufRead, Read};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+pub const DEFAULT_CHUNKS: usize = 8;
+
+/// Reads from a source on a dedicated thread, passing chunks of bytes to the
+/// consuming thread through a bounded channel.
+pub struct PrefetchReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl PrefetchReader {
+    /// Start reading `source` on a new thread, keeping up to `chunks` chunks
+    /// of `chunk_size` bytes ready to be consumed.
+    pub fn new<R: Read + Send + 'static>(mut source: R, chunk_size: usize, chunks: usize) -> Self {
+        let (tx, rx) = sync_channel(chunks.max(1));
+        let chunk_size = chunk_size.max(1);
+        thread::spawn(move || loop {
+            let mut chunk = Vec::with_capacity(chunk_size);
+            let result = (&mut source)
+                .take(chunk_size as u64)
+                .read_to_end(&mut chunk);
+            let eof = matches!(result, Ok(0));
+            let failed = result.is_err();
+            // Sending only fails when the reader has been dropped
+            if tx.send(result.map(|_| chunk)).is_err() || eof || failed {
+                break;
+            }
+        });
+        Self {
+            chunks: rx,
+            chunk: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl Read for PrefetchReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for PrefetchReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos >= self.chunk.len() && !self.done {
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) if chunk.is_empty() => self.done = true,
+                Ok(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => {
+                    self.done = true;
+                    return Err(e);
+                }
+                Err(_) => self.done = true,
+            }
+        }
+        Ok(&self.chunk[self.pos.min(self.chunk.len())..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
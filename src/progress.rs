@@ -0,0 +1,96 @@
+//! Reporting how far through a dump a reader is.
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A snapshot of a reader's progress, passed to the
+/// [`progress`](crate::ReaderOptions::progress) callback.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    /// Bytes read from the file, which are compressed for gzipped dumps.
+    pub bytes_read: u64,
+    /// The size of the file, if the dump was opened from a path.
+    pub total_bytes: Option<u64>,
+    /// Bytes of XML parsed.
+    pub uncompressed_bytes: u64,
+    pub items: u64,
+    pub elapsed: Duration,
+    /// Whether the end of the dump has been reached.
+    pub finished: bool,
+}
+
+impl Progress {
+    /// The fraction of the file that has been read, from 0 to 1.
+    pub fn fraction(&self) -> Option<f64> {
+        if self.finished {
+            return Some(1.0);
+        }
+        match self.total_bytes {
+            Some(total) if total > 0 => Some((self.bytes_read as f64 / total as f64).min(1.0)),
+            _ => None,
+        }
+    }
+
+    pub fn percent(&self) -> Option<f64> {
+        self.fraction().map(|f| f * 100.0)
+    }
+
+    /// The estimated time until the end of the dump, assuming the rate so far continues.
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction()?;
+        if fraction <= 0.0 {
+            return None;
+        }
+        let remaining = self.elapsed.as_secs_f64() * (1.0 - fraction) / fraction;
+        Some(Duration::from_secs_f64(remaining))
+    }
+
+    pub fn items_per_sec(&self) -> f64 {
+        self.items as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// The number of bytes read from a file, shared between the file reader and the
+/// entity reader that reports progress.
+#[derive(Debug, Default)]
+pub(crate) struct ByteCounter {
+    read: AtomicU64,
+    /// The file size, or zero if unknown.
+    total: AtomicU64,
+}
+
+impl ByteCounter {
+    pub(crate) fn reset(&self, total: Option<u64>) {
+        self.read.store(0, Ordering::Relaxed);
+        self.total.store(total.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub(crate) fn read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn total(&self) -> Option<u64> {
+        Some(self.total.load(Ordering::Relaxed)).filter(|t| *t > 0)
+    }
+}
+
+/// A reader that adds the number of bytes read to a [`ByteCounter`].
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    counter: Arc<ByteCounter>,
+}
+
+impl<R> CountingReader<R> {
+    pub(crate) fn new(inner: R, counter: Arc<ByteCounter>) -> Self {
+        Self { inner, counter }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.counter.read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
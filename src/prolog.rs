@@ -0,0 +1,139 @@
+//! Finding the root element of a dump past whatever comes before it.
+//!
+//! The dumps start with an XML declaration, but dumps that have been recompressed
+//! or edited by other tools can also start with a byte order mark, a doctype,
+//! comments, processing instructions or stray bytes. These are skipped when a dump
+//! is opened, and what was skipped is available from
+//! [`DiscogsReader::prolog`](crate::DiscogsReader::prolog).
+use crate::reader::{ReaderError, XmlReader};
+use quick_xml::events::{BytesStart, Event};
+use std::fmt;
+use std::io::BufRead;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The number of parse errors tolerated before the root element.
+pub(crate) const MAX_ERRORS: usize = 16;
+
+/// Something skipped before the root element of a dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Skipped {
+    ByteOrderMark,
+    Declaration(String),
+    DocType(String),
+    Comment(String),
+    Instruction(String),
+    /// Text, CDATA and tags that don't belong before the root element, and parts
+    /// that couldn't be parsed at all.
+    Junk(String),
+}
+
+impl Skipped {
+    /// Whether it's something that doesn't belong in an XML prolog.
+    pub fn is_junk(&self) -> bool {
+        matches!(self, Skipped::Junk(_))
+    }
+}
+
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Skipped::ByteOrderMark => write!(f, "byte order mark"),
+            Skipped::Declaration(s) => write!(f, "declaration <?{s}?>"),
+            Skipped::DocType(s) => write!(f, "doctype <!DOCTYPE {s}>"),
+            Skipped::Comment(s) => write!(f, "comment <!--{s}-->"),
+            Skipped::Instruction(s) => write!(f, "processing instruction <?{s}?>"),
+            Skipped::Junk(s) => write!(f, "junk {s:?}"),
+        }
+    }
+}
+
+/// What to do with an event read before the root element.
+pub(crate) enum Prefix<'a> {
+    /// The start tag of the root element, and any junk read along with it.
+    Root(BytesStart<'a>, Option<Skipped>),
+    Skip(Option<Skipped>),
+    Eof,
+}
+
+pub(crate) fn classify(ev: Event) -> Prefix {
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+    let skipped = match ev {
+        Event::Start(e) if is_name(e.name().as_ref()) => return Prefix::Root(e, None),
+        // A stray `<` makes the parser read up to the end of the next tag
+        Event::Start(e) => match e.iter().rposition(|b| *b == b'<') {
+            Some(i) if is_name(name_of(&e[i + 1..])) => {
+                let name_len = name_of(&e[i + 1..]).len();
+                let root = BytesStart::from_content(text(&e[i + 1..]), name_len);
+                return Prefix::Root(root, Some(Skipped::Junk(format!("<{}", text(&e[..i])))));
+            }
+            _ => Skipped::Junk(format!("<{}>", text(&e))),
+        },
+        Event::Eof => return Prefix::Eof,
+        Event::Decl(e) => Skipped::Declaration(text(&e)),
+        Event::DocType(e) => Skipped::DocType(text(e.trim_ascii())),
+        Event::Comment(e) => Skipped::Comment(text(&e)),
+        Event::PI(e) => Skipped::Instruction(text(&e)),
+        Event::Text(e) if e.iter().all(u8::is_ascii_whitespace) => return Prefix::Skip(None),
+        Event::Text(e) => Skipped::Junk(text(&e)),
+        Event::CData(e) => Skipped::Junk(format!("<![CDATA[{}]]>", text(&e))),
+        Event::Empty(e) => Skipped::Junk(format!("<{}/>", text(&e))),
+        Event::End(e) => Skipped::Junk(format!("</{}>", text(e.name().as_ref()))),
+    };
+    Prefix::Skip(Some(skipped))
+}
+
+/// Read up to and including the start tag of the root element, returning it and
+/// what was skipped before it.
+pub(crate) fn read_start_tag(
+    reader: &mut XmlReader,
+    buf: &mut Vec<u8>,
+) -> Result<(BytesStart<'static>, Vec<Skipped>), ReaderError> {
+    let mut skipped = Vec::new();
+    if reader.buffer_position() == 0 && reader.get_mut().fill_buf()?.starts_with(UTF8_BOM) {
+        skipped.push(Skipped::ByteOrderMark);
+    }
+    // Stray end tags before the root element aren't an error
+    reader.check_end_names(false);
+    let mut errors = 0;
+    let (start, recovered) = loop {
+        buf.clear();
+        match reader.read_event_into(buf) {
+            Ok(ev) => match classify(ev) {
+                Prefix::Root(start, junk) => {
+                    let recovered = junk.is_some();
+                    skipped.extend(junk);
+                    break (start.into_owned(), recovered);
+                }
+                Prefix::Skip(s) => skipped.extend(s),
+                Prefix::Eof => return Err(ReaderError::NoStartTag),
+            },
+            Err(e) if errors < MAX_ERRORS => {
+                errors += 1;
+                skipped.push(Skipped::Junk(format!("unparseable markup ({e})")));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    // The parser didn't see the root start tag it was recovered from, so the
+    // root end tag won't match the tag it thinks is open
+    reader.check_end_names(!recovered);
+    Ok((start, skipped))
+}
+
+/// The part of the content of a tag before any whitespace.
+fn name_of(content: &[u8]) -> &[u8] {
+    let end = content.iter().position(u8::is_ascii_whitespace);
+    &content[..end.unwrap_or(content.len())]
+}
+
+/// Whether `name` could be the name of an element, as opposed to a stray `<`.
+fn is_name(name: &[u8]) -> bool {
+    let name_start = |b: u8| b.is_ascii_alphabetic() || b == b'_' || b == b':' || b >= 0x80;
+    let name_char = |b: u8| name_start(b) || b.is_ascii_digit() || b == b'-' || b == b'.';
+    match name.split_first() {
+        Some((first, rest)) => name_start(*first) && rest.iter().all(|b| name_char(*b)),
+        None => false,
+    }
+}
@@ -1,28 +1,647 @@
 pub use crate::artist::ArtistsReader;
+use crate::custom::{self, CustomReader};
+use crate::entity::Entity;
+use crate::filter::expr::{Query, Queryable};
+use crate::filter::{EarlyChecks, EarlyDecision, EarlyFilter, Filter};
 pub use crate::label::LabelsReader;
 pub use crate::master::MastersReader;
+use crate::options::{ErrorPolicy, ReaderOptions, SkippedRecord};
+use crate::parser::{Parser, ParserError};
+use crate::prefetch::PrefetchReader;
+use crate::progress::{CountingReader, Progress};
+use crate::prolog::{read_start_tag, Skipped};
 pub use crate::release::ReleasesReader;
-use flate2::read::GzDecoder;
-use quick_xml::events::Event;
+use crate::shared::EntityType;
+use crate::util::Throttle;
+use crate::warning::{Inspector, ParseWarning, WarningKind};
+use flate2::read::MultiGzDecoder;
+use log::{debug, warn, Level};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::QName;
 use quick_xml::Error as XmlError;
+use std::collections::{HashSet, VecDeque};
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Error as IoError};
+use std::io::{self, BufRead, BufReader, Error as IoError, Read};
+use std::ops::RangeBounds;
 use std::path::Path;
+use std::thread;
+use std::time::Instant;
 use thiserror::Error;
 
 pub type XmlReader = quick_xml::Reader<Box<dyn BufRead>>;
 
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 pub fn get_xml_reader(path: &Path) -> Result<XmlReader, IoError> {
-    let file = File::open(path)?;
-    let gz = GzDecoder::new(file);
-    let reader: Box<dyn BufRead> = if gz.header().is_some() {
-        Box::new(BufReader::new(gz))
-    } else {
+    get_xml_reader_with_options(path, &ReaderOptions::default())
+}
+
+pub fn get_xml_reader_with_options(
+    path: &Path,
+    options: &ReaderOptions,
+) -> Result<XmlReader, IoError> {
+    let reader: Box<dyn BufRead> = open_dump(path, options)?;
+    Ok(quick_xml::Reader::from_reader(reader))
+}
+
+/// Open a dump file, decompressing it if it's gzipped.
+pub(crate) fn open_dump(
+    path: &Path,
+    options: &ReaderOptions,
+) -> Result<Box<dyn BufRead + Send>, IoError> {
+    let open = || -> Result<Box<dyn Read + Send>, IoError> {
         let file = File::open(path)?;
-        Box::new(BufReader::new(file))
+        let file: Box<dyn Read + Send> = match &options.progress {
+            Some(progress) => {
+                progress
+                    .counter
+                    .reset(file.metadata().ok().map(|m| m.len()));
+                Box::new(CountingReader::new(file, progress.counter.clone()))
+            }
+            None => Box::new(file),
+        };
+        Ok(match options.max_bytes_per_sec {
+            Some(limit) => Box::new(Throttle::new(file, limit)),
+            None => Box::new(file),
+        })
     };
-    Ok(quick_xml::Reader::from_reader(reader))
+    // Dumps written by `index::write_seekable` have a gzip member every few records
+    let gz = MultiGzDecoder::new(open()?);
+    Ok(if gz.header().is_some() {
+        match options.prefetch {
+            Some((chunk_size, chunks)) => Box::new(PrefetchReader::new(gz, chunk_size, chunks)),
+            None => Box::new(BufReader::new(gz)),
+        }
+    } else {
+        Box::new(BufReader::new(open()?))
+    })
+}
+
+/// Iterator that feeds the events of a dump to a [`Parser`] and yields its items.
+pub struct EntityReader<P: Parser> {
+    buf: Vec<u8>,
+    reader: XmlReader,
+    state: RecordState<P>,
+    finished: bool,
+    /// The buffer used when skipping elements.
+    skip_buf: Vec<u8>,
+    filter: Option<Filter<P::Item>>,
+    /// The items to yield instead of parsing, for readers created with [`Self::from_results`].
+    in_memory: Option<VecDeque<Result<P::Item, ReaderError>>>,
+    items: u64,
+    /// The bytes skipped by [`resume`](Self::resume), which the XML reader didn't see.
+    skipped: u64,
+    /// What came before the root element.
+    prolog: Vec<Skipped>,
+    start: Instant,
+    last_progress: Instant,
+}
+
+/// A position between two records of a dump, from [`EntityReader::checkpoint`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    /// The offset in the decompressed XML of the end of the last record read.
+    pub offset: u64,
+    /// The number of records read before the checkpoint.
+    pub records: u64,
+    /// The number of records started before the checkpoint, including those that
+    /// weren't read, which [`ReaderOptions::sample`] counts positions from.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub started: u64,
+}
+
+impl<P: Parser> EntityReader<P> {
+    /// Create a reader from an XML reader that's positioned after the root start tag.
+    pub fn new(reader: XmlReader, buf: Vec<u8>) -> Self {
+        Self::with_options(reader, buf, ReaderOptions::default())
+    }
+
+    pub fn with_options(mut reader: XmlReader, mut buf: Vec<u8>, options: ReaderOptions) -> Self {
+        RecordState::<P>::configure(&options, &mut reader, &mut buf);
+        Self {
+            buf,
+            reader,
+            state: RecordState::new(options),
+            finished: false,
+            skip_buf: Vec::new(),
+            filter: None,
+            in_memory: None,
+            items: 0,
+            skipped: 0,
+            prolog: Vec::new(),
+            start: Instant::now(),
+            last_progress: Instant::now(),
+        }
+    }
+
+    /// Create a reader that yields `items` instead of parsing a dump, so code that
+    /// consumes readers can be tested without XML fixtures.
+    pub fn from_items(items: impl IntoIterator<Item = P::Item>) -> Self {
+        Self::from_results(items.into_iter().map(Ok))
+    }
+
+    /// Like [`from_items`](Self::from_items), but errors can be included to test how
+    /// they're handled. They're yielded as they are, regardless of the error policy.
+    pub fn from_results(results: impl IntoIterator<Item = Result<P::Item, ReaderError>>) -> Self {
+        let reader: Box<dyn BufRead> = Box::new(io::empty());
+        let mut entity_reader = Self::new(quick_xml::Reader::from_reader(reader), Vec::new());
+        entity_reader.in_memory = Some(results.into_iter().collect());
+        entity_reader
+    }
+
+    /// Only yield the records matching `filter`. It's combined with any previous filter.
+    pub fn filter_records(mut self, filter: impl Into<Filter<P::Item>>) -> Self
+    where
+        P::Item: 'static,
+    {
+        let filter = filter.into();
+        self.filter = Some(match self.filter.take() {
+            Some(previous) => previous.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    /// Only parse the records whose ID and status match `filter`, skipping the others
+    /// as soon as their ID is read. It's combined with any previous early filter.
+    /// Readers created with [`from_items`](Self::from_items) ignore it.
+    pub fn filter_early(mut self, filter: impl Into<EarlyFilter>) -> Self {
+        let filter = filter.into();
+        self.state.early.filter = Some(match self.state.early.filter.take() {
+            Some(previous) => previous.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    /// Only yield the records matching `query`, skipping those that fail its checks
+    /// of the ID and status without parsing them. See [`filter::expr`](crate::filter::expr).
+    pub fn filter_query(self, query: Query<P::Item>) -> Self
+    where
+        P::Item: Queryable + 'static,
+    {
+        let reader = match query.early_filter() {
+            Some(early) => self.filter_early(early),
+            None => self,
+        };
+        reader.filter_records(query)
+    }
+
+    /// Only parse the records with one of `ids`. See [`filter_early`](Self::filter_early).
+    pub fn filter_ids(self, ids: HashSet<u32>) -> Self {
+        self.filter_early(EarlyFilter::ids(ids))
+    }
+
+    /// Only yield the records with IDs in `range`, skipping those before it without
+    /// parsing them and stopping at the first record after it. The dumps are sorted by
+    /// ID, so no records in the range are missed. Readers created with
+    /// [`from_items`](Self::from_items) ignore it.
+    pub fn take_id_range(mut self, range: impl RangeBounds<u32>) -> Self {
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+        self.state.early.id_range = Some((start, end));
+        self
+    }
+
+    /// The warnings collected so far if [`ReaderOptions::collect_warnings`] is set.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.state.warnings
+    }
+
+    /// What was skipped before the root element, if the reader was opened with
+    /// [`DiscogsReader`].
+    pub fn prolog(&self) -> &[Skipped] {
+        &self.prolog
+    }
+
+    /// Remove and return the warnings collected so far.
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        std::mem::take(&mut self.state.warnings)
+    }
+
+    /// The position after the last record read. A run that's stopped can continue
+    /// from it with [`resume`](Self::resume) or [`DiscogsReader::resume`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            offset: self.offset(),
+            records: self.items,
+            started: self.state.started,
+        }
+    }
+
+    /// Skip to a checkpoint of a reader of the same dump, without parsing the records
+    /// before it. The reader must not have read any records yet. Gzipped dumps still
+    /// have to be decompressed up to the checkpoint.
+    pub fn resume(&mut self, checkpoint: &Checkpoint) -> Result<(), ReaderError> {
+        let position = self.offset();
+        if checkpoint.offset < position || self.items > 0 {
+            return Err(ReaderError::InvalidCheckpoint(checkpoint.offset));
+        }
+        let skip = checkpoint.offset - position;
+        let skipped = io::copy(&mut self.reader.get_mut().take(skip), &mut io::sink())?;
+        if skipped < skip {
+            return Err(ReaderError::InvalidCheckpoint(checkpoint.offset));
+        }
+        self.skipped += skipped;
+        self.items = checkpoint.records;
+        self.state.started = checkpoint.started;
+        Ok(())
+    }
+
+    /// The offset in the decompressed XML.
+    fn offset(&self) -> u64 {
+        self.reader.buffer_position() as u64 + self.skipped
+    }
+
+    fn report_progress(&mut self, finished: bool) {
+        let Some(progress) = &self.state.options.progress else {
+            return;
+        };
+        if !finished && self.last_progress.elapsed() < progress.interval {
+            return;
+        }
+        self.last_progress = Instant::now();
+        (progress.callback)(&Progress {
+            bytes_read: progress.counter.read(),
+            total_bytes: progress.counter.total(),
+            uncompressed_bytes: self.offset(),
+            items: self.items,
+            elapsed: self.start.elapsed(),
+            finished,
+        });
+    }
+}
+
+/// What a reader does after giving an event to [`RecordState::event`].
+pub(crate) enum Step<T> {
+    /// Read the next event.
+    Next,
+    /// Read to the end of the element named [`RecordState::skip_name`], then the next
+    /// event.
+    Skip,
+    Item(T),
+    /// Yield the error and stop reading.
+    Error(ReaderError),
+    /// Stop reading.
+    Stop,
+}
+
+/// The part of reading records that doesn't do IO, shared by [`EntityReader`] and
+/// the async reader of the `tokio` feature. The reader reads each event and gives it
+/// to [`event`](Self::event), which says what to do next.
+pub(crate) struct RecordState<P: Parser> {
+    parser: P,
+    pub(crate) options: ReaderOptions,
+    depth: usize,
+    /// The name of the element most recently started, for error context.
+    element: Vec<u8>,
+    /// The name of the record element being read, for the error when the dump ends
+    /// inside it.
+    record_tag: Vec<u8>,
+    /// The name of the element to skip after [`Step::Skip`].
+    pub(crate) skip_name: Vec<u8>,
+    /// Whether the rest of a record is being read after an error in it.
+    recovering: bool,
+    inspector: Option<Inspector>,
+    pending: Vec<WarningKind>,
+    pub(crate) warnings: Vec<ParseWarning>,
+    early: EarlyChecks,
+    /// The name and status of the record being parsed while its ID is unknown.
+    record: Option<(Vec<u8>, Option<String>)>,
+    /// The number of records started, which are sampled by their position.
+    pub(crate) started: u64,
+    /// The number of records parsed, with or without errors, which the error rate
+    /// is counted over. Records skipped by sampling or filters aren't included.
+    parsed: u64,
+    /// The number of records skipped because of errors.
+    errors: u64,
+}
+
+impl<P: Parser> RecordState<P> {
+    pub(crate) fn new(options: ReaderOptions) -> Self {
+        Self {
+            parser: P::with_options(&options),
+            inspector: options
+                .inspect()
+                .then(|| Inspector::new(options.video_duration_bounds.unwrap_or_default())),
+            options,
+            depth: 0,
+            element: Vec::new(),
+            record_tag: Vec::new(),
+            skip_name: Vec::new(),
+            recovering: false,
+            pending: Vec::new(),
+            warnings: Vec::new(),
+            early: EarlyChecks::default(),
+            record: None,
+            started: 0,
+            parsed: 0,
+            errors: 0,
+        }
+    }
+
+    /// Apply the reader options that are settings of the XML reader.
+    pub(crate) fn configure<R>(
+        options: &ReaderOptions,
+        reader: &mut quick_xml::Reader<R>,
+        buf: &mut Vec<u8>,
+    ) {
+        if let Some(trim) = options.trim_text {
+            reader.trim_text(trim);
+        }
+        if let Some(check) = options.check_end_names {
+            reader.check_end_names(check);
+        }
+        if let Some(capacity) = options.buffer_capacity {
+            buf.reserve(capacity.saturating_sub(buf.len()));
+        }
+    }
+
+    /// Handle the next event of the dump, read at `offset` in the decompressed XML.
+    pub(crate) fn event(&mut self, ev: Event, offset: u64) -> Step<P::Item> {
+        if self.recovering {
+            // Read to the end of the record an error occurred in
+            match ev {
+                Event::Start(_) => self.depth += 1,
+                Event::End(_) => self.depth -= 1,
+                Event::Eof => return self.end(),
+                _ => {}
+            }
+            self.recovering = self.depth > 0;
+            return Step::Next;
+        }
+        let sampled = match &ev {
+            Event::Start(e) if self.depth == 0 => {
+                self.started += 1;
+                self.record_tag.clear();
+                self.record_tag.extend_from_slice(e.name().as_ref());
+                match &self.options.sample {
+                    Some(sample) => sample.decide(self.started - 1, self.options.sample_seed),
+                    None => EarlyDecision::Parse,
+                }
+            }
+            _ => EarlyDecision::Parse,
+        };
+        match &ev {
+            Event::Start(_) if sampled == EarlyDecision::Stop => return Step::Stop,
+            Event::Start(e) if sampled == EarlyDecision::Skip => return self.skip(e.name()),
+            Event::Start(e) if self.depth == 0 && !self.early.is_empty() => {
+                self.depth += 1;
+                let status = attr_value(e, "status");
+                match attr_value(e, "id").and_then(|id| id.trim().parse().ok()) {
+                    // Releases and masters have their ID as an attribute
+                    Some(id) => match self.early.check(id, status.as_deref()) {
+                        EarlyDecision::Parse => {}
+                        EarlyDecision::Stop => return Step::Stop,
+                        EarlyDecision::Skip => {
+                            self.depth -= 1;
+                            return self.skip(e.name());
+                        }
+                    },
+                    None => self.record = Some((e.name().as_ref().to_vec(), status)),
+                }
+                self.element.clear();
+                self.element.extend_from_slice(e.local_name().as_ref());
+            }
+            Event::Text(e) if self.depth == 2 && self.element == b"id" => {
+                let id = std::str::from_utf8(e)
+                    .ok()
+                    .and_then(|id| id.trim().parse().ok());
+                if let (Some(id), Some((name, status))) = (id, self.record.take()) {
+                    match self.early.check(id, status.as_deref()) {
+                        EarlyDecision::Parse => {}
+                        EarlyDecision::Stop => return Step::Stop,
+                        EarlyDecision::Skip => {
+                            self.depth = 0;
+                            self.parser = P::with_options(&self.options);
+                            self.skip_name = name;
+                            return Step::Skip;
+                        }
+                    }
+                }
+            }
+            Event::Start(e) => {
+                self.depth += 1;
+                if self.depth == 2 && self.options.skips(e.local_name().as_ref()) {
+                    self.depth -= 1;
+                    return self.skip(e.name());
+                }
+                self.element.clear();
+                self.element.extend_from_slice(e.local_name().as_ref());
+            }
+            Event::Empty(e) => {
+                self.element.clear();
+                self.element.extend_from_slice(e.local_name().as_ref());
+            }
+            Event::End(_) => self.depth = self.depth.saturating_sub(1),
+            Event::Eof => return self.end(),
+            _ => {}
+        }
+        if let Some(inspector) = &mut self.inspector {
+            inspector.inspect(&ev, &mut self.pending);
+        }
+        let result = self.parser.process(ev);
+        if !self.pending.is_empty() {
+            self.report_warnings(offset);
+        }
+        if let Err(e) = result {
+            let e = e.with_context(self.parser.current_id(), &self.element, offset);
+            if let Err(e) = self.handle_error(e.into(), offset) {
+                return Step::Error(e);
+            }
+        }
+        if self.parser.item_ready() {
+            self.parsed += 1;
+            return Step::Item(self.parser.take());
+        }
+        Step::Next
+    }
+
+    /// Stop at the end of the dump, which is an error if it ends inside a record,
+    /// as when a download was cut short.
+    fn end(&mut self) -> Step<P::Item> {
+        if self.depth == 0 {
+            return Step::Stop;
+        }
+        self.depth = 0;
+        let tag = String::from_utf8_lossy(&self.record_tag).into_owned();
+        Step::Error(quick_xml::Error::UnexpectedEof(tag).into())
+    }
+
+    fn skip(&mut self, name: QName) -> Step<P::Item> {
+        self.skip_name.clear();
+        self.skip_name.extend_from_slice(name.as_ref());
+        Step::Skip
+    }
+
+    fn report_warnings(&mut self, offset: u64) {
+        for kind in self.pending.drain(..) {
+            let warning = ParseWarning {
+                id: self.parser.current_id(),
+                offset,
+                kind,
+            };
+            if self.options.log_enabled(Level::Debug) {
+                debug!("{warning}");
+            }
+            if let Some(on_warning) = &self.options.on_warning {
+                on_warning(&warning);
+            }
+            if self.options.collect_warnings {
+                self.warnings.push(warning);
+            }
+        }
+    }
+
+    /// Skip the record an error occurred in if the error policy allows it, or return
+    /// the error to yield.
+    fn handle_error(&mut self, error: ReaderError, offset: u64) -> Result<(), ReaderError> {
+        if self.options.on_error == ErrorPolicy::Abort {
+            return Err(error);
+        }
+        self.errors += 1;
+        self.parsed += 1;
+        if self.options.too_many_errors(self.errors, self.parsed) {
+            return Err(ReaderError::TooManyErrors {
+                skipped: self.errors,
+                records: self.parsed,
+                last: Box::new(error),
+            });
+        }
+        let record = SkippedRecord {
+            id: self.parser.current_id(),
+            offset,
+            error: &error,
+        };
+        if self.options.log_enabled(Level::Warn) {
+            match record.id {
+                Some(id) => warn!("Skipping record {id} at byte {}: {error}", record.offset),
+                None => warn!("Skipping record at byte {}: {error}", record.offset),
+            }
+        }
+        if let Some(on_skip) = &self.options.on_skip {
+            on_skip(&record);
+        }
+        self.parser = P::with_options(&self.options);
+        self.recovering = self.depth > 0;
+        Ok(())
+    }
+}
+
+impl<P: Parser> Iterator for EntityReader<P> {
+    type Item = Result<P::Item, ReaderError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if let Some(in_memory) = &mut self.in_memory {
+            let state = &mut self.state;
+            while let Some(result) = in_memory.pop_front() {
+                if let Some(sample) = &state.options.sample {
+                    let decision = sample.decide(state.started, state.options.sample_seed);
+                    state.started += 1;
+                    match decision {
+                        EarlyDecision::Parse => {}
+                        EarlyDecision::Skip => continue,
+                        EarlyDecision::Stop => break,
+                    }
+                }
+                if let (Ok(item), Some(filter)) = (&result, &self.filter) {
+                    if !filter.matches(item) {
+                        continue;
+                    }
+                }
+                self.items += 1;
+                return Some(result);
+            }
+            self.finished = true;
+            return None;
+        }
+        loop {
+            self.buf.clear();
+            let ev = match self.reader.read_event_into(&mut self.buf) {
+                Ok(ev) => ev,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e.into()));
+                }
+            };
+            let offset = self.reader.buffer_position() as u64 + self.skipped;
+            match self.state.event(ev, offset) {
+                Step::Next => {}
+                Step::Skip => {
+                    let name = QName(&self.state.skip_name);
+                    let result = self.reader.read_to_end_into(name, &mut self.skip_buf);
+                    self.skip_buf.clear();
+                    if let Err(e) = result {
+                        self.finished = true;
+                        return Some(Err(e.into()));
+                    }
+                }
+                Step::Item(item) => {
+                    if let Some(filter) = &self.filter {
+                        if !filter.matches(&item) {
+                            continue;
+                        }
+                    }
+                    match self.state.options.record_delay {
+                        Some(delay) if delay.is_zero() => thread::yield_now(),
+                        Some(delay) => thread::sleep(delay),
+                        None => {}
+                    }
+                    self.items += 1;
+                    self.report_progress(false);
+                    return Some(Ok(item));
+                }
+                Step::Error(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                Step::Stop => {
+                    self.finished = true;
+                    self.report_progress(true);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Whether `reader` starts with the gzip magic, and a reader of all its bytes. A
+/// source like a pipe can return less than the magic from one `fill_buf`, in which
+/// case the bytes are read one buffer at a time and put back in front.
+fn peek_gzip(mut reader: impl BufRead + 'static) -> io::Result<(bool, Box<dyn BufRead>)> {
+    let buf = reader.fill_buf()?;
+    if buf.len() >= GZIP_MAGIC.len() {
+        let gzip = buf.starts_with(&GZIP_MAGIC);
+        return Ok((gzip, Box::new(reader)));
+    }
+    let mut start = Vec::with_capacity(GZIP_MAGIC.len());
+    while start.len() < GZIP_MAGIC.len() {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let n = buf.len().min(GZIP_MAGIC.len() - start.len());
+        start.extend_from_slice(&buf[..n]);
+        reader.consume(n);
+    }
+    let gzip = start == GZIP_MAGIC;
+    Ok((gzip, Box::new(io::Cursor::new(start).chain(reader))))
+}
+
+/// The value of an attribute, if it's present and valid.
+fn attr_value(e: &BytesStart, name: &str) -> Option<String> {
+    let attr = e.try_get_attribute(name).ok()??;
+    Some(attr.unescape_value().ok()?.into_owned())
+}
+
+impl<P: Parser> FromIterator<P::Item> for EntityReader<P> {
+    fn from_iter<I: IntoIterator<Item = P::Item>>(items: I) -> Self {
+        Self::from_items(items)
+    }
 }
 
 pub enum DiscogsReader {
@@ -30,30 +649,180 @@ pub enum DiscogsReader {
     Labels(Box<LabelsReader>),
     Masters(Box<MastersReader>),
     Releases(Box<ReleasesReader>),
+    Custom(Box<CustomReader>),
 }
 
 impl DiscogsReader {
     pub fn from_path(path: &Path) -> Result<DiscogsReader, ReaderError> {
-        let mut xml_reader = get_xml_reader(path)?;
-        let mut buf = Vec::with_capacity(4096);
-        let start_event = loop {
-            match xml_reader.read_event_into(&mut buf)? {
-                Event::Start(ev) => break ev,
-                Event::Eof => return Err(ReaderError::NoStartTag),
-                _ => continue,
+        Self::from_path_with_options(path, ReaderOptions::default())
+    }
+
+    pub fn from_path_with_options(
+        path: &Path,
+        options: ReaderOptions,
+    ) -> Result<DiscogsReader, ReaderError> {
+        let xml_reader = get_xml_reader_with_options(path, &options)?;
+        Self::from_xml_reader(xml_reader, None, options)
+    }
+
+    /// Open a dump and skip to a checkpoint taken while reading it before, so a run
+    /// that was stopped can continue where it left off. Custom readers can't resume.
+    pub fn resume(
+        path: &Path,
+        checkpoint: &Checkpoint,
+        options: ReaderOptions,
+    ) -> Result<DiscogsReader, ReaderError> {
+        let mut reader = Self::from_path_with_options(path, options)?;
+        match &mut reader {
+            DiscogsReader::Artists(r) => r.resume(checkpoint)?,
+            DiscogsReader::Labels(r) => r.resume(checkpoint)?,
+            DiscogsReader::Masters(r) => r.resume(checkpoint)?,
+            DiscogsReader::Releases(r) => r.resume(checkpoint)?,
+            DiscogsReader::Custom(_) => {
+                return Err(ReaderError::InvalidCheckpoint(checkpoint.offset))
             }
+        }
+        Ok(reader)
+    }
+
+    /// The checkpoint of the reader, or None for custom readers.
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        match self {
+            DiscogsReader::Artists(r) => Some(r.checkpoint()),
+            DiscogsReader::Labels(r) => Some(r.checkpoint()),
+            DiscogsReader::Masters(r) => Some(r.checkpoint()),
+            DiscogsReader::Releases(r) => Some(r.checkpoint()),
+            DiscogsReader::Custom(_) => None,
+        }
+    }
+
+    /// Read a dump from any source, e.g. stdin or a network stream. Gzipped input is
+    /// detected and decompressed. If `entity_type` is None it's detected from the root
+    /// tag, otherwise the root tag isn't checked.
+    ///
+    /// A dump that ends inside a record, e.g. because its download was cut short,
+    /// ends with an error:
+    ///
+    /// ```
+    /// use disco_quick::{DiscogsReader, ReaderError};
+    /// use std::io::Cursor;
+    ///
+    /// let xml = r#"<releases><release id="1" status="Accepted"><title>A</title></release>
+    /// <release id="2" status="Accepted"><title>B</title>"#;
+    /// let reader = DiscogsReader::from_reader(Cursor::new(xml), None).unwrap();
+    /// let DiscogsReader::Releases(releases) = reader else { unreachable!() };
+    /// let results: Vec<_> = releases.collect();
+    /// assert_eq!(results.len(), 2);
+    /// assert_eq!(results[0].as_ref().unwrap().id, 1);
+    /// assert!(matches!(results[1], Err(ReaderError::XmlError(_))));
+    /// ```
+    pub fn from_reader(
+        reader: impl BufRead + 'static,
+        entity_type: Option<EntityType>,
+    ) -> Result<DiscogsReader, ReaderError> {
+        Self::from_reader_with_options(reader, entity_type, ReaderOptions::default())
+    }
+
+    /// Like [`from_reader`](Self::from_reader). The throughput limit and prefetch
+    /// options only apply to [`from_path`](Self::from_path) and are ignored.
+    pub fn from_reader_with_options(
+        reader: impl BufRead + 'static,
+        entity_type: Option<EntityType>,
+        options: ReaderOptions,
+    ) -> Result<DiscogsReader, ReaderError> {
+        let (gzip, reader) = peek_gzip(reader)?;
+        let reader: Box<dyn BufRead> = if gzip {
+            Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))
+        } else {
+            reader
         };
-        let reader = match start_event.name().as_ref() {
-            b"artists" => DiscogsReader::Artists(Box::new(ArtistsReader::new(xml_reader, buf))),
-            b"labels" => DiscogsReader::Labels(Box::new(LabelsReader::new(xml_reader, buf))),
-            b"masters" => DiscogsReader::Masters(Box::new(MastersReader::new(xml_reader, buf))),
-            b"releases" => DiscogsReader::Releases(Box::new(ReleasesReader::new(xml_reader, buf))),
-            _ => {
-                return Err(ReaderError::InvalidStartTag);
+        let xml_reader = quick_xml::Reader::from_reader(reader);
+        Self::from_xml_reader(xml_reader, entity_type, options)
+    }
+
+    /// Stream a dump from a URL, decompressing it as it's read. Dropped connections
+    /// are resumed with range requests. See [`crate::http`].
+    #[cfg(feature = "http")]
+    pub fn from_url(url: &str) -> Result<DiscogsReader, ReaderError> {
+        Self::from_url_with_options(url, ReaderOptions::default())
+    }
+
+    /// Like [`from_url`](Self::from_url). The options that only apply to
+    /// [`from_path`](Self::from_path) are ignored.
+    #[cfg(feature = "http")]
+    pub fn from_url_with_options(
+        url: &str,
+        options: ReaderOptions,
+    ) -> Result<DiscogsReader, ReaderError> {
+        let reader = BufReader::new(crate::http::HttpReader::open(url)?);
+        Self::from_reader_with_options(reader, None, options)
+    }
+
+    fn from_xml_reader(
+        mut xml_reader: XmlReader,
+        entity_type: Option<EntityType>,
+        options: ReaderOptions,
+    ) -> Result<DiscogsReader, ReaderError> {
+        let mut buf = options.buffer();
+        let (start_event, prolog) = read_start_tag(&mut xml_reader, &mut buf)?;
+        if options.log_enabled(Level::Warn) {
+            for skipped in prolog.iter().filter(|s| s.is_junk()) {
+                warn!("Skipped {skipped} before the root element");
             }
+        }
+        let tag = match entity_type {
+            Some(entity_type) => entity_type.root_tag().as_bytes().to_vec(),
+            None => start_event.name().as_ref().to_vec(),
+        };
+        let mut reader = match tag.as_slice() {
+            b"artists" => DiscogsReader::Artists(Box::new(ArtistsReader::with_options(
+                xml_reader, buf, options,
+            ))),
+            b"labels" => DiscogsReader::Labels(Box::new(LabelsReader::with_options(
+                xml_reader, buf, options,
+            ))),
+            b"masters" => DiscogsReader::Masters(Box::new(MastersReader::with_options(
+                xml_reader, buf, options,
+            ))),
+            b"releases" => DiscogsReader::Releases(Box::new(ReleasesReader::with_options(
+                xml_reader, buf, options,
+            ))),
+            tag => match custom::open(tag, xml_reader, buf) {
+                Some(custom) => DiscogsReader::Custom(Box::new(custom)),
+                None => return Err(ReaderError::InvalidStartTag),
+            },
         };
+        match &mut reader {
+            DiscogsReader::Artists(r) => r.prolog = prolog,
+            DiscogsReader::Labels(r) => r.prolog = prolog,
+            DiscogsReader::Masters(r) => r.prolog = prolog,
+            DiscogsReader::Releases(r) => r.prolog = prolog,
+            DiscogsReader::Custom(_) => {}
+        }
         Ok(reader)
     }
+
+    /// What was skipped before the root element of the dump. Empty for custom readers.
+    pub fn prolog(&self) -> &[Skipped] {
+        match self {
+            DiscogsReader::Artists(r) => r.prolog(),
+            DiscogsReader::Labels(r) => r.prolog(),
+            DiscogsReader::Masters(r) => r.prolog(),
+            DiscogsReader::Releases(r) => r.prolog(),
+            DiscogsReader::Custom(_) => &[],
+        }
+    }
+
+    /// The type of entity the reader yields, or None for custom readers.
+    pub fn entity_type(&self) -> Option<EntityType> {
+        match self {
+            DiscogsReader::Artists(_) => Some(EntityType::Artist),
+            DiscogsReader::Labels(_) => Some(EntityType::Label),
+            DiscogsReader::Masters(_) => Some(EntityType::Master),
+            DiscogsReader::Releases(_) => Some(EntityType::Release),
+            DiscogsReader::Custom(_) => None,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -62,10 +831,38 @@ pub enum ReaderError {
     IoError(#[from] IoError),
     #[error(transparent)]
     XmlError(#[from] XmlError),
+    #[error(transparent)]
+    ParserError(#[from] ParserError),
     #[error("No start tag present in file")]
     NoStartTag,
     #[error("Invalid start tag present in file")]
     InvalidStartTag,
+    #[error("Can't resume at byte {0} of the dump")]
+    InvalidCheckpoint(u64),
+    /// The limit set with [`ReaderOptions::max_errors`] or
+    /// [`ReaderOptions::max_error_rate`] was reached.
+    #[error(
+        "Too many errors: skipped {skipped} of {records} records, the last because of: {last}"
+    )]
+    TooManyErrors {
+        skipped: u64,
+        records: u64,
+        last: Box<ReaderError>,
+    },
+}
+
+impl Iterator for DiscogsReader {
+    type Item = Result<Entity, ReaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(match self {
+            DiscogsReader::Artists(r) => r.next()?.map(Entity::Artist),
+            DiscogsReader::Labels(r) => r.next()?.map(Entity::Label),
+            DiscogsReader::Masters(r) => r.next()?.map(Entity::Master),
+            DiscogsReader::Releases(r) => r.next()?.map(Entity::Release),
+            DiscogsReader::Custom(r) => Ok(Entity::Custom(r.next()?)),
+        })
+    }
 }
 
 impl fmt::Display for DiscogsReader {
@@ -75,6 +872,7 @@ impl fmt::Display for DiscogsReader {
             DiscogsReader::Labels(_) => "labels",
             DiscogsReader::Masters(_) => "masters",
             DiscogsReader::Releases(_) => "releases",
+            DiscogsReader::Custom(custom) => custom.tag(),
         };
         write!(f, "{name}")
     }
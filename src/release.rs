@@ -1,32 +1,53 @@
 use crate::artist_credit::{get_credit_string, ArtistCredit, ArtistCreditParser};
 use crate::company::CompanyParser;
+use crate::country::Country;
+use crate::date::ReleaseDate;
+use crate::id::{MasterId, ReleaseId};
+use crate::identifiers::{barcode_digits, gtin14, validate_ean_upc, IdentifierKind};
+use crate::intern::{intern, Symbol};
+use crate::options::ReaderOptions;
 use crate::parser::{Parser, ParserError};
-use crate::reader::XmlReader;
-use crate::shared::{Image, ReleaseLabel};
+use crate::reader::EntityReader;
+#[cfg(feature = "extra-fields")]
+use crate::shared::UnknownElement;
+use crate::shared::{normalize_in_place, string_enum, DataQuality, Image, ReleaseLabel};
+use crate::tags::Tag;
+use crate::title::CanonicalTitle;
 use crate::track::{Track, TrackParser};
+use crate::translations::Translations;
 use crate::util::get_attr;
 use crate::video::{Video, VideoParser};
 use log::debug;
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
+#[cfg(feature = "extra-fields")]
+use std::collections::BTreeMap;
 use std::fmt;
 use std::mem::take;
+use std::sync::Arc;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Release {
-    pub id: i32,
-    pub status: String,
+    pub id: ReleaseId,
+    pub status: ReleaseStatus,
     pub title: String,
     pub artists: Vec<ArtistCredit>,
-    pub country: String,
+    pub country: Country,
     pub labels: Vec<ReleaseLabel>,
     pub released: String,
     pub notes: Option<String>,
-    pub genres: Vec<String>,
-    pub styles: Vec<String>,
-    pub master_id: Option<i32>,
+    pub genres: Vec<Symbol>,
+    pub styles: Vec<Symbol>,
+    /// Whether the record has a `<genres>` element, even an empty one. Use
+    /// [`tagged_genres`](Self::tagged_genres) to tell untagged records from unknown ones.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub genres_present: bool,
+    /// Whether the record has a `<styles>` element, even an empty one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub styles_present: bool,
+    pub master_id: Option<MasterId>,
     pub is_main_release: bool,
-    pub data_quality: String,
+    pub data_quality: DataQuality,
     pub images: Vec<Image>,
     pub videos: Vec<Video>,
     pub extraartists: Vec<ArtistCredit>,
@@ -34,67 +55,332 @@ pub struct Release {
     pub formats: Vec<ReleaseFormat>,
     pub companies: Vec<ReleaseLabel>,
     pub identifiers: Vec<ReleaseIdentifier>,
+    /// The text of elements the parser doesn't know, by name, so data isn't lost when
+    /// Discogs adds elements before the crate supports them. Elements with children
+    /// aren't kept.
+    #[cfg(feature = "extra-fields")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_fields: BTreeMap<String, String>,
 }
 
-#[derive(Clone, Debug, Default)]
+/// What to do with the `tracks` of the release artists when working out the
+/// artists of a track.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TracksField {
+    /// Only apply artists limited to some tracks to those tracks.
+    #[default]
+    Apply,
+    /// Apply all release artists to every track.
+    Ignore,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReleaseFormat {
     pub qty: String, // https://www.discogs.com/release/8262262
-    pub name: String,
+    pub name: Symbol,
     pub text: Option<String>,
     pub descriptions: Vec<String>,
 }
 
-#[derive(Clone, Debug, Default)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "IdentifierFields")
+)]
 pub struct ReleaseIdentifier {
     pub r#type: String,
+    /// The kind of identifier, parsed from the `type`.
+    pub kind: IdentifierKind,
     pub description: String,
     pub value: Option<String>,
 }
 
-impl fmt::Display for Release {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let artist_credit = get_credit_string(&self.artists);
-        write!(f, "{} - {}", artist_credit, self.title)
+/// The fields of a serialized [`ReleaseIdentifier`]. The kind is parsed from the
+/// type again, so it matches even if the type was edited or it wasn't serialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct IdentifierFields {
+    r#type: String,
+    #[serde(default)]
+    #[allow(dead_code)]
+    kind: String,
+    description: String,
+    value: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl From<IdentifierFields> for ReleaseIdentifier {
+    fn from(fields: IdentifierFields) -> Self {
+        Self {
+            kind: IdentifierKind::from(fields.r#type.as_str()),
+            r#type: fields.r#type,
+            description: fields.description,
+            value: fields.value,
+        }
     }
 }
 
-pub struct ReleasesReader {
-    buf: Vec<u8>,
-    reader: XmlReader,
-    parser: ReleaseParser,
+string_enum! {
+    /// Whether a release is in the database or is a draft, deleted or rejected
+    /// submission. Only accepted releases are shown on the site.
+    pub enum ReleaseStatus {
+        Accepted => "Accepted",
+        Draft => "Draft",
+        Deleted => "Deleted",
+        Rejected => "Rejected",
+    }
+    /// A value the crate doesn't know, or an empty string if the release had none.
+    else Other(String);
 }
 
-impl ReleasesReader {
-    pub fn new(reader: XmlReader, buf: Vec<u8>) -> Self {
-        Self {
-            buf,
-            reader,
-            parser: ReleaseParser::new(),
+impl Release {
+    /// The identifiers of `kind`.
+    pub fn identifiers_of<'a>(
+        &'a self,
+        kind: &'a IdentifierKind,
+    ) -> impl Iterator<Item = &'a ReleaseIdentifier> {
+        self.identifiers.iter().filter(move |i| i.kind == *kind)
+    }
+
+    /// The barcode identifiers, valid or not.
+    pub fn barcodes(&self) -> impl Iterator<Item = &ReleaseIdentifier> {
+        self.identifiers.iter().filter(|i| i.is_barcode())
+    }
+
+    /// The digits of the valid barcodes, without duplicates, e.g. to match a scanned
+    /// barcode to releases.
+    pub fn normalized_barcodes(&self) -> Vec<String> {
+        let mut barcodes = Vec::new();
+        for barcode in self
+            .barcodes()
+            .filter_map(ReleaseIdentifier::normalized_barcode)
+        {
+            if !barcodes.contains(&barcode) {
+                barcodes.push(barcode);
+            }
         }
+        barcodes
     }
-}
 
-impl Iterator for ReleasesReader {
-    type Item = Release;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.reader.read_event_into(&mut self.buf).unwrap() {
-                Event::Eof => {
-                    return None;
+    /// Barcode identifiers whose value isn't a valid EAN or UPC.
+    pub fn invalid_barcodes(&self) -> impl Iterator<Item = &ReleaseIdentifier> {
+        self.identifiers
+            .iter()
+            .filter(|i| i.is_barcode() && !i.is_valid_barcode())
+    }
+
+    /// All tracks of the tracklist, with sub-tracks following their index track at
+    /// any depth.
+    ///
+    /// ```
+    /// use disco_quick::release::Release;
+    /// use disco_quick::track::Track;
+    ///
+    /// let track = |position: &str, sub_tracks| Track {
+    ///     position: position.to_string(),
+    ///     sub_tracks,
+    ///     ..Default::default()
+    /// };
+    /// let release = Release {
+    ///     tracklist: vec![
+    ///         track("", vec![track("1a", vec![track("1a.i", vec![])]), track("1b", vec![])]),
+    ///         track("2", vec![]),
+    ///     ],
+    ///     ..Default::default()
+    /// };
+    /// let positions: Vec<_> = release.all_tracks().map(|t| t.position.as_str()).collect();
+    /// assert_eq!(positions, ["", "1a", "1a.i", "1b", "2"]);
+    /// ```
+    pub fn all_tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracklist.iter().flat_map(Track::with_sub_tracks)
+    }
+
+    /// The artists of `track`: its own, or else those of the release. Release
+    /// artists limited to some tracks, as on split releases, only apply to those.
+    pub fn effective_track_artists<'a>(&'a self, track: &'a Track) -> Vec<&'a ArtistCredit> {
+        self.effective_track_artists_with(track, TracksField::Apply)
+    }
+
+    /// Like [`effective_track_artists`](Self::effective_track_artists), with the
+    /// `tracks` of the release artists applied or ignored.
+    pub fn effective_track_artists_with<'a>(
+        &'a self,
+        track: &'a Track,
+        tracks_field: TracksField,
+    ) -> Vec<&'a ArtistCredit> {
+        if !track.artists.is_empty() {
+            return track.artists.iter().collect();
+        }
+        if tracks_field == TracksField::Ignore {
+            return self.artists.iter().collect();
+        }
+        let positions = self.track_positions();
+        let index = self.track_index(track);
+        let mut unlimited = Vec::new();
+        let mut covering = Vec::new();
+        for credit in &self.artists {
+            match credit.track_selection() {
+                None => unlimited.push(credit),
+                Some(selection) => {
+                    if index.is_some_and(|i| selection.covers(&positions)[i]) {
+                        covering.push(credit);
+                    }
                 }
-                ev => self.parser.process(ev).unwrap(),
-            };
-            if self.parser.item_ready {
-                return Some(self.parser.take());
             }
-            self.buf.clear();
         }
+        // The artists of the tracks no selection covers are unknown, so fall back
+        // to all of them
+        match (covering.is_empty(), unlimited.is_empty()) {
+            (false, _) => covering.into_iter().chain(unlimited).collect(),
+            (true, false) => unlimited,
+            (true, true) => self.artists.iter().collect(),
+        }
+    }
+
+    /// The extra artists of `track` followed by those of the release limited to
+    /// tracks including it. Release extra artists without `tracks` are credits for
+    /// the whole release and aren't included.
+    pub fn effective_track_extraartists<'a>(&'a self, track: &'a Track) -> Vec<&'a ArtistCredit> {
+        let positions = self.track_positions();
+        let index = self.track_index(track);
+        let release_credits = self.extraartists.iter().filter(|credit| {
+            credit
+                .track_selection()
+                .is_some_and(|s| index.is_some_and(|i| s.covers(&positions)[i]))
+        });
+        track.extraartists.iter().chain(release_credits).collect()
+    }
+
+    fn track_positions(&self) -> Vec<&str> {
+        self.all_tracks().map(|t| t.position.as_str()).collect()
+    }
+
+    /// The index of `track` in [`all_tracks`](Self::all_tracks), or of the first
+    /// track with its position if it's not from this release.
+    fn track_index(&self, track: &Track) -> Option<usize> {
+        self.all_tracks()
+            .position(|t| std::ptr::eq(t, track))
+            .or_else(|| self.all_tracks().position(|t| t.position == track.position))
+    }
+
+    /// The title with the formats appended, so versions with the same title can be
+    /// told apart. See [`CanonicalTitle`] for other options.
+    pub fn canonical_title(&self) -> String {
+        CanonicalTitle::new().build(self)
+    }
+
+    /// The release date parsed from [`released`](Self::released).
+    pub fn released_parsed(&self) -> ReleaseDate {
+        ReleaseDate::parse(&self.released)
+    }
+
+    /// The genres, or None if the record has no `<genres>` element at all.
+    pub fn tagged_genres(&self) -> Option<&[Symbol]> {
+        self.genres_present.then_some(self.genres.as_slice())
+    }
+
+    /// The styles, or None if the record has no `<styles>` element at all.
+    pub fn tagged_styles(&self) -> Option<&[Symbol]> {
+        self.styles_present.then_some(self.styles.as_slice())
+    }
+}
+
+impl ReleaseFormat {
+    pub fn from_event(ev: BytesStart) -> Result<Self, ParserError> {
+        let mut attrs = ev.attributes();
+        let mut format = ReleaseFormat {
+            name: intern(&get_attr(attrs.next())?),
+            qty: get_attr(attrs.next())?.to_string(),
+            ..Default::default()
+        };
+        let text = get_attr(attrs.next())?.to_string();
+        if !text.is_empty() {
+            format.text = Some(text)
+        }
+        Ok(format)
     }
 }
 
-#[derive(Debug, Default)]
+impl ReleaseIdentifier {
+    pub fn new(r#type: &str, description: &str, value: Option<String>) -> Self {
+        Self {
+            r#type: r#type.to_string(),
+            kind: IdentifierKind::from(r#type),
+            description: description.to_string(),
+            value,
+        }
+    }
+
+    pub fn is_barcode(&self) -> bool {
+        self.kind == IdentifierKind::Barcode
+    }
+
+    /// The digits of a barcode with the spaces and hyphens removed, if this is a
+    /// barcode with a valid EAN/UPC check digit.
+    pub fn normalized_barcode(&self) -> Option<String> {
+        self.value
+            .as_deref()
+            .filter(|v| self.is_barcode() && validate_ean_upc(v))
+            .and_then(barcode_digits)
+    }
+
+    /// Whether this is a barcode with a valid EAN/UPC check digit.
+    pub fn is_valid_barcode(&self) -> bool {
+        self.is_barcode() && self.value.as_deref().is_some_and(validate_ean_upc)
+    }
+
+    /// The barcode as a 14 digit GTIN, if this is a valid barcode.
+    pub fn gtin14(&self) -> Option<String> {
+        self.value
+            .as_deref()
+            .filter(|_| self.is_barcode())
+            .and_then(gtin14)
+    }
+
+    pub fn from_event(ev: BytesStart) -> Result<Self, ParserError> {
+        // Identifiers without a description leave the attribute out, so they're
+        // found by name rather than position
+        let attr = |name: &str| -> Result<Option<String>, ParserError> {
+            match ev.try_get_attribute(name)? {
+                Some(a) => Ok(Some(a.unescape_value()?.into_owned())),
+                None => Ok(None),
+            }
+        };
+        let r#type = attr("type")?.ok_or(ParserError::MissingAttr)?;
+        let description = attr("description")?.unwrap_or_default();
+        Ok(ReleaseIdentifier::new(
+            &r#type,
+            &description,
+            attr("value")?,
+        ))
+    }
+}
+
+impl ReleaseStatus {
+    /// ```
+    /// use disco_quick::release::ReleaseStatus;
+    ///
+    /// assert!(ReleaseStatus::from(" Accepted\n").is_accepted());
+    /// assert_eq!(ReleaseStatus::from("Pending "), ReleaseStatus::Other("Pending".into()));
+    /// ```
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted)
+    }
+}
+
+impl fmt::Display for Release {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let artist_credit = get_credit_string(&self.artists);
+        write!(f, "{} - {}", artist_credit, self.title)
+    }
+}
+
+/// Iterator over the releases in a releases dump.
+pub type ReleasesReader = EntityReader<ReleaseParser>;
+
+#[derive(Clone, Copy, Debug, Default)]
 enum ParserState {
     #[default]
     Release,
@@ -114,8 +400,33 @@ enum ParserState {
     Format,
     Companies,
     Identifiers,
+    #[cfg(feature = "extra-fields")]
+    Unknown,
 }
 
+/// The state for each child element of a release, indexed by [`Tag`].
+const FIELD_STATES: [ParserState; Tag::ALL.len()] = {
+    let mut states = [ParserState::Release; Tag::ALL.len()];
+    states[Tag::Title as usize] = ParserState::Title;
+    states[Tag::Country as usize] = ParserState::Country;
+    states[Tag::Released as usize] = ParserState::Released;
+    states[Tag::Notes as usize] = ParserState::Notes;
+    states[Tag::Genres as usize] = ParserState::Genres;
+    states[Tag::Styles as usize] = ParserState::Styles;
+    states[Tag::MasterId as usize] = ParserState::MasterId;
+    states[Tag::DataQuality as usize] = ParserState::DataQuality;
+    states[Tag::Labels as usize] = ParserState::Labels;
+    states[Tag::Videos as usize] = ParserState::Videos;
+    states[Tag::Artists as usize] = ParserState::Artists;
+    states[Tag::ExtraArtists as usize] = ParserState::ExtraArtists;
+    states[Tag::TrackList as usize] = ParserState::TrackList;
+    states[Tag::Formats as usize] = ParserState::Format;
+    states[Tag::Identifiers as usize] = ParserState::Identifiers;
+    states[Tag::Companies as usize] = ParserState::Companies;
+    states
+};
+
+/// Parses a `<release>` element of the releases dump into a [`Release`].
 #[derive(Debug, Default)]
 pub struct ReleaseParser {
     state: ParserState,
@@ -125,6 +436,10 @@ pub struct ReleaseParser {
     track_parser: TrackParser,
     company_parser: CompanyParser,
     item_ready: bool,
+    translations: Option<Arc<Translations>>,
+    normalize_text: bool,
+    #[cfg(feature = "extra-fields")]
+    unknown: UnknownElement,
 }
 
 impl Parser for ReleaseParser {
@@ -134,48 +449,94 @@ impl Parser for ReleaseParser {
         Self::default()
     }
 
+    fn with_options(options: &ReaderOptions) -> Self {
+        Self {
+            translations: options.translations.clone(),
+            normalize_text: options.normalize_text,
+            ..Self::default()
+        }
+    }
+
     fn take(&mut self) -> Release {
         self.item_ready = false;
         take(&mut self.current_item)
     }
 
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
+
+    fn current_id(&self) -> Option<u32> {
+        Some(self.current_item.id.get()).filter(|id| *id != 0)
+    }
+
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
+            #[cfg(feature = "extra-fields")]
+            ParserState::Unknown => {
+                if self
+                    .unknown
+                    .process(ev, &mut self.current_item.extra_fields)?
+                {
+                    ParserState::Release
+                } else {
+                    ParserState::Unknown
+                }
+            }
+
             ParserState::Release => match ev {
                 Event::End(e) if e.local_name().as_ref() == b"release" => {
+                    if let Some(translations) = &self.translations {
+                        translations.apply_release(&mut self.current_item);
+                    }
+                    if self.normalize_text {
+                        let release = &mut self.current_item;
+                        release.notes.as_mut().map(normalize_in_place);
+                        for video in &mut release.videos {
+                            normalize_in_place(&mut video.description);
+                        }
+                    }
                     self.item_ready = true;
                     ParserState::Release
                 }
-                Event::Start(e) if e.local_name().as_ref() == b"release" => {
-                    let mut a = e.attributes();
-                    self.current_item.id = get_attr(a.next()).parse()?;
-                    debug!("Began parsing Release {}", self.current_item.id);
-                    self.current_item.status = get_attr(a.next()).to_string();
+                Event::Start(e) => match Tag::of(e.local_name().as_ref()) {
+                    Some(Tag::Release) => {
+                        let mut a = e.attributes();
+                        self.current_item.id = get_attr(a.next())?.parse()?;
+                        debug!("Began parsing Release {}", self.current_item.id);
+                        self.current_item.status = ReleaseStatus::from(&*get_attr(a.next())?);
+                        ParserState::Release
+                    }
+                    Some(Tag::MasterId) => {
+                        let mut a = e.attributes();
+                        self.current_item.is_main_release = get_attr(a.next())?.parse()?;
+                        ParserState::MasterId
+                    }
+                    Some(Tag::Genres) => {
+                        self.current_item.genres_present = true;
+                        ParserState::Genres
+                    }
+                    Some(Tag::Styles) => {
+                        self.current_item.styles_present = true;
+                        ParserState::Styles
+                    }
+                    Some(tag) => FIELD_STATES[tag as usize],
+                    #[cfg(feature = "extra-fields")]
+                    None => {
+                        self.unknown.start(&e);
+                        ParserState::Unknown
+                    }
+                    #[cfg(not(feature = "extra-fields"))]
+                    None => ParserState::Release,
+                },
+                Event::Empty(e) => {
+                    match e.local_name().as_ref() {
+                        b"genres" => self.current_item.genres_present = true,
+                        b"styles" => self.current_item.styles_present = true,
+                        _ => {}
+                    }
                     ParserState::Release
                 }
-                Event::Start(e) if e.local_name().as_ref() == b"master_id" => {
-                    let mut a = e.attributes();
-                    self.current_item.is_main_release = get_attr(a.next()).parse()?;
-                    ParserState::MasterId
-                }
-                Event::Start(e) => match e.local_name().as_ref() {
-                    b"title" => ParserState::Title,
-                    b"country" => ParserState::Country,
-                    b"released" => ParserState::Released,
-                    b"notes" => ParserState::Notes,
-                    b"genres" => ParserState::Genres,
-                    b"styles" => ParserState::Styles,
-                    b"data_quality" => ParserState::DataQuality,
-                    b"labels" => ParserState::Labels,
-                    b"videos" => ParserState::Videos,
-                    b"artists" => ParserState::Artists,
-                    b"extraartists" => ParserState::ExtraArtists,
-                    b"tracklist" => ParserState::TrackList,
-                    b"formats" => ParserState::Format,
-                    b"identifiers" => ParserState::Identifiers,
-                    b"companies" => ParserState::Companies,
-                    _ => ParserState::Release,
-                },
                 _ => ParserState::Release,
             },
 
@@ -189,7 +550,7 @@ impl Parser for ReleaseParser {
 
             ParserState::Country => match ev {
                 Event::Text(e) => {
-                    self.current_item.country = e.unescape()?.to_string();
+                    self.current_item.country = Country::new(&e.unescape()?);
                     ParserState::Country
                 }
                 _ => ParserState::Release,
@@ -216,7 +577,7 @@ impl Parser for ReleaseParser {
 
                 ev => {
                     self.artist_parser.process(ev)?;
-                    if self.artist_parser.item_ready {
+                    if self.artist_parser.item_ready() {
                         self.current_item.artists.push(self.artist_parser.take());
                     }
                     ParserState::Artists
@@ -228,7 +589,7 @@ impl Parser for ReleaseParser {
 
                 ev => {
                     self.artist_parser.process(ev)?;
-                    if self.artist_parser.item_ready {
+                    if self.artist_parser.item_ready() {
                         let ea = self.artist_parser.take();
                         self.current_item.extraartists.push(ea);
                     }
@@ -239,8 +600,9 @@ impl Parser for ReleaseParser {
             ParserState::Genres => match ev {
                 Event::End(e) if e.local_name().as_ref() == b"genres" => ParserState::Release,
 
+                Event::Text(e) if e.iter().all(|b| b.is_ascii_whitespace()) => ParserState::Genres,
                 Event::Text(e) => {
-                    self.current_item.genres.push(e.unescape()?.to_string());
+                    self.current_item.genres.push(intern(&e.unescape()?));
                     ParserState::Genres
                 }
                 _ => ParserState::Genres,
@@ -249,8 +611,9 @@ impl Parser for ReleaseParser {
             ParserState::Styles => match ev {
                 Event::End(e) if e.local_name().as_ref() == b"styles" => ParserState::Release,
 
+                Event::Text(e) if e.iter().all(|b| b.is_ascii_whitespace()) => ParserState::Styles,
                 Event::Text(e) => {
-                    self.current_item.styles.push(e.unescape()?.to_string());
+                    self.current_item.styles.push(intern(&e.unescape()?));
                     ParserState::Styles
                 }
                 _ => ParserState::Styles,
@@ -258,16 +621,7 @@ impl Parser for ReleaseParser {
 
             ParserState::Format => match ev {
                 Event::Start(e) if e.local_name().as_ref() == b"format" => {
-                    let mut attrs = e.attributes();
-                    let mut format = ReleaseFormat {
-                        name: get_attr(attrs.next()).to_string(),
-                        qty: get_attr(attrs.next()).to_string(),
-                        ..Default::default()
-                    };
-                    let text = get_attr(attrs.next()).to_string();
-                    if !text.is_empty() {
-                        format.text = Some(text)
-                    }
+                    let format = ReleaseFormat::from_event(e)?;
                     self.current_item.formats.push(format);
                     ParserState::Format
                 }
@@ -284,16 +638,7 @@ impl Parser for ReleaseParser {
 
             ParserState::Identifiers => match ev {
                 Event::Empty(e) => {
-                    let mut attrs = e.attributes();
-                    let identifier = ReleaseIdentifier {
-                        r#type: get_attr(attrs.next()).to_string(),
-                        description: get_attr(attrs.next()).to_string(),
-                        value: if let Some(v) = attrs.next() {
-                            Some(v.unwrap().unescape_value()?.to_string())
-                        } else {
-                            None
-                        },
-                    };
+                    let identifier = ReleaseIdentifier::from_event(e)?;
                     self.current_item.identifiers.push(identifier);
                     ParserState::Identifiers
                 }
@@ -312,7 +657,7 @@ impl Parser for ReleaseParser {
 
             ParserState::DataQuality => match ev {
                 Event::Text(e) => {
-                    self.current_item.data_quality = e.unescape()?.to_string();
+                    self.current_item.data_quality = DataQuality::from(&*e.unescape()?);
                     ParserState::DataQuality
                 }
                 _ => ParserState::Release,
@@ -320,14 +665,7 @@ impl Parser for ReleaseParser {
 
             ParserState::Labels => match ev {
                 Event::Empty(e) => {
-                    let mut attrs = e.attributes();
-                    let label = ReleaseLabel {
-                        name: get_attr(attrs.next()).to_string(),
-                        catno: Some(get_attr(attrs.next()).to_string()),
-                        id: get_attr(attrs.next()).parse()?,
-                        entity_type: 1,
-                        entity_type_name: "Label".to_string(),
-                    };
+                    let label = ReleaseLabel::from_event(e)?;
                     self.current_item.labels.push(label);
                     ParserState::Labels
                 }
@@ -339,7 +677,7 @@ impl Parser for ReleaseParser {
 
                 ev => {
                     self.video_parser.process(ev)?;
-                    if self.video_parser.item_ready {
+                    if self.video_parser.item_ready() {
                         self.current_item.videos.push(self.video_parser.take());
                     }
                     ParserState::Videos
@@ -351,7 +689,7 @@ impl Parser for ReleaseParser {
 
                 ev => {
                     self.track_parser.process(ev)?;
-                    if self.track_parser.item_ready {
+                    if self.track_parser.item_ready() {
                         self.current_item.tracklist.push(self.track_parser.take());
                     }
                     ParserState::TrackList
@@ -363,7 +701,7 @@ impl Parser for ReleaseParser {
 
                 ev => {
                     self.company_parser.process(ev)?;
-                    if self.company_parser.item_ready {
+                    if self.company_parser.item_ready() {
                         self.current_item.companies.push(self.company_parser.take());
                     }
                     ParserState::Companies
@@ -0,0 +1,318 @@
+//! One-page summaries of dumps, e.g. to attach to the entries of a data catalog.
+//!
+//! [`summary`] reads a dump once without parsing its records into structs, and
+//! gathers the number of records, the range of IDs, the month of the dump, how many
+//! records have each field and the number of anomalies. The [`DumpSummary`] is
+//! printed as text by [`Display`](fmt::Display), and can be serialized as JSON with
+//! the `serde` feature.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! let summary = disco_quick::report::summary("discogs_20240101_labels.xml.gz".as_ref())?;
+//! println!("{summary}");
+//! # Ok(())
+//! # }
+//! ```
+use crate::borrowed::RecordReader;
+use crate::dumps::dir::date_from_name;
+use crate::dumps::{estimate_from_paths, push_name, MonthEstimate};
+use crate::reader::ReaderError;
+use crate::schema::DumpDate;
+use crate::shared::EntityType;
+use crate::video::DurationBounds;
+use crate::warning::Inspector;
+use quick_xml::events::Event;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A summary of a dump.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DumpSummary {
+    /// The name of the file.
+    pub file: String,
+    /// The size of the file in bytes, compressed if it's gzipped.
+    pub bytes: u64,
+    pub entity: EntityType,
+    pub records: u64,
+    /// The lowest and highest IDs, or None if there are no records.
+    pub min_id: Option<u32>,
+    pub max_id: Option<u32>,
+    /// The month in the name of the file, if it's named like the published dumps.
+    pub named_month: Option<DumpDate>,
+    /// The month estimated from the contents of the dump.
+    pub estimated_month: MonthEstimate,
+    /// The top-level fields of the records, in the order they first appear.
+    pub coverage: Vec<FieldCoverage>,
+    pub anomalies: Anomalies,
+}
+
+/// The number of records with a top-level field that isn't empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldCoverage {
+    pub field: String,
+    pub records: u64,
+}
+
+/// Oddities found in a dump that don't stop it being read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Anomalies {
+    /// Records with an ID lower than that of an earlier record.
+    pub out_of_order: u64,
+    /// Records with the same ID as an earlier record.
+    pub duplicate_ids: u64,
+    /// Records without an ID.
+    pub missing_ids: u64,
+    /// The number of each kind of [`ParseWarning`](crate::warning::ParseWarning),
+    /// e.g. `empty <title> element`.
+    pub warnings: BTreeMap<String, u64>,
+}
+
+impl Anomalies {
+    pub fn total(&self) -> u64 {
+        self.out_of_order
+            + self.duplicate_ids
+            + self.missing_ids
+            + self.warnings.values().sum::<u64>()
+    }
+}
+
+impl FieldCoverage {
+    /// The percentage of `records` with the field.
+    pub fn percent(&self, records: u64) -> f64 {
+        match records {
+            0 => 0.0,
+            n => self.records as f64 * 100.0 / n as f64,
+        }
+    }
+}
+
+impl DumpSummary {
+    /// The month of the dump, from its file name or else the most likely estimate.
+    pub fn month(&self) -> Option<DumpDate> {
+        self.named_month.or(self.estimated_month.likely)
+    }
+
+    /// The summary as pretty-printed JSON.
+    #[cfg(feature = "jsonl")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a summary serializes to JSON")
+    }
+}
+
+impl fmt::Display for DumpSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} ({} bytes)", self.file, self.bytes)?;
+        writeln!(f, "Entity:    {}", self.entity)?;
+        writeln!(f, "Records:   {}", self.records)?;
+        match (self.min_id, self.max_id) {
+            (Some(min), Some(max)) => writeln!(f, "IDs:       {min} to {max}")?,
+            _ => writeln!(f, "IDs:       none")?,
+        }
+        write!(f, "Month:     ")?;
+        if let Some(named) = self.named_month {
+            write!(f, "{named} from the file name, ")?;
+        }
+        let estimate = &self.estimated_month;
+        let fmt_date = |d: Option<DumpDate>| d.map_or("?".to_string(), |d| d.to_string());
+        write!(
+            f,
+            "estimated from {} to {}",
+            fmt_date(estimate.earliest),
+            fmt_date(estimate.latest)
+        )?;
+        match estimate.likely {
+            Some(likely) => writeln!(f, ", likely {likely}")?,
+            None => writeln!(f)?,
+        }
+        let anomalies = &self.anomalies;
+        writeln!(
+            f,
+            "Anomalies: {} ({} out of order, {} duplicate IDs, {} missing IDs, {} warnings)",
+            anomalies.total(),
+            anomalies.out_of_order,
+            anomalies.duplicate_ids,
+            anomalies.missing_ids,
+            anomalies.warnings.values().sum::<u64>()
+        )?;
+        for (kind, count) in &anomalies.warnings {
+            writeln!(f, "  {kind}: {count}")?;
+        }
+        writeln!(f, "Field coverage:")?;
+        let width = self
+            .coverage
+            .iter()
+            .map(|c| c.field.len())
+            .max()
+            .unwrap_or(0);
+        for field in &self.coverage {
+            writeln!(
+                f,
+                "  {:width$}  {:5.1}%",
+                field.field,
+                field.percent(self.records)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Summarize the dump at `path`.
+pub fn summary(path: &Path) -> Result<DumpSummary, ReaderError> {
+    let file = path
+        .file_name()
+        .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+    let bytes = fs::metadata(path)?.len();
+    let mut reader = RecordReader::from_path(path)?;
+    let entity = reader.entity_type();
+
+    let mut inspector = Inspector::new(DurationBounds::DEFAULT);
+    let mut warnings = Vec::new();
+    let mut anomalies = Anomalies::default();
+    let mut coverage: Vec<FieldCoverage> = Vec::new();
+    let mut seen_ids = IdSet::default();
+    let mut seen_paths: HashSet<Vec<u8>> = HashSet::new();
+    let mut path = Vec::new();
+    let (mut records, mut min_id, mut max_id) = (0, None, None);
+    while let Some(xml) = reader.next_record()? {
+        records += 1;
+        let mut xml_reader = quick_xml::Reader::from_reader(xml);
+        // The length of the path before each open element
+        let mut lengths = Vec::new();
+        // The index in coverage of the open top-level field, until it has content
+        let mut field = None;
+        let mut fields = HashSet::new();
+        let mut id = None;
+        let mut id_element = false;
+        path.clear();
+        loop {
+            let ev = xml_reader.read_event()?;
+            inspector.inspect(&ev, &mut warnings);
+            match &ev {
+                Event::Start(e) if lengths.is_empty() => {
+                    lengths.push(0);
+                    if let Some(attr) = e.try_get_attribute("id")? {
+                        id = attr.unescape_value()?.trim().parse().ok();
+                    }
+                }
+                Event::Start(e) => {
+                    lengths.push(path.len());
+                    push_name(&mut path, e.local_name().as_ref());
+                    if !seen_paths.contains(&path) {
+                        seen_paths.insert(path.clone());
+                    }
+                    if lengths.len() == 2 {
+                        field = Some(field_index(&mut coverage, &path));
+                    } else if let Some(i) = field.take() {
+                        fields.insert(i);
+                    }
+                    id_element = path == b"id";
+                }
+                Event::Empty(e) if !lengths.is_empty() => {
+                    let len = path.len();
+                    push_name(&mut path, e.local_name().as_ref());
+                    if !seen_paths.contains(&path) {
+                        seen_paths.insert(path.clone());
+                    }
+                    if lengths.len() == 1 {
+                        field_index(&mut coverage, &path);
+                    } else if let Some(i) = field.take() {
+                        fields.insert(i);
+                    }
+                    path.truncate(len);
+                }
+                Event::Text(e) if !e.iter().all(|b| b.is_ascii_whitespace()) => {
+                    if id_element && id.is_none() {
+                        id = e.unescape()?.trim().parse().ok();
+                    }
+                    if let Some(i) = field.take() {
+                        fields.insert(i);
+                    }
+                }
+                Event::CData(_) => {
+                    if let Some(i) = field.take() {
+                        fields.insert(i);
+                    }
+                }
+                Event::End(_) => {
+                    id_element = false;
+                    field = None;
+                    match lengths.pop() {
+                        Some(len) if !lengths.is_empty() => path.truncate(len),
+                        _ => break,
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        for i in fields {
+            coverage[i].records += 1;
+        }
+        for kind in warnings.drain(..) {
+            *anomalies.warnings.entry(kind.to_string()).or_default() += 1;
+        }
+        let Some(id) = id else {
+            anomalies.missing_ids += 1;
+            continue;
+        };
+        if !seen_ids.insert(id) {
+            anomalies.duplicate_ids += 1;
+        } else if max_id.is_some_and(|max| id < max) {
+            anomalies.out_of_order += 1;
+        }
+        min_id = Some(min_id.map_or(id, |min: u32| min.min(id)));
+        max_id = Some(max_id.map_or(id, |max: u32| max.max(id)));
+    }
+    let estimated_month = estimate_from_paths(entity, records, max_id.unwrap_or(0), &seen_paths);
+    Ok(DumpSummary {
+        named_month: date_from_name(&file),
+        file,
+        bytes,
+        entity,
+        records,
+        min_id,
+        max_id,
+        estimated_month,
+        coverage,
+        anomalies,
+    })
+}
+
+/// The index in `coverage` of the field `name`, adding it if it's new.
+fn field_index(coverage: &mut Vec<FieldCoverage>, name: &[u8]) -> usize {
+    match coverage.iter().position(|c| c.field.as_bytes() == name) {
+        Some(i) => i,
+        None => {
+            coverage.push(FieldCoverage {
+                field: String::from_utf8_lossy(name).into_owned(),
+                records: 0,
+            });
+            coverage.len() - 1
+        }
+    }
+}
+
+/// The IDs seen so far, as one bit per ID. The highest IDs in the dumps are in the
+/// tens of millions, so this takes a few MB where a `HashSet` would take hundreds.
+#[derive(Default)]
+struct IdSet {
+    bits: Vec<u64>,
+}
+
+impl IdSet {
+    /// Add `id`, returning false if it was already present.
+    fn insert(&mut self, id: u32) -> bool {
+        let (word, bit) = (id as usize / 64, 1 << (id % 64));
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
+        }
+        let new = self.bits[word] & bit == 0;
+        self.bits[word] |= bit;
+        new
+    }
+}
@@ -0,0 +1,339 @@
+//! The roles of credits, parsed from their `role` text.
+//!
+//! The role of a credit can hold several roles separated by commas, each with
+//! details in square brackets, e.g. `Guitar [Electric, Acoustic], Vocals` or
+//! `Photography By [Photo]`. [`Role::parse_all`] splits them into [`Role`]s and maps
+//! each name to a [`RoleKind`], following the credit list of Discogs, so credits can
+//! be grouped by what the artist did rather than how the role was written. The many
+//! instruments are all [`RoleKind::Instrument`], with the name of the instrument.
+//!
+//! ```
+//! use disco_quick::role::{Role, RoleCategory, RoleKind};
+//!
+//! let roles = Role::parse_all("Guitar [Electric, Acoustic], Written By, Producer");
+//! assert_eq!(roles.len(), 3);
+//! assert_eq!(roles[0].kind, RoleKind::Instrument("Guitar".into()));
+//! assert_eq!(roles[0].details, ["Electric", "Acoustic"]);
+//! assert_eq!(roles[1].kind, RoleKind::WrittenBy);
+//! assert_eq!(roles[2].kind.category(), RoleCategory::Production);
+//! ```
+use crate::artist_credit::ArtistCredit;
+use crate::shared::string_enum;
+use std::fmt;
+
+/// Names of roles that are written more than one way, with the canonical name.
+const ALIASES: &[(&str, &str)] = &[
+    ("Written By", "Written-By"),
+    ("Writer", "Written-By"),
+    ("Composer", "Composed By"),
+    ("Lyrics", "Lyrics By"),
+    ("Music", "Music By"),
+    ("Arranger", "Arranged By"),
+    ("Co-Producer", "Co-producer"),
+    ("Executive Producer", "Executive-Producer"),
+    ("Recorded-By", "Recorded By"),
+    ("Mixed-By", "Mixed By"),
+    ("Mastered-By", "Mastered By"),
+    ("Remixed By", "Remix"),
+    ("Photography", "Photography By"),
+    ("Photographer", "Photography By"),
+    ("Lead Vocal", "Lead Vocals"),
+    ("Backing Vocal", "Backing Vocals"),
+    ("Voice", "Vocals"),
+];
+
+/// Instruments, which are all [`RoleKind::Instrument`]. Other names ending in one
+/// of [`INSTRUMENT_SUFFIXES`] are taken to be instruments too.
+const INSTRUMENTS: &[&str] = &[
+    "Accordion",
+    "Bagpipes",
+    "Banjo",
+    "Bass",
+    "Bassoon",
+    "Bongos",
+    "Brass",
+    "Celesta",
+    "Cello",
+    "Clarinet",
+    "Clavinet",
+    "Congas",
+    "Contrabass",
+    "Cymbal",
+    "Didgeridoo",
+    "Double Bass",
+    "Drum Machine",
+    "Drums",
+    "Fiddle",
+    "Flute",
+    "French Horn",
+    "Glockenspiel",
+    "Guitar",
+    "Harmonica",
+    "Harp",
+    "Harpsichord",
+    "Horn",
+    "Horns",
+    "Instruments",
+    "Keyboards",
+    "Mandolin",
+    "Marimba",
+    "Mellotron",
+    "Oboe",
+    "Organ",
+    "Percussion",
+    "Piano",
+    "Recorder",
+    "Sampler",
+    "Saxophone",
+    "Sitar",
+    "Strings",
+    "Synthesizer",
+    "Tabla",
+    "Tambourine",
+    "Theremin",
+    "Timpani",
+    "Trombone",
+    "Trumpet",
+    "Tuba",
+    "Turntables",
+    "Ukulele",
+    "Vibraphone",
+    "Viola",
+    "Violin",
+    "Woodwind",
+    "Xylophone",
+];
+
+/// Endings of the names of instruments with many kinds, e.g. `Slide Guitar`.
+const INSTRUMENT_SUFFIXES: &[&str] = &[" Guitar", " Saxophone", " Piano", " Bass", " Drums"];
+
+/// A role of a credit.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Role {
+    /// The name as written, e.g. `Photography By`.
+    pub name: String,
+    pub kind: RoleKind,
+    /// The details in square brackets, e.g. `Photo` for `Photography By [Photo]`.
+    pub details: Vec<String>,
+}
+
+string_enum! {
+    /// What a role is, following the credit list of Discogs.
+    pub enum RoleKind {
+        WrittenBy => "Written-By",
+        ComposedBy => "Composed By",
+        LyricsBy => "Lyrics By",
+        MusicBy => "Music By",
+        Songwriter => "Songwriter",
+        ArrangedBy => "Arranged By",
+        Producer => "Producer",
+        CoProducer => "Co-producer",
+        ExecutiveProducer => "Executive-Producer",
+        Engineer => "Engineer",
+        RecordedBy => "Recorded By",
+        MixedBy => "Mixed By",
+        MasteredBy => "Mastered By",
+        LacquerCutBy => "Lacquer Cut By",
+        EditedBy => "Edited By",
+        ProgrammedBy => "Programmed By",
+        Remix => "Remix",
+        DjMix => "DJ Mix",
+        Featuring => "Featuring",
+        Presenter => "Presenter",
+        Vocals => "Vocals",
+        LeadVocals => "Lead Vocals",
+        BackingVocals => "Backing Vocals",
+        Choir => "Choir",
+        Performer => "Performer",
+        Conductor => "Conductor",
+        Orchestra => "Orchestra",
+        Design => "Design",
+        ArtDirection => "Art Direction",
+        Artwork => "Artwork",
+        Illustration => "Illustration",
+        Layout => "Layout",
+        Photography => "Photography By",
+        LinerNotes => "Liner Notes",
+        Management => "Management",
+        AAndR => "A&R",
+    }
+    /// A role the crate doesn't know, as written.
+    else Other(String);
+    /// An instrument, with its name as written, e.g. `Electric Guitar`.
+    also Instrument(String);
+    from = role_kind;
+}
+
+/// The groups of roles in the credit list of Discogs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RoleCategory {
+    Writing,
+    Production,
+    Technical,
+    Remix,
+    DjMix,
+    Featuring,
+    Vocals,
+    Instruments,
+    Conducting,
+    Visual,
+    Literary,
+    Management,
+    Other,
+}
+
+impl Role {
+    /// Split a role text into its roles. Commas inside square brackets separate
+    /// details rather than roles.
+    pub fn parse_all(text: &str) -> Vec<Role> {
+        let mut roles = Vec::new();
+        let mut depth = 0usize;
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    roles.extend(Self::parse(&text[start..i]));
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        roles.extend(Self::parse(&text[start..]));
+        roles
+    }
+
+    /// Parse a single role with its details, or None if it's empty.
+    pub fn parse(text: &str) -> Option<Role> {
+        let (name, mut rest) = match text.find('[') {
+            Some(i) => (&text[..i], &text[i..]),
+            None => (text, ""),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut details = Vec::new();
+        while let Some(open) = rest.find('[') {
+            let inner = &rest[open + 1..];
+            let close = inner.find(']').unwrap_or(inner.len());
+            details.extend(
+                inner[..close]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|d| !d.is_empty())
+                    .map(str::to_string),
+            );
+            rest = inner.get(close + 1..).unwrap_or("");
+        }
+        Some(Role {
+            name: name.to_string(),
+            kind: RoleKind::from(name),
+            details,
+        })
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.details.is_empty() {
+            write!(f, " [{}]", self.details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl RoleKind {
+    pub fn category(&self) -> RoleCategory {
+        match self {
+            Self::WrittenBy
+            | Self::ComposedBy
+            | Self::LyricsBy
+            | Self::MusicBy
+            | Self::Songwriter
+            | Self::ArrangedBy => RoleCategory::Writing,
+            Self::Producer | Self::CoProducer | Self::ExecutiveProducer => RoleCategory::Production,
+            Self::Engineer
+            | Self::RecordedBy
+            | Self::MixedBy
+            | Self::MasteredBy
+            | Self::LacquerCutBy
+            | Self::EditedBy
+            | Self::ProgrammedBy => RoleCategory::Technical,
+            Self::Remix => RoleCategory::Remix,
+            Self::DjMix => RoleCategory::DjMix,
+            Self::Featuring | Self::Presenter => RoleCategory::Featuring,
+            Self::Vocals | Self::LeadVocals | Self::BackingVocals | Self::Choir => {
+                RoleCategory::Vocals
+            }
+            Self::Performer | Self::Instrument(_) => RoleCategory::Instruments,
+            Self::Conductor | Self::Orchestra => RoleCategory::Conducting,
+            Self::Design
+            | Self::ArtDirection
+            | Self::Artwork
+            | Self::Illustration
+            | Self::Layout
+            | Self::Photography => RoleCategory::Visual,
+            Self::LinerNotes => RoleCategory::Literary,
+            Self::Management | Self::AAndR => RoleCategory::Management,
+            Self::Other(_) => RoleCategory::Other,
+        }
+    }
+
+    /// Whether the role is performing on the recording, by singing or playing.
+    pub fn is_performance(&self) -> bool {
+        matches!(
+            self.category(),
+            RoleCategory::Vocals | RoleCategory::Instruments | RoleCategory::Conducting
+        )
+    }
+}
+
+/// Map the name of a role to its kind, ignoring case and the different ways some
+/// are written.
+fn role_kind(name: &str) -> RoleKind {
+    let canonical = ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        .map_or(name, |(_, canonical)| canonical);
+    if let Some(kind) = RoleKind::KNOWN
+        .into_iter()
+        .find(|k| k.as_str().eq_ignore_ascii_case(canonical))
+    {
+        return kind;
+    }
+    let is_instrument = INSTRUMENTS.iter().any(|i| i.eq_ignore_ascii_case(name))
+        || INSTRUMENT_SUFFIXES.iter().any(|suffix| {
+            name.len() > suffix.len()
+                && name.is_char_boundary(name.len() - suffix.len())
+                && name[name.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+        });
+    match is_instrument {
+        true => RoleKind::Instrument(name.to_string()),
+        false => RoleKind::Other(name.to_string()),
+    }
+}
+
+impl ArtistCredit {
+    /// The roles of the credit, parsed from its role text.
+    pub fn roles(&self) -> Vec<Role> {
+        self.role
+            .as_deref()
+            .map(Role::parse_all)
+            .unwrap_or_default()
+    }
+
+    /// Whether any role of the credit is of `kind`. Instruments match by name.
+    pub fn has_role(&self, kind: &RoleKind) -> bool {
+        self.roles().iter().any(|r| r.kind == *kind)
+    }
+
+    /// Whether any role of the credit is in `category`.
+    pub fn has_role_in(&self, category: RoleCategory) -> bool {
+        self.roles().iter().any(|r| r.kind.category() == category)
+    }
+}
@@ -0,0 +1,119 @@
+//! Changes to the structure of the dumps over time, and how they affect parsing.
+//!
+//! No changes are registered yet: a change is only added once it's been seen in the
+//! published dumps, with the month of the first dump that has it. Until then every
+//! field is treated as present in the dumps of every month.
+use crate::shared::EntityType;
+use std::fmt;
+use std::str::FromStr;
+
+/// The month a dump was published.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DumpDate {
+    pub year: u16,
+    pub month: u8,
+}
+
+impl DumpDate {
+    pub const fn new(year: u16, month: u8) -> Self {
+        Self { year, month }
+    }
+
+    /// The number of months since the start of year 0, for date arithmetic.
+    pub fn months(&self) -> u32 {
+        self.year as u32 * 12 + self.month as u32 - 1
+    }
+
+    pub fn from_months(months: u32) -> Self {
+        Self::new((months / 12) as u16, (months % 12) as u8 + 1)
+    }
+
+    pub fn previous(&self) -> Self {
+        Self::from_months(self.months().saturating_sub(1))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::from_months(self.months() + 1)
+    }
+}
+
+impl fmt::Display for DumpDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+impl FromStr for DumpDate {
+    type Err = String;
+
+    /// Parse dates like "2023-10", "202310" or "20231001" as used in dump file names.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits: String = s.chars().filter(|c| c.is_ascii_digit()).collect();
+        let err = || format!("Invalid dump date {s}");
+        if digits.len() < 6 {
+            return Err(err());
+        }
+        let year = digits[..4].parse().map_err(|_| err())?;
+        let month = digits[4..6].parse().map_err(|_| err())?;
+        if !(1..=12).contains(&month) {
+            return Err(err());
+        }
+        Ok(Self { year, month })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// The field is still present but its contents or the way it's parsed changed.
+    Changed,
+}
+
+/// A change to a field of a dump that took effect in the dump of `since`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SchemaChange {
+    pub since: DumpDate,
+    pub entity: EntityType,
+    /// The path of the element or attribute relative to the record element.
+    pub field: &'static str,
+    pub kind: ChangeKind,
+    pub description: &'static str,
+}
+
+const CHANGES: &[SchemaChange] = &[];
+
+/// All known changes, ordered by date.
+pub fn changes() -> &'static [SchemaChange] {
+    CHANGES
+}
+
+/// The changes affecting dumps of `entity` published after `date`.
+pub fn changes_since(
+    entity: EntityType,
+    date: DumpDate,
+) -> impl Iterator<Item = &'static SchemaChange> {
+    CHANGES
+        .iter()
+        .filter(move |c| c.entity == entity && c.since > date)
+}
+
+/// Whether `field` is present in dumps of `entity` published in the month of `date`.
+/// Fields without any recorded changes are assumed to always be present.
+pub fn is_field_available(entity: EntityType, field: &str, date: DumpDate) -> bool {
+    let mut available = true;
+    for change in CHANGES
+        .iter()
+        .filter(|c| c.entity == entity && c.field == field)
+    {
+        match change.kind {
+            ChangeKind::Added if change.since > date => available = false,
+            ChangeKind::Removed if change.since <= date => available = false,
+            _ => {}
+        }
+    }
+    available
+}
@@ -0,0 +1,136 @@
+//! Comparing records while ignoring fields that change between dumps without the
+//! data changing.
+//!
+//! Some fields of a record churn from month to month: image dimensions change when
+//! Discogs reprocesses its images, the data quality is re-rated, and videos are
+//! listed in a different order. [`SemanticEq::semantic_eq`] compares records with
+//! all of these ignored, and [`SemanticEq::semantic_eq_with`] ignores only the
+//! [`Volatile`] fields in a [`VolatileSet`].
+//!
+//! ```
+//! use disco_quick::id::ReleaseId;
+//! use disco_quick::release::Release;
+//! use disco_quick::semantic::{SemanticEq, Volatile};
+//!
+//! let a = Release { id: ReleaseId(1), data_quality: "Correct".into(), ..Default::default() };
+//! let b = Release { id: ReleaseId(1), data_quality: "Needs Vote".into(), ..Default::default() };
+//! assert!(a.semantic_eq(&b));
+//! assert!(!a.semantic_eq_with(&b, Volatile::VideoOrder.into()));
+//! ```
+use crate::artist::Artist;
+use crate::label::Label;
+use crate::master::Master;
+use crate::options::flag_set;
+use crate::release::Release;
+use crate::shared::{DataQuality, Image};
+use crate::video::Video;
+
+/// A field that's ignored when comparing records with [`SemanticEq`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Volatile {
+    /// The width and height of images.
+    ImageDimensions = 1,
+    /// The data quality rating.
+    DataQuality = 1 << 1,
+    /// The order of videos.
+    VideoOrder = 1 << 2,
+}
+
+impl Volatile {
+    pub const ALL: [Volatile; 3] = [
+        Volatile::ImageDimensions,
+        Volatile::DataQuality,
+        Volatile::VideoOrder,
+    ];
+}
+
+flag_set! {
+    /// A set of [`Volatile`] fields, built by combining them with `|`.
+    VolatileSet(u8) of Volatile
+}
+
+/// Records that can be compared while ignoring [`Volatile`] fields.
+pub trait SemanticEq: Clone + PartialEq {
+    /// Reset the fields in `fields` to a value that doesn't vary, or put them in a
+    /// fixed order.
+    fn clear_volatile(&mut self, fields: VolatileSet);
+
+    /// Whether the records are equal, ignoring all the [`Volatile`] fields.
+    fn semantic_eq(&self, other: &Self) -> bool {
+        self.semantic_eq_with(other, VolatileSet::all())
+    }
+
+    /// Whether the records are equal, ignoring the fields in `fields`.
+    fn semantic_eq_with(&self, other: &Self, fields: VolatileSet) -> bool {
+        if self == other {
+            return true;
+        }
+        if fields.is_empty() {
+            return false;
+        }
+        let (mut a, mut b) = (self.clone(), other.clone());
+        a.clear_volatile(fields);
+        b.clear_volatile(fields);
+        a == b
+    }
+}
+
+impl SemanticEq for Artist {
+    fn clear_volatile(&mut self, fields: VolatileSet) {
+        clear_images(&mut self.images, fields);
+        clear_data_quality(&mut self.data_quality, fields);
+    }
+}
+
+impl SemanticEq for Label {
+    fn clear_volatile(&mut self, fields: VolatileSet) {
+        clear_images(&mut self.images, fields);
+        clear_data_quality(&mut self.data_quality, fields);
+    }
+}
+
+impl SemanticEq for Master {
+    fn clear_volatile(&mut self, fields: VolatileSet) {
+        clear_images(&mut self.images, fields);
+        clear_data_quality(&mut self.data_quality, fields);
+        clear_videos(&mut self.videos, fields);
+    }
+}
+
+impl SemanticEq for Release {
+    fn clear_volatile(&mut self, fields: VolatileSet) {
+        clear_images(&mut self.images, fields);
+        clear_data_quality(&mut self.data_quality, fields);
+        clear_videos(&mut self.videos, fields);
+    }
+}
+
+fn clear_images(images: &mut [Image], fields: VolatileSet) {
+    if fields.contains(Volatile::ImageDimensions) {
+        for image in images {
+            image.width = 0;
+            image.height = 0;
+        }
+    }
+}
+
+fn clear_data_quality(data_quality: &mut DataQuality, fields: VolatileSet) {
+    if fields.contains(Volatile::DataQuality) {
+        *data_quality = DataQuality::default();
+    }
+}
+
+fn clear_videos(videos: &mut [Video], fields: VolatileSet) {
+    if fields.contains(Volatile::VideoOrder) {
+        videos.sort_by(|a, b| {
+            (&a.src, &a.title, &a.description, a.duration, a.embed).cmp(&(
+                &b.src,
+                &b.title,
+                &b.description,
+                b.duration,
+                b.embed,
+            ))
+        });
+    }
+}
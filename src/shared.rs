@@ -1,17 +1,271 @@
+use crate::id::LabelId;
+use crate::parser::ParserError;
 use crate::util::get_attr;
 use quick_xml::events::BytesStart;
+#[cfg(feature = "extra-fields")]
+use quick_xml::events::Event;
+#[cfg(feature = "extra-fields")]
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone, Debug, Default)]
+/// The type of entity contained in a dump.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntityType {
+    Artist,
+    Label,
+    Master,
+    Release,
+}
+
+impl EntityType {
+    pub const ALL: [EntityType; 4] = [Self::Artist, Self::Label, Self::Master, Self::Release];
+
+    /// The name of the root element of the dump, e.g. "artists".
+    pub fn root_tag(&self) -> &'static str {
+        match self {
+            Self::Artist => "artists",
+            Self::Label => "labels",
+            Self::Master => "masters",
+            Self::Release => "releases",
+        }
+    }
+
+    /// The name of the element of each record, e.g. "artist".
+    pub fn record_tag(&self) -> &'static str {
+        match self {
+            Self::Artist => "artist",
+            Self::Label => "label",
+            Self::Master => "master",
+            Self::Release => "release",
+        }
+    }
+}
+
+impl fmt::Display for EntityType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.root_tag())
+    }
+}
+
+impl FromStr for EntityType {
+    type Err = String;
+
+    /// Parse either the singular or plural name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|t| s == t.root_tag() || s == t.record_tag())
+            .ok_or_else(|| format!("Unknown entity type {s}"))
+    }
+}
+
+/// Define an enum of the values of a text field of the dumps, with a variant for
+/// each value the crate knows and an `else` variant that keeps any other value as
+/// written. Variants marked `also` hold their text too, and `from` replaces the
+/// exact match of the known values when parsing. Values are trimmed before they're
+/// parsed. The enum is serialized as its text, or as the result of the `serde`
+/// method without being deserializable.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$known_meta:meta])* $known:ident => $text:literal,)*
+        }
+        $(#[$other_meta:meta])*
+        else $other:ident(String);
+        $($(#[$extra_meta:meta])* also $extra:ident(String);)*
+        $(from = $from:path;)?
+        $(serde = $serde:ident;)?
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($(#[$known_meta])* $known,)*
+            $($(#[$extra_meta])* $extra(String),)*
+            $(#[$other_meta])*
+            $other(String),
+        }
+
+        impl $name {
+            /// The values the crate knows.
+            pub const KNOWN: [$name; [$($text),*].len()] = [$(Self::$known),*];
+
+            /// The value as written in the dumps.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$known => $text,)*
+                    $(Self::$extra(text) |)* Self::$other(text) => text,
+                }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::$other(String::new())
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                let s = s.trim();
+                string_enum!(@from s $other $($from)?)
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = std::convert::Infallible;
+
+            #[doc = concat!(
+                "Parse a value as written in the dumps. Unknown values are kept as [`",
+                stringify!($other),
+                "`](Self::",
+                stringify!($other),
+                ")."
+            )]
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(Self::from(s))
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                self.as_str()
+            }
+        }
+
+        string_enum!(@serde $name $($serde)?);
+    };
+    (@from $s:ident $other:ident) => {
+        Self::KNOWN
+            .into_iter()
+            .find(|k| k.as_str() == $s)
+            .unwrap_or_else(|| Self::$other($s.to_string()))
+    };
+    (@from $s:ident $other:ident $from:path) => {
+        $from($s)
+    };
+    (@serde $name:ident) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+                Ok(Self::from(&*s))
+            }
+        }
+    };
+    (@serde $name:ident $serde:ident) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.$serde())
+            }
+        }
+    };
+}
+pub(crate) use string_enum;
+
+string_enum! {
+    /// How accurate and complete the community has voted a record to be.
+    pub enum DataQuality {
+        NeedsVote => "Needs Vote",
+        Correct => "Correct",
+        CompleteAndCorrect => "Complete and Correct",
+        NeedsMinorChanges => "Needs Minor Changes",
+        NeedsMajorChanges => "Needs Major Changes",
+        EntirelyIncorrect => "Entirely Incorrect",
+        EntirelyIncorrectEdit => "Entirely Incorrect Edit",
+    }
+    /// A value the crate doesn't know, or an empty string if the record had none.
+    else Other(String);
+}
+
+impl DataQuality {
+    /// Whether the record has been voted correct, with or without being complete.
+    pub fn is_correct(&self) -> bool {
+        matches!(self, Self::Correct | Self::CompleteAndCorrect)
+    }
+}
+
+/// The most consecutive blank lines kept by [`normalize_text`].
+pub const MAX_BLANK_LINES: usize = 2;
+
+/// Normalize the whitespace of free text: line endings become `\n`, whitespace at
+/// the end of lines and blank lines at the start and end are removed, and runs of
+/// more than [`MAX_BLANK_LINES`] blank lines are shortened.
+pub fn normalize_text(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+    for line in text.split('\n') {
+        // A lone \r is a line break too
+        for line in line.split('\r') {
+            let line = line.trim_end();
+            if line.is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if !normalized.is_empty() {
+                let breaks = blank_lines.min(MAX_BLANK_LINES) + 1;
+                normalized.extend(std::iter::repeat_n('\n', breaks));
+            }
+            blank_lines = 0;
+            normalized.push_str(line);
+        }
+        // The \r of a \r\n ends the line already
+        if line.ends_with('\r') {
+            blank_lines -= 1;
+        }
+    }
+    normalized
+}
+
+/// Normalize `text` in place, see [`normalize_text`].
+pub(crate) fn normalize_in_place(text: &mut String) {
+    let normalized = normalize_text(text);
+    if normalized != *text {
+        *text = normalized;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReleaseLabel {
-    pub id: u32,
+    pub id: LabelId,
     pub name: String,
     pub catno: Option<String>,
     pub entity_type: u8,
     pub entity_type_name: String,
 }
 
-#[derive(Clone, Debug, Default)]
+impl ReleaseLabel {
+    /// Parse a `<label>` element of a release's `<labels>`.
+    pub fn from_event(ev: BytesStart) -> Result<Self, ParserError> {
+        let mut attrs = ev.attributes();
+        Ok(ReleaseLabel {
+            name: get_attr(attrs.next())?.to_string(),
+            catno: Some(get_attr(attrs.next())?.to_string()),
+            id: get_attr(attrs.next())?.parse()?,
+            entity_type: 1,
+            entity_type_name: "Label".to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Image {
     pub r#type: String,
@@ -22,14 +276,65 @@ pub struct Image {
 }
 
 impl Image {
-    pub fn from_event(ev: BytesStart) -> Self {
+    pub fn from_event(ev: BytesStart) -> Result<Self, ParserError> {
         let mut attrs = ev.attributes();
-        Image {
-            r#type: get_attr(attrs.next()).to_string(),
-            uri: get_attr(attrs.next()).to_string(),
-            uri150: get_attr(attrs.next()).to_string(),
-            width: get_attr(attrs.next()).parse().unwrap(),
-            height: get_attr(attrs.next()).parse().unwrap(),
+        Ok(Image {
+            r#type: get_attr(attrs.next())?.to_string(),
+            uri: get_attr(attrs.next())?.to_string(),
+            uri150: get_attr(attrs.next())?.to_string(),
+            width: get_attr(attrs.next())?.parse()?,
+            height: get_attr(attrs.next())?.parse()?,
+        })
+    }
+}
+
+/// Reads an element of a record that its parser doesn't know, keeping its text if it
+/// has no children.
+#[cfg(feature = "extra-fields")]
+#[derive(Debug, Default)]
+pub(crate) struct UnknownElement {
+    name: String,
+    text: String,
+    depth: usize,
+    nested: bool,
+}
+
+#[cfg(feature = "extra-fields")]
+impl UnknownElement {
+    pub(crate) fn start(&mut self, e: &BytesStart) {
+        self.name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+        self.text.clear();
+        self.depth = 0;
+        self.nested = false;
+    }
+
+    /// Process an event inside the element, returning true once it has ended.
+    pub(crate) fn process(
+        &mut self,
+        ev: Event,
+        fields: &mut BTreeMap<String, String>,
+    ) -> Result<bool, ParserError> {
+        match ev {
+            Event::Start(_) => {
+                self.depth += 1;
+                self.nested = true;
+            }
+            Event::End(_) if self.depth == 0 => {
+                if !self.nested {
+                    fields.insert(
+                        std::mem::take(&mut self.name),
+                        std::mem::take(&mut self.text),
+                    );
+                }
+                return Ok(true);
+            }
+            Event::End(_) => self.depth -= 1,
+            Event::Text(e) if self.depth == 0 => self.text.push_str(&e.unescape()?),
+            Event::CData(e) if self.depth == 0 => {
+                self.text.push_str(&String::from_utf8_lossy(&e));
+            }
+            _ => {}
         }
+        Ok(false)
     }
 }
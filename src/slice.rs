@@ -0,0 +1,172 @@
+//! Cutting a few records out of a dump into a new dump, e.g. to share the records a
+//! bug report is about.
+//!
+//! The output has the same root element as the input, so it can be read like any
+//! other dump. It's gzipped if the output path ends with `.gz`. The records are
+//! copied as they are in the input without being parsed, so they're kept byte for
+//! byte, comments and whitespace included, and extracting a few thousand records by
+//! ID takes little more than reading the dump.
+use crate::borrowed::RecordReader;
+use crate::reader::ReaderError;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use quick_xml::events::Event;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// The records to extract from a dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SliceRange {
+    /// The records that start within a range of offsets in the decompressed XML.
+    Bytes(Range<u64>),
+    /// The records with these indexes, counting from 0.
+    Records(Range<u64>),
+    /// The records with these IDs. Reading stops after the highest, which assumes
+    /// the dump is in order of ID like the published dumps. Use
+    /// [`extract_matching`] for other dumps.
+    Ids(BTreeSet<u32>),
+}
+
+/// What to do with a record when extracting.
+enum Selection {
+    Copy,
+    Skip,
+    /// Skip the record and the rest of the dump.
+    Stop,
+}
+
+impl SliceRange {
+    fn select(&self, index: u64, offset: u64, record: &[u8]) -> Selection {
+        let (contains, is_past) = match self {
+            Self::Bytes(range) => (range.contains(&offset), offset >= range.end),
+            Self::Records(range) => (range.contains(&index), index >= range.end),
+            Self::Ids(ids) => match record_id(record) {
+                Some(id) => (ids.contains(&id), ids.last().is_none_or(|last| id > *last)),
+                None => (false, false),
+            },
+        };
+        match (contains, is_past) {
+            (_, true) => Selection::Stop,
+            (true, false) => Selection::Copy,
+            (false, false) => Selection::Skip,
+        }
+    }
+}
+
+/// Write the records of the dump at `path` that are in `range` to a new dump at
+/// `out_path`, returning the number of records written.
+pub fn extract(path: &Path, range: SliceRange, out_path: &Path) -> Result<u64, ReaderError> {
+    extract_with(path, out_path, |index, offset, record| {
+        range.select(index, offset, record)
+    })
+}
+
+/// Write the records of the dump at `path` for which `predicate` returns true to a
+/// new dump at `out_path`, returning the number of records written. `predicate` is
+/// called with the ID of each record, or 0 if it has none, and its XML.
+///
+/// ```no_run
+/// use disco_quick::slice;
+///
+/// # fn main() -> Result<(), disco_quick::ReaderError> {
+/// let written = slice::extract_matching(
+///     "releases.xml.gz".as_ref(),
+///     "vinyl.xml.gz".as_ref(),
+///     |_id, xml| xml.windows(14).any(|w| w == br#"name="Vinyl""#),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn extract_matching(
+    path: &Path,
+    out_path: &Path,
+    mut predicate: impl FnMut(u32, &[u8]) -> bool,
+) -> Result<u64, ReaderError> {
+    extract_with(path, out_path, |_, _, record| {
+        match predicate(record_id(record).unwrap_or(0), record) {
+            true => Selection::Copy,
+            false => Selection::Skip,
+        }
+    })
+}
+
+fn extract_with(
+    path: &Path,
+    out_path: &Path,
+    select: impl FnMut(u64, u64, &[u8]) -> Selection,
+) -> Result<u64, ReaderError> {
+    let reader = RecordReader::from_path(path)?;
+    let file = BufWriter::new(File::create(out_path)?);
+    if out_path.extension().is_some_and(|ext| ext == "gz") {
+        let mut gz = GzEncoder::new(file, Compression::default());
+        let written = write_records(reader, select, &mut gz)?;
+        gz.finish()?.flush()?;
+        Ok(written)
+    } else {
+        let mut file = file;
+        let written = write_records(reader, select, &mut file)?;
+        file.flush()?;
+        Ok(written)
+    }
+}
+
+fn write_records(
+    mut reader: RecordReader,
+    mut select: impl FnMut(u64, u64, &[u8]) -> Selection,
+    out: &mut impl Write,
+) -> Result<u64, ReaderError> {
+    let root = reader.entity_type().root_tag();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    write!(out, "<{root}>")?;
+    let mut index = 0;
+    let mut written = 0;
+    loop {
+        let offset = reader.position();
+        let Some(record) = reader.next_record()? else {
+            break;
+        };
+        match select(index, offset, record) {
+            Selection::Copy => {
+                out.write_all(record)?;
+                written += 1;
+            }
+            Selection::Skip => {}
+            Selection::Stop => break,
+        }
+        index += 1;
+    }
+    writeln!(out, "</{root}>")?;
+    Ok(written)
+}
+
+/// The ID of a record from the `id` attribute of releases and masters, or the `id`
+/// element of artists and labels.
+fn record_id(record: &[u8]) -> Option<u32> {
+    let mut reader = quick_xml::Reader::from_reader(record);
+    let mut depth = 0;
+    let mut id_element = false;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) if depth == 0 => {
+                depth += 1;
+                if let Some(id) = e.try_get_attribute("id").ok()? {
+                    return id.unescape_value().ok()?.trim().parse().ok();
+                }
+            }
+            Event::Start(e) => {
+                depth += 1;
+                id_element = depth == 2 && e.local_name().as_ref() == b"id";
+            }
+            Event::Text(e) if id_element => return e.unescape().ok()?.trim().parse().ok(),
+            Event::End(_) => {
+                depth -= 1;
+                id_element = false;
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
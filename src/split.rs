@@ -0,0 +1,203 @@
+//! Splitting a dump into smaller dumps, e.g. to parse the shards on several machines.
+//!
+//! Each shard is a valid dump with the root element of the input, written with a
+//! [`DumpWriter`]. The records of a shard stay in the order of the input, so they're
+//! in order of ID like the dumps. [`by_count`] deals the records to the shards in
+//! turn, so the IDs of every shard span the whole dump; use [`by_id_ranges`] for
+//! shards of contiguous IDs. Shards are named after the input with the number of
+//! the shard appended, e.g. `discogs_20240101_releases_001.xml.gz`, and are gzipped
+//! if the input is.
+//!
+//! [`sample`] writes a single smaller dump of a [`Sample`] of the records instead,
+//! e.g. for test pipelines.
+//!
+//! ```no_run
+//! use disco_quick::split;
+//!
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! let shards = split::by_count("discogs_20240101_releases.xml.gz".as_ref(), 8)?;
+//! for shard in shards {
+//!     println!("{}: {} records", shard.path.display(), shard.records);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::options::{ReaderOptions, Sample};
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::writer::{DumpWriter, WriteXml};
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// Where and how shards are written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SplitOptions {
+    out_dir: Option<PathBuf>,
+    gzip: Option<bool>,
+}
+
+impl SplitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the shards to `dir` instead of the directory of the input.
+    pub fn out_dir(mut self, dir: &Path) -> Self {
+        self.out_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Whether to gzip the shards. By default they're gzipped if the input is.
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.gzip = Some(gzip);
+        self
+    }
+}
+
+/// A dump written by splitting another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shard {
+    pub path: PathBuf,
+    pub records: u64,
+    /// The IDs of the first and last records, or None if the shard is empty. Other
+    /// shards can have records between them unless the dump was split by ID ranges.
+    pub first_id: Option<u32>,
+    pub last_id: Option<u32>,
+}
+
+/// Split the dump at `path` into `n` shards with the same number of records, give
+/// or take one. Records are dealt to the shards in turn, so the first shard has the
+/// 1st, `n + 1`th, `2n + 1`th record and so on.
+pub fn by_count(path: &Path, n: usize) -> Result<Vec<Shard>, ReaderError> {
+    by_count_with_options(path, n, &SplitOptions::default())
+}
+
+pub fn by_count_with_options(
+    path: &Path,
+    n: usize,
+    options: &SplitOptions,
+) -> Result<Vec<Shard>, ReaderError> {
+    let n = n.max(1);
+    split(path, n, options, |index, _| {
+        vec![(index % n as u64) as usize]
+    })
+}
+
+/// Split the dump at `path` into a shard for each range of IDs in `ranges`.
+/// Records outside all of the ranges are left out, and records in several
+/// overlapping ranges are written to each of their shards.
+pub fn by_id_ranges(
+    path: &Path,
+    ranges: &[RangeInclusive<u32>],
+) -> Result<Vec<Shard>, ReaderError> {
+    by_id_ranges_with_options(path, ranges, &SplitOptions::default())
+}
+
+pub fn by_id_ranges_with_options(
+    path: &Path,
+    ranges: &[RangeInclusive<u32>],
+    options: &SplitOptions,
+) -> Result<Vec<Shard>, ReaderError> {
+    split(path, ranges.len(), options, |_, id| {
+        ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| range.contains(&id))
+            .map(|(i, _)| i)
+            .collect()
+    })
+}
+
+/// Write a dump of a sample of the records of the dump at `path` to `out`, which is
+/// gzipped if its name ends in `.gz`.
+pub fn sample(path: &Path, out: &Path, sample: Sample) -> Result<Shard, ReaderError> {
+    let options = ReaderOptions::new().sample(sample);
+    let paths = vec![out.to_path_buf()];
+    let gzip = is_gzip(out);
+    let assign = |_, _| vec![0];
+    let mut shards = match DiscogsReader::from_path_with_options(path, options)? {
+        DiscogsReader::Artists(r) => write_shards(*r, paths, gzip, assign)?,
+        DiscogsReader::Labels(r) => write_shards(*r, paths, gzip, assign)?,
+        DiscogsReader::Masters(r) => write_shards(*r, paths, gzip, assign)?,
+        DiscogsReader::Releases(r) => write_shards(*r, paths, gzip, assign)?,
+        DiscogsReader::Custom(_) => return Err(ReaderError::InvalidStartTag),
+    };
+    Ok(shards.remove(0))
+}
+
+/// Write each record to the shards `assign` returns for its index and ID.
+fn split(
+    path: &Path,
+    shards: usize,
+    options: &SplitOptions,
+    assign: impl Fn(u64, u32) -> Vec<usize>,
+) -> Result<Vec<Shard>, ReaderError> {
+    let paths = shard_paths(path, shards, options);
+    let gzip = options.gzip.unwrap_or_else(|| is_gzip(path));
+    match DiscogsReader::from_path(path)? {
+        DiscogsReader::Artists(r) => write_shards(*r, paths, gzip, assign),
+        DiscogsReader::Labels(r) => write_shards(*r, paths, gzip, assign),
+        DiscogsReader::Masters(r) => write_shards(*r, paths, gzip, assign),
+        DiscogsReader::Releases(r) => write_shards(*r, paths, gzip, assign),
+        DiscogsReader::Custom(_) => Err(ReaderError::InvalidStartTag),
+    }
+}
+
+fn write_shards<T: WriteXml>(
+    records: impl Iterator<Item = Result<T, ReaderError>>,
+    paths: Vec<PathBuf>,
+    gzip: bool,
+    assign: impl Fn(u64, u32) -> Vec<usize>,
+) -> Result<Vec<Shard>, ReaderError> {
+    let mut writers = Vec::with_capacity(paths.len());
+    let mut shards = Vec::with_capacity(paths.len());
+    for path in paths {
+        let file = BufWriter::new(File::create(&path)?);
+        writers.push(DumpWriter::<T, _>::new(file, gzip)?);
+        shards.push(Shard {
+            path,
+            records: 0,
+            first_id: None,
+            last_id: None,
+        });
+    }
+    for (index, record) in records.enumerate() {
+        let record = record?;
+        let id = record.id();
+        for i in assign(index as u64, id) {
+            writers[i].write(&record)?;
+            let shard = &mut shards[i];
+            shard.records += 1;
+            shard.first_id.get_or_insert(id);
+            shard.last_id = Some(id);
+        }
+    }
+    for writer in writers {
+        writer.finish()?;
+    }
+    Ok(shards)
+}
+
+/// The paths of the shards of the dump at `path`, numbered from 1.
+fn shard_paths(path: &Path, shards: usize, options: &SplitOptions) -> Vec<PathBuf> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("dump");
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let stem = name.strip_suffix(".xml").unwrap_or(name);
+    let extension = match options.gzip.unwrap_or_else(|| is_gzip(path)) {
+        true => "xml.gz",
+        false => "xml",
+    };
+    let dir = match &options.out_dir {
+        Some(dir) => dir.as_path(),
+        None => path.parent().unwrap_or(Path::new("")),
+    };
+    let width = shards.to_string().len().max(3);
+    (1..=shards)
+        .map(|i| dir.join(format!("{stem}_{i:0width$}.{extension}")))
+        .collect()
+}
+
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gz")
+}
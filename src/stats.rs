@@ -0,0 +1,543 @@
+use crate::artist::Artist;
+use crate::date::ReleaseDate;
+use crate::duration::TrackDuration;
+use crate::id::ReleaseId;
+use crate::label::Label;
+use crate::master::Master;
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::release::Release;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The number of buckets in the histograms of images per record. The last counts
+/// the records with at least that many images.
+pub const IMAGE_BUCKETS: usize = 20;
+
+/// Aggregate counts gathered from one or more dumps of the same month.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DumpStats {
+    pub artists: EntityStats,
+    pub labels: EntityStats,
+    pub masters: EntityStats,
+    pub releases: EntityStats,
+    pub releases_by_country: BTreeMap<String, u64>,
+    pub releases_by_genre: BTreeMap<String, u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub releases_by_style: BTreeMap<String, u64>,
+    /// Releases by the decade of their release date, e.g. `1970s`, or an empty key
+    /// for releases without a year.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub releases_by_decade: BTreeMap<String, u64>,
+    /// Releases by the name of their formats, counting releases with several of the
+    /// same format once.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub releases_by_format: BTreeMap<String, u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntityStats {
+    pub count: u64,
+    pub max_id: u64,
+    pub images: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub by_data_quality: BTreeMap<String, u64>,
+    /// The number of records with each number of images, up to [`IMAGE_BUCKETS`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub images_histogram: Vec<u64>,
+}
+
+impl EntityStats {
+    fn add(&mut self, id: u64, images: usize, data_quality: &str) {
+        self.count += 1;
+        self.max_id = self.max_id.max(id);
+        self.images += images as u64;
+        increment(&mut self.by_data_quality, data_quality);
+        if self.images_histogram.is_empty() {
+            self.images_histogram = vec![0; IMAGE_BUCKETS];
+        }
+        self.images_histogram[images.min(IMAGE_BUCKETS - 1)] += 1;
+    }
+}
+
+impl DumpStats {
+    pub fn add_artist(&mut self, artist: &Artist) {
+        self.artists.add(
+            u64::from(artist.id),
+            artist.images.len(),
+            artist.data_quality.as_str(),
+        );
+    }
+
+    pub fn add_label(&mut self, label: &Label) {
+        self.labels.add(
+            u64::from(label.id),
+            label.images.len(),
+            label.data_quality.as_str(),
+        );
+    }
+
+    pub fn add_master(&mut self, master: &Master) {
+        self.masters.add(
+            u64::from(master.id),
+            master.images.len(),
+            master.data_quality.as_str(),
+        );
+    }
+
+    pub fn add_release(&mut self, release: &Release) {
+        self.releases.add(
+            u64::from(release.id),
+            release.images.len(),
+            release.data_quality.as_str(),
+        );
+        increment(&mut self.releases_by_country, &release.country);
+        for genre in &release.genres {
+            increment(&mut self.releases_by_genre, genre);
+        }
+        for style in &release.styles {
+            increment(&mut self.releases_by_style, style);
+        }
+        let decade = match ReleaseDate::parse(&release.released).year {
+            Some(year) => format!("{}s", year / 10 * 10),
+            None => String::new(),
+        };
+        increment(&mut self.releases_by_decade, &decade);
+        let formats: BTreeSet<&str> = release.formats.iter().map(|f| &*f.name).collect();
+        for format in formats {
+            increment(&mut self.releases_by_format, format);
+        }
+    }
+
+    /// The total number of images across all entities.
+    pub fn images(&self) -> u64 {
+        self.artists.images + self.labels.images + self.masters.images + self.releases.images
+    }
+}
+
+/// Add one to the count of `key`, only allocating it the first time it's seen.
+fn increment(counts: &mut BTreeMap<String, u64>, key: &str) {
+    if let Some(count) = counts.get_mut(key) {
+        *count += 1;
+    } else {
+        counts.insert(key.to_string(), 1);
+    }
+}
+
+/// Consume a reader, adding all of its items to `stats`.
+/// Call it once for each dump of a month to gather that month's stats.
+pub fn collect(reader: DiscogsReader, stats: &mut DumpStats) -> Result<(), ReaderError> {
+    match reader {
+        DiscogsReader::Artists(artists) => {
+            for artist in *artists {
+                stats.add_artist(&artist?);
+            }
+        }
+        DiscogsReader::Labels(labels) => {
+            for label in *labels {
+                stats.add_label(&label?);
+            }
+        }
+        DiscogsReader::Masters(masters) => {
+            for master in *masters {
+                stats.add_master(&master?);
+            }
+        }
+        DiscogsReader::Releases(releases) => {
+            for release in *releases {
+                stats.add_release(&release?);
+            }
+        }
+        DiscogsReader::Custom(_) => {}
+    }
+    Ok(())
+}
+
+/// The change in a count between two months.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Growth {
+    pub before: u64,
+    pub after: u64,
+}
+
+impl Growth {
+    pub fn new(before: u64, after: u64) -> Self {
+        Self { before, after }
+    }
+
+    pub fn change(&self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+
+    /// The change as a percentage of the earlier count, or None if it was zero.
+    pub fn percent(&self) -> Option<f64> {
+        if self.before == 0 {
+            None
+        } else {
+            Some(self.change() as f64 / self.before as f64 * 100.0)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntityGrowth {
+    pub count: Growth,
+    /// The number of IDs allocated between the two dumps, based on the highest ID seen.
+    pub new_ids: u64,
+    pub images: Growth,
+}
+
+impl EntityGrowth {
+    fn new(a: &EntityStats, b: &EntityStats) -> Self {
+        Self {
+            count: Growth::new(a.count, b.count),
+            new_ids: b.max_id.saturating_sub(a.max_id),
+            images: Growth::new(a.images, b.images),
+        }
+    }
+}
+
+/// Growth metrics between two months of dumps, as produced by [`compare`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatsComparison {
+    pub artists: EntityGrowth,
+    pub labels: EntityGrowth,
+    pub masters: EntityGrowth,
+    pub releases: EntityGrowth,
+    pub images: Growth,
+    pub releases_by_country: BTreeMap<String, Growth>,
+    pub releases_by_genre: BTreeMap<String, Growth>,
+}
+
+/// Compare the stats of an earlier month `a` with those of a later month `b`.
+pub fn compare(a: &DumpStats, b: &DumpStats) -> StatsComparison {
+    StatsComparison {
+        artists: EntityGrowth::new(&a.artists, &b.artists),
+        labels: EntityGrowth::new(&a.labels, &b.labels),
+        masters: EntityGrowth::new(&a.masters, &b.masters),
+        releases: EntityGrowth::new(&a.releases, &b.releases),
+        images: Growth::new(a.images(), b.images()),
+        releases_by_country: compare_counts(&a.releases_by_country, &b.releases_by_country),
+        releases_by_genre: compare_counts(&a.releases_by_genre, &b.releases_by_genre),
+    }
+}
+
+fn compare_counts(
+    a: &BTreeMap<String, u64>,
+    b: &BTreeMap<String, u64>,
+) -> BTreeMap<String, Growth> {
+    let mut growth = BTreeMap::new();
+    for key in a.keys().chain(b.keys()) {
+        growth.entry(key.clone()).or_insert_with(|| {
+            let before = a.get(key).copied().unwrap_or_default();
+            let after = b.get(key).copied().unwrap_or_default();
+            Growth::new(before, after)
+        });
+    }
+    growth
+}
+
+/// The default duration above which a track is reported as an outlier.
+pub const MAX_TRACK_SECS: u32 = 4 * 60 * 60;
+const MAX_OUTLIER_SAMPLES: usize = 1000;
+const HISTOGRAM_MINUTES: usize = 60;
+
+/// Gathers track duration distributions per style and format and flags
+/// durations that are probably data errors.
+#[derive(Clone, Debug)]
+pub struct DurationAnalysis {
+    max_secs: u32,
+    max_samples: usize,
+    report: DurationReport,
+}
+
+impl Default for DurationAnalysis {
+    fn default() -> Self {
+        Self {
+            max_secs: MAX_TRACK_SECS,
+            max_samples: MAX_OUTLIER_SAMPLES,
+            report: DurationReport::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DurationReport {
+    pub all: DurationDistribution,
+    pub by_style: BTreeMap<String, DurationDistribution>,
+    pub by_format: BTreeMap<String, DurationDistribution>,
+    /// The number of tracks with a duration that couldn't be parsed.
+    pub unparsed: u64,
+    /// The number of tracks flagged as outliers, which may exceed the number of samples.
+    pub outlier_count: u64,
+    pub outliers: Vec<DurationOutlier>,
+}
+
+/// Durations of tracks without a style or format are only counted in [`DurationReport::all`].
+/// Outliers aren't included in any distribution.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DurationDistribution {
+    pub count: u64,
+    pub total_secs: u64,
+    pub min_secs: u32,
+    pub max_secs: u32,
+    /// Track counts by whole minute, with the last bucket holding everything longer.
+    pub histogram: Vec<u64>,
+}
+
+impl Default for DurationDistribution {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            total_secs: 0,
+            min_secs: u32::MAX,
+            max_secs: 0,
+            histogram: vec![0; HISTOGRAM_MINUTES + 1],
+        }
+    }
+}
+
+impl DurationDistribution {
+    fn add(&mut self, secs: u32) {
+        self.count += 1;
+        self.total_secs += secs as u64;
+        self.min_secs = self.min_secs.min(secs);
+        self.max_secs = self.max_secs.max(secs);
+        let bucket = (secs as usize / 60).min(HISTOGRAM_MINUTES);
+        self.histogram[bucket] += 1;
+    }
+
+    pub fn mean_secs(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total_secs as f64 / self.count as f64)
+    }
+
+    /// The minute bucket containing the median duration.
+    pub fn median_minute(&self) -> Option<usize> {
+        let mut remaining = self.count.div_ceil(2);
+        for (minute, count) in self.histogram.iter().enumerate() {
+            if remaining <= *count {
+                return Some(minute);
+            }
+            remaining -= count;
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DurationOutlier {
+    pub release_id: ReleaseId,
+    pub position: String,
+    pub duration: String,
+    pub kind: OutlierKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutlierKind {
+    Zero,
+    TooLong,
+}
+
+impl DurationAnalysis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag tracks longer than `secs` as outliers.
+    pub fn max_secs(mut self, secs: u32) -> Self {
+        self.max_secs = secs;
+        self
+    }
+
+    /// Keep at most `n` outliers in the report.
+    pub fn max_samples(mut self, n: usize) -> Self {
+        self.max_samples = n;
+        self
+    }
+
+    /// Add the durations of the tracks of `release`. A release with several of the
+    /// same format, like a 2xLP, counts its tracks once for that format.
+    pub fn add_release(&mut self, release: &Release) {
+        let report = &mut self.report;
+        let formats: BTreeSet<&str> = release.formats.iter().map(|f| &*f.name).collect();
+        for track in &release.tracklist {
+            let Some(duration) = track.duration.as_deref().filter(|d| !d.is_empty()) else {
+                continue;
+            };
+            let Some(secs) = TrackDuration::parse(duration).map(|d| d.secs()) else {
+                report.unparsed += 1;
+                continue;
+            };
+            let kind = if secs == 0 {
+                Some(OutlierKind::Zero)
+            } else if secs > self.max_secs {
+                Some(OutlierKind::TooLong)
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                report.outlier_count += 1;
+                if report.outliers.len() < self.max_samples {
+                    report.outliers.push(DurationOutlier {
+                        release_id: release.id,
+                        position: track.position.clone(),
+                        duration: duration.to_string(),
+                        kind,
+                    });
+                }
+                continue;
+            }
+            report.all.add(secs);
+            for style in &release.styles {
+                report
+                    .by_style
+                    .entry(style.to_string())
+                    .or_default()
+                    .add(secs);
+            }
+            for format in &formats {
+                report
+                    .by_format
+                    .entry(format.to_string())
+                    .or_default()
+                    .add(secs);
+            }
+        }
+    }
+
+    pub fn report(&self) -> &DurationReport {
+        &self.report
+    }
+
+    pub fn finish(self) -> DurationReport {
+        self.report
+    }
+}
+
+/// A writing system detected in titles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Georgian,
+    Hangul,
+    Kana,
+    Han,
+    Other,
+}
+
+impl Script {
+    /// The script of a character, or None for characters shared between scripts,
+    /// such as digits, punctuation and whitespace.
+    pub fn of(c: char) -> Option<Script> {
+        if !c.is_alphabetic() {
+            return None;
+        }
+        let script = match c as u32 {
+            0x0041..=0x024F | 0x1E00..=0x1EFF | 0x2C60..=0x2C7F | 0xA720..=0xA7FF => Script::Latin,
+            0xFF21..=0xFF3A | 0xFF41..=0xFF5A => Script::Latin,
+            0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x0400..=0x052F | 0x1C80..=0x1C8F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => {
+                Script::Cyrillic
+            }
+            0x0530..=0x058F => Script::Armenian,
+            0x0590..=0x05FF | 0xFB1D..=0xFB4F => Script::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF => Script::Arabic,
+            0xFE70..=0xFEFF => Script::Arabic,
+            0x0900..=0x097F => Script::Devanagari,
+            0x0E00..=0x0E7F => Script::Thai,
+            0x10A0..=0x10FF | 0x1C90..=0x1CBF => Script::Georgian,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3134F => Script::Han,
+            _ => Script::Other,
+        };
+        Some(script)
+    }
+
+    /// The scripts used in `text`.
+    pub fn detect(text: &str) -> BTreeSet<Script> {
+        text.chars().filter_map(Script::of).collect()
+    }
+
+    /// Whether the script is one of the Chinese, Japanese or Korean scripts.
+    pub fn is_cjk(&self) -> bool {
+        matches!(self, Script::Han | Script::Kana | Script::Hangul)
+    }
+}
+
+/// Counts of releases by the scripts used in their title and track titles.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScriptCounts {
+    pub releases: u64,
+    /// The number of releases using each script. A release can use several.
+    pub scripts: BTreeMap<Script, u64>,
+    /// The number of releases using more than one script.
+    pub mixed: u64,
+}
+
+impl ScriptCounts {
+    fn add(&mut self, scripts: &BTreeSet<Script>) {
+        self.releases += 1;
+        for script in scripts {
+            *self.scripts.entry(*script).or_default() += 1;
+        }
+        if scripts.len() > 1 {
+            self.mixed += 1;
+        }
+    }
+}
+
+/// Gathers the scripts used in release and track titles, overall and per label and country.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScriptAnalysis {
+    pub all: ScriptCounts,
+    pub by_label: BTreeMap<String, ScriptCounts>,
+    pub by_country: BTreeMap<String, ScriptCounts>,
+}
+
+impl ScriptAnalysis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The scripts used in the title and track titles of a release.
+    pub fn release_scripts(release: &Release) -> BTreeSet<Script> {
+        let mut scripts = Script::detect(&release.title);
+        for track in &release.tracklist {
+            scripts.extend(Script::detect(&track.title));
+        }
+        scripts
+    }
+
+    pub fn add_release(&mut self, release: &Release) {
+        let scripts = Self::release_scripts(release);
+        self.all.add(&scripts);
+        for label in &release.labels {
+            self.by_label
+                .entry(label.name.clone())
+                .or_default()
+                .add(&scripts);
+        }
+        self.by_country
+            .entry(release.country.to_string())
+            .or_default()
+            .add(&scripts);
+    }
+}
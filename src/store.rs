@@ -0,0 +1,282 @@
+//! Keeping records that are looked up often in memory.
+//!
+//! Looking up a record in a [`DumpIndex`] or a [`Cache`](crate::cache::Cache) reads
+//! and parses or deserializes it every time, which adds up when the same artists
+//! and labels are looked up over and over, e.g. while tagging a collection. A
+//! [`CachedStore`] wraps either and keeps the records it returns, evicting the least
+//! recently used once they're over its budget.
+//!
+//! The budget is a number of records by default. [`CachedStore::weigh_with`] sets a
+//! function that gives each record a weight instead, e.g. an estimate of its size.
+//!
+//! ```no_run
+//! use disco_quick::artist::Artist;
+//! use disco_quick::index::DumpIndex;
+//! use disco_quick::store::CachedStore;
+//! use std::time::Duration;
+//!
+//! # fn main() -> Result<(), disco_quick::index::IndexError> {
+//! let index = DumpIndex::open("artists.xml.gz".as_ref(), "artists.xml.gz.idx".as_ref())?;
+//! let artists = CachedStore::<_, Artist>::new(index, 10_000).ttl(Duration::from_secs(3600));
+//! if let Some(artist) = artists.get(1)? {
+//!     println!("{}", artist.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::Artist;
+use crate::index::{DumpIndex, IndexError};
+use crate::label::Label;
+use crate::master::Master;
+use crate::release::Release;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Something records of type `T` can be looked up in by ID.
+pub trait Store<T> {
+    type Error;
+
+    /// The record with `id`, or None if there isn't one.
+    fn fetch(&self, id: u32) -> Result<Option<T>, Self::Error>;
+}
+
+macro_rules! index_store {
+    ($t:ty, $get:ident) => {
+        impl Store<$t> for DumpIndex {
+            type Error = IndexError;
+
+            fn fetch(&self, id: u32) -> Result<Option<$t>, IndexError> {
+                self.$get(id)
+            }
+        }
+    };
+}
+
+index_store!(Artist, get_artist);
+index_store!(Label, get_label);
+index_store!(Master, get_master);
+index_store!(Release, get_release);
+
+impl<T: Clone> Store<T> for HashMap<u32, T> {
+    type Error = Infallible;
+
+    fn fetch(&self, id: u32) -> Result<Option<T>, Infallible> {
+        Ok(self.get(&id).cloned())
+    }
+}
+
+#[cfg(feature = "cache")]
+impl<T: crate::cache::Cached> Store<T> for crate::cache::Cache<T> {
+    type Error = crate::cache::CacheError;
+
+    fn fetch(&self, id: u32) -> Result<Option<T>, Self::Error> {
+        self.get(id)
+    }
+}
+
+/// The number of lookups a [`CachedStore`] answered from memory and from its store.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// The records dropped to keep within the budget.
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// The share of lookups answered from memory, from 0 to 1.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+struct Entry<T> {
+    record: Arc<T>,
+    weight: usize,
+    fetched: Instant,
+    /// The position of the entry in the order of use.
+    used: u64,
+}
+
+struct Lru<T> {
+    entries: HashMap<u32, Entry<T>>,
+    /// The IDs of the entries by when they were last used.
+    order: BTreeMap<u64, u32>,
+    clock: u64,
+    weight: usize,
+    stats: CacheStats,
+}
+
+impl<T> Lru<T> {
+    fn remove(&mut self, id: u32) -> Option<Entry<T>> {
+        let entry = self.entries.remove(&id)?;
+        self.order.remove(&entry.used);
+        self.weight -= entry.weight;
+        Some(entry)
+    }
+
+    fn touch(&mut self, id: u32) {
+        let Some(entry) = self.entries.get_mut(&id) else {
+            return;
+        };
+        self.clock += 1;
+        self.order.remove(&entry.used);
+        self.order.insert(self.clock, id);
+        entry.used = self.clock;
+    }
+}
+
+/// A [`Store`] that keeps the records it returns in memory.
+pub struct CachedStore<S, T> {
+    store: S,
+    budget: usize,
+    ttl: Option<Duration>,
+    weigh: fn(&T) -> usize,
+    lru: RefCell<Lru<T>>,
+}
+
+impl<S: Store<T>, T> CachedStore<S, T> {
+    /// Keep up to `budget` records from `store`.
+    pub fn new(store: S, budget: usize) -> Self {
+        Self {
+            store,
+            budget,
+            ttl: None,
+            weigh: |_| 1,
+            lru: RefCell::new(Lru {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                clock: 0,
+                weight: 0,
+                stats: CacheStats::default(),
+            }),
+        }
+    }
+
+    /// Look records up again once they've been kept for longer than `ttl`, e.g.
+    /// when the store can be replaced by a newer dump.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Count each record as `weigh(record)` against the budget instead of as 1.
+    /// Records heavier than the whole budget aren't kept.
+    pub fn weigh_with(mut self, weigh: fn(&T) -> usize) -> Self {
+        self.weigh = weigh;
+        self
+    }
+
+    /// The record with `id`, from memory if it's been looked up recently.
+    pub fn get(&self, id: u32) -> Result<Option<Arc<T>>, S::Error> {
+        let mut lru = self.lru.borrow_mut();
+        match lru.entries.get(&id) {
+            Some(entry) if !self.expired(entry) => {
+                let record = entry.record.clone();
+                lru.stats.hits += 1;
+                lru.touch(id);
+                return Ok(Some(record));
+            }
+            Some(_) => {
+                lru.remove(id);
+            }
+            None => {}
+        }
+        lru.stats.misses += 1;
+        // Don't hold the borrow while the store is read, in case it panics
+        drop(lru);
+        let Some(record) = self.store.fetch(id)? else {
+            return Ok(None);
+        };
+        let record = Arc::new(record);
+        self.insert(id, record.clone());
+        Ok(Some(record))
+    }
+
+    /// Forget the record with `id`, so it's read from the store when it's next
+    /// looked up.
+    pub fn invalidate(&self, id: u32) {
+        self.lru.borrow_mut().remove(id);
+    }
+
+    /// Forget all records. The stats are kept.
+    pub fn clear(&self) {
+        let mut lru = self.lru.borrow_mut();
+        lru.entries.clear();
+        lru.order.clear();
+        lru.weight = 0;
+    }
+
+    /// The number of records in memory.
+    pub fn len(&self) -> usize {
+        self.lru.borrow().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lru.borrow().entries.is_empty()
+    }
+
+    /// The total weight of the records in memory.
+    pub fn weight(&self) -> usize {
+        self.lru.borrow().weight
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.lru.borrow().stats
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    fn expired(&self, entry: &Entry<T>) -> bool {
+        self.ttl.is_some_and(|ttl| entry.fetched.elapsed() > ttl)
+    }
+
+    fn insert(&self, id: u32, record: Arc<T>) {
+        let weight = (self.weigh)(&record);
+        if weight > self.budget {
+            return;
+        }
+        let mut lru = self.lru.borrow_mut();
+        lru.remove(id);
+        while lru.weight + weight > self.budget {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.remove(oldest);
+            lru.stats.evictions += 1;
+        }
+        lru.clock += 1;
+        let used = lru.clock;
+        lru.order.insert(used, id);
+        lru.entries.insert(
+            id,
+            Entry {
+                record,
+                weight,
+                fetched: Instant::now(),
+                used,
+            },
+        );
+        lru.weight += weight;
+    }
+}
+
+impl<S: Store<T>, T> Store<Arc<T>> for CachedStore<S, T> {
+    type Error = S::Error;
+
+    fn fetch(&self, id: u32) -> Result<Option<Arc<T>>, S::Error> {
+        self.get(id)
+    }
+}
@@ -0,0 +1,290 @@
+//! Reading dumps in async code as streams of records.
+//!
+//! The readers here are the async counterparts of [`DiscogsReader`](crate::DiscogsReader)
+//! and [`EntityReader`](crate::reader::EntityReader), built on tokio's `AsyncBufRead`.
+//! Gzipped dumps are decompressed with `async-compression`. Parsing itself is still
+//! synchronous, but it never blocks on IO, so a stream can be polled on the runtime
+//! without a blocking thread.
+//!
+//! The records are read by the same code as [`EntityReader`](crate::reader::EntityReader),
+//! so the parse-time settings of [`ReaderOptions`], sampling, warnings and the error
+//! policy are applied. Filters, progress reports and throughput limits aren't, and
+//! custom readers aren't supported.
+//!
+//! ```no_run
+//! use disco_quick::stream::AsyncDiscogsReader;
+//! use futures_util::StreamExt;
+//!
+//! # async fn run() -> Result<(), disco_quick::ReaderError> {
+//! let reader = AsyncDiscogsReader::from_path("releases.xml.gz".as_ref()).await?;
+//! let mut entities = std::pin::pin!(reader.into_stream());
+//! while let Some(entity) = entities.next().await {
+//!     println!("{}", entity?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::entity::Entity;
+use crate::options::ReaderOptions;
+use crate::parser::{ArtistParser, LabelParser, MasterParser, Parser, ReleaseParser};
+use crate::prolog::{self, Prefix};
+use crate::reader::{ReaderError, RecordState, Step};
+use crate::shared::EntityType;
+use crate::warning::ParseWarning;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures_util::stream::{self, Stream};
+use quick_xml::name::QName;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+pub type AsyncXmlReader = quick_xml::Reader<Box<dyn AsyncBufRead + Send + Unpin>>;
+
+/// Feeds the events of a dump to a [`Parser`] and yields its items asynchronously.
+pub struct AsyncEntityReader<P: Parser> {
+    buf: Vec<u8>,
+    reader: AsyncXmlReader,
+    state: RecordState<P>,
+    finished: bool,
+    /// The buffer used when skipping elements.
+    skip_buf: Vec<u8>,
+}
+
+pub type AsyncArtistsReader = AsyncEntityReader<ArtistParser>;
+pub type AsyncLabelsReader = AsyncEntityReader<LabelParser>;
+pub type AsyncMastersReader = AsyncEntityReader<MasterParser>;
+pub type AsyncReleasesReader = AsyncEntityReader<ReleaseParser>;
+
+impl<P: Parser> AsyncEntityReader<P> {
+    /// Create a reader from an XML reader that's positioned after the root start tag.
+    pub fn with_options(
+        mut reader: AsyncXmlReader,
+        mut buf: Vec<u8>,
+        options: ReaderOptions,
+    ) -> Self {
+        RecordState::<P>::configure(&options, &mut reader, &mut buf);
+        Self {
+            buf,
+            reader,
+            state: RecordState::new(options),
+            finished: false,
+            skip_buf: Vec::new(),
+        }
+    }
+
+    /// Read the next item, or None at the end of the dump or after an error that
+    /// isn't skipped.
+    pub async fn next_item(&mut self) -> Option<Result<P::Item, ReaderError>> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            self.buf.clear();
+            let ev = match self.reader.read_event_into_async(&mut self.buf).await {
+                Ok(ev) => ev,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e.into()));
+                }
+            };
+            let offset = self.reader.buffer_position() as u64;
+            match self.state.event(ev, offset) {
+                Step::Next => {}
+                Step::Skip => {
+                    let name = QName(&self.state.skip_name);
+                    let result = self
+                        .reader
+                        .read_to_end_into_async(name, &mut self.skip_buf)
+                        .await;
+                    self.skip_buf.clear();
+                    if let Err(e) = result {
+                        self.finished = true;
+                        return Some(Err(e.into()));
+                    }
+                }
+                Step::Item(item) => return Some(Ok(item)),
+                Step::Error(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                Step::Stop => {
+                    self.finished = true;
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// The warnings collected so far if [`ReaderOptions::collect_warnings`] is set.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.state.warnings
+    }
+
+    /// Remove and return the warnings collected so far.
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        std::mem::take(&mut self.state.warnings)
+    }
+
+    /// The items of the dump as a stream.
+    pub fn into_stream(self) -> impl Stream<Item = Result<P::Item, ReaderError>> {
+        stream::unfold(self, |mut reader| async move {
+            let item = reader.next_item().await?;
+            Some((item, reader))
+        })
+    }
+}
+
+/// The async counterpart of [`DiscogsReader`](crate::DiscogsReader).
+pub enum AsyncDiscogsReader {
+    Artists(Box<AsyncArtistsReader>),
+    Labels(Box<AsyncLabelsReader>),
+    Masters(Box<AsyncMastersReader>),
+    Releases(Box<AsyncReleasesReader>),
+}
+
+impl AsyncDiscogsReader {
+    pub async fn from_path(path: &Path) -> Result<AsyncDiscogsReader, ReaderError> {
+        Self::from_path_with_options(path, ReaderOptions::default()).await
+    }
+
+    pub async fn from_path_with_options(
+        path: &Path,
+        options: ReaderOptions,
+    ) -> Result<AsyncDiscogsReader, ReaderError> {
+        let file = BufReader::new(File::open(path).await?);
+        Self::from_reader_with_options(file, None, options).await
+    }
+
+    /// Read a dump from any async source. Gzipped input is detected and decompressed.
+    /// If `entity_type` is None it's detected from the root tag, otherwise the root
+    /// tag isn't checked.
+    pub async fn from_reader(
+        reader: impl AsyncBufRead + Send + Unpin + 'static,
+        entity_type: Option<EntityType>,
+    ) -> Result<AsyncDiscogsReader, ReaderError> {
+        Self::from_reader_with_options(reader, entity_type, ReaderOptions::default()).await
+    }
+
+    pub async fn from_reader_with_options(
+        reader: impl AsyncBufRead + Send + Unpin + 'static,
+        entity_type: Option<EntityType>,
+        options: ReaderOptions,
+    ) -> Result<AsyncDiscogsReader, ReaderError> {
+        let (gzip, reader) = peek_gzip(reader).await?;
+        let reader: Box<dyn AsyncBufRead + Send + Unpin> = if gzip {
+            let mut gz = GzipDecoder::new(reader);
+            // Dumps written by `index::write_seekable` have a gzip member every few records
+            gz.multiple_members(true);
+            Box::new(BufReader::new(gz))
+        } else {
+            reader
+        };
+        let mut xml_reader = quick_xml::Reader::from_reader(reader);
+        let mut buf = options.buffer();
+        // The same as `prolog::read_start_tag`, without recording what's skipped
+        xml_reader.check_end_names(false);
+        let mut errors = 0;
+        let (start_event, recovered) = loop {
+            buf.clear();
+            match xml_reader.read_event_into_async(&mut buf).await {
+                Ok(ev) => match prolog::classify(ev) {
+                    Prefix::Root(start, junk) => {
+                        break (start.into_owned(), junk.is_some());
+                    }
+                    Prefix::Skip(_) => continue,
+                    Prefix::Eof => return Err(ReaderError::NoStartTag),
+                },
+                Err(_) if errors < prolog::MAX_ERRORS => errors += 1,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        xml_reader.check_end_names(!recovered);
+        let tag = match entity_type {
+            Some(entity_type) => entity_type.root_tag().as_bytes().to_vec(),
+            None => start_event.name().as_ref().to_vec(),
+        };
+        let reader = match tag.as_slice() {
+            b"artists" => AsyncDiscogsReader::Artists(Box::new(AsyncArtistsReader::with_options(
+                xml_reader, buf, options,
+            ))),
+            b"labels" => AsyncDiscogsReader::Labels(Box::new(AsyncLabelsReader::with_options(
+                xml_reader, buf, options,
+            ))),
+            b"masters" => AsyncDiscogsReader::Masters(Box::new(AsyncMastersReader::with_options(
+                xml_reader, buf, options,
+            ))),
+            b"releases" => AsyncDiscogsReader::Releases(Box::new(
+                AsyncReleasesReader::with_options(xml_reader, buf, options),
+            )),
+            _ => return Err(ReaderError::InvalidStartTag),
+        };
+        Ok(reader)
+    }
+
+    pub fn entity_type(&self) -> EntityType {
+        match self {
+            AsyncDiscogsReader::Artists(_) => EntityType::Artist,
+            AsyncDiscogsReader::Labels(_) => EntityType::Label,
+            AsyncDiscogsReader::Masters(_) => EntityType::Master,
+            AsyncDiscogsReader::Releases(_) => EntityType::Release,
+        }
+    }
+
+    pub async fn next_entity(&mut self) -> Option<Result<Entity, ReaderError>> {
+        Some(match self {
+            AsyncDiscogsReader::Artists(r) => r.next_item().await?.map(Entity::Artist),
+            AsyncDiscogsReader::Labels(r) => r.next_item().await?.map(Entity::Label),
+            AsyncDiscogsReader::Masters(r) => r.next_item().await?.map(Entity::Master),
+            AsyncDiscogsReader::Releases(r) => r.next_item().await?.map(Entity::Release),
+        })
+    }
+
+    /// The records of the dump as a stream. Use the readers of the variants for
+    /// streams of a single record type.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Entity, ReaderError>> {
+        stream::unfold(self, |mut reader| async move {
+            let entity = reader.next_entity().await?;
+            Some((entity, reader))
+        })
+    }
+}
+
+/// Whether `reader` starts with the gzip magic, and a reader of all its bytes, like
+/// the synchronous `peek_gzip` of the reader module.
+async fn peek_gzip(
+    mut reader: impl AsyncBufRead + Send + Unpin + 'static,
+) -> io::Result<(bool, Box<dyn AsyncBufRead + Send + Unpin>)> {
+    let buf = reader.fill_buf().await?;
+    if buf.len() >= GZIP_MAGIC.len() {
+        let gzip = buf.starts_with(&GZIP_MAGIC);
+        return Ok((gzip, Box::new(reader)));
+    }
+    let mut start = Vec::with_capacity(GZIP_MAGIC.len());
+    while start.len() < GZIP_MAGIC.len() {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            break;
+        }
+        let n = buf.len().min(GZIP_MAGIC.len() - start.len());
+        start.extend_from_slice(&buf[..n]);
+        reader.consume(n);
+    }
+    let gzip = start == GZIP_MAGIC;
+    Ok((gzip, Box::new(io::Cursor::new(start).chain(reader))))
+}
+
+impl fmt::Display for AsyncDiscogsReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match &self {
+            AsyncDiscogsReader::Artists(_) => "artists",
+            AsyncDiscogsReader::Labels(_) => "labels",
+            AsyncDiscogsReader::Masters(_) => "masters",
+            AsyncDiscogsReader::Releases(_) => "releases",
+        };
+        write!(f, "{name}")
+    }
+}
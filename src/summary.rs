@@ -0,0 +1,204 @@
+//! Compact output for logging records.
+//!
+//! The derived [`Debug`] of a record prints every field, which for a release with
+//! long notes and a big tracklist can be tens of KB. Wrapping a record in
+//! [`SummaryDebug`] prints the same structure with long text truncated and lists of
+//! images, videos, tracks and the like reduced to their length.
+use crate::artist::Artist;
+use crate::artist_credit::get_credit_string;
+use crate::label::Label;
+use crate::master::Master;
+use crate::release::Release;
+use crate::shared::EntityType;
+use std::fmt;
+
+/// The most characters of a text field that are printed.
+pub const MAX_TEXT_CHARS: usize = 80;
+
+/// A record that is printed compactly by [`Debug`] and [`Display`](fmt::Display).
+#[derive(Clone, Copy)]
+pub struct SummaryDebug<'a, T: ?Sized>(pub &'a T);
+
+/// Records that can be printed compactly.
+pub trait Summary {
+    /// Wrap the record for compact printing, e.g. `debug!("{:?}", release.summary())`.
+    fn summary(&self) -> SummaryDebug<'_, Self> {
+        SummaryDebug(self)
+    }
+}
+
+impl Summary for Artist {}
+impl Summary for Label {}
+impl Summary for Master {}
+impl Summary for Release {}
+
+/// A text field that is cut short after [`MAX_TEXT_CHARS`].
+struct Truncated<'a>(&'a str);
+
+impl fmt::Debug for Truncated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.char_indices().nth(MAX_TEXT_CHARS) {
+            Some((end, _)) => {
+                let rest = self.0[end..].chars().count();
+                write!(f, "{:?}... ({rest} more chars)", &self.0[..end])
+            }
+            None => fmt::Debug::fmt(self.0, f),
+        }
+    }
+}
+
+/// A list that is printed as its length.
+struct Elided(usize);
+
+impl fmt::Debug for Elided {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{} elided]", self.0)
+    }
+}
+
+fn truncated(text: &Option<String>) -> Option<Truncated<'_>> {
+    text.as_deref().map(Truncated)
+}
+
+impl fmt::Debug for SummaryDebug<'_, Artist> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let a = self.0;
+        f.debug_struct("Artist")
+            .field("id", &a.id)
+            .field("name", &Truncated(&a.name))
+            .field("real_name", &truncated(&a.real_name))
+            .field("profile", &truncated(&a.profile))
+            .field("data_quality", &a.data_quality)
+            .field("name_variations", &Elided(a.name_variations.len()))
+            .field("urls", &Elided(a.urls.len()))
+            .field("aliases", &Elided(a.aliases.len()))
+            .field("members", &Elided(a.members.len()))
+            .field("groups", &Elided(a.groups.len()))
+            .field("images", &Elided(a.images.len()))
+            .finish()
+    }
+}
+
+impl fmt::Debug for SummaryDebug<'_, Label> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let l = self.0;
+        f.debug_struct("Label")
+            .field("id", &l.id)
+            .field("name", &Truncated(&l.name))
+            .field("contactinfo", &truncated(&l.contactinfo))
+            .field("profile", &truncated(&l.profile))
+            .field("parent_label", &l.parent_label)
+            .field("sublabels", &Elided(l.sublabels.len()))
+            .field("urls", &Elided(l.urls.len()))
+            .field("data_quality", &l.data_quality)
+            .field("images", &Elided(l.images.len()))
+            .finish()
+    }
+}
+
+impl fmt::Debug for SummaryDebug<'_, Master> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = self.0;
+        f.debug_struct("Master")
+            .field("id", &m.id)
+            .field("title", &Truncated(&m.title))
+            .field("artists", &Truncated(&get_credit_string(&m.artists)))
+            .field("main_release", &m.main_release)
+            .field("year", &m.year)
+            .field("notes", &truncated(&m.notes))
+            .field("genres", &m.genres)
+            .field("styles", &m.styles)
+            .field("data_quality", &m.data_quality)
+            .field("images", &Elided(m.images.len()))
+            .field("videos", &Elided(m.videos.len()))
+            .finish()
+    }
+}
+
+impl fmt::Debug for SummaryDebug<'_, Release> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let r = self.0;
+        f.debug_struct("Release")
+            .field("id", &r.id)
+            .field("status", &r.status)
+            .field("title", &Truncated(&r.title))
+            .field("artists", &Truncated(&get_credit_string(&r.artists)))
+            .field("country", &r.country)
+            .field("released", &r.released)
+            .field("notes", &truncated(&r.notes))
+            .field("genres", &r.genres)
+            .field("styles", &r.styles)
+            .field("master_id", &r.master_id)
+            .field("is_main_release", &r.is_main_release)
+            .field("data_quality", &r.data_quality)
+            .field("labels", &Elided(r.labels.len()))
+            .field("images", &Elided(r.images.len()))
+            .field("videos", &Elided(r.videos.len()))
+            .field("extraartists", &Elided(r.extraartists.len()))
+            .field("tracklist", &Elided(r.tracklist.len()))
+            .field("formats", &Elided(r.formats.len()))
+            .field("companies", &Elided(r.companies.len()))
+            .field("identifiers", &Elided(r.identifiers.len()))
+            .finish()
+    }
+}
+
+/// The kind and ID of the record followed by its usual [`Display`](fmt::Display),
+/// truncated, e.g. `release 42: Artist - Title`.
+impl<T> fmt::Display for SummaryDebug<'_, T>
+where
+    T: fmt::Display + Identified,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (kind, id) = self.0.kind_and_id();
+        let text = self.0.to_string();
+        match text.char_indices().nth(MAX_TEXT_CHARS) {
+            Some((end, _)) => write!(f, "{kind} {id}: {}...", &text[..end]),
+            None => write!(f, "{kind} {id}: {text}"),
+        }
+    }
+}
+
+/// Records with an ID, for the [`Display`](fmt::Display) of [`SummaryDebug`] and
+/// [`DiscogsEntity`](crate::entity::DiscogsEntity).
+pub trait Identified {
+    const ENTITY_TYPE: EntityType;
+
+    fn id(&self) -> u32;
+
+    fn kind_and_id(&self) -> (&'static str, i64) {
+        (Self::ENTITY_TYPE.record_tag(), self.id().into())
+    }
+}
+
+impl Identified for Artist {
+    const ENTITY_TYPE: EntityType = EntityType::Artist;
+
+    fn id(&self) -> u32 {
+        self.id.get()
+    }
+}
+
+impl Identified for Label {
+    const ENTITY_TYPE: EntityType = EntityType::Label;
+
+    fn id(&self) -> u32 {
+        self.id.get()
+    }
+}
+
+impl Identified for Master {
+    const ENTITY_TYPE: EntityType = EntityType::Master;
+
+    fn id(&self) -> u32 {
+        self.id.get()
+    }
+}
+
+impl Identified for Release {
+    const ENTITY_TYPE: EntityType = EntityType::Release;
+
+    fn id(&self) -> u32 {
+        self.id.get()
+    }
+}
@@ -0,0 +1,139 @@
+//! Extra data about artists, labels, masters or releases from CSV files keyed by
+//! their Discogs IDs, such as community datasets.
+//!
+//! A [`Supplement`] holds the rows of such a file by ID. The exporters can join it
+//! onto the records they write:
+//! [`CsvExport::supplement`](crate::export::csv::CsvExport::supplement) adds its
+//! columns to the entity's table, and `JsonlOptions::supplement` adds them as fields
+//! with the `jsonl` feature.
+use crate::options::ReaderOptions;
+use crate::reader::open_dump;
+use crate::util::{CsvError, CsvRecords};
+use log::warn;
+use std::collections::HashMap;
+use std::io::{BufRead, Error as IoError};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SupplementError {
+    #[error(transparent)]
+    IoError(#[from] IoError),
+    #[error(transparent)]
+    Csv(#[from] CsvError),
+    #[error("The CSV has no column named {0:?}")]
+    MissingColumn(String),
+    #[error("Invalid ID {value:?} on record {record}")]
+    InvalidId { record: usize, value: String },
+    #[error("Invalid value {value:?} in column {column:?} for ID {id}")]
+    InvalidValue {
+        id: u32,
+        column: String,
+        value: String,
+    },
+}
+
+/// The rows of a CSV file by the Discogs ID in one of its columns.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Supplement {
+    columns: Vec<String>,
+    rows: HashMap<u32, Vec<String>>,
+}
+
+impl Supplement {
+    /// Read a CSV file, which may be gzipped, with the IDs in `id_column`.
+    pub fn from_path(path: &Path, id_column: &str) -> Result<Self, SupplementError> {
+        Self::from_reader(open_dump(path, &ReaderOptions::default())?, id_column)
+    }
+
+    /// Read CSV with a header row, keeping the rows by the ID in `id_column`. Rows
+    /// without an ID are skipped. If an ID appears more than once the last row is kept.
+    pub fn from_reader(reader: impl BufRead, id_column: &str) -> Result<Self, SupplementError> {
+        let mut records = CsvRecords::new(reader);
+        let mut headers = records.next_record()?.unwrap_or_default();
+        if let Some(first) = headers.first_mut() {
+            if let Some(stripped) = first.strip_prefix('\u{feff}') {
+                *first = stripped.to_string();
+            }
+        }
+        let id_index = headers
+            .iter()
+            .position(|h| h.trim() == id_column)
+            .ok_or_else(|| SupplementError::MissingColumn(id_column.to_string()))?;
+        headers.remove(id_index);
+
+        let mut rows = HashMap::new();
+        let mut record_number = 1;
+        while let Some(mut record) = records.next_record()? {
+            record_number += 1;
+            if id_index >= record.len() {
+                continue;
+            }
+            let value = record.remove(id_index);
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let id = value.parse().map_err(|_| SupplementError::InvalidId {
+                record: record_number,
+                value: value.to_string(),
+            })?;
+            record.resize(headers.len(), String::new());
+            if rows.insert(id, record).is_some() {
+                warn!("ID {id} appears more than once in the supplement, keeping the last row");
+            }
+        }
+        Ok(Self {
+            columns: headers,
+            rows,
+        })
+    }
+
+    /// The names of the columns, other than the ID.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The values of the row for `id`, in the order of [`columns`](Self::columns).
+    pub fn row(&self, id: u32) -> Option<&[String]> {
+        self.rows.get(&id).map(|r| r.as_slice())
+    }
+
+    /// The value of `column` for `id`.
+    pub fn get(&self, id: u32, column: &str) -> Option<&str> {
+        let index = self.columns.iter().position(|c| c == column)?;
+        self.rows.get(&id).map(|r| r[index].as_str())
+    }
+
+    /// The values of `column` by ID, parsed as `T`. Empty values are left out.
+    pub fn column<T: FromStr>(&self, column: &str) -> Result<HashMap<u32, T>, SupplementError> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| c == column)
+            .ok_or_else(|| SupplementError::MissingColumn(column.to_string()))?;
+        let mut values = HashMap::with_capacity(self.rows.len());
+        for (id, row) in &self.rows {
+            let value = row[index].trim();
+            if value.is_empty() {
+                continue;
+            }
+            let parsed = value.parse().map_err(|_| SupplementError::InvalidValue {
+                id: *id,
+                column: column.to_string(),
+                value: value.to_string(),
+            })?;
+            values.insert(*id, parsed);
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,143 @@
+//! Element names of the releases dump resolved to tokens, so the parsers of the
+//! most common elements can dispatch on a small integer instead of comparing
+//! names byte by byte.
+
+/// A known element name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum Tag {
+    Release,
+    Title,
+    Country,
+    Released,
+    Notes,
+    Genres,
+    Styles,
+    MasterId,
+    DataQuality,
+    Labels,
+    Videos,
+    Artists,
+    ExtraArtists,
+    TrackList,
+    Formats,
+    Identifiers,
+    Companies,
+    Images,
+    Track,
+    Position,
+    Duration,
+    SubTracks,
+    Artist,
+    Id,
+    Name,
+    Anv,
+    Join,
+    Role,
+    Tracks,
+}
+
+impl Tag {
+    pub(crate) const ALL: [Tag; 29] = [
+        Tag::Release,
+        Tag::Title,
+        Tag::Country,
+        Tag::Released,
+        Tag::Notes,
+        Tag::Genres,
+        Tag::Styles,
+        Tag::MasterId,
+        Tag::DataQuality,
+        Tag::Labels,
+        Tag::Videos,
+        Tag::Artists,
+        Tag::ExtraArtists,
+        Tag::TrackList,
+        Tag::Formats,
+        Tag::Identifiers,
+        Tag::Companies,
+        Tag::Images,
+        Tag::Track,
+        Tag::Position,
+        Tag::Duration,
+        Tag::SubTracks,
+        Tag::Artist,
+        Tag::Id,
+        Tag::Name,
+        Tag::Anv,
+        Tag::Join,
+        Tag::Role,
+        Tag::Tracks,
+    ];
+
+    pub(crate) const fn name(self) -> &'static [u8] {
+        match self {
+            Tag::Release => b"release",
+            Tag::Title => b"title",
+            Tag::Country => b"country",
+            Tag::Released => b"released",
+            Tag::Notes => b"notes",
+            Tag::Genres => b"genres",
+            Tag::Styles => b"styles",
+            Tag::MasterId => b"master_id",
+            Tag::DataQuality => b"data_quality",
+            Tag::Labels => b"labels",
+            Tag::Videos => b"videos",
+            Tag::Artists => b"artists",
+            Tag::ExtraArtists => b"extraartists",
+            Tag::TrackList => b"tracklist",
+            Tag::Formats => b"formats",
+            Tag::Identifiers => b"identifiers",
+            Tag::Companies => b"companies",
+            Tag::Images => b"images",
+            Tag::Track => b"track",
+            Tag::Position => b"position",
+            Tag::Duration => b"duration",
+            Tag::SubTracks => b"sub_tracks",
+            Tag::Artist => b"artist",
+            Tag::Id => b"id",
+            Tag::Name => b"name",
+            Tag::Anv => b"anv",
+            Tag::Join => b"join",
+            Tag::Role => b"role",
+            Tag::Tracks => b"tracks",
+        }
+    }
+
+    /// Resolve an element name, or None if it isn't known.
+    #[inline]
+    pub(crate) fn of(name: &[u8]) -> Option<Tag> {
+        match TABLE[slot(name)] {
+            Some(tag) if tag.name() == name => Some(tag),
+            _ => None,
+        }
+    }
+}
+
+const SLOTS: usize = 64;
+
+/// A perfect hash of the known names, using their length and first and last bytes.
+/// The factors were chosen so no two names share a slot, which is checked when
+/// the table is built.
+const fn slot(name: &[u8]) -> usize {
+    match name {
+        [] => 0,
+        [first, .., last] => (name.len() * 27 + *first as usize * 3 + *last as usize) % SLOTS,
+        [only] => (27 + *only as usize * 4) % SLOTS,
+    }
+}
+
+const TABLE: [Option<Tag>; SLOTS] = {
+    let mut table = [None; SLOTS];
+    let mut i = 0;
+    while i < Tag::ALL.len() {
+        let tag = Tag::ALL[i];
+        let slot = slot(tag.name());
+        if table[slot].is_some() {
+            panic!("Two tags have the same slot");
+        }
+        table[slot] = Some(tag);
+        i += 1;
+    }
+    table
+};
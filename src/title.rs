@@ -0,0 +1,185 @@
+//! Titles of releases that tell their versions apart.
+//!
+//! The versions of a master often share a title, so a title alone collides in search
+//! indexes. Following the way Discogs lists versions, a [`CanonicalTitle`] appends
+//! the formats of a release to its title, e.g. `Stockholm (2×Vinyl, 12", 33 ⅓ RPM)`,
+//! and optionally the free text of its formats, e.g. `Deluxe Edition`. Parts of the
+//! free text that describe the packaging or the condition of a copy, like
+//! `Gatefold Sleeve` or `Sealed`, are left out, as they don't distinguish editions.
+//!
+//! ```
+//! use disco_quick::release::{Release, ReleaseFormat};
+//!
+//! let release = Release {
+//!     title: "Stockholm ".into(),
+//!     formats: vec![ReleaseFormat {
+//!         qty: "2".into(),
+//!         name: "Vinyl".into(),
+//!         text: Some("Deluxe Edition, Gatefold".into()),
+//!         descriptions: vec!["LP".into(), "Album".into()],
+//!     }],
+//!     ..Default::default()
+//! };
+//! assert_eq!(release.canonical_title(), "Stockholm (2×Vinyl, LP, Album)");
+//! let title = disco_quick::title::CanonicalTitle::new().edition(true);
+//! assert_eq!(title.build(&release), "Stockholm (2×Vinyl, LP, Album; Deluxe Edition)");
+//! ```
+use crate::release::{Release, ReleaseFormat};
+use std::collections::HashMap;
+
+/// Words in the free text of a format that describe packaging or condition.
+const PACKAGING: &[&str] = &[
+    "box",
+    "boxed",
+    "cover",
+    "digipak",
+    "digipack",
+    "gatefold",
+    "insert",
+    "jacket",
+    "jewel",
+    "obi",
+    "poster",
+    "sealed",
+    "shrink",
+    "shrinkwrap",
+    "sleeve",
+    "slipcase",
+    "slipcover",
+    "sticker",
+    "unsealed",
+];
+
+/// Builds the canonical titles of releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CanonicalTitle {
+    formats: bool,
+    edition: bool,
+}
+
+impl Default for CanonicalTitle {
+    fn default() -> Self {
+        Self {
+            formats: true,
+            edition: false,
+        }
+    }
+}
+
+impl CanonicalTitle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to append the formats. On by default.
+    pub fn formats(mut self, formats: bool) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Whether to append the free text of the formats, without the parts about
+    /// packaging or condition. Off by default.
+    pub fn edition(mut self, edition: bool) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    /// The canonical title of `release`.
+    pub fn build(&self, release: &Release) -> String {
+        let mut title = base_title(&release.title);
+        let mut parts = Vec::new();
+        if self.formats {
+            parts.extend(format_names(&release.formats));
+        }
+        let mut disambiguator = parts.join(" + ");
+        if self.edition {
+            let edition = edition_text(&release.formats);
+            if !edition.is_empty() {
+                if !disambiguator.is_empty() {
+                    disambiguator.push_str("; ");
+                }
+                disambiguator.push_str(&edition);
+            }
+        }
+        if !disambiguator.is_empty() {
+            title.push_str(" (");
+            title.push_str(&disambiguator);
+            title.push(')');
+        }
+        title
+    }
+
+    /// The titles of several releases, e.g. the versions of a master, with the
+    /// disambiguators only appended to titles that would otherwise be the same.
+    /// Titles are compared ignoring case.
+    pub fn build_all<'a>(&self, releases: impl IntoIterator<Item = &'a Release>) -> Vec<String> {
+        let releases: Vec<&Release> = releases.into_iter().collect();
+        let bases: Vec<String> = releases.iter().map(|r| base_title(&r.title)).collect();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for base in &bases {
+            *counts.entry(base.to_lowercase()).or_default() += 1;
+        }
+        releases
+            .iter()
+            .zip(bases)
+            .map(|(release, base)| {
+                if counts[&base.to_lowercase()] > 1 {
+                    self.build(release)
+                } else {
+                    base
+                }
+            })
+            .collect()
+    }
+}
+
+/// The title with its whitespace trimmed and collapsed.
+fn base_title(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Each format as its quantity, name and descriptions, e.g. `2×Vinyl, LP, Album`.
+fn format_names(formats: &[ReleaseFormat]) -> impl Iterator<Item = String> + '_ {
+    formats.iter().map(|format| {
+        let mut name = String::new();
+        let qty = format.qty.trim();
+        if qty.parse::<u32>().is_ok_and(|qty| qty > 1) {
+            name.push_str(qty);
+            name.push('×');
+        }
+        name.push_str(&format.name);
+        for description in &format.descriptions {
+            name.push_str(", ");
+            name.push_str(description);
+        }
+        name
+    })
+}
+
+/// The parts of the free text of the formats that aren't about packaging or
+/// condition, without duplicates.
+fn edition_text(formats: &[ReleaseFormat]) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for text in formats.iter().filter_map(|f| f.text.as_deref()) {
+        for part in text.split([',', ';']).map(str::trim) {
+            if part.is_empty()
+                || is_packaging(part)
+                || parts.iter().any(|p| p.eq_ignore_ascii_case(part))
+            {
+                continue;
+            }
+            parts.push(part);
+        }
+    }
+    parts.join(", ")
+}
+
+fn is_packaging(text: &str) -> bool {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| {
+            let singular = word.strip_suffix("es").filter(|w| w.ends_with('x'));
+            let singular = singular.or_else(|| word.strip_suffix('s')).unwrap_or(word);
+            PACKAGING.contains(&word) || PACKAGING.contains(&singular)
+        })
+}
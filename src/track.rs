@@ -1,9 +1,13 @@
-use crate::artist_credit::{ArtistCredit, ArtistCreditParser};
+use crate::artist_credit::{credit_string_with, ArtistCredit, ArtistCreditParser, CreditStyle};
+use crate::duration::TrackDuration;
 use crate::parser::{Parser, ParserError};
+use crate::position::TrackPosition;
+use crate::tags::Tag;
 use quick_xml::events::Event;
 use std::mem::take;
+use std::time::Duration;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Track {
     pub position: String,
@@ -11,6 +15,71 @@ pub struct Track {
     pub duration: Option<String>,
     pub artists: Vec<ArtistCredit>,
     pub extraartists: Vec<ArtistCredit>,
+    /// The tracks grouped under an index track.
+    pub sub_tracks: Vec<Track>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackKind {
+    Track,
+    /// An index track grouping its sub-tracks under a shared title.
+    Index,
+    /// A heading between tracks, e.g. the name of a side or a part of a work.
+    Heading,
+}
+
+impl Track {
+    /// The track followed by its sub-tracks, each followed by its own, at any depth.
+    pub fn with_sub_tracks(&self) -> Box<dyn Iterator<Item = &Track> + '_> {
+        let sub_tracks = self.sub_tracks.iter().flat_map(Track::with_sub_tracks);
+        Box::new(std::iter::once(self).chain(sub_tracks))
+    }
+
+    /// The parsed [`position`](Self::position), or None if it's empty or unrecognised.
+    pub fn parsed_position(&self) -> Option<TrackPosition> {
+        TrackPosition::parse(&self.position)
+    }
+
+    /// Tracks without a position are index tracks if they have sub-tracks and
+    /// headings otherwise.
+    pub fn kind(&self) -> TrackKind {
+        if !self.position.trim().is_empty() {
+            TrackKind::Track
+        } else if self.sub_tracks.is_empty() {
+            TrackKind::Heading
+        } else {
+            TrackKind::Index
+        }
+    }
+
+    /// The duration in seconds, or None if it's missing or can't be parsed.
+    pub fn duration_secs(&self) -> Option<u32> {
+        self.duration
+            .as_deref()
+            .and_then(TrackDuration::parse)
+            .map(|d| d.secs())
+    }
+
+    /// The duration, or None if it's missing or can't be parsed.
+    pub fn parsed_duration(&self) -> Option<Duration> {
+        self.duration
+            .as_deref()
+            .and_then(TrackDuration::parse)
+            .map(Duration::from)
+    }
+
+    /// The track's artists as they're credited, followed by its featured artists.
+    /// It's empty if the track has no artists of its own, in which case the
+    /// release's artists apply.
+    pub fn credit_string(&self) -> String {
+        self.credit_string_with(CreditStyle::default())
+    }
+
+    /// The track's artists rendered with `style`.
+    pub fn credit_string_with(&self, style: CreditStyle) -> String {
+        credit_string_with(&self.artists, &self.extraartists, style)
+    }
 }
 
 #[derive(Debug, Default)]
@@ -22,20 +91,23 @@ enum ParserState {
     Duration,
     Artists,
     ExtraArtists,
+    SubTracks,
 }
 
+/// Parses a `<track>` element of a release tracklist into a [`Track`].
 #[derive(Debug, Default)]
 pub struct TrackParser {
     state: ParserState,
     current_item: Track,
     artist_parser: ArtistCreditParser,
-    pub item_ready: bool,
+    sub_track_parser: Option<Box<TrackParser>>,
+    item_ready: bool,
 }
 
 impl Parser for TrackParser {
     type Item = Track;
     fn new() -> Self {
-        TrackParser::default()
+        Self::default()
     }
 
     fn take(&mut self) -> Track {
@@ -43,16 +115,20 @@ impl Parser for TrackParser {
         take(&mut self.current_item)
     }
 
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
+
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
             ParserState::Track => match ev {
-                Event::Start(e) => match e.local_name().as_ref() {
-                    b"track" => ParserState::Track,
-                    b"position" => ParserState::Position,
-                    b"title" => ParserState::Title,
-                    b"duration" => ParserState::Duration,
-                    b"artists" => ParserState::Artists,
-                    b"extraartists" => ParserState::ExtraArtists,
+                Event::Start(e) => match Tag::of(e.local_name().as_ref()) {
+                    Some(Tag::Position) => ParserState::Position,
+                    Some(Tag::Title) => ParserState::Title,
+                    Some(Tag::Duration) => ParserState::Duration,
+                    Some(Tag::Artists) => ParserState::Artists,
+                    Some(Tag::ExtraArtists) => ParserState::ExtraArtists,
+                    Some(Tag::SubTracks) => ParserState::SubTracks,
                     _ => ParserState::Track,
                 },
                 Event::End(e) if e.local_name().as_ref() == b"track" => {
@@ -91,7 +167,7 @@ impl Parser for TrackParser {
 
                 ev => {
                     self.artist_parser.process(ev)?;
-                    if self.artist_parser.item_ready {
+                    if self.artist_parser.item_ready() {
                         self.current_item.artists.push(self.artist_parser.take());
                     }
                     ParserState::Artists
@@ -103,7 +179,7 @@ impl Parser for TrackParser {
 
                 ev => {
                     self.artist_parser.process(ev)?;
-                    if self.artist_parser.item_ready {
+                    if self.artist_parser.item_ready() {
                         self.current_item
                             .extraartists
                             .push(self.artist_parser.take());
@@ -111,6 +187,19 @@ impl Parser for TrackParser {
                     ParserState::ExtraArtists
                 }
             },
+
+            ParserState::SubTracks => match ev {
+                Event::End(e) if e.local_name().as_ref() == b"sub_tracks" => ParserState::Track,
+
+                ev => {
+                    let parser = self.sub_track_parser.get_or_insert_with(Default::default);
+                    parser.process(ev)?;
+                    if parser.item_ready() {
+                        self.current_item.sub_tracks.push(parser.take());
+                    }
+                    ParserState::SubTracks
+                }
+            },
         };
         Ok(())
     }
@@ -0,0 +1,102 @@
+use crate::intern::{intern, Symbol};
+use crate::master::Master;
+use crate::release::Release;
+use std::collections::HashMap;
+
+/// Spelling variants of genres and styles that have appeared in past dumps,
+/// mapped to their current names.
+const BUILTIN_GENRES: &[(&str, &str)] = &[("Hip-Hop", "Hip Hop"), ("Funk/Soul", "Funk / Soul")];
+const BUILTIN_STYLES: &[(&str, &str)] = &[
+    ("Drum And Bass", "Drum n Bass"),
+    ("Synthpop", "Synth-pop"),
+    ("Hip-Hop", "Hip Hop"),
+];
+
+/// A mapping of renamed genres and styles, used to keep a consistent vocabulary
+/// when combining data from different months.
+///
+/// Apply it at parse time with [`ReaderOptions::translations`](crate::ReaderOptions::translations)
+/// or to already parsed items with [`apply_release`](Translations::apply_release) and
+/// [`apply_master`](Translations::apply_master).
+#[derive(Clone, Debug, Default)]
+pub struct Translations {
+    genres: HashMap<String, String>,
+    styles: HashMap<String, String>,
+}
+
+impl Translations {
+    /// An empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table with the built-in renames, which can be extended or overridden.
+    pub fn builtin() -> Self {
+        let mut translations = Self::new();
+        for (from, to) in BUILTIN_GENRES {
+            translations = translations.genre(from, to);
+        }
+        for (from, to) in BUILTIN_STYLES {
+            translations = translations.style(from, to);
+        }
+        translations
+    }
+
+    /// Rename the genre `from` to `to`, replacing any existing mapping for `from`.
+    pub fn genre(mut self, from: &str, to: &str) -> Self {
+        self.genres.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    /// Rename the style `from` to `to`, replacing any existing mapping for `from`.
+    pub fn style(mut self, from: &str, to: &str) -> Self {
+        self.styles.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    pub fn translate_genre<'a>(&'a self, genre: &'a str) -> &'a str {
+        self.genres.get(genre).map_or(genre, String::as_str)
+    }
+
+    pub fn translate_style<'a>(&'a self, style: &'a str) -> &'a str {
+        self.styles.get(style).map_or(style, String::as_str)
+    }
+
+    /// Translate lists of genres and styles in place. Duplicates created by
+    /// merging two names into one are removed.
+    pub fn apply(&self, genres: &mut Vec<Symbol>, styles: &mut Vec<Symbol>) {
+        translate(&self.genres, genres);
+        translate(&self.styles, styles);
+    }
+
+    pub fn apply_release(&self, release: &mut Release) {
+        self.apply(&mut release.genres, &mut release.styles);
+    }
+
+    pub fn apply_master(&self, master: &mut Master) {
+        self.apply(&mut master.genres, &mut master.styles);
+    }
+}
+
+fn translate(table: &HashMap<String, String>, values: &mut Vec<Symbol>) {
+    if table.is_empty() {
+        return;
+    }
+    let mut changed = false;
+    for value in values.iter_mut() {
+        if let Some(new) = table.get(value.as_str()) {
+            *value = intern(new);
+            changed = true;
+        }
+    }
+    if changed {
+        let mut seen = Vec::with_capacity(values.len());
+        values.retain(|v| {
+            let dupe = seen.contains(v);
+            if !dupe {
+                seen.push(v.clone());
+            }
+            !dupe
+        });
+    }
+}
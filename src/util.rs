@@ -1,14 +1,125 @@
+use crate::parser::ParserError;
 use quick_xml::events::{
     attributes::{AttrError, Attribute},
     BytesStart,
 };
 use std::borrow::Cow;
+use std::io::{self, BufRead, Read};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use thiserror::Error;
 
-pub fn get_attr(attr: Option<Result<Attribute<'_>, AttrError>>) -> Cow<'_, str> {
-    attr.unwrap().unwrap().unescape_value().unwrap()
+pub fn get_attr(
+    attr: Option<Result<Attribute<'_>, AttrError>>,
+) -> Result<Cow<'_, str>, ParserError> {
+    let attr = attr.ok_or(ParserError::MissingAttr)??;
+    Ok(attr.unescape_value()?)
 }
 
-pub fn get_attr_id(ev: BytesStart) -> u32 {
+pub fn get_attr_id<T: From<u32>>(ev: BytesStart) -> Result<T, ParserError> {
     let mut attrs = ev.attributes();
-    get_attr(attrs.next()).parse().unwrap()
+    Ok(get_attr(attrs.next())?.parse::<u32>()?.into())
+}
+
+/// A reader that sleeps when needed to stay below a maximum throughput.
+pub struct Throttle<R> {
+    inner: R,
+    bytes_per_sec: u64,
+    start: Instant,
+    bytes_read: u64,
+}
+
+impl<R> Throttle<R> {
+    pub fn new(inner: R, bytes_per_sec: u64) -> Self {
+        Self {
+            inner,
+            bytes_per_sec: bytes_per_sec.max(1),
+            start: Instant::now(),
+            bytes_read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Throttle<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Read at most a tenth of a second's worth at a time so the rate stays smooth
+        let max_len = (self.bytes_per_sec / 10).max(1) as usize;
+        let len = buf.len().min(max_len);
+        let n = self.inner.read(&mut buf[..len])?;
+        self.bytes_read += n as u64;
+        let expected = Duration::from_secs_f64(self.bytes_read as f64 / self.bytes_per_sec as f64);
+        if let Some(ahead) = expected.checked_sub(self.start.elapsed()) {
+            sleep(ahead);
+        }
+        Ok(n)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CsvError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error("The CSV has no header row")]
+    NoHeader,
+    #[error("Unterminated quoted field starting on line {0}")]
+    UnterminatedQuote(usize),
+}
+
+/// Reads the records of a CSV file one at a time, following RFC 4180 quoting.
+pub struct CsvRecords<R> {
+    reader: R,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: BufRead> CsvRecords<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+
+    /// The fields of the next record, or None at the end of the file.
+    pub fn next_record(&mut self) -> Result<Option<Vec<String>>, CsvError> {
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quote_line = None;
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                if let Some(line) = quote_line {
+                    return Err(CsvError::UnterminatedQuote(line));
+                }
+                if field.is_empty() && record.is_empty() {
+                    return Ok(None);
+                }
+                record.push(field);
+                return Ok(Some(record));
+            }
+            self.line_number += 1;
+            let mut chars = self.line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if quote_line.is_some() => {
+                        if chars.peek() == Some(&'"') {
+                            chars.next();
+                            field.push('"');
+                        } else {
+                            quote_line = None;
+                        }
+                    }
+                    '"' if field.is_empty() => quote_line = Some(self.line_number),
+                    ',' if quote_line.is_none() => record.push(std::mem::take(&mut field)),
+                    '\r' if quote_line.is_none() && chars.peek() == Some(&'\n') => {}
+                    '\n' if quote_line.is_none() => {
+                        record.push(field);
+                        return Ok(Some(record));
+                    }
+                    c => field.push(c),
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,274 @@
+//! Checking parsed records against constraints the dumps are expected to meet.
+//!
+//! A [`Validator`] checks each record on its own, unlike the
+//! [`Verifier`](crate::verify::Verifier), which checks the references between the
+//! dumps of a month. Violations point either at bad data in the dumps or at a bug in
+//! the parsers, so the samples give the ID of each record to look it up.
+//!
+//! ```no_run
+//! use disco_quick::validate::validate_path;
+//!
+//! # fn main() -> Result<(), disco_quick::ReaderError> {
+//! let report = validate_path(
+//!     "discogs_20240101_releases.xml.gz".as_ref(),
+//!     Some("discogs_20240101_masters.xml.gz".as_ref()),
+//! )?;
+//! if !report.is_ok() {
+//!     println!("{} empty tracklists", report.empty_tracklists.failed);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::Artist;
+use crate::artist_credit::ArtistCredit;
+use crate::date::ReleaseDate;
+use crate::id::MasterId;
+use crate::label::Label;
+use crate::master::Master;
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::release::Release;
+use std::collections::HashSet;
+use std::path::Path;
+
+const MAX_SAMPLES: usize = 100;
+
+/// Checks records one at a time and gathers a [`ValidationReport`].
+///
+/// Masters that are added before releases are used to check the master IDs of the
+/// releases. Without them, master IDs aren't checked.
+#[derive(Debug, Default)]
+pub struct Validator {
+    master_ids: HashSet<MasterId>,
+    report: ValidationReport,
+}
+
+/// The results of a [`Validator`], with up to 100 sample violations for each rule.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationReport {
+    /// The number of records checked.
+    pub records: u64,
+    /// Artist credits of releases, tracks and masters, and the aliases, members and
+    /// groups of artists, have an artist ID. Extra artists aren't checked, as their
+    /// credits don't always link to an artist.
+    pub missing_artist_ids: Rule,
+    /// Accepted releases have a tracklist. Releases with another status are
+    /// placeholders that may have been emptied.
+    pub empty_tracklists: Rule,
+    /// Release dates are empty or parse as a date, with any month and day given
+    /// either zero or in range, and master years are zero or have four digits.
+    pub malformed_dates: Rule,
+    /// The master IDs of releases exist in the masters added to the validator.
+    pub dangling_masters: Rule,
+    /// Releases don't list the same identifier twice.
+    pub duplicate_identifiers: Rule,
+    /// Labels don't list the same sublabel twice, and aren't their own parent.
+    pub label_references: Rule,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    pub checked: u64,
+    pub failed: u64,
+    pub samples: Vec<Violation>,
+}
+
+/// A record that broke a rule, with what broke it, e.g. the malformed date or the
+/// name of the credit without an ID.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Violation {
+    pub id: u32,
+    pub detail: String,
+}
+
+impl Rule {
+    fn check(&mut self, ok: bool, id: impl Into<u32>, detail: impl FnOnce() -> String) {
+        self.checked += 1;
+        if !ok {
+            self.failed += 1;
+            if self.samples.len() < MAX_SAMPLES {
+                self.samples.push(Violation {
+                    id: id.into(),
+                    detail: detail(),
+                });
+            }
+        }
+    }
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.rules().iter().all(|(_, rule)| rule.failed == 0)
+    }
+
+    /// The rules by name, e.g. to print the number of violations of each.
+    pub fn rules(&self) -> [(&'static str, &Rule); 6] {
+        [
+            ("missing_artist_ids", &self.missing_artist_ids),
+            ("empty_tracklists", &self.empty_tracklists),
+            ("malformed_dates", &self.malformed_dates),
+            ("dangling_masters", &self.dangling_masters),
+            ("duplicate_identifiers", &self.duplicate_identifiers),
+            ("label_references", &self.label_references),
+        ]
+    }
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_artist(&mut self, artist: &Artist) {
+        self.report.records += 1;
+        let id = artist.id;
+        let related = artist
+            .aliases
+            .iter()
+            .chain(&artist.members)
+            .chain(&artist.groups);
+        for info in related {
+            self.report
+                .missing_artist_ids
+                .check(info.id != 0, id, || info.name.clone());
+        }
+    }
+
+    pub fn add_label(&mut self, label: &Label) {
+        self.report.records += 1;
+        let rule = &mut self.report.label_references;
+        let mut sublabels = HashSet::new();
+        for sublabel in &label.sublabels {
+            rule.check(sublabels.insert(sublabel.id), label.id, || {
+                format!("sublabel {} listed twice", sublabel.id)
+            });
+        }
+        if let Some(parent) = &label.parent_label {
+            rule.check(parent.id != label.id, label.id, || {
+                "label is its own parent".to_string()
+            });
+        }
+    }
+
+    pub fn add_master(&mut self, master: &Master) {
+        self.report.records += 1;
+        self.master_ids.insert(master.id);
+        check_credits(
+            &mut self.report.missing_artist_ids,
+            master.id,
+            &master.artists,
+        );
+        let year_ok = master.year == 0 || (1000..=9999).contains(&master.year);
+        self.report
+            .malformed_dates
+            .check(year_ok, master.id, || master.year.to_string());
+    }
+
+    pub fn add_release(&mut self, release: &Release) {
+        let report = &mut self.report;
+        report.records += 1;
+        let id = release.id;
+        check_credits(&mut report.missing_artist_ids, id, &release.artists);
+        for track in release.all_tracks() {
+            check_credits(&mut report.missing_artist_ids, id, &track.artists);
+        }
+        if release.status.is_accepted() {
+            report
+                .empty_tracklists
+                .check(!release.tracklist.is_empty(), id, || release.title.clone());
+        }
+        report
+            .malformed_dates
+            .check(!is_malformed_date(&release.released), id, || {
+                release.released.clone()
+            });
+        if let (Some(master_id), false) = (release.master_id, self.master_ids.is_empty()) {
+            let exists = self.master_ids.contains(&master_id);
+            report
+                .dangling_masters
+                .check(exists, id, || master_id.to_string());
+        }
+        let mut identifiers = HashSet::new();
+        for identifier in &release.identifiers {
+            let Some(value) = &identifier.value else {
+                continue;
+            };
+            let new = identifiers.insert((&identifier.r#type, value));
+            report
+                .duplicate_identifiers
+                .check(new, id, || format!("{} {value}", identifier.r#type));
+        }
+    }
+
+    /// Add all the items of a reader.
+    pub fn add_reader(&mut self, reader: DiscogsReader) -> Result<(), ReaderError> {
+        match reader {
+            DiscogsReader::Artists(artists) => {
+                for artist in *artists {
+                    self.add_artist(&artist?);
+                }
+            }
+            DiscogsReader::Labels(labels) => {
+                for label in *labels {
+                    self.add_label(&label?);
+                }
+            }
+            DiscogsReader::Masters(masters) => {
+                for master in *masters {
+                    self.add_master(&master?);
+                }
+            }
+            DiscogsReader::Releases(releases) => {
+                for release in *releases {
+                    self.add_release(&release?);
+                }
+            }
+            DiscogsReader::Custom(_) => {}
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> ValidationReport {
+        self.report
+    }
+}
+
+fn check_credits(rule: &mut Rule, id: impl Into<u32> + Copy, credits: &[ArtistCredit]) {
+    for credit in credits {
+        rule.check(credit.id != 0, id, || credit.name.clone());
+    }
+}
+
+/// Whether a release date has a part that isn't a number, or a month or day that's
+/// out of range rather than zero for unknown.
+fn is_malformed_date(released: &str) -> bool {
+    let released = released.trim();
+    if released.is_empty() {
+        return false;
+    }
+    let date = ReleaseDate::parse(released);
+    if date.year.is_none() {
+        return true;
+    }
+    let given = |part: Option<&str>| part.is_some_and(|p| !p.trim_start_matches('0').is_empty());
+    let (month, day) = if released.len() == 8 && released.bytes().all(|b| b.is_ascii_digit()) {
+        (Some(&released[4..6]), Some(&released[6..]))
+    } else {
+        let mut parts = released.split(['-', '/', '.']).map(str::trim).skip(1);
+        (parts.next(), parts.next())
+    };
+    (given(month) && date.month.is_none()) || (given(day) && date.day.is_none())
+}
+
+/// Validate the dump at `path`. If `masters` is given, that dump is validated first,
+/// and its IDs are used to check the master IDs of releases.
+pub fn validate_path(path: &Path, masters: Option<&Path>) -> Result<ValidationReport, ReaderError> {
+    let mut validator = Validator::new();
+    if let Some(masters) = masters {
+        validator.add_reader(DiscogsReader::from_path(masters)?)?;
+    }
+    validator.add_reader(DiscogsReader::from_path(path)?)?;
+    Ok(validator.finish())
+}
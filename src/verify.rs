@@ -0,0 +1,340 @@
+use crate::artist::Artist;
+use crate::country::Country;
+use crate::label::Label;
+use crate::master::Master;
+use crate::options::splitmix64;
+use crate::reader::{DiscogsReader, ReaderError};
+use crate::release::Release;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+const MAX_SAMPLES: usize = 100;
+
+/// The number of releases a label needs before its country is inferred.
+const MIN_LABEL_RELEASES: u32 = 3;
+
+/// The sorted label IDs of a release and its country.
+type CountryGroup = (Vec<u32>, Country);
+
+/// Checks referential integrity between the four dumps of a month.
+///
+/// Add the artists, labels and masters first, then the releases, which are checked
+/// as they're added. The remaining checks are done by [`finish`](Verifier::finish).
+///
+/// The IDs of all the records are kept in memory, along with each distinct
+/// combination of labels and country of the releases for the country check, as a
+/// label's country is only known once all its releases are read. For a full
+/// releases dump that's millions of combinations, so expect the verifier to take
+/// more memory than the IDs alone.
+#[derive(Debug, Default)]
+pub struct Verifier {
+    artist_ids: HashSet<u32>,
+    label_ids: HashSet<u32>,
+    master_ids: HashSet<u32>,
+    release_ids: HashSet<u32>,
+    main_releases: Vec<(u32, u32)>,
+    members: HashMap<u32, Vec<u32>>,
+    groups: HashMap<u32, Vec<u32>>,
+    /// The number of releases of each label from each country.
+    label_countries: HashMap<u32, HashMap<Country, u32>>,
+    /// The number of releases with each combination of labels and country, and the
+    /// first of them, sorted so they're checked in the same order each time.
+    country_groups: BTreeMap<CountryGroup, (u64, u32)>,
+    seed: u64,
+    report: IntegrityReport,
+}
+
+/// The results of a [`Verifier`], with up to 100 sample failures for each check.
+/// The samples are chosen pseudo-randomly from all the failures using the seed of
+/// the verifier, so verifying the same dumps with the same seed gives the same
+/// samples. They're sorted by the IDs they refer from and to.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntegrityReport {
+    /// Artist IDs credited on releases and their tracks exist in the artists dump.
+    pub release_artists: Check,
+    /// Label IDs on releases exist in the labels dump.
+    pub release_labels: Check,
+    /// Master IDs on releases exist in the masters dump.
+    pub release_masters: Check,
+    /// The main release of each master exists in the releases dump.
+    pub master_main_releases: Check,
+    /// Each member of a group lists the group in its groups.
+    pub artist_members: Check,
+    /// Each group of an artist lists the artist in its members.
+    pub artist_groups: Check,
+    /// Releases from a different country than all of their labels, whose countries
+    /// are inferred as the country most of their releases are from. These point to
+    /// export or licensed pressings rather than broken data, so they don't affect
+    /// [`is_ok`](Self::is_ok). Releases without a country or without any label
+    /// whose country could be inferred aren't checked.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub country_mismatches: Check,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Check {
+    pub checked: u64,
+    pub failed: u64,
+    pub samples: Vec<Dangling>,
+}
+
+/// A reference from the entity with ID `from` to the missing or unreciprocated `to`,
+/// or for country mismatches, a release and one of its labels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dangling {
+    pub from: u32,
+    pub to: u32,
+}
+
+impl Check {
+    fn check(&mut self, ok: bool, from: u32, to: u32, seed: u64) {
+        self.checked += 1;
+        if !ok {
+            self.failed += 1;
+            self.sample(Dangling { from, to }, seed);
+        }
+    }
+
+    /// Keep `failure` if it's among the [`MAX_SAMPLES`] failures with the lowest
+    /// hashes, which makes the samples independent of the order they're found in.
+    fn sample(&mut self, failure: Dangling, seed: u64) {
+        if self.samples.contains(&failure) {
+            return;
+        }
+        if self.samples.len() < MAX_SAMPLES {
+            self.samples.push(failure);
+            return;
+        }
+        let hash = failure.hash(seed);
+        let (highest, highest_hash) = self
+            .samples
+            .iter()
+            .map(|d| d.hash(seed))
+            .enumerate()
+            .max_by_key(|(_, hash)| *hash)
+            .expect("samples are full");
+        if hash < highest_hash {
+            self.samples[highest] = failure;
+        }
+    }
+}
+
+impl Dangling {
+    fn hash(&self, seed: u64) -> u64 {
+        splitmix64(seed ^ ((self.from as u64) << 32 | self.to as u64))
+    }
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        [
+            &self.release_artists,
+            &self.release_labels,
+            &self.release_masters,
+            &self.master_main_releases,
+            &self.artist_members,
+            &self.artist_groups,
+        ]
+        .iter()
+        .all(|c| c.failed == 0)
+    }
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The seed that the sample failures of the report are chosen with. Defaults
+    /// to 0.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn add_artist(&mut self, artist: &Artist) {
+        let id = artist.id.get();
+        self.artist_ids.insert(id);
+        if !artist.members.is_empty() {
+            let members = artist.members.iter().map(|m| m.id.get()).collect();
+            self.members.insert(id, members);
+        }
+        if !artist.groups.is_empty() {
+            let groups = artist.groups.iter().map(|g| g.id.get()).collect();
+            self.groups.insert(id, groups);
+        }
+    }
+
+    pub fn add_label(&mut self, label: &Label) {
+        self.label_ids.insert(label.id.get());
+    }
+
+    pub fn add_master(&mut self, master: &Master) {
+        self.master_ids.insert(master.id.get());
+        self.main_releases
+            .push((master.id.get(), master.main_release.get()));
+    }
+
+    pub fn add_release(&mut self, release: &Release) {
+        let id = release.id.get();
+        self.release_ids.insert(id);
+        let seed = self.seed;
+        let report = &mut self.report;
+        let track_credits = release
+            .all_tracks()
+            .flat_map(|t| t.artists.iter().chain(&t.extraartists));
+        for credit in release
+            .artists
+            .iter()
+            .chain(&release.extraartists)
+            .chain(track_credits)
+        {
+            let credit_id = credit.id.get();
+            let exists = self.artist_ids.contains(&credit_id);
+            report.release_artists.check(exists, id, credit_id, seed);
+        }
+        for label in &release.labels {
+            let label_id = label.id.get();
+            let exists = self.label_ids.contains(&label_id);
+            report.release_labels.check(exists, id, label_id, seed);
+        }
+        if let Some(master_id) = release.master_id {
+            let master_id = master_id.get();
+            let exists = self.master_ids.contains(&master_id);
+            report.release_masters.check(exists, id, master_id, seed);
+        }
+        if release.country.is_empty() || release.labels.is_empty() {
+            return;
+        }
+        let mut label_ids: Vec<u32> = release.labels.iter().map(|l| l.id.get()).collect();
+        label_ids.sort_unstable();
+        label_ids.dedup();
+        for label_id in &label_ids {
+            *self
+                .label_countries
+                .entry(*label_id)
+                .or_default()
+                .entry(release.country.clone())
+                .or_default() += 1;
+        }
+        self.country_groups
+            .entry((label_ids, release.country.clone()))
+            .or_insert((0, id))
+            .0 += 1;
+    }
+
+    /// Add all the items of a reader.
+    pub fn add_reader(&mut self, reader: DiscogsReader) -> Result<(), ReaderError> {
+        match reader {
+            DiscogsReader::Artists(artists) => {
+                for artist in *artists {
+                    self.add_artist(&artist?);
+                }
+            }
+            DiscogsReader::Labels(labels) => {
+                for label in *labels {
+                    self.add_label(&label?);
+                }
+            }
+            DiscogsReader::Masters(masters) => {
+                for master in *masters {
+                    self.add_master(&master?);
+                }
+            }
+            DiscogsReader::Releases(releases) => {
+                for release in *releases {
+                    self.add_release(&release?);
+                }
+            }
+            DiscogsReader::Custom(_) => {}
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> IntegrityReport {
+        let seed = self.seed;
+        let report = &mut self.report;
+        for (master_id, release_id) in &self.main_releases {
+            let exists = self.release_ids.contains(release_id);
+            report
+                .master_main_releases
+                .check(exists, *master_id, *release_id, seed);
+        }
+        for (group_id, members) in &self.members {
+            for member_id in members {
+                let ok = self
+                    .groups
+                    .get(member_id)
+                    .is_some_and(|g| g.contains(group_id));
+                report.artist_members.check(ok, *group_id, *member_id, seed);
+            }
+        }
+        for (artist_id, groups) in &self.groups {
+            for group_id in groups {
+                let ok = self
+                    .members
+                    .get(group_id)
+                    .is_some_and(|m| m.contains(artist_id));
+                report.artist_groups.check(ok, *artist_id, *group_id, seed);
+            }
+        }
+        for ((label_ids, country), (count, release_id)) in &self.country_groups {
+            let inferred: Vec<(u32, &Country)> = label_ids
+                .iter()
+                .filter_map(|id| Some((*id, label_country(self.label_countries.get(id)?)?)))
+                .collect();
+            let Some((label_id, _)) = inferred.first() else {
+                continue;
+            };
+            let check = &mut self.report.country_mismatches;
+            let mismatch = inferred.iter().all(|(_, c)| *c != country);
+            check.checked += count;
+            if mismatch {
+                check.failed += count;
+                let failure = Dangling {
+                    from: *release_id,
+                    to: *label_id,
+                };
+                check.sample(failure, seed);
+            }
+        }
+        let report = &mut self.report;
+        for check in [
+            &mut report.release_artists,
+            &mut report.release_labels,
+            &mut report.release_masters,
+            &mut report.master_main_releases,
+            &mut report.artist_members,
+            &mut report.artist_groups,
+            &mut report.country_mismatches,
+        ] {
+            check.samples.sort_unstable_by_key(|d| (d.from, d.to));
+        }
+        self.report
+    }
+}
+
+/// The country most of a label's releases are from, if it has enough releases and
+/// more than half of them are from one country.
+fn label_country(countries: &HashMap<Country, u32>) -> Option<&Country> {
+    let total: u32 = countries.values().sum();
+    let (country, count) = countries.iter().max_by_key(|(_, count)| **count)?;
+    (total >= MIN_LABEL_RELEASES && *count * 2 > total).then_some(country)
+}
+
+/// Verify the dumps of a month, reading them in the required order.
+pub fn verify_paths(
+    artists: &Path,
+    labels: &Path,
+    masters: &Path,
+    releases: &Path,
+) -> Result<IntegrityReport, ReaderError> {
+    let mut verifier = Verifier::new();
+    for path in [artists, labels, masters, releases] {
+        verifier.add_reader(DiscogsReader::from_path(path)?)?;
+    }
+    Ok(verifier.finish())
+}
@@ -0,0 +1,179 @@
+pub mod csv;
+pub mod groups;
+use crate::date::ReleaseDate;
+use crate::id::{LabelId, MasterId, ReleaseId};
+use crate::release::Release;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Format descriptions that mark a version as not being an original pressing.
+const REISSUE_DESCRIPTIONS: &[&str] = &["Reissue", "Repress", "Remastered", "Unofficial Release"];
+
+/// The fields of a release needed to compare the versions of a master.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReleaseSummary {
+    pub id: ReleaseId,
+    pub master_id: Option<MasterId>,
+    pub is_main_release: bool,
+    pub title: String,
+    pub country: String,
+    pub released: String,
+    pub label_ids: Vec<LabelId>,
+    pub formats: Vec<String>,
+    pub format_descriptions: Vec<String>,
+}
+
+impl From<&Release> for ReleaseSummary {
+    fn from(release: &Release) -> Self {
+        Self {
+            id: release.id,
+            master_id: release.master_id,
+            is_main_release: release.is_main_release,
+            title: release.title.clone(),
+            country: release.country.to_string(),
+            released: release.released.clone(),
+            label_ids: release.labels.iter().map(|l| l.id).collect(),
+            formats: release.formats.iter().map(|f| f.name.to_string()).collect(),
+            format_descriptions: release
+                .formats
+                .iter()
+                .flat_map(|f| f.descriptions.iter().cloned())
+                .collect(),
+        }
+    }
+}
+
+impl ReleaseSummary {
+    pub fn is_reissue(&self) -> bool {
+        self.format_descriptions
+            .iter()
+            .any(|d| REISSUE_DESCRIPTIONS.contains(&d.as_str()))
+    }
+
+    /// A sort key for the release date where unknown parts sort after known ones.
+    fn date_key(&self) -> (u16, u8, u8) {
+        let date = ReleaseDate::parse(&self.released);
+        (
+            date.year.unwrap_or(u16::MAX),
+            date.month.unwrap_or(u8::MAX),
+            date.day.unwrap_or(u8::MAX),
+        )
+    }
+}
+
+/// The number of versions of a master by country, format and year, e.g. for the
+/// facets of a filter of its versions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VersionsMatrix {
+    /// The number of versions of each format from each country. A version with
+    /// several formats, e.g. a CD and a DVD, is counted once for each.
+    pub by_country_format: BTreeMap<String, BTreeMap<String, u32>>,
+    /// The number of versions from each country.
+    pub by_country: BTreeMap<String, u32>,
+    /// The number of versions released in each year.
+    pub by_year: BTreeMap<u16, u32>,
+    /// The number of versions without a year.
+    pub unknown_year: u32,
+}
+
+impl VersionsMatrix {
+    /// The number of versions of each format, from any country.
+    pub fn formats(&self) -> BTreeMap<&str, u32> {
+        let mut formats = BTreeMap::new();
+        for counts in self.by_country_format.values() {
+            for (format, count) in counts {
+                *formats.entry(format.as_str()).or_default() += count;
+            }
+        }
+        formats
+    }
+
+    /// The number of versions of `format` from `country`.
+    pub fn count(&self, country: &str, format: &str) -> u32 {
+        self.by_country_format
+            .get(country)
+            .and_then(|formats| formats.get(format))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Analysis of all the versions of a master release.
+#[derive(Clone, Debug, Default)]
+pub struct VersionsAnalysis {
+    pub master_id: MasterId,
+    pub versions: Vec<ReleaseSummary>,
+    label_countries: HashMap<LabelId, String>,
+}
+
+impl VersionsAnalysis {
+    pub fn new(master_id: MasterId, versions: Vec<ReleaseSummary>) -> Self {
+        Self {
+            master_id,
+            versions,
+            label_countries: HashMap::new(),
+        }
+    }
+
+    /// Provide the country each label is based in, used to prefer versions released
+    /// in their label's home country.
+    pub fn label_countries(mut self, countries: HashMap<LabelId, String>) -> Self {
+        self.label_countries = countries;
+        self
+    }
+
+    pub fn main_release(&self) -> Option<&ReleaseSummary> {
+        self.versions.iter().find(|v| v.is_main_release)
+    }
+
+    /// The version flagged as the main release, or the probable first pressing if
+    /// none is, e.g. when only some of the versions were read.
+    pub fn detect_main_release(&self) -> Option<&ReleaseSummary> {
+        self.main_release().or_else(|| self.first_pressing())
+    }
+
+    /// Count the versions by country and format, and by year.
+    pub fn matrix(&self) -> VersionsMatrix {
+        let mut matrix = VersionsMatrix::default();
+        for version in &self.versions {
+            *matrix
+                .by_country
+                .entry(version.country.clone())
+                .or_default() += 1;
+            match ReleaseDate::parse(&version.released).year {
+                Some(year) => *matrix.by_year.entry(year).or_default() += 1,
+                None => matrix.unknown_year += 1,
+            }
+            if version.formats.is_empty() {
+                continue;
+            }
+            let formats: BTreeSet<&String> = version.formats.iter().collect();
+            let counts = matrix
+                .by_country_format
+                .entry(version.country.clone())
+                .or_default();
+            for format in formats {
+                *counts.entry(format.clone()).or_default() += 1;
+            }
+        }
+        matrix
+    }
+
+    /// The probable first pressing: the earliest dated version, preferring ones
+    /// without reissue descriptions and ones released in their label's country.
+    /// Remaining ties go to the lowest release ID, i.e. the first one submitted.
+    pub fn first_pressing(&self) -> Option<&ReleaseSummary> {
+        self.versions.iter().min_by_key(|v| {
+            let foreign = match v
+                .label_ids
+                .first()
+                .and_then(|id| self.label_countries.get(id))
+            {
+                Some(country) => *country != v.country,
+                None => false,
+            };
+            (v.is_reissue(), v.date_key(), foreign, v.id)
+        })
+    }
+}
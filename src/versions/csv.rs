@@ -0,0 +1,141 @@
+//! Reading the CSV exports of a master's version list from the Discogs website,
+//! and joining them against releases from the dump.
+//!
+//! Columns are matched by their header, case-insensitively, so the order doesn't
+//! matter and unknown columns are kept in [`VersionRow::extra`]. If there's no ID
+//! column the release ID is taken from a release URL column if present.
+use crate::id::ReleaseId;
+pub use crate::util::CsvError;
+use crate::util::CsvRecords;
+use crate::versions::ReleaseSummary;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// A row of a version list export.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VersionRow {
+    pub release_id: Option<ReleaseId>,
+    pub title: String,
+    pub format: String,
+    pub label: String,
+    pub catno: String,
+    pub country: String,
+    pub released: String,
+    /// Columns that weren't recognised, as (header, value) pairs.
+    pub extra: Vec<(String, String)>,
+}
+
+/// A row of a version list export and the matching release from the dump, if any.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JoinedVersion {
+    pub row: VersionRow,
+    pub release: Option<ReleaseSummary>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Column {
+    Id,
+    Url,
+    Title,
+    Format,
+    Label,
+    Catno,
+    Country,
+    Released,
+    Extra,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Self {
+        match header.trim().to_lowercase().as_str() {
+            "release_id" | "release id" | "id" => Column::Id,
+            "url" | "release url" | "link" => Column::Url,
+            "title" | "title (format)" => Column::Title,
+            "format" => Column::Format,
+            "label" | "labels" => Column::Label,
+            "catno" | "cat#" | "catalog#" | "catalog number" => Column::Catno,
+            "country" => Column::Country,
+            "released" | "year" => Column::Released,
+            _ => Column::Extra,
+        }
+    }
+}
+
+/// Read a version list export.
+pub fn read_versions(mut reader: impl Read) -> Result<Vec<VersionRow>, CsvError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    parse_versions(&text)
+}
+
+/// Parse the contents of a version list export.
+pub fn parse_versions(text: &str) -> Result<Vec<VersionRow>, CsvError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = CsvRecords::new(text.as_bytes());
+    let headers = records.next_record()?.ok_or(CsvError::NoHeader)?;
+    let columns: Vec<Column> = headers.iter().map(|h| Column::from_header(h)).collect();
+    let has_id = columns.iter().any(|c| matches!(c, Column::Id));
+
+    let mut rows = Vec::new();
+    while let Some(record) = records.next_record()? {
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let mut row = VersionRow::default();
+        for (i, value) in record.into_iter().enumerate() {
+            match columns.get(i).copied().unwrap_or(Column::Extra) {
+                Column::Id => row.release_id = value.trim().parse().ok(),
+                Column::Url if !has_id => row.release_id = release_id_from_url(&value),
+                Column::Title => row.title = value,
+                Column::Format => row.format = value,
+                Column::Label => row.label = value,
+                Column::Catno => row.catno = value,
+                Column::Country => row.country = value,
+                Column::Released => row.released = value,
+                Column::Url | Column::Extra => {
+                    let header = headers.get(i).cloned().unwrap_or_default();
+                    row.extra.push((header, value))
+                }
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Match rows to releases by ID. `releases` can be every release in the dump, only
+/// the ones that appear in `rows` are kept.
+pub fn join(
+    rows: Vec<VersionRow>,
+    releases: impl IntoIterator<Item = ReleaseSummary>,
+) -> Vec<JoinedVersion> {
+    let mut found: HashMap<ReleaseId, Option<ReleaseSummary>> = rows
+        .iter()
+        .filter_map(|r| r.release_id)
+        .map(|id| (id, None))
+        .collect();
+    for release in releases {
+        if let Some(slot) = found.get_mut(&release.id) {
+            *slot = Some(release);
+        }
+    }
+    rows.into_iter()
+        .map(|row| {
+            let release = row
+                .release_id
+                .and_then(|id| found.get(&id).cloned().flatten());
+            JoinedVersion { row, release }
+        })
+        .collect()
+}
+
+/// Extract the ID from a URL like `https://www.discogs.com/release/123-Artist-Title`.
+fn release_id_from_url(url: &str) -> Option<ReleaseId> {
+    let (_, rest) = url.split_once("/release/")?;
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
@@ -0,0 +1,362 @@
+//! Grouping the releases of a dump by their master.
+//!
+//! The releases of a master are spread across the dump, so grouping them means
+//! holding them all until the end. [`MasterGrouper`] keeps a [`ReleaseSummary`] of
+//! each rather than the whole release, and once it has more than a set number it
+//! sorts them by master and writes them to a run file. The runs are merged as the
+//! groups are read, so memory use is bounded by the limit rather than the dump.
+//!
+//! ```no_run
+//! use disco_quick::versions::groups::MasterGrouper;
+//! use disco_quick::versions::VersionsAnalysis;
+//! use disco_quick::DiscogsReader;
+//!
+//! # fn main() -> Result<(), disco_quick::versions::groups::GroupError> {
+//! let DiscogsReader::Releases(releases) = DiscogsReader::from_path("releases.xml.gz".as_ref())?
+//! else {
+//!     return Ok(());
+//! };
+//! for group in MasterGrouper::new().spill_dir("tmp".as_ref()).group(*releases)? {
+//!     let (master_id, versions) = group?;
+//!     let analysis = VersionsAnalysis::new(master_id, versions);
+//!     if let Some(main) = analysis.detect_main_release() {
+//!         println!("{master_id}: {} versions of {}", analysis.versions.len(), main.title);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::id::{LabelId, MasterId, ReleaseId};
+use crate::reader::ReaderError;
+use crate::release::Release;
+use crate::versions::ReleaseSummary;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::vec;
+use thiserror::Error;
+
+/// The default number of releases held in memory before they're written to a run.
+pub const DEFAULT_MAX_IN_MEMORY: usize = 1_000_000;
+
+/// Distinguishes the runs of groupers in the same process.
+static GROUPERS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Error, Debug)]
+pub enum GroupError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+}
+
+/// Groups releases by master, spilling to disk once there are too many to hold.
+#[derive(Clone, Debug)]
+pub struct MasterGrouper {
+    max_in_memory: usize,
+    spill_dir: PathBuf,
+}
+
+impl Default for MasterGrouper {
+    fn default() -> Self {
+        Self {
+            max_in_memory: DEFAULT_MAX_IN_MEMORY,
+            spill_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+impl MasterGrouper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the releases held to a run once there are `n` of them.
+    pub fn max_in_memory(mut self, n: usize) -> Self {
+        self.max_in_memory = n.max(1);
+        self
+    }
+
+    /// Write runs to `dir` instead of the system's temporary directory.
+    pub fn spill_dir(mut self, dir: &Path) -> Self {
+        self.spill_dir = dir.to_path_buf();
+        self
+    }
+
+    /// Read all of `releases` and return their groups in order of master ID.
+    /// Releases without a master are left out.
+    pub fn group<E>(
+        &self,
+        releases: impl IntoIterator<Item = Result<Release, E>>,
+    ) -> Result<MasterGroups, GroupError>
+    where
+        GroupError: From<E>,
+    {
+        let grouper = GROUPERS.fetch_add(1, Ordering::Relaxed);
+        let mut runs = Runs::default();
+        let mut held: Vec<(u32, ReleaseSummary)> = Vec::new();
+        for release in releases {
+            let release = release?;
+            let Some(master_id) = release.master_id.and_then(MasterId::valid) else {
+                continue;
+            };
+            held.push((master_id.get(), ReleaseSummary::from(&release)));
+            if held.len() >= self.max_in_memory {
+                if runs.paths.is_empty() {
+                    fs::create_dir_all(&self.spill_dir)?;
+                }
+                let name = format!(
+                    "disco-quick-masters-{}-{grouper}-{}.run",
+                    std::process::id(),
+                    runs.paths.len()
+                );
+                runs.write(self.spill_dir.join(name), &mut held)?;
+            }
+        }
+        sort(&mut held);
+        let source = if runs.paths.is_empty() {
+            Source::Memory(held.into_iter().peekable())
+        } else {
+            let mut heap = BinaryHeap::new();
+            let mut readers = Vec::with_capacity(runs.paths.len());
+            for (i, path) in runs.paths.iter().enumerate() {
+                let mut reader = BufReader::new(File::open(path)?);
+                if let Some(entry) = read_entry(&mut reader)? {
+                    heap.push(Reverse(Head(entry, i)));
+                }
+                readers.push(reader);
+            }
+            // The releases still held are merged as one more run
+            let mut memory = held.into_iter();
+            if let Some(entry) = memory.next() {
+                heap.push(Reverse(Head(entry, readers.len())));
+            }
+            Source::Merge {
+                heap,
+                readers,
+                memory,
+                _runs: runs,
+            }
+        };
+        Ok(MasterGroups {
+            source,
+            failed: false,
+        })
+    }
+}
+
+/// An iterator over the groups of releases of each master, from
+/// [`MasterGrouper::group`]. It ends after the first error, as the groups that
+/// follow could be missing the releases of the run that failed.
+pub struct MasterGroups {
+    source: Source,
+    failed: bool,
+}
+
+enum Source {
+    Memory(std::iter::Peekable<vec::IntoIter<(u32, ReleaseSummary)>>),
+    Merge {
+        heap: BinaryHeap<Reverse<Head>>,
+        readers: Vec<BufReader<File>>,
+        memory: vec::IntoIter<(u32, ReleaseSummary)>,
+        /// Kept so the runs are deleted when the groups are dropped.
+        _runs: Runs,
+    },
+}
+
+/// The next release of a run, ordered by master and release ID.
+struct Head((u32, ReleaseSummary), usize);
+
+impl Head {
+    fn key(&self) -> (u32, ReleaseId, usize) {
+        (self.0 .0, self.0 .1.id, self.1)
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Source {
+    fn peek_master(&mut self) -> Option<u32> {
+        match self {
+            Source::Memory(entries) => entries.peek().map(|(master, _)| *master),
+            Source::Merge { heap, .. } => heap.peek().map(|Reverse(head)| head.0 .0),
+        }
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<(u32, ReleaseSummary)>> {
+        match self {
+            Source::Memory(entries) => Ok(entries.next()),
+            Source::Merge {
+                heap,
+                readers,
+                memory,
+                ..
+            } => {
+                let Some(Reverse(Head(entry, run))) = heap.pop() else {
+                    return Ok(None);
+                };
+                let next = match readers.get_mut(run) {
+                    Some(reader) => read_entry(reader)?,
+                    None => memory.next(),
+                };
+                if let Some(next) = next {
+                    heap.push(Reverse(Head(next, run)));
+                }
+                Ok(Some(entry))
+            }
+        }
+    }
+}
+
+impl Iterator for MasterGroups {
+    type Item = io::Result<(MasterId, Vec<ReleaseSummary>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let master = self.source.peek_master()?;
+        let mut versions = Vec::new();
+        while self.source.peek_master() == Some(master) {
+            match self.source.next_entry() {
+                Ok(Some((_, summary))) => versions.push(summary),
+                Ok(None) => break,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        Some(Ok((MasterId(master), versions)))
+    }
+}
+
+/// The run files written so far, deleted when dropped.
+#[derive(Default)]
+struct Runs {
+    paths: Vec<PathBuf>,
+}
+
+impl Runs {
+    fn write(&mut self, path: PathBuf, held: &mut Vec<(u32, ReleaseSummary)>) -> io::Result<()> {
+        sort(held);
+        self.paths.push(path.clone());
+        let mut out = BufWriter::new(File::create(&path)?);
+        for (master, summary) in held.drain(..) {
+            write_entry(&mut out, master, &summary)?;
+        }
+        out.flush()
+    }
+}
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn sort(held: &mut [(u32, ReleaseSummary)]) {
+    held.sort_unstable_by_key(|(master, summary)| (*master, summary.id));
+}
+
+fn write_entry(out: &mut impl Write, master: u32, summary: &ReleaseSummary) -> io::Result<()> {
+    out.write_all(&master.to_le_bytes())?;
+    out.write_all(&summary.id.get().to_le_bytes())?;
+    out.write_all(&[summary.is_main_release as u8])?;
+    for text in [&summary.title, &summary.country, &summary.released] {
+        write_str(out, text)?;
+    }
+    out.write_all(&(summary.label_ids.len() as u32).to_le_bytes())?;
+    for id in &summary.label_ids {
+        out.write_all(&id.get().to_le_bytes())?;
+    }
+    for list in [&summary.formats, &summary.format_descriptions] {
+        out.write_all(&(list.len() as u32).to_le_bytes())?;
+        for text in list {
+            write_str(out, text)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read an entry written by [`write_entry`], or None at the end of the run. A run
+/// that ends partway through an entry is an error.
+fn read_entry(input: &mut impl Read) -> io::Result<Option<(u32, ReleaseSummary)>> {
+    let mut buf = [0; 4];
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let master = u32::from_le_bytes(buf);
+    let id = ReleaseId(read_u32(input)?);
+    let mut flag = [0; 1];
+    input.read_exact(&mut flag)?;
+    let title = read_str(input)?;
+    let country = read_str(input)?;
+    let released = read_str(input)?;
+    let label_ids = (0..read_u32(input)?)
+        .map(|_| read_u32(input).map(LabelId))
+        .collect::<io::Result<_>>()?;
+    let mut read_list =
+        || -> io::Result<Vec<String>> { (0..read_u32(input)?).map(|_| read_str(input)).collect() };
+    let formats = read_list()?;
+    let format_descriptions = read_list()?;
+    let summary = ReleaseSummary {
+        id,
+        master_id: Some(MasterId(master)),
+        is_main_release: flag[0] != 0,
+        title,
+        country,
+        released,
+        label_ids,
+        formats,
+        format_descriptions,
+    };
+    Ok(Some((master, summary)))
+}
+
+fn write_str(out: &mut impl Write, text: &str) -> io::Result<()> {
+    out.write_all(&(text.len() as u32).to_le_bytes())?;
+    out.write_all(text.as_bytes())
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_str(input: &mut impl Read) -> io::Result<String> {
+    let mut buf = vec![0; read_u32(input)? as usize];
+    input.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
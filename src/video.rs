@@ -2,8 +2,9 @@ use crate::parser::{Parser, ParserError};
 use crate::util::get_attr;
 use quick_xml::events::Event;
 use std::mem::take;
+use std::time::Duration;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Video {
     pub src: String,
@@ -13,6 +14,49 @@ pub struct Video {
     pub embed: bool,
 }
 
+/// The range of video durations that are believable. The dumps have some videos
+/// with durations of days or years, which are errors in the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DurationBounds {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl DurationBounds {
+    /// Up to twelve hours, the longest video YouTube accepts. There's no minimum, as
+    /// some real tracks are only a second or two long; set one with [`new`](Self::new)
+    /// to flag those too.
+    pub const DEFAULT: Self = Self::new(Duration::ZERO, Duration::from_secs(12 * 3600));
+
+    pub const fn new(min: Duration, max: Duration) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, duration: Duration) -> bool {
+        self.min <= duration && duration <= self.max
+    }
+}
+
+impl Default for DurationBounds {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Video {
+    /// The duration, or None if it's outside [`DurationBounds::DEFAULT`].
+    pub fn duration_checked(&self) -> Option<Duration> {
+        self.duration_checked_with(DurationBounds::DEFAULT)
+    }
+
+    /// The duration, or None if it's outside `bounds`.
+    pub fn duration_checked_with(&self, bounds: DurationBounds) -> Option<Duration> {
+        let duration = Duration::from_secs(self.duration.into());
+        bounds.contains(duration).then_some(duration)
+    }
+}
+
 #[derive(Debug, Default)]
 enum ParserState {
     #[default]
@@ -21,11 +65,12 @@ enum ParserState {
     Description,
 }
 
+/// Parses a `<video>` element of a release or master into a [`Video`].
 #[derive(Debug, Default)]
 pub struct VideoParser {
     state: ParserState,
-    pub current_item: Video,
-    pub item_ready: bool,
+    current_item: Video,
+    item_ready: bool,
 }
 
 impl Parser for VideoParser {
@@ -38,15 +83,19 @@ impl Parser for VideoParser {
         self.item_ready = false;
         take(&mut self.current_item)
     }
+
+    fn item_ready(&self) -> bool {
+        self.item_ready
+    }
     fn process(&mut self, ev: Event) -> Result<(), ParserError> {
         self.state = match self.state {
             ParserState::Video => match ev {
                 Event::Start(e) => match e.local_name().as_ref() {
                     b"video" => {
                         let mut attrs = e.attributes();
-                        self.current_item.src = get_attr(attrs.next()).to_string();
-                        self.current_item.duration = get_attr(attrs.next()).parse()?;
-                        self.current_item.embed = get_attr(attrs.next()).parse()?;
+                        self.current_item.src = get_attr(attrs.next())?.to_string();
+                        self.current_item.duration = get_attr(attrs.next())?.parse()?;
+                        self.current_item.embed = get_attr(attrs.next())?.parse()?;
                         ParserState::Video
                     }
                     b"title" => ParserState::Title,
@@ -0,0 +1,158 @@
+//! Non-fatal observations about the data that are reported while parsing.
+use crate::video::DurationBounds;
+use quick_xml::events::{BytesStart, Event};
+use std::fmt;
+use std::time::Duration;
+
+/// Attributes that should always have a value, by element.
+const REQUIRED_ATTRS: &[(&[u8], &[u8])] = &[
+    (b"release", b"id"),
+    (b"release", b"status"),
+    (b"master", b"id"),
+    (b"label", b"id"),
+    (b"name", b"id"),
+];
+
+/// Elements that should always have text.
+const REQUIRED_TEXT: &[&[u8]] = &[b"id", b"name", b"title"];
+
+/// Something unusual about a record that didn't prevent it from being parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// The ID of the record, if it had been parsed when the issue was found.
+    pub id: Option<u32>,
+    /// The position in the decompressed XML after the element with the issue.
+    pub offset: u64,
+    pub kind: WarningKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// An attribute that should have a value is empty, given as (element, attribute).
+    EmptyAttribute(String, String),
+    /// The attributes of an element couldn't be read, so some were ignored.
+    MalformedAttribute(String),
+    /// An element that should have text is empty.
+    EmptyElement(String),
+    /// A video's duration in seconds is outside the bounds set with
+    /// [`ReaderOptions::video_duration_bounds`](crate::ReaderOptions::video_duration_bounds).
+    ImplausibleVideoDuration(u32),
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.id {
+            Some(id) => write!(f, "Record {id} at byte {}: ", self.offset)?,
+            None => write!(f, "Record at byte {}: ", self.offset)?,
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WarningKind::EmptyAttribute(e, a) => write!(f, "empty {a} attribute of <{e}>"),
+            WarningKind::MalformedAttribute(e) => write!(f, "malformed attribute of <{e}>"),
+            WarningKind::EmptyElement(e) => write!(f, "empty <{e}> element"),
+            WarningKind::ImplausibleVideoDuration(secs) => {
+                write!(f, "implausible video duration of {secs} seconds")
+            }
+        }
+    }
+}
+
+/// Detects issues in a stream of events.
+#[derive(Debug, Default)]
+pub(crate) struct Inspector {
+    /// The required text element that was just started, until text is seen.
+    open: Option<Vec<u8>>,
+    video_durations: DurationBounds,
+}
+
+impl Inspector {
+    pub(crate) fn new(video_durations: DurationBounds) -> Self {
+        Self {
+            open: None,
+            video_durations,
+        }
+    }
+
+    pub(crate) fn inspect(&mut self, ev: &Event, warnings: &mut Vec<WarningKind>) {
+        match ev {
+            Event::Start(e) => {
+                check_attrs(e, warnings);
+                self.check_video(e, warnings);
+                let name = e.local_name();
+                self.open = REQUIRED_TEXT
+                    .contains(&name.as_ref())
+                    .then(|| name.as_ref().to_vec());
+            }
+            Event::Empty(e) => {
+                check_attrs(e, warnings);
+                self.check_video(e, warnings);
+                let name = e.local_name();
+                if REQUIRED_TEXT.contains(&name.as_ref()) {
+                    warnings.push(WarningKind::EmptyElement(lossy(name.as_ref())));
+                }
+                self.open = None;
+            }
+            Event::End(e) => {
+                if let Some(open) = self.open.take() {
+                    if open == e.local_name().as_ref() {
+                        warnings.push(WarningKind::EmptyElement(lossy(&open)));
+                    }
+                }
+            }
+            Event::Text(e) if e.iter().all(|b| b.is_ascii_whitespace()) => {}
+            _ => self.open = None,
+        }
+    }
+
+    fn check_video(&self, e: &BytesStart, warnings: &mut Vec<WarningKind>) {
+        if e.local_name().as_ref() != b"video" {
+            return;
+        }
+        let duration = e
+            .attributes()
+            .flatten()
+            .find(|a| a.key.local_name().as_ref() == b"duration");
+        let secs: Option<u32> =
+            duration.and_then(|d| std::str::from_utf8(&d.value).ok()?.parse().ok());
+        if let Some(secs) = secs {
+            if !self
+                .video_durations
+                .contains(Duration::from_secs(secs.into()))
+            {
+                warnings.push(WarningKind::ImplausibleVideoDuration(secs));
+            }
+        }
+    }
+}
+
+fn check_attrs(e: &BytesStart, warnings: &mut Vec<WarningKind>) {
+    let name = e.local_name();
+    for attr in e.attributes() {
+        let attr = match attr {
+            Ok(attr) => attr,
+            Err(_) => {
+                warnings.push(WarningKind::MalformedAttribute(lossy(name.as_ref())));
+                return;
+            }
+        };
+        let key = attr.key.local_name();
+        let required = REQUIRED_ATTRS
+            .iter()
+            .any(|(e, a)| *e == name.as_ref() && *a == key.as_ref());
+        if required && attr.value.iter().all(|b| b.is_ascii_whitespace()) {
+            warnings.push(WarningKind::EmptyAttribute(
+                lossy(name.as_ref()),
+                lossy(key.as_ref()),
+            ));
+        }
+    }
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
@@ -0,0 +1,513 @@
+//! Writing records back out as the XML of the dumps.
+//!
+//! The records are written in the layout of the dumps, so the files can be read by
+//! this crate or any other tool that reads the dumps. This allows filtering, splitting
+//! or repairing dumps, e.g. by reading one, changing or leaving out records, and
+//! writing the rest. Reading a written dump gives back the same records.
+//!
+//! Like the dumps, the XML has no declaration or whitespace between elements.
+//! Fields that can't be told apart from missing ones when they're read, like an
+//! empty `anv` of an artist credit, are written as empty elements.
+//!
+//! ```no_run
+//! use disco_quick::writer::DumpWriter;
+//! use disco_quick::DiscogsReader;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let DiscogsReader::Labels(labels) = DiscogsReader::from_path("labels.xml.gz".as_ref())? else {
+//!     return Ok(());
+//! };
+//! let mut writer = DumpWriter::create("uk_labels.xml.gz".as_ref())?;
+//! for label in labels {
+//!     let label = label?;
+//!     if label.contactinfo.as_deref().is_some_and(|c| c.contains("UK")) {
+//!         writer.write(&label)?;
+//!     }
+//! }
+//! writer.finish()?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::artist::{Artist, ArtistInfo};
+use crate::artist_credit::ArtistCredit;
+use crate::entity::DiscogsEntity;
+use crate::label::Label;
+use crate::master::Master;
+use crate::release::Release;
+use crate::shared::{Image, ReleaseLabel};
+use crate::track::Track;
+use crate::video::Video;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use quick_xml::escape::{escape, partial_escape};
+use std::borrow::Borrow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// Records that can be written as the XML of a dump.
+pub trait WriteXml: DiscogsEntity {
+    /// Write the element of the record, e.g. `<release id="1" ...>...</release>`.
+    fn write_xml(&self, out: &mut dyn Write) -> io::Result<()>;
+
+    /// The element of the record as a string.
+    fn to_xml(&self) -> String {
+        let mut out = Vec::new();
+        self.write_xml(&mut out)
+            .expect("writing to a Vec doesn't fail");
+        String::from_utf8(out).expect("the XML is written from strings")
+    }
+}
+
+/// Writes records as a dump, between the start and end tags of its root element.
+pub struct DumpWriter<T: WriteXml, W: Write> {
+    out: Output<W>,
+    records: u64,
+    record_type: PhantomData<fn(&T)>,
+}
+
+enum Output<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+}
+
+impl<W: Write> Output<W> {
+    fn get(&mut self) -> &mut dyn Write {
+        match self {
+            Output::Plain(out) => out,
+            Output::Gzip(gz) => gz,
+        }
+    }
+}
+
+impl<T: WriteXml> DumpWriter<T, BufWriter<File>> {
+    /// Create a file at `path`, gzipped if its name ends in `.gz`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let gzip = path.extension().is_some_and(|e| e == "gz");
+        Self::new(BufWriter::new(File::create(path)?), gzip)
+    }
+}
+
+impl<T: WriteXml, W: Write> DumpWriter<T, W> {
+    /// Write to `out`, gzipped if `gzip` is true.
+    pub fn new(out: W, gzip: bool) -> io::Result<Self> {
+        let out = if gzip {
+            Output::Gzip(GzEncoder::new(out, Compression::default()))
+        } else {
+            Output::Plain(out)
+        };
+        let mut writer = Self {
+            out,
+            records: 0,
+            record_type: PhantomData,
+        };
+        write!(writer.out.get(), "<{}>", T::ENTITY_TYPE.root_tag())?;
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, record: &T) -> io::Result<()> {
+        record.write_xml(self.out.get())?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// The number of records written so far.
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Write the end tag of the root element and flush the output, returning the
+    /// writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        write!(self.out.get(), "</{}>", T::ENTITY_TYPE.root_tag())?;
+        let mut out = match self.out {
+            Output::Plain(out) => out,
+            Output::Gzip(gz) => gz.finish()?,
+        };
+        out.flush()?;
+        Ok(out)
+    }
+}
+
+/// Write `records` to `out` as a dump, returning the number written.
+pub fn write_dump<T, R>(records: impl IntoIterator<Item = R>, out: impl Write) -> io::Result<u64>
+where
+    T: WriteXml,
+    R: Borrow<T>,
+{
+    let mut writer = DumpWriter::new(out, false)?;
+    for record in records {
+        writer.write(record.borrow())?;
+    }
+    let records = writer.records();
+    writer.finish()?;
+    Ok(records)
+}
+
+pub fn write_artists<R: Borrow<Artist>>(
+    artists: impl IntoIterator<Item = R>,
+    out: impl Write,
+) -> io::Result<u64> {
+    write_dump::<Artist, R>(artists, out)
+}
+
+pub fn write_labels<R: Borrow<Label>>(
+    labels: impl IntoIterator<Item = R>,
+    out: impl Write,
+) -> io::Result<u64> {
+    write_dump::<Label, R>(labels, out)
+}
+
+pub fn write_masters<R: Borrow<Master>>(
+    masters: impl IntoIterator<Item = R>,
+    out: impl Write,
+) -> io::Result<u64> {
+    write_dump::<Master, R>(masters, out)
+}
+
+pub fn write_releases<R: Borrow<Release>>(
+    releases: impl IntoIterator<Item = R>,
+    out: impl Write,
+) -> io::Result<u64> {
+    write_dump::<Release, R>(releases, out)
+}
+
+/// Writes the elements of a record.
+struct Xml<'a> {
+    out: &'a mut dyn Write,
+}
+
+impl Xml<'_> {
+    fn start(&mut self, tag: &str) -> io::Result<()> {
+        write!(self.out, "<{tag}>")
+    }
+
+    fn end(&mut self, tag: &str) -> io::Result<()> {
+        write!(self.out, "</{tag}>")
+    }
+
+    /// An element with text, empty if the text is.
+    fn text(&mut self, tag: &str, text: &str) -> io::Result<()> {
+        write!(self.out, "<{tag}>{}</{tag}>", partial_escape(text))
+    }
+
+    /// An element with text if there is any, or nothing.
+    fn optional(&mut self, tag: &str, text: Option<&str>) -> io::Result<()> {
+        match text {
+            Some(text) => self.text(tag, text),
+            None => Ok(()),
+        }
+    }
+
+    /// A start tag with attributes, or an empty element if `empty`.
+    fn tag_with(&mut self, tag: &str, attrs: &[(&str, &str)], empty: bool) -> io::Result<()> {
+        write!(self.out, "<{tag}")?;
+        for (name, value) in attrs {
+            write!(self.out, " {name}=\"{}\"", escape(value))?;
+        }
+        self.out.write_all(if empty { b"/>" } else { b">" })
+    }
+
+    /// A list of elements with text, e.g. `<urls><url>...</url></urls>`, or nothing
+    /// if it's empty.
+    fn list<S: AsRef<str>>(&mut self, tag: &str, item: &str, items: &[S]) -> io::Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        self.start(tag)?;
+        for text in items {
+            self.text(item, text.as_ref())?;
+        }
+        self.end(tag)
+    }
+
+    /// A list of genres or styles, which is written empty if the record had one.
+    fn tags<S: AsRef<str>>(
+        &mut self,
+        tag: &str,
+        item: &str,
+        items: &[S],
+        present: bool,
+    ) -> io::Result<()> {
+        if items.is_empty() && present {
+            return self.tag_with(tag, &[], true);
+        }
+        self.list(tag, item, items)
+    }
+
+    fn images(&mut self, images: &[Image]) -> io::Result<()> {
+        if images.is_empty() {
+            return Ok(());
+        }
+        self.start("images")?;
+        for image in images {
+            let (width, height) = (image.width.to_string(), image.height.to_string());
+            let attrs = [
+                ("type", image.r#type.as_str()),
+                ("uri", &image.uri),
+                ("uri150", &image.uri150),
+                ("width", &width),
+                ("height", &height),
+            ];
+            self.tag_with("image", &attrs, true)?;
+        }
+        self.end("images")
+    }
+
+    /// Artists or labels with their IDs as attributes, e.g. `<name id="1">...</name>`.
+    fn named(&mut self, item: &str, id: u32, name: &str) -> io::Result<()> {
+        self.tag_with(item, &[("id", &id.to_string())], false)?;
+        write!(self.out, "{}", partial_escape(name))?;
+        self.end(item)
+    }
+
+    fn artist_infos(&mut self, tag: &str, artists: &[ArtistInfo]) -> io::Result<()> {
+        if artists.is_empty() {
+            return Ok(());
+        }
+        self.start(tag)?;
+        for artist in artists {
+            self.named("name", artist.id.get(), &artist.name)?;
+        }
+        self.end(tag)
+    }
+
+    fn credits(&mut self, tag: &str, credits: &[ArtistCredit]) -> io::Result<()> {
+        if credits.is_empty() {
+            return Ok(());
+        }
+        self.start(tag)?;
+        for credit in credits {
+            self.start("artist")?;
+            self.text("id", &credit.id.to_string())?;
+            self.text("name", &credit.name)?;
+            self.text("anv", credit.anv.as_deref().unwrap_or_default())?;
+            self.text("join", credit.join.as_deref().unwrap_or_default())?;
+            self.text("role", credit.role.as_deref().unwrap_or_default())?;
+            self.text("tracks", credit.tracks.as_deref().unwrap_or_default())?;
+            self.end("artist")?;
+        }
+        self.end(tag)
+    }
+
+    fn videos(&mut self, videos: &[Video]) -> io::Result<()> {
+        if videos.is_empty() {
+            return Ok(());
+        }
+        self.start("videos")?;
+        for video in videos {
+            let duration = video.duration.to_string();
+            let attrs = [
+                ("src", video.src.as_str()),
+                ("duration", &duration),
+                ("embed", if video.embed { "true" } else { "false" }),
+            ];
+            self.tag_with("video", &attrs, false)?;
+            self.text("title", &video.title)?;
+            self.text("description", &video.description)?;
+            self.end("video")?;
+        }
+        self.end("videos")
+    }
+
+    fn tracks(&mut self, tag: &str, tracks: &[Track]) -> io::Result<()> {
+        if tracks.is_empty() {
+            return Ok(());
+        }
+        self.start(tag)?;
+        for track in tracks {
+            self.start("track")?;
+            self.text("position", &track.position)?;
+            self.text("title", &track.title)?;
+            self.text("duration", track.duration.as_deref().unwrap_or_default())?;
+            self.credits("artists", &track.artists)?;
+            self.credits("extraartists", &track.extraartists)?;
+            self.tracks("sub_tracks", &track.sub_tracks)?;
+            self.end("track")?;
+        }
+        self.end(tag)
+    }
+
+    fn companies(&mut self, companies: &[ReleaseLabel]) -> io::Result<()> {
+        if companies.is_empty() {
+            return Ok(());
+        }
+        self.start("companies")?;
+        for company in companies {
+            self.start("company")?;
+            self.text("id", &company.id.to_string())?;
+            self.text("name", &company.name)?;
+            self.text("catno", company.catno.as_deref().unwrap_or_default())?;
+            self.text("entity_type", &company.entity_type.to_string())?;
+            self.text("entity_type_name", &company.entity_type_name)?;
+            let url = format!("https://api.discogs.com/labels/{}", company.id);
+            self.text("resource_url", &url)?;
+            self.end("company")?;
+        }
+        self.end("companies")
+    }
+
+    #[cfg(feature = "extra-fields")]
+    fn extra_fields(
+        &mut self,
+        fields: &std::collections::BTreeMap<String, String>,
+    ) -> io::Result<()> {
+        for (tag, text) in fields {
+            self.text(tag, text)?;
+        }
+        Ok(())
+    }
+}
+
+impl WriteXml for Artist {
+    fn write_xml(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut xml = Xml { out };
+        xml.start("artist")?;
+        xml.images(&self.images)?;
+        xml.text("id", &self.id.to_string())?;
+        xml.text("name", &self.name)?;
+        xml.optional("realname", self.real_name.as_deref())?;
+        xml.optional("profile", self.profile.as_deref())?;
+        xml.text("data_quality", self.data_quality.as_str())?;
+        xml.list("urls", "url", &self.urls)?;
+        xml.list("namevariations", "name", &self.name_variations)?;
+        xml.artist_infos("aliases", &self.aliases)?;
+        if !self.members.is_empty() {
+            // The IDs of members were elements of their own until 2025
+            xml.start("members")?;
+            for member in &self.members {
+                xml.text("id", &member.id.to_string())?;
+                xml.named("name", member.id.get(), &member.name)?;
+            }
+            xml.end("members")?;
+        }
+        xml.artist_infos("groups", &self.groups)?;
+        #[cfg(feature = "extra-fields")]
+        xml.extra_fields(&self.extra_fields)?;
+        xml.end("artist")
+    }
+}
+
+impl WriteXml for Label {
+    fn write_xml(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut xml = Xml { out };
+        xml.start("label")?;
+        xml.images(&self.images)?;
+        xml.text("id", &self.id.to_string())?;
+        xml.text("name", &self.name)?;
+        xml.optional("contactinfo", self.contactinfo.as_deref())?;
+        xml.optional("profile", self.profile.as_deref())?;
+        xml.text("data_quality", self.data_quality.as_str())?;
+        xml.list("urls", "url", &self.urls)?;
+        if let Some(parent) = &self.parent_label {
+            xml.named("parentLabel", parent.id.get(), &parent.name)?;
+        }
+        if !self.sublabels.is_empty() {
+            xml.start("sublabels")?;
+            for sublabel in &self.sublabels {
+                xml.named("label", sublabel.id.get(), &sublabel.name)?;
+            }
+            xml.end("sublabels")?;
+        }
+        #[cfg(feature = "extra-fields")]
+        xml.extra_fields(&self.extra_fields)?;
+        xml.end("label")
+    }
+}
+
+impl WriteXml for Master {
+    fn write_xml(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut xml = Xml { out };
+        xml.tag_with("master", &[("id", &self.id.to_string())], false)?;
+        xml.text("main_release", &self.main_release.to_string())?;
+        xml.images(&self.images)?;
+        xml.credits("artists", &self.artists)?;
+        xml.tags("genres", "genre", &self.genres, self.genres_present)?;
+        xml.tags("styles", "style", &self.styles, self.styles_present)?;
+        xml.text("year", &self.year.to_string())?;
+        xml.text("title", &self.title)?;
+        xml.optional("notes", self.notes.as_deref())?;
+        xml.text("data_quality", self.data_quality.as_str())?;
+        xml.videos(&self.videos)?;
+        #[cfg(feature = "extra-fields")]
+        xml.extra_fields(&self.extra_fields)?;
+        xml.end("master")
+    }
+}
+
+impl WriteXml for Release {
+    fn write_xml(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut xml = Xml { out };
+        let id = self.id.to_string();
+        xml.tag_with(
+            "release",
+            &[("id", &id), ("status", self.status.as_str())],
+            false,
+        )?;
+        xml.images(&self.images)?;
+        xml.credits("artists", &self.artists)?;
+        xml.text("title", &self.title)?;
+        if !self.labels.is_empty() {
+            xml.start("labels")?;
+            for label in &self.labels {
+                let id = label.id.to_string();
+                let catno = label.catno.as_deref().unwrap_or_default();
+                let attrs = [("name", label.name.as_str()), ("catno", catno), ("id", &id)];
+                xml.tag_with("label", &attrs, true)?;
+            }
+            xml.end("labels")?;
+        }
+        xml.credits("extraartists", &self.extraartists)?;
+        if !self.formats.is_empty() {
+            xml.start("formats")?;
+            for format in &self.formats {
+                let attrs = [
+                    ("name", &*format.name),
+                    ("qty", &format.qty),
+                    ("text", format.text.as_deref().unwrap_or_default()),
+                ];
+                xml.tag_with("format", &attrs, false)?;
+                xml.list("descriptions", "description", &format.descriptions)?;
+                xml.end("format")?;
+            }
+            xml.end("formats")?;
+        }
+        xml.tags("genres", "genre", &self.genres, self.genres_present)?;
+        xml.tags("styles", "style", &self.styles, self.styles_present)?;
+        xml.text("country", &self.country)?;
+        xml.text("released", &self.released)?;
+        xml.optional("notes", self.notes.as_deref())?;
+        xml.text("data_quality", self.data_quality.as_str())?;
+        if let Some(master_id) = self.master_id {
+            let main = if self.is_main_release {
+                "true"
+            } else {
+                "false"
+            };
+            xml.tag_with("master_id", &[("is_main_release", main)], false)?;
+            write!(xml.out, "{master_id}")?;
+            xml.end("master_id")?;
+        }
+        xml.tracks("tracklist", &self.tracklist)?;
+        if !self.identifiers.is_empty() {
+            xml.start("identifiers")?;
+            for identifier in &self.identifiers {
+                let mut attrs = vec![
+                    ("type", identifier.r#type.as_str()),
+                    ("description", &identifier.description),
+                ];
+                if let Some(value) = &identifier.value {
+                    attrs.push(("value", value));
+                }
+                xml.tag_with("identifier", &attrs, true)?;
+            }
+            xml.end("identifiers")?;
+        }
+        xml.videos(&self.videos)?;
+        xml.companies(&self.companies)?;
+        #[cfg(feature = "extra-fields")]
+        xml.extra_fields(&self.extra_fields)?;
+        xml.end("release")
+    }
+}